@@ -0,0 +1,502 @@
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::events::EventStream;
+use crate::models::{
+    Bot, Channel, CommandOption, CommandResult, CreatedBot, DmConversation, DmMessage,
+    DmParticipant, Friend, Identity, Message, PeerInfo, Room, SearchResults, SlashCommand,
+    UnreadSummary, VoiceState,
+};
+use crate::Error;
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
+    message: String,
+}
+
+/// REST client for one node. Cheap to clone.
+#[derive(Debug, Clone)]
+pub struct Client {
+    base_url: String,
+    token: String,
+    http: reqwest::Client,
+}
+
+impl Client {
+    /// `base_url` is the node's API address, e.g. `http://127.0.0.1:9847`.
+    pub fn new(base_url: &str, token: &str) -> Result<Self, Error> {
+        let base_url = base_url.trim_end_matches('/');
+        if !(base_url.starts_with("http://") || base_url.starts_with("https://")) {
+            return Err(Error::InvalidUrl(base_url.to_string()));
+        }
+        Ok(Self {
+            base_url: base_url.to_string(),
+            token: token.to_string(),
+            http: reqwest::Client::new(),
+        })
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Call any route; `path` starts with `/api/v1/`. For routes without a
+    /// typed method here.
+    pub async fn request<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, String)],
+        body: Option<&Value>,
+    ) -> Result<T, Error> {
+        let mut request = self
+            .http
+            .request(method, format!("{}{}", self.base_url, path))
+            .bearer_auth(&self.token)
+            .query(query);
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            let (code, message) = match serde_json::from_str::<ErrorBody>(&text) {
+                Ok(body) => (body.error, body.message),
+                Err(_) => (String::new(), text),
+            };
+            return Err(Error::Api {
+                status: status.as_u16(),
+                code,
+                message,
+            });
+        }
+        serde_json::from_str(if text.is_empty() { "null" } else { &text }).map_err(Error::Json)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+        self.request(Method::GET, path, &[], None).await
+    }
+
+    async fn send<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: &impl Serialize,
+    ) -> Result<T, Error> {
+        let body = serde_json::to_value(body)?;
+        self.request(method, path, &[], Some(&body)).await
+    }
+
+    /// Open the event socket. See [`EventStream`].
+    pub async fn events(&self) -> Result<EventStream, Error> {
+        EventStream::connect(&self.base_url, &self.token).await
+    }
+
+    // Identity
+
+    /// The node's own identity. Not available to bot tokens; see [`Client::current_bot`].
+    pub async fn identity(&self) -> Result<Identity, Error> {
+        self.get("/api/v1/identity").await
+    }
+
+    /// `status_type` is `online`, `idle`, `dnd` or `invisible`.
+    pub async fn set_status(
+        &self,
+        status_type: Option<&str>,
+        message: Option<&str>,
+    ) -> Result<(), Error> {
+        let body = json!({ "status_type": status_type, "message": message });
+        self.send::<Value>(Method::PUT, "/api/v1/identity/status", &body)
+            .await
+            .map(drop)
+    }
+
+    /// The bot this client's token acts as.
+    pub async fn current_bot(&self) -> Result<Bot, Error> {
+        self.get("/api/v1/bot").await
+    }
+
+    // Rooms and channels
+
+    /// Rooms visible to the token; a bot sees only its own.
+    pub async fn rooms(&self) -> Result<Vec<Room>, Error> {
+        self.get("/api/v1/rooms").await
+    }
+
+    pub async fn create_room(&self, name: &str) -> Result<Room, Error> {
+        self.send(Method::POST, "/api/v1/rooms", &json!({ "name": name }))
+            .await
+    }
+
+    /// Join by invite code. Fails unless a peer that has the room is online.
+    pub async fn join_room(&self, invite_code: &str) -> Result<Room, Error> {
+        self.send(
+            Method::POST,
+            "/api/v1/rooms/join",
+            &json!({ "invite_code": invite_code }),
+        )
+        .await
+    }
+
+    pub async fn channels(&self, room_id: &str) -> Result<Vec<Channel>, Error> {
+        self.get(&format!("/api/v1/rooms/{}/channels", room_id))
+            .await
+    }
+
+    /// `channel_type` is `text` (the default) or `voice`.
+    pub async fn create_channel(
+        &self,
+        room_id: &str,
+        name: &str,
+        channel_type: Option<&str>,
+    ) -> Result<Channel, Error> {
+        let path = format!("/api/v1/rooms/{}/channels", room_id);
+        self.send(
+            Method::POST,
+            &path,
+            &json!({ "name": name, "channel_type": channel_type }),
+        )
+        .await
+    }
+
+    /// The room and channel with this ID, from the rooms visible to the token.
+    pub async fn find_channel(&self, channel_id: &str) -> Result<Option<(Room, Channel)>, Error> {
+        for room in self.rooms().await? {
+            let channels = self.channels(&room.id).await?;
+            if let Some(channel) = channels.into_iter().find(|c| c.id == channel_id) {
+                return Ok(Some((room, channel)));
+            }
+        }
+        Ok(None)
+    }
+
+    pub async fn room_peers(&self, room_id: &str) -> Result<Vec<PeerInfo>, Error> {
+        self.get(&format!("/api/v1/rooms/{}/peers", room_id)).await
+    }
+
+    // Messages
+
+    /// The channel's latest messages; pass the oldest timestamp seen as `before`
+    /// for the page before them.
+    pub async fn messages(
+        &self,
+        channel_id: &str,
+        limit: Option<i64>,
+        before: Option<&str>,
+    ) -> Result<Vec<Message>, Error> {
+        let mut query = Vec::new();
+        if let Some(limit) = limit {
+            query.push(("limit", limit.to_string()));
+        }
+        if let Some(before) = before {
+            query.push(("before", before.to_string()));
+        }
+        self.request(
+            Method::GET,
+            &format!("/api/v1/channels/{}/messages", channel_id),
+            &query,
+            None,
+        )
+        .await
+    }
+
+    pub async fn send_message(&self, channel_id: &str, content: &str) -> Result<Message, Error> {
+        let path = format!("/api/v1/channels/{}/messages", channel_id);
+        self.send(Method::POST, &path, &json!({ "content": content }))
+            .await
+    }
+
+    /// Send with any of the optional parts: a reply target and a thread.
+    pub async fn send_message_with(
+        &self,
+        channel_id: &str,
+        content: &str,
+        reply_to_id: Option<&str>,
+        thread_id: Option<&str>,
+    ) -> Result<Message, Error> {
+        let path = format!("/api/v1/channels/{}/messages", channel_id);
+        self.send(
+            Method::POST,
+            &path,
+            &json!({ "content": content, "reply_to_id": reply_to_id, "thread_id": thread_id }),
+        )
+        .await
+    }
+
+    pub async fn reply(
+        &self,
+        channel_id: &str,
+        reply_to_id: &str,
+        content: &str,
+    ) -> Result<Message, Error> {
+        let path = format!("/api/v1/channels/{}/messages", channel_id);
+        self.send(
+            Method::POST,
+            &path,
+            &json!({ "content": content, "reply_to_id": reply_to_id }),
+        )
+        .await
+    }
+
+    /// Returns whether the message existed.
+    pub async fn delete_message(&self, message_id: &str) -> Result<bool, Error> {
+        let result: Value = self
+            .request(
+                Method::DELETE,
+                &format!("/api/v1/messages/{}", message_id),
+                &[],
+                None,
+            )
+            .await?;
+        Ok(result["deleted"].as_bool().unwrap_or(false))
+    }
+
+    /// Tell the channel's peers we started or stopped typing.
+    pub async fn set_typing(&self, channel_id: &str, typing: bool) -> Result<(), Error> {
+        let path = format!("/api/v1/channels/{}/typing", channel_id);
+        self.send::<Value>(Method::POST, &path, &json!({ "typing": typing }))
+            .await
+            .map(drop)
+    }
+
+    /// Move the read marker of a channel or DM conversation to this message.
+    pub async fn mark_read(
+        &self,
+        channel_id: &str,
+        last_read_message_id: &str,
+    ) -> Result<(), Error> {
+        let path = format!("/api/v1/channels/{}/read", channel_id);
+        self.send::<Value>(
+            Method::POST,
+            &path,
+            &json!({ "last_read_message_id": last_read_message_id }),
+        )
+        .await
+        .map(drop)
+    }
+
+    pub async fn unread(&self) -> Result<UnreadSummary, Error> {
+        self.get("/api/v1/unread").await
+    }
+
+    /// Search channel messages, DMs and files. `sources` limits it to some of
+    /// `channel`, `dm` and `file`.
+    pub async fn search(
+        &self,
+        query: &str,
+        sources: &[&str],
+        room_id: Option<&str>,
+        limit: Option<i64>,
+    ) -> Result<SearchResults, Error> {
+        let mut params = vec![("q", query.to_string())];
+        if !sources.is_empty() {
+            params.push(("sources", sources.join(",")));
+        }
+        if let Some(room_id) = room_id {
+            params.push(("room_id", room_id.to_string()));
+        }
+        if let Some(limit) = limit {
+            params.push(("limit", limit.to_string()));
+        }
+        self.request(Method::GET, "/api/v1/search", &params, None)
+            .await
+    }
+
+    // Direct messages and friends
+
+    pub async fn dms(&self) -> Result<Vec<DmConversation>, Error> {
+        self.get("/api/v1/dms").await
+    }
+
+    /// Open (or reuse) a conversation with the peers.
+    pub async fn create_dm(
+        &self,
+        peer_ids: &[&str],
+        name: Option<&str>,
+    ) -> Result<DmConversation, Error> {
+        self.send(
+            Method::POST,
+            "/api/v1/dms",
+            &json!({ "peer_ids": peer_ids, "name": name }),
+        )
+        .await
+    }
+
+    pub async fn dm_participants(
+        &self,
+        conversation_id: &str,
+    ) -> Result<Vec<DmParticipant>, Error> {
+        self.get(&format!("/api/v1/dms/{}/participants", conversation_id))
+            .await
+    }
+
+    /// Like [`Client::messages`], for a conversation.
+    pub async fn dm_messages(
+        &self,
+        conversation_id: &str,
+        limit: Option<i64>,
+        before: Option<&str>,
+    ) -> Result<Vec<DmMessage>, Error> {
+        let mut query = Vec::new();
+        if let Some(limit) = limit {
+            query.push(("limit", limit.to_string()));
+        }
+        if let Some(before) = before {
+            query.push(("before", before.to_string()));
+        }
+        let path = format!("/api/v1/dms/{}/messages", conversation_id);
+        self.request(Method::GET, &path, &query, None).await
+    }
+
+    pub async fn send_dm(&self, conversation_id: &str, content: &str) -> Result<DmMessage, Error> {
+        let path = format!("/api/v1/dms/{}/messages", conversation_id);
+        self.send(Method::POST, &path, &json!({ "content": content }))
+            .await
+    }
+
+    pub async fn friends(&self) -> Result<Vec<Friend>, Error> {
+        self.get("/api/v1/friends").await
+    }
+
+    // Voice
+
+    /// Join a voice channel; the node's media engine does the audio.
+    pub async fn join_voice(&self, room_id: &str, channel_id: &str) -> Result<(), Error> {
+        self.send::<Value>(
+            Method::POST,
+            "/api/v1/voice/join",
+            &json!({ "room_id": room_id, "channel_id": channel_id }),
+        )
+        .await
+        .map(drop)
+    }
+
+    pub async fn leave_voice(&self) -> Result<(), Error> {
+        self.request::<Value>(Method::POST, "/api/v1/voice/leave", &[], None)
+            .await
+            .map(drop)
+    }
+
+    pub async fn set_muted(&self, muted: bool) -> Result<(), Error> {
+        self.send::<Value>(
+            Method::PUT,
+            "/api/v1/voice/muted",
+            &json!({ "muted": muted }),
+        )
+        .await
+        .map(drop)
+    }
+
+    pub async fn set_deafened(&self, deafened: bool) -> Result<(), Error> {
+        self.send::<Value>(
+            Method::PUT,
+            "/api/v1/voice/deafened",
+            &json!({ "deafened": deafened }),
+        )
+        .await
+        .map(drop)
+    }
+
+    pub async fn voice_state(&self) -> Result<VoiceState, Error> {
+        self.get("/api/v1/voice/state").await
+    }
+
+    // Slash commands
+
+    /// Commands available in the channel: built-in ones and its room's bot commands.
+    pub async fn commands(&self, channel_id: &str) -> Result<Vec<SlashCommand>, Error> {
+        self.get(&format!("/api/v1/channels/{}/commands", channel_id))
+            .await
+    }
+
+    /// Run `input`, e.g. `/topic Release day`, in the channel.
+    pub async fn run_command(&self, channel_id: &str, input: &str) -> Result<CommandResult, Error> {
+        let path = format!("/api/v1/channels/{}/commands", channel_id);
+        self.send(Method::POST, &path, &json!({ "input": input }))
+            .await
+    }
+
+    /// The commands this bot registered (bot tokens).
+    pub async fn bot_commands(&self) -> Result<Vec<SlashCommand>, Error> {
+        self.get("/api/v1/bot/commands").await
+    }
+
+    /// Register or replace `/name` in one of the bot's rooms (bot tokens).
+    /// Invocations arrive as `CommandInvoked` events; see [`Event::command_invocation`](crate::Event::command_invocation).
+    pub async fn register_command(
+        &self,
+        room_id: &str,
+        name: &str,
+        description: &str,
+        options: &[CommandOption],
+    ) -> Result<SlashCommand, Error> {
+        let path = format!("/api/v1/bot/rooms/{}/commands/{}", room_id, name);
+        self.send(
+            Method::PUT,
+            &path,
+            &json!({ "description": description, "options": options }),
+        )
+        .await
+    }
+
+    pub async fn unregister_command(&self, room_id: &str, name: &str) -> Result<bool, Error> {
+        let path = format!("/api/v1/bot/rooms/{}/commands/{}", room_id, name);
+        let result: Value = self.request(Method::DELETE, &path, &[], None).await?;
+        Ok(result["deleted"].as_bool().unwrap_or(false))
+    }
+
+    // Bots (admin tokens)
+
+    pub async fn bots(&self) -> Result<Vec<Bot>, Error> {
+        self.get("/api/v1/bots").await
+    }
+
+    pub async fn create_bot(&self, display_name: &str) -> Result<CreatedBot, Error> {
+        self.send(
+            Method::POST,
+            "/api/v1/bots",
+            &json!({ "display_name": display_name }),
+        )
+        .await
+    }
+
+    pub async fn delete_bot(&self, bot_peer_id: &str) -> Result<bool, Error> {
+        let result: Value = self
+            .request(
+                Method::DELETE,
+                &format!("/api/v1/bots/{}", bot_peer_id),
+                &[],
+                None,
+            )
+            .await?;
+        Ok(result["deleted"].as_bool().unwrap_or(false))
+    }
+
+    pub async fn add_bot_to_room(&self, bot_peer_id: &str, room_id: &str) -> Result<Bot, Error> {
+        self.request(
+            Method::PUT,
+            &format!("/api/v1/bots/{}/rooms/{}", bot_peer_id, room_id),
+            &[],
+            None,
+        )
+        .await
+    }
+
+    pub async fn remove_bot_from_room(
+        &self,
+        bot_peer_id: &str,
+        room_id: &str,
+    ) -> Result<Bot, Error> {
+        self.request(
+            Method::DELETE,
+            &format!("/api/v1/bots/{}/rooms/{}", bot_peer_id, room_id),
+            &[],
+            None,
+        )
+        .await
+    }
+}
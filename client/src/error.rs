@@ -0,0 +1,70 @@
+use std::fmt;
+
+/// Everything a [`Client`](crate::Client) or [`EventStream`](crate::EventStream) call can fail with.
+#[derive(Debug)]
+pub enum Error {
+    /// The node answered with an error status. `code` is the machine-readable
+    /// kind from the JSON error body (`not_found`, `forbidden`, ...).
+    Api {
+        status: u16,
+        code: String,
+        message: String,
+    },
+    /// A JSON-RPC call over the socket returned an error.
+    Rpc {
+        code: i32,
+        message: String,
+    },
+    /// Could not reach the node, or the HTTP exchange failed.
+    Http(reqwest::Error),
+    WebSocket(Box<tokio_tungstenite::tungstenite::Error>),
+    Json(serde_json::Error),
+    InvalidUrl(String),
+    /// The token can't be sent in a header.
+    InvalidToken,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Api {
+                status, message, ..
+            } => write!(f, "API error {}: {}", status, message),
+            Error::Rpc { code, message } => write!(f, "RPC error {}: {}", code, message),
+            Error::Http(e) => write!(f, "HTTP error: {}", e),
+            Error::WebSocket(e) => write!(f, "WebSocket error: {}", e),
+            Error::Json(e) => write!(f, "Invalid JSON: {}", e),
+            Error::InvalidUrl(url) => write!(f, "Invalid URL: {}", url),
+            Error::InvalidToken => write!(f, "Invalid API token"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Http(e) => Some(e),
+            Error::WebSocket(e) => Some(e),
+            Error::Json(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(e)
+    }
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for Error {
+    fn from(e: tokio_tungstenite::tungstenite::Error) -> Self {
+        Error::WebSocket(Box::new(e))
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::VecDeque;
+
+use futures_util::{SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::{header, HeaderValue};
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::models::{CommandInvocation, Message};
+use crate::Error;
+
+/// One event from the node: `{"seq", "type", "data"}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    /// Journal sequence number; `None` for transient events such as typing.
+    #[serde(default)]
+    pub seq: Option<i64>,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    #[serde(default)]
+    pub data: Value,
+}
+
+impl Event {
+    /// The payload as `T`, if it has that shape.
+    pub fn data_as<T: DeserializeOwned>(&self) -> Option<T> {
+        serde_json::from_value(self.data.clone()).ok()
+    }
+
+    /// The message, for `NewMessage` events.
+    pub fn new_message(&self) -> Option<Message> {
+        if self.event_type == "NewMessage" {
+            self.data_as()
+        } else {
+            None
+        }
+    }
+
+    /// The invocation, for `CommandInvoked` events. Bots in the same room see
+    /// each other's; check `bot_peer_id`.
+    pub fn command_invocation(&self) -> Option<CommandInvocation> {
+        if self.event_type == "CommandInvoked" {
+            self.data_as()
+        } else {
+            None
+        }
+    }
+}
+
+/// Which events the socket delivers. Empty lists don't filter.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EventFilter {
+    pub rooms: Vec<String>,
+    pub channels: Vec<String>,
+    pub dms: Vec<String>,
+    pub types: Vec<String>,
+}
+
+/// Result of [`EventStream::subscribe`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct Subscribed {
+    /// Resume from here after reconnecting.
+    pub last_seq: Option<i64>,
+    /// False if events after `since` were already trimmed from the journal.
+    pub complete: bool,
+}
+
+/// The `/ws` socket: events out, JSON-RPC calls in. Events that arrive while
+/// a call waits for its reply are kept for [`EventStream::next_event`].
+pub struct EventStream {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    next_id: u64,
+    pending: VecDeque<Event>,
+}
+
+impl EventStream {
+    pub(crate) async fn connect(base_url: &str, token: &str) -> Result<Self, Error> {
+        let ws_url = match base_url.split_once("://") {
+            Some(("https", rest)) => format!("wss://{}/ws", rest),
+            Some((_, rest)) => format!("ws://{}/ws", rest),
+            None => return Err(Error::InvalidUrl(base_url.to_string())),
+        };
+        let mut request = ws_url.into_client_request()?;
+        let auth =
+            HeaderValue::from_str(&format!("Bearer {}", token)).map_err(|_| Error::InvalidToken)?;
+        request.headers_mut().insert(header::AUTHORIZATION, auth);
+        let (socket, _) = tokio_tungstenite::connect_async(request).await?;
+        Ok(Self {
+            socket,
+            next_id: 1,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Next text frame as JSON; `None` once the socket closes.
+    async fn next_frame(&mut self) -> Result<Option<Value>, Error> {
+        while let Some(frame) = self.socket.next().await {
+            match frame? {
+                WsMessage::Text(text) => return Ok(Some(serde_json::from_str(text.as_str())?)),
+                WsMessage::Close(_) => return Ok(None),
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+
+    /// Call any RPC method (e.g. `"messages.send"`) and wait for its result.
+    pub async fn call<T: DeserializeOwned>(
+        &mut self,
+        method: &str,
+        params: Value,
+    ) -> Result<T, Error> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let request = json!({ "id": id, "method": method, "params": params });
+        self.socket
+            .send(WsMessage::text(request.to_string()))
+            .await?;
+        loop {
+            let Some(frame) = self.next_frame().await? else {
+                return Err(tokio_tungstenite::tungstenite::Error::ConnectionClosed.into());
+            };
+            if frame.get("id").and_then(Value::as_u64) == Some(id) {
+                if let Some(error) = frame.get("error") {
+                    return Err(Error::Rpc {
+                        code: error["code"].as_i64().unwrap_or_default() as i32,
+                        message: error["message"].as_str().unwrap_or_default().to_string(),
+                    });
+                }
+                return Ok(serde_json::from_value(
+                    frame.get("result").cloned().unwrap_or(Value::Null),
+                )?);
+            }
+            if let Ok(event) = serde_json::from_value(frame) {
+                self.pending.push_back(event);
+            }
+        }
+    }
+
+    /// Only deliver events passing `filter`. With `since`, first replays
+    /// journaled events after that sequence number.
+    pub async fn subscribe(
+        &mut self,
+        filter: &EventFilter,
+        since: Option<i64>,
+    ) -> Result<Subscribed, Error> {
+        let mut params = serde_json::to_value(filter)?;
+        params["since"] = json!(since);
+        self.call("events.subscribe", params).await
+    }
+
+    /// Wait for the next event; `None` once the socket closes.
+    pub async fn next_event(&mut self) -> Result<Option<Event>, Error> {
+        if let Some(event) = self.pending.pop_front() {
+            return Ok(Some(event));
+        }
+        while let Some(frame) = self.next_frame().await? {
+            // Replies to calls nobody waits for any more are dropped
+            if frame.get("type").is_some() {
+                return Ok(Some(serde_json::from_value(frame)?));
+            }
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,34 @@
+//! Client for a Chatr node's local API, for bots and scripts.
+//!
+//! [`Client`] wraps the REST API; [`EventStream`] is the `/ws` socket, which
+//! delivers events and takes JSON-RPC calls. Both authenticate with an API
+//! token, e.g. the one returned when a bot is created.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), chatr_client::Error> {
+//! use chatr_client::{Client, EventFilter};
+//!
+//! let client = Client::new("http://127.0.0.1:9847", "chatr_...")?;
+//! let me = client.current_bot().await?;
+//! let mut events = client.events().await?;
+//! events.subscribe(&EventFilter { rooms: me.rooms.clone(), ..Default::default() }, None).await?;
+//! while let Some(event) = events.next_event().await? {
+//!     if let Some(message) = event.new_message() {
+//!         if message.content == "!ping" && message.sender_peer_id != me.peer_id {
+//!             client.send_message(&message.channel_id, "pong").await?;
+//!         }
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod client;
+mod error;
+mod events;
+mod models;
+
+pub use client::Client;
+pub use error::Error;
+pub use events::{Event, EventFilter, EventStream, Subscribed};
+pub use models::*;
@@ -0,0 +1,292 @@
+//! The node's API models. Fields the node may omit are `Option` or default.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Message {
+    pub id: String,
+    pub channel_id: String,
+    pub sender_peer_id: String,
+    pub sender_display_name: String,
+    pub content: String,
+    pub timestamp: String,
+    #[serde(default)]
+    pub edited_at: Option<String>,
+    #[serde(default)]
+    pub deleted_at: Option<String>,
+    #[serde(default)]
+    pub reply_to_id: Option<String>,
+    #[serde(default)]
+    pub thread_id: Option<String>,
+    #[serde(default)]
+    pub webhook_id: Option<String>,
+    #[serde(default)]
+    pub embeds: Vec<MessageEmbed>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MessageEmbed {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<EmbedField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub footer: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbedField {
+    pub name: String,
+    pub value: String,
+    #[serde(default)]
+    pub inline: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Room {
+    pub id: String,
+    pub name: String,
+    pub invite_code: String,
+    pub created_at: String,
+    #[serde(default)]
+    pub owner_peer_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Channel {
+    pub id: String,
+    pub room_id: String,
+    pub name: String,
+    pub created_at: String,
+    pub channel_type: String,
+    #[serde(default)]
+    pub topic: Option<String>,
+    #[serde(default)]
+    pub position: i32,
+    #[serde(default)]
+    pub category_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerInfo {
+    pub peer_id: String,
+    pub display_name: String,
+    pub is_online: bool,
+    #[serde(default)]
+    pub is_bot: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Identity {
+    pub peer_id: String,
+    pub display_name: String,
+    #[serde(default)]
+    pub avatar_hash: Option<String>,
+    #[serde(default)]
+    pub status_message: Option<String>,
+    #[serde(default)]
+    pub status_type: Option<String>,
+}
+
+/// A bot hosted by the node.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bot {
+    pub peer_id: String,
+    pub display_name: String,
+    pub rooms: Vec<String>,
+    pub created_at: String,
+}
+
+/// A newly created bot and its API token.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatedBot {
+    #[serde(flatten)]
+    pub bot: Bot,
+    pub token: String,
+}
+
+/// How a slash command argument is parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandOptionType {
+    /// One word, or a "quoted phrase".
+    String,
+    /// The rest of the input; only valid as the last option.
+    Text,
+    Integer,
+    /// A member mention; the invocation carries the peer ID.
+    User,
+    /// `10m`, `1h30m`, …; the invocation carries seconds.
+    Duration,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandOption {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(rename = "type")]
+    pub option_type: CommandOptionType,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// A slash command: built in (no `bot_peer_id`) or registered by a bot in a room.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlashCommand {
+    pub name: String,
+    pub description: String,
+    #[serde(default)]
+    pub options: Vec<CommandOption>,
+    #[serde(default)]
+    pub bot_peer_id: Option<String>,
+    #[serde(default)]
+    pub room_id: Option<String>,
+}
+
+/// Someone ran a bot's command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandInvocation {
+    pub id: String,
+    pub command: String,
+    pub bot_peer_id: String,
+    pub room_id: String,
+    pub channel_id: String,
+    pub invoker_peer_id: String,
+    pub invoker_display_name: String,
+    pub options: serde_json::Map<String, serde_json::Value>,
+    pub created_at: String,
+}
+
+/// What running a command did.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandResult {
+    #[serde(default)]
+    pub message: Option<Message>,
+    /// Feedback for the invoker only.
+    #[serde(default)]
+    pub response: Option<String>,
+    #[serde(default)]
+    pub invocation: Option<CommandInvocation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DmConversation {
+    pub id: String,
+    pub is_group: bool,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DmMessage {
+    pub id: String,
+    pub conversation_id: String,
+    pub sender_peer_id: String,
+    pub sender_display_name: String,
+    pub content: String,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DmParticipant {
+    pub conversation_id: String,
+    pub peer_id: String,
+    pub joined_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Friend {
+    pub peer_id: String,
+    pub display_name: String,
+    /// `pending_outgoing`, `pending_incoming`, `accepted` or `blocked`.
+    pub status: String,
+    pub created_at: String,
+}
+
+/// The node's voice session.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VoiceState {
+    pub in_voice: bool,
+    #[serde(default)]
+    pub room_id: Option<String>,
+    #[serde(default)]
+    pub channel_id: Option<String>,
+    pub muted: bool,
+    pub deafened: bool,
+    #[serde(default)]
+    pub connected_peers: Vec<String>,
+    #[serde(default)]
+    pub camera_enabled: bool,
+    #[serde(default)]
+    pub screen_sharing: bool,
+}
+
+/// One hit of [`Client::search`](crate::Client::search), tagged by where it was found.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum SearchHit {
+    Channel {
+        message: Message,
+        #[serde(default)]
+        snippet: Option<String>,
+    },
+    Dm {
+        message: DmMessage,
+        #[serde(default)]
+        snippet: Option<String>,
+    },
+    /// A shared file; `file` is its metadata.
+    File {
+        file: serde_json::Value,
+        #[serde(default)]
+        snippet: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResults {
+    pub results: Vec<SearchHit>,
+    pub total: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelUnread {
+    pub channel_id: String,
+    pub unread: i64,
+    pub mentions: i64,
+    #[serde(default)]
+    pub last_message_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomUnread {
+    pub room_id: String,
+    pub unread: i64,
+    pub mentions: i64,
+    pub channels: Vec<ChannelUnread>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DmUnread {
+    pub conversation_id: String,
+    pub unread: i64,
+    #[serde(default)]
+    pub last_message_at: Option<String>,
+}
+
+/// Unread counts since the last read marker of each channel and DM.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UnreadSummary {
+    pub total_unread: i64,
+    pub total_mentions: i64,
+    pub rooms: Vec<RoomUnread>,
+    pub dms: Vec<DmUnread>,
+}
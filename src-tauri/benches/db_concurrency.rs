@@ -0,0 +1,138 @@
+//! Reads while a write transaction is open.
+//!
+//! A writer holds a write transaction for `WRITE_HOLD` while reader threads
+//! page through a channel. With a single connection (the old
+//! `Mutex<Connection>` setup) every read queues behind the writer; with the
+//! WAL-mode pool, reads complete while the write is still in progress.
+//!
+//! Run with `cargo bench --bench db_concurrency`.
+
+use std::path::Path;
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chatr_lib::db::migrations;
+use chatr_lib::db::pool::ConnectionPool;
+use chatr_lib::db::Database;
+use rusqlite::Connection;
+
+const MESSAGES: usize = 20_000;
+const READERS: usize = 3;
+const READS_PER_READER: usize = 20;
+const WRITE_HOLD: Duration = Duration::from_millis(300);
+const CHANNEL_ID: &str = "bench-channel";
+
+fn main() {
+    let dir = std::env::temp_dir().join(format!("chatr-bench-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("create bench dir");
+
+    for (label, pool_size) in [("single connection", 1), ("WAL pool", READERS + 1)] {
+        let path = dir.join(format!("pool-{}.db", pool_size));
+        let db = Arc::new(open(&path, pool_size));
+        seed(&db);
+        let latencies = run(&db);
+        report(label, &latencies);
+    }
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+fn open(path: &Path, pool_size: usize) -> Database {
+    let mut conn = Connection::open(path).expect("open database");
+    migrations::migrate(&mut conn).expect("migrate");
+    Database {
+        pool: ConnectionPool::open(path, conn, pool_size, None).expect("open pool"),
+    }
+}
+
+fn seed(db: &Database) {
+    let mut conn = db.pool.get();
+    let tx = conn.transaction().expect("begin");
+    tx.execute_batch(&format!(
+        "INSERT INTO rooms (id, name, invite_code, created_at) VALUES ('bench-room', 'bench', 'BENCH', '2024-01-01T00:00:00+00:00');
+         INSERT INTO channels (id, room_id, name, created_at) VALUES ('{CHANNEL_ID}', 'bench-room', 'general', '2024-01-01T00:00:00+00:00');"
+    ))
+    .expect("seed room");
+    {
+        let mut stmt = tx
+            .prepare(
+                "INSERT INTO messages (id, channel_id, sender_peer_id, sender_display_name, content, timestamp)
+                 VALUES (?1, ?2, 'peer', 'Bench', ?3, ?4)",
+            )
+            .expect("prepare insert");
+        for i in 0..MESSAGES {
+            stmt.execute(rusqlite::params![
+                format!("msg-{i}"),
+                CHANNEL_ID,
+                format!("benchmark message number {i} about deploys and releases"),
+                format!("2024-01-01T00:00:00.{i:06}+00:00"),
+            ])
+            .expect("insert message");
+        }
+    }
+    tx.commit().expect("commit seed");
+}
+
+/// Returns the latency of every read issued while the writer held its transaction.
+fn run(db: &Arc<Database>) -> Vec<Duration> {
+    let started = Arc::new(Barrier::new(READERS + 1));
+
+    let writer = {
+        let db = Arc::clone(db);
+        let started = Arc::clone(&started);
+        thread::spawn(move || {
+            let conn = db.pool.get();
+            conn.execute_batch("BEGIN IMMEDIATE").expect("begin write");
+            conn.execute(
+                "INSERT INTO messages (id, channel_id, sender_peer_id, sender_display_name, content, timestamp)
+                 VALUES ('slow-write', ?1, 'peer', 'Bench', 'written slowly', '2024-01-02T00:00:00+00:00')",
+                [CHANNEL_ID],
+            )
+            .expect("write");
+            started.wait();
+            thread::sleep(WRITE_HOLD);
+            conn.execute_batch("COMMIT").expect("commit write");
+        })
+    };
+
+    // Readers are only released once the write transaction is open
+    let readers: Vec<_> = (0..READERS)
+        .map(|_| {
+            let db = Arc::clone(db);
+            let started = Arc::clone(&started);
+            thread::spawn(move || {
+                started.wait();
+                (0..READS_PER_READER)
+                    .map(|_| {
+                        let t = Instant::now();
+                        db.get_messages(CHANNEL_ID, 50, None).expect("read");
+                        t.elapsed()
+                    })
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+
+    let latencies = readers
+        .into_iter()
+        .flat_map(|r| r.join().expect("reader thread"))
+        .collect();
+    writer.join().expect("writer thread");
+    latencies
+}
+
+fn report(label: &str, latencies: &[Duration]) {
+    let mut sorted = latencies.to_vec();
+    sorted.sort();
+    let median = sorted[sorted.len() / 2];
+    let max = *sorted.last().unwrap();
+    let blocked = sorted.iter().filter(|d| **d >= WRITE_HOLD / 2).count();
+    println!(
+        "{label:>18}: {} reads, median {:>9.2?}, max {:>9.2?}, {blocked} waited on the writer ({:?} hold)",
+        sorted.len(),
+        median,
+        max,
+        WRITE_HOLD,
+    );
+}
@@ -0,0 +1,341 @@
+//! Token authentication and CORS for the local API.
+//!
+//! Every request, `/ws` and the MJPEG frame routes included, needs a token
+//! issued by this node: `Authorization: Bearer <token>`, or for GETs of `/ws`
+//! and `/media/*`, which browsers can't add headers to, `?access_token=<token>`.
+//! Tokens are random secrets; only their SHA-256 is stored. Incoming webhook
+//! endpoints are the exception: the token in their path is their credential.
+//! The OpenAPI document is public too.
+//!
+//! A bot's token acts as the bot: it reaches only [`BOT_ROUTES`], and only in
+//! the bot's rooms.
+
+use axum::{
+    extract::{MatchedPath, RawPathParams, Request, State},
+    http::{header, HeaderValue, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::api::openapi::OPENAPI_PATH;
+use crate::db::Database;
+use crate::models::{ApiScope, ApiToken, IssuedApiToken};
+use crate::services::ServiceError;
+use crate::state::ServiceContext;
+
+/// Name of the token the GUI is issued on every start; older ones are revoked.
+pub const GUI_TOKEN_NAME: &str = "gui";
+
+/// Environment variable with extra comma-separated origins allowed by CORS.
+pub const ALLOWED_ORIGINS_ENV: &str = "CHATR_API_ORIGINS";
+
+/// Origins of the Tauri webview (per platform) and the Vite dev server.
+const DEFAULT_ORIGINS: &[&str] = &[
+    "tauri://localhost",
+    "http://tauri.localhost",
+    "https://tauri.localhost",
+    "http://localhost:1420",
+];
+
+const TOKEN_PREFIX: &str = "chatr_";
+
+/// Incoming webhook endpoints, authenticated by their own token.
+const WEBHOOK_EXECUTE_PREFIX: &str = "/api/v1/hooks/";
+
+/// What a bot token may call: `(method, route)`.
+pub const BOT_ROUTES: &[(Method, &str)] = &[
+    (Method::GET, "/api/v1/bot"),
+    (Method::GET, "/api/v1/bot/commands"),
+    (Method::PUT, "/api/v1/bot/rooms/:room_id/commands/:name"),
+    (Method::DELETE, "/api/v1/bot/rooms/:room_id/commands/:name"),
+    (Method::GET, "/api/v1/rooms"),
+    (Method::GET, "/api/v1/rooms/:room_id/channels"),
+    (Method::GET, "/api/v1/rooms/:room_id/layout"),
+    (Method::GET, "/api/v1/rooms/:room_id/peers"),
+    (Method::GET, "/api/v1/channels/:channel_id/messages"),
+    (Method::POST, "/api/v1/channels/:channel_id/messages"),
+    (Method::DELETE, "/api/v1/messages/:message_id"),
+];
+
+/// Whether a bot token may call `route` (the matched pattern) with `method`.
+fn bot_may_call(method: &Method, route: &str) -> bool {
+    BOT_ROUTES.iter().any(|(m, r)| m == method && *r == route)
+}
+
+/// Scopes granted to a request, available to handlers as an extension.
+#[derive(Debug, Clone)]
+pub struct Authorized {
+    pub scopes: Vec<ApiScope>,
+    /// Set for bot tokens: the bot the request acts as.
+    pub bot: Option<String>,
+}
+
+impl Authorized {
+    pub fn allows(&self, scope: ApiScope) -> bool {
+        self.scopes.iter().any(|s| *s >= scope)
+    }
+}
+
+pub fn hash_token(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+/// Create a token with `scopes`, acting as `bot_peer_id` if set, and store its hash.
+pub fn issue_token(db: &Database, name: &str, scopes: Vec<ApiScope>, bot_peer_id: Option<&str>) -> Result<IssuedApiToken, ServiceError> {
+    if scopes.is_empty() {
+        return Err(ServiceError::bad_request("A token needs at least one scope"));
+    }
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let secret = format!("{}{}", TOKEN_PREFIX, hex::encode(bytes));
+    let token = ApiToken {
+        id: Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        scopes,
+        created_at: Utc::now().to_rfc3339(),
+        last_used_at: None,
+        bot_peer_id: bot_peer_id.map(str::to_string),
+    };
+    db.insert_api_token(&token, &hash_token(&secret)).map_err(ServiceError::internal)?;
+    Ok(IssuedApiToken { token, secret })
+}
+
+/// Replace the GUI's token with a fresh admin one.
+pub fn issue_gui_token(db: &Database) -> Result<String, ServiceError> {
+    db.revoke_api_tokens_named(GUI_TOKEN_NAME).map_err(ServiceError::internal)?;
+    issue_token(db, GUI_TOKEN_NAME, vec![ApiScope::Admin], None).map(|issued| issued.secret)
+}
+
+/// Scope a request needs: identity keys, devices, backups, tokens, bots and webhooks are
+/// admin; other reads are read; everything else is write.
+pub fn required_scope(method: &Method, path: &str) -> ApiScope {
+    const ADMIN_PREFIXES: &[&str] = &[
+        "/api/v1/tokens",
+        "/api/v1/bots",
+        "/api/v1/webhooks",
+        "/api/v1/incoming-webhooks",
+        "/api/v1/backup",
+        "/api/v1/devices",
+        "/api/v1/identity/passphrase",
+        "/api/v1/identity/recovery-phrase",
+        "/api/v1/identity/import",
+        "/api/v1/identity/rotate",
+    ];
+    if ADMIN_PREFIXES.iter().any(|prefix| path.starts_with(prefix)) {
+        ApiScope::Admin
+    } else if method == Method::GET || method == Method::HEAD {
+        ApiScope::Read
+    } else {
+        ApiScope::Write
+    }
+}
+
+fn bearer_token(req: &Request) -> Option<String> {
+    let value = req.headers().get(header::AUTHORIZATION)?.to_str().ok()?;
+    value.strip_prefix("Bearer ").map(|t| t.trim().to_string())
+}
+
+fn query_token(req: &Request) -> Option<String> {
+    let path = req.uri().path();
+    if req.method() != Method::GET || !(path == "/ws" || path.starts_with("/media/")) {
+        return None;
+    }
+    req.uri()
+        .query()?
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("access_token="))
+        .map(str::to_string)
+}
+
+/// Reject requests without a valid token carrying the scope they need.
+pub async fn require_token(State(ctx): State<ServiceContext>, mut req: Request, next: Next) -> Response {
+    if req.method() == Method::POST && req.uri().path().starts_with(WEBHOOK_EXECUTE_PREFIX) {
+        return next.run(req).await;
+    }
+    if req.method() == Method::GET && req.uri().path() == OPENAPI_PATH {
+        return next.run(req).await;
+    }
+    let Some(secret) = bearer_token(&req).or_else(|| query_token(&req)) else {
+        return ServiceError::unauthorized("Missing API token").into_response();
+    };
+    let token_hash = hash_token(&secret);
+    let now = Utc::now().to_rfc3339();
+    let (scopes, bot) = match ctx.db.run(move |db| db.authenticate_api_token(&token_hash, &now)).await {
+        Ok(Some(token)) => token,
+        Ok(None) => return ServiceError::unauthorized("Invalid API token").into_response(),
+        Err(e) => return ServiceError::internal(e).into_response(),
+    };
+    let authorized = Authorized { scopes, bot };
+    let needed = required_scope(req.method(), req.uri().path());
+    if !authorized.allows(needed) {
+        return ServiceError::forbidden(format!("Token lacks the '{}' scope", needed.as_str())).into_response();
+    }
+    req.extensions_mut().insert(authorized);
+    next.run(req).await
+}
+
+/// The room a bot request touches, from the route's first path parameter.
+/// Deleting a message also requires the bot to be its author.
+async fn bot_request_room(ctx: &ServiceContext, bot: &str, method: &Method, params: &RawPathParams) -> Result<Option<String>, Response> {
+    let Some((name, value)) = params.iter().next() else {
+        return Ok(None);
+    };
+    let value = value.to_string();
+    let lookup = match name {
+        "room_id" => return Ok(Some(value)),
+        "channel_id" => ctx.db.run(move |db| db.get_room_id_for_channel(&value)).await,
+        "message_id" => {
+            let message = match ctx.db.run(move |db| db.get_message(&value)).await {
+                Ok(Some(message)) => message,
+                Ok(None) => return Err(ServiceError::not_found("Message not found").into_response()),
+                Err(e) => return Err(ServiceError::internal(e).into_response()),
+            };
+            if method != Method::GET && message.sender_peer_id != bot {
+                return Err(ServiceError::forbidden("Bots can only change their own messages").into_response());
+            }
+            ctx.db.run(move |db| db.get_room_id_for_channel(&message.channel_id)).await
+        }
+        _ => return Ok(None),
+    };
+    match lookup {
+        Ok(Some(room_id)) => Ok(Some(room_id)),
+        Ok(None) => Err(ServiceError::not_found("Channel not found").into_response()),
+        Err(e) => Err(ServiceError::internal(e).into_response()),
+    }
+}
+
+/// Keep bot tokens to [`BOT_ROUTES`] in the bot's rooms. Runs on the REST
+/// router itself so WebSocket RPC calls are checked too.
+pub async fn restrict_bot_tokens(
+    State(ctx): State<ServiceContext>,
+    matched: MatchedPath,
+    params: RawPathParams,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Some(bot) = req.extensions().get::<Authorized>().and_then(|a| a.bot.clone()) else {
+        return next.run(req).await;
+    };
+    if !bot_may_call(req.method(), matched.as_str()) {
+        return ServiceError::forbidden("Not available to bot tokens").into_response();
+    }
+    let room_id = match bot_request_room(&ctx, &bot, req.method(), &params).await {
+        Ok(room_id) => room_id,
+        Err(response) => return response,
+    };
+    if let Some(room_id) = room_id {
+        let rooms = match ctx.db.run(move |db| db.get_bot(&bot)).await {
+            Ok(Some(bot)) => bot.rooms,
+            Ok(None) => return ServiceError::unauthorized("Bot no longer exists").into_response(),
+            Err(e) => return ServiceError::internal(e).into_response(),
+        };
+        if !rooms.contains(&room_id) {
+            return ServiceError::forbidden("The bot is not in this room").into_response();
+        }
+    }
+    next.run(req).await
+}
+
+/// CORS for the Tauri webview plus origins from [`ALLOWED_ORIGINS_ENV`].
+pub fn cors_layer() -> CorsLayer {
+    let extra = std::env::var(ALLOWED_ORIGINS_ENV).unwrap_or_default();
+    let origins: Vec<HeaderValue> = DEFAULT_ORIGINS
+        .iter()
+        .copied()
+        .chain(extra.split(',').map(str::trim).filter(|o| !o.is_empty()))
+        .filter_map(|origin| match HeaderValue::from_str(origin) {
+            Ok(value) => Some(value),
+            Err(_) => {
+                warn!("Ignoring invalid API origin '{}'", origin);
+                None
+            }
+        })
+        .collect();
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods(Any)
+        .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
+        .expose_headers([header::CONTENT_DISPOSITION])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn authorized(scopes: &[ApiScope]) -> Authorized {
+        Authorized { scopes: scopes.to_vec(), bot: None }
+    }
+
+    #[test]
+    fn scopes_are_ordered() {
+        assert!(ApiScope::Read < ApiScope::Write && ApiScope::Write < ApiScope::Admin);
+    }
+
+    #[test]
+    fn higher_scopes_imply_lower_ones() {
+        let admin = authorized(&[ApiScope::Admin]);
+        assert!(admin.allows(ApiScope::Read) && admin.allows(ApiScope::Write) && admin.allows(ApiScope::Admin));
+        let write = authorized(&[ApiScope::Write]);
+        assert!(write.allows(ApiScope::Read) && write.allows(ApiScope::Write));
+        assert!(!write.allows(ApiScope::Admin));
+        let read = authorized(&[ApiScope::Read]);
+        assert!(!read.allows(ApiScope::Write));
+        assert!(!authorized(&[]).allows(ApiScope::Read));
+    }
+
+    #[test]
+    fn required_scope_by_method_and_path() {
+        assert_eq!(required_scope(&Method::GET, "/api/v1/rooms"), ApiScope::Read);
+        assert_eq!(required_scope(&Method::HEAD, "/api/v1/rooms"), ApiScope::Read);
+        assert_eq!(required_scope(&Method::POST, "/api/v1/rooms"), ApiScope::Write);
+        assert_eq!(required_scope(&Method::DELETE, "/api/v1/messages/m1"), ApiScope::Write);
+        // Reading the identity is fine, its secrets are not
+        assert_eq!(required_scope(&Method::GET, "/api/v1/identity"), ApiScope::Read);
+        assert_eq!(required_scope(&Method::GET, "/api/v1/identity/recovery-phrase"), ApiScope::Admin);
+        assert_eq!(required_scope(&Method::GET, "/api/v1/tokens"), ApiScope::Admin);
+        assert_eq!(required_scope(&Method::GET, "/api/v1/backup"), ApiScope::Admin);
+        assert_eq!(required_scope(&Method::DELETE, "/api/v1/webhooks/w1"), ApiScope::Admin);
+    }
+
+    #[test]
+    fn bot_tokens_reach_only_the_allow_list() {
+        assert!(bot_may_call(&Method::POST, "/api/v1/channels/:channel_id/messages"));
+        assert!(bot_may_call(&Method::GET, "/api/v1/rooms"));
+        assert!(bot_may_call(&Method::PUT, "/api/v1/bot/rooms/:room_id/commands/:name"));
+        // Matched against the route pattern, not the concrete path
+        assert!(!bot_may_call(&Method::POST, "/api/v1/channels/c1/messages"));
+        assert!(!bot_may_call(&Method::POST, "/api/v1/rooms"));
+        assert!(!bot_may_call(&Method::PUT, "/api/v1/messages/:message_id"));
+        assert!(!bot_may_call(&Method::GET, "/api/v1/tokens"));
+        assert!(!bot_may_call(&Method::POST, "/api/v1/bots"));
+        assert!(!bot_may_call(&Method::GET, "/api/v1/dms"));
+    }
+
+    #[test]
+    fn bot_routes_are_rpc_routes_without_admin_scope() {
+        for (method, route) in BOT_ROUTES {
+            assert!(
+                crate::api::rpc::RPC_METHODS.iter().any(|(_, m, r)| m == method && r == route),
+                "{} {} is not a known route",
+                method,
+                route
+            );
+            assert_ne!(required_scope(method, route), ApiScope::Admin, "{} {}", method, route);
+        }
+    }
+
+    #[test]
+    fn query_tokens_only_for_socket_and_media_gets() {
+        let request = |method: Method, uri: &str| Request::builder().method(method).uri(uri).body(axum::body::Body::empty()).unwrap();
+        assert_eq!(query_token(&request(Method::GET, "/ws?access_token=chatr_x")).as_deref(), Some("chatr_x"));
+        assert_eq!(query_token(&request(Method::GET, "/media/f1?a=1&access_token=chatr_x")).as_deref(), Some("chatr_x"));
+        assert_eq!(query_token(&request(Method::GET, "/api/v1/rooms?access_token=chatr_x")), None);
+        assert_eq!(query_token(&request(Method::POST, "/ws?access_token=chatr_x")), None);
+    }
+}
@@ -0,0 +1,44 @@
+//! Error responses of the REST API.
+//!
+//! Every error is a [`ServiceError`] answered with its status code and a JSON
+//! body: `{"error": "not_found", "message": "Channel not found"}`.
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::services::ServiceError;
+
+/// The body of every error response.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ErrorBody {
+    /// Machine-readable kind: `bad_request`, `unauthorized`, `forbidden`,
+    /// `not_found`, `conflict` or `internal`.
+    pub error: String,
+    pub message: String,
+}
+
+pub fn status_code(error: &ServiceError) -> StatusCode {
+    match error {
+        ServiceError::BadRequest(_) => StatusCode::BAD_REQUEST,
+        ServiceError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+        ServiceError::Forbidden(_) => StatusCode::FORBIDDEN,
+        ServiceError::NotFound(_) => StatusCode::NOT_FOUND,
+        ServiceError::Conflict(_) => StatusCode::CONFLICT,
+        ServiceError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+impl IntoResponse for ServiceError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            error: self.code().to_string(),
+            message: self.message().to_string(),
+        };
+        (status_code(&self), Json(body)).into_response()
+    }
+}
@@ -1,3 +1,7 @@
+pub mod auth;
+pub mod error;
+pub mod openapi;
 pub mod routes;
+pub mod rpc;
 pub mod server;
 pub mod websocket;
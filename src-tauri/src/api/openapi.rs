@@ -0,0 +1,570 @@
+//! OpenAPI 3 description of the REST API, served without a token at [`OPENAPI_PATH`].
+//!
+//! Each operation below names its request, query and response types; their
+//! schemas come from the `JsonSchema` derives, path parameters from the
+//! route's `:name` segments, and operation ids from [`RPC_METHODS`]. Keep the
+//! table in step with `api::server::rest_routes`.
+
+use std::sync::OnceLock;
+
+use axum::{http::Method, Json};
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::{ObjectValidation, Schema, SchemaObject};
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+
+use crate::api::error::ErrorBody;
+use crate::api::routes::{
+    api_tokens, backup, bots, channels, commands, devices, dms, emoji, export, files, friends, identity, import,
+    incoming_webhooks, messaging, moderation, notifications, retention, roles, rooms, settings, threads, voice,
+    webhooks,
+};
+use crate::api::rpc::RPC_METHODS;
+use crate::models::{
+    ApiToken, BlockedPeer, Bot, Channel, ChannelCategory, ChannelLayout, CommandResult, CommandSuggestion,
+    CreatedBot, CreatedIncomingWebhook, CreatedWebhook, CustomEmoji, DeviceCertificate, DeviceLinkRequest,
+    DmConversation, DmMessage, DmParticipant, FileMetadata, Friend, Identity, ImportSummary, IncomingWebhook,
+    IssuedApiToken, KeyRotation, LinkedDevice, Message, MessageMention, ModerationAction, NotificationSetting,
+    PeerInfo, PinnedMessage, Reaction, ReadReceipt, RetentionPolicy, Room, RoomRole, RoomUnread, SearchResult,
+    Setting, SlashCommand, ThreadInfo, UnifiedSearchResult, UnreadSummary, Webhook, WebhookDelivery,
+};
+
+/// Where the document is served.
+pub const OPENAPI_PATH: &str = "/api/v1/openapi.json";
+
+type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+
+fn schema<T: JsonSchema>(gen: &mut SchemaGenerator) -> Schema {
+    gen.subschema_for::<T>()
+}
+
+/// Untyped JSON, for handlers answering with small ad-hoc objects like `{"deleted": true}`.
+type Object = Value;
+
+struct Operation {
+    method: Method,
+    path: &'static str,
+    summary: &'static str,
+    body: Option<SchemaFn>,
+    query: Option<SchemaFn>,
+    response: Option<SchemaFn>,
+    status: u16,
+    /// Answers with a file download rather than JSON.
+    file: bool,
+}
+
+impl Operation {
+    fn new(method: Method, path: &'static str, summary: &'static str) -> Self {
+        Self { method, path, summary, body: None, query: None, response: None, status: 200, file: false }
+    }
+
+    fn body<T: JsonSchema>(mut self) -> Self {
+        self.body = Some(schema::<T>);
+        self
+    }
+
+    fn query<T: JsonSchema>(mut self) -> Self {
+        self.query = Some(schema::<T>);
+        self
+    }
+
+    fn returns<T: JsonSchema>(mut self) -> Self {
+        self.response = Some(schema::<T>);
+        self
+    }
+
+    /// Answers `201 Created` with `T`.
+    fn creates<T: JsonSchema>(mut self) -> Self {
+        self.status = 201;
+        self.returns::<T>()
+    }
+
+    fn file(mut self) -> Self {
+        self.file = true;
+        self
+    }
+}
+
+fn get(path: &'static str, summary: &'static str) -> Operation {
+    Operation::new(Method::GET, path, summary)
+}
+
+fn post(path: &'static str, summary: &'static str) -> Operation {
+    Operation::new(Method::POST, path, summary)
+}
+
+fn put(path: &'static str, summary: &'static str) -> Operation {
+    Operation::new(Method::PUT, path, summary)
+}
+
+fn delete(path: &'static str, summary: &'static str) -> Operation {
+    Operation::new(Method::DELETE, path, summary)
+}
+
+/// Every REST operation, grouped by tag.
+fn operations() -> Vec<(&'static str, Vec<Operation>)> {
+    vec![
+        (
+            "Identity",
+            vec![
+                get("/api/v1/identity", "The node's identity").returns::<Identity>(),
+                put("/api/v1/identity/display-name", "Set the display name")
+                    .body::<identity::SetDisplayNameRequest>()
+                    .returns::<Object>(),
+                put("/api/v1/identity/status", "Set the status").body::<identity::SetStatusRequest>().returns::<Object>(),
+                put("/api/v1/identity/avatar", "Set the avatar").body::<identity::SetAvatarRequest>().returns::<Object>(),
+                get("/api/v1/identity/encryption", "Whether the database is encrypted at rest").returns::<Object>(),
+                put("/api/v1/identity/passphrase", "Set, change or remove the database passphrase")
+                    .body::<identity::ChangePassphraseRequest>()
+                    .returns::<Object>(),
+                get("/api/v1/identity/recovery-phrase", "The identity's recovery phrase").returns::<Object>(),
+                post("/api/v1/identity/import", "Restore an identity from a recovery phrase")
+                    .body::<identity::ImportIdentityRequest>()
+                    .returns::<Object>(),
+                post("/api/v1/identity/rotate", "Rotate the identity key").returns::<KeyRotation>(),
+            ],
+        ),
+        (
+            "API tokens",
+            vec![
+                get("/api/v1/tokens", "List tokens").returns::<Vec<ApiToken>>(),
+                post("/api/v1/tokens", "Issue a token").body::<api_tokens::IssueTokenRequest>().returns::<IssuedApiToken>(),
+                delete("/api/v1/tokens/:token_id", "Revoke a token").returns::<Object>(),
+            ],
+        ),
+        (
+            "Bots",
+            vec![
+                get("/api/v1/bots", "List bots").returns::<Vec<Bot>>(),
+                post("/api/v1/bots", "Create a bot").body::<bots::BotNameRequest>().creates::<CreatedBot>(),
+                get("/api/v1/bots/:bot_peer_id", "Get a bot").returns::<Bot>(),
+                put("/api/v1/bots/:bot_peer_id", "Rename a bot").body::<bots::BotNameRequest>().returns::<Bot>(),
+                delete("/api/v1/bots/:bot_peer_id", "Delete a bot").returns::<Object>(),
+                post("/api/v1/bots/:bot_peer_id/token", "Replace a bot's token").returns::<Object>(),
+                put("/api/v1/bots/:bot_peer_id/rooms/:room_id", "Add a bot to a room").returns::<Bot>(),
+                delete("/api/v1/bots/:bot_peer_id/rooms/:room_id", "Remove a bot from a room").returns::<Bot>(),
+                get("/api/v1/bot", "The bot a bot token acts as").returns::<Bot>(),
+                get("/api/v1/bot/commands", "Commands the bot registered").returns::<Vec<SlashCommand>>(),
+                put("/api/v1/bot/rooms/:room_id/commands/:name", "Register or replace a command")
+                    .body::<commands::RegisterCommandRequest>()
+                    .returns::<SlashCommand>(),
+                delete("/api/v1/bot/rooms/:room_id/commands/:name", "Unregister a command").returns::<Object>(),
+            ],
+        ),
+        (
+            "Webhooks",
+            vec![
+                get("/api/v1/webhooks", "List webhooks").returns::<Vec<Webhook>>(),
+                post("/api/v1/webhooks", "Create a webhook")
+                    .body::<webhooks::CreateWebhookRequest>()
+                    .creates::<CreatedWebhook>(),
+                get("/api/v1/webhooks/:webhook_id", "Get a webhook").returns::<Webhook>(),
+                put("/api/v1/webhooks/:webhook_id", "Update a webhook")
+                    .body::<webhooks::UpdateWebhookRequest>()
+                    .returns::<Webhook>(),
+                delete("/api/v1/webhooks/:webhook_id", "Delete a webhook").returns::<Object>(),
+                get("/api/v1/webhooks/:webhook_id/deliveries", "Recent delivery attempts")
+                    .query::<webhooks::GetDeliveriesQuery>()
+                    .returns::<Vec<WebhookDelivery>>(),
+                post("/api/v1/webhooks/:webhook_id/test", "Send a test event").returns::<WebhookDelivery>(),
+            ],
+        ),
+        (
+            "Incoming webhooks",
+            vec![
+                get("/api/v1/incoming-webhooks", "List incoming webhooks")
+                    .query::<incoming_webhooks::ListIncomingWebhooksQuery>()
+                    .returns::<Vec<IncomingWebhook>>(),
+                post("/api/v1/incoming-webhooks", "Create an incoming webhook")
+                    .body::<incoming_webhooks::CreateIncomingWebhookRequest>()
+                    .creates::<CreatedIncomingWebhook>(),
+                delete("/api/v1/incoming-webhooks/:webhook_id", "Revoke an incoming webhook").returns::<Object>(),
+                post("/api/v1/hooks/:webhook_id/:token", "Post through an incoming webhook; the token is the credential")
+                    .body::<incoming_webhooks::ExecuteWebhookRequest>()
+                    .creates::<Message>(),
+            ],
+        ),
+        (
+            "Devices",
+            vec![
+                get("/api/v1/devices", "List linked devices").returns::<Vec<LinkedDevice>>(),
+                post("/api/v1/devices", "Link a device from its link request")
+                    .body::<DeviceLinkRequest>()
+                    .returns::<DeviceCertificate>(),
+                get("/api/v1/devices/link-request", "This device's link request")
+                    .query::<devices::LinkRequestQuery>()
+                    .returns::<DeviceLinkRequest>(),
+                post("/api/v1/devices/accept", "Accept a device certificate").body::<DeviceCertificate>().returns::<Object>(),
+                delete("/api/v1/devices/:device_peer_id", "Unlink a device").returns::<Object>(),
+            ],
+        ),
+        (
+            "Import and backup",
+            vec![
+                post("/api/v1/import/discord", "Import DiscordChatExporter history")
+                    .body::<import::DiscordImportRequest>()
+                    .returns::<ImportSummary>(),
+                post("/api/v1/backup", "Download an encrypted backup").body::<backup::CreateBackupRequest>().file(),
+                get("/api/v1/rooms/:room_id/export", "Download a room's history").query::<export::ExportQuery>().file(),
+            ],
+        ),
+        (
+            "Rooms",
+            vec![
+                get("/api/v1/rooms", "List rooms").returns::<Vec<Room>>(),
+                post("/api/v1/rooms", "Create a room").body::<rooms::CreateRoomRequest>().creates::<Room>(),
+                post("/api/v1/rooms/join", "Join a room by invite code").body::<rooms::JoinRoomRequest>().returns::<Room>(),
+                delete("/api/v1/rooms/:room_id/messages/mine", "Delete my messages in a room").returns::<Object>(),
+                get("/api/v1/rooms/:room_id/retention", "The room's retention policy").returns::<Option<RetentionPolicy>>(),
+                put("/api/v1/rooms/:room_id/retention", "Set the room's retention policy")
+                    .body::<retention::RetentionRequest>()
+                    .returns::<RetentionPolicy>(),
+                get("/api/v1/rooms/:room_id/channels", "List channels").returns::<Vec<Channel>>(),
+                post("/api/v1/rooms/:room_id/channels", "Create a channel")
+                    .body::<channels::CreateChannelRequest>()
+                    .creates::<Channel>(),
+                get("/api/v1/rooms/:room_id/categories", "List categories").returns::<Vec<ChannelCategory>>(),
+                post("/api/v1/rooms/:room_id/categories", "Create a category")
+                    .body::<channels::CreateCategoryRequest>()
+                    .creates::<ChannelCategory>(),
+                get("/api/v1/rooms/:room_id/layout", "Categories and channels in order").returns::<ChannelLayout>(),
+                get("/api/v1/rooms/:room_id/unread", "Unread counts in a room").returns::<RoomUnread>(),
+                get("/api/v1/rooms/:room_id/peers", "Peers in a room").returns::<Vec<PeerInfo>>(),
+                get("/api/v1/rooms/:room_id/roles", "Roles in a room").returns::<Vec<RoomRole>>(),
+                post("/api/v1/rooms/:room_id/roles", "Set a peer's role").body::<roles::SetRoleRequest>().creates::<RoomRole>(),
+                delete("/api/v1/rooms/:room_id/roles/:peer_id", "Remove a peer's role").returns::<Object>(),
+                post("/api/v1/rooms/:room_id/moderate", "Kick, ban, warn or mute a peer")
+                    .body::<moderation::ModerateRequest>()
+                    .creates::<ModerationAction>(),
+                get("/api/v1/rooms/:room_id/audit-log", "Moderation actions").returns::<Vec<ModerationAction>>(),
+                get("/api/v1/rooms/:room_id/emoji", "Custom emoji").returns::<Vec<CustomEmoji>>(),
+                post("/api/v1/rooms/:room_id/emoji", "Add a custom emoji").body::<emoji::AddEmojiRequest>().creates::<CustomEmoji>(),
+            ],
+        ),
+        (
+            "Channels",
+            vec![
+                put("/api/v1/channels/:channel_id", "Update a channel")
+                    .body::<channels::UpdateChannelRequest>()
+                    .returns::<Object>(),
+                delete("/api/v1/channels/:channel_id", "Delete a channel").returns::<Object>(),
+                put("/api/v1/channels/:channel_id/category", "Move a channel")
+                    .body::<channels::MoveChannelRequest>()
+                    .returns::<Channel>(),
+                get("/api/v1/channels/:channel_id/retention", "The channel's retention policy")
+                    .returns::<Option<RetentionPolicy>>(),
+                put("/api/v1/channels/:channel_id/retention", "Set the channel's retention policy")
+                    .body::<retention::RetentionRequest>()
+                    .returns::<RetentionPolicy>(),
+                get("/api/v1/channels/:channel_id/permissions", "My effective permissions").returns::<Object>(),
+                put("/api/v1/channels/:channel_id/permissions", "Set the channel's permission overwrites")
+                    .body::<channels::ChannelPermissionsRequest>()
+                    .returns::<Channel>(),
+                get("/api/v1/channels/:channel_id/threads", "Threads in a channel").returns::<Vec<ThreadInfo>>(),
+                get("/api/v1/channels/:channel_id/commands", "Slash commands available in a channel")
+                    .returns::<Vec<SlashCommand>>(),
+                post("/api/v1/channels/:channel_id/commands", "Run a slash command")
+                    .body::<commands::ExecuteCommandRequest>()
+                    .returns::<CommandResult>(),
+                get("/api/v1/channels/:channel_id/commands/autocomplete", "Complete a partly typed command")
+                    .query::<commands::AutocompleteQuery>()
+                    .returns::<Vec<CommandSuggestion>>(),
+                post("/api/v1/channels/:channel_id/typing", "Send a typing indicator")
+                    .body::<messaging::TypingRequest>()
+                    .returns::<Object>(),
+                post("/api/v1/channels/:channel_id/read", "Mark a channel read")
+                    .body::<messaging::MarkReadRequest>()
+                    .returns::<Object>(),
+                get("/api/v1/channels/:channel_id/read-receipts", "Read receipts").returns::<Vec<ReadReceipt>>(),
+                get("/api/v1/channels/:channel_id/pins", "Pinned messages").returns::<Vec<PinnedMessage>>(),
+                post("/api/v1/channels/:channel_id/pins", "Pin a message")
+                    .body::<messaging::PinRequest>()
+                    .creates::<PinnedMessage>(),
+                delete("/api/v1/channels/:channel_id/pins/:message_id", "Unpin a message").returns::<Object>(),
+                put("/api/v1/categories/:category_id", "Update a category")
+                    .body::<channels::UpdateCategoryRequest>()
+                    .returns::<ChannelCategory>(),
+                delete("/api/v1/categories/:category_id", "Delete a category").returns::<Object>(),
+                put("/api/v1/categories/:category_id/collapsed", "Collapse or expand a category")
+                    .body::<channels::CollapseCategoryRequest>()
+                    .returns::<Object>(),
+            ],
+        ),
+        (
+            "Messages",
+            vec![
+                get("/api/v1/channels/:channel_id/messages", "A page of messages")
+                    .query::<messaging::GetMessagesQuery>()
+                    .returns::<Vec<Message>>(),
+                post("/api/v1/channels/:channel_id/messages", "Send a message")
+                    .body::<messaging::SendMessageRequest>()
+                    .creates::<Message>(),
+                put("/api/v1/messages/:message_id", "Edit a message")
+                    .body::<messaging::EditMessageRequest>()
+                    .returns::<Object>(),
+                delete("/api/v1/messages/:message_id", "Delete a message").returns::<Object>(),
+                get("/api/v1/messages/:message_id/reactions", "Reactions").returns::<Vec<Reaction>>(),
+                post("/api/v1/messages/:message_id/reactions", "React")
+                    .body::<messaging::ReactionRequest>()
+                    .creates::<Reaction>(),
+                delete("/api/v1/messages/:message_id/reactions/:emoji", "Remove my reaction").returns::<Object>(),
+                get("/api/v1/messages/:message_id/attachments", "Attached files").returns::<Vec<FileMetadata>>(),
+                post("/api/v1/messages/:message_id/attachments", "Attach a file")
+                    .body::<files::AttachFileRequest>()
+                    .returns::<Object>(),
+                get("/api/v1/messages/:message_id/mentions", "Mentions in a message").returns::<Vec<MessageMention>>(),
+                get("/api/v1/search", "Search messages, DMs and files")
+                    .query::<messaging::UnifiedSearchQuery>()
+                    .returns::<UnifiedSearchResult>(),
+                get("/api/v1/search/messages", "Search channel messages")
+                    .query::<messaging::SearchQuery>()
+                    .returns::<SearchResult>(),
+            ],
+        ),
+        (
+            "Threads",
+            vec![
+                post("/api/v1/messages/:message_id/thread", "Start a thread")
+                    .body::<threads::CreateThreadRequest>()
+                    .creates::<ThreadInfo>(),
+                get("/api/v1/threads/subscribed", "Threads I follow").returns::<Vec<ThreadInfo>>(),
+                get("/api/v1/threads/:thread_id", "Get a thread").returns::<ThreadInfo>(),
+                get("/api/v1/threads/:thread_id/messages", "Replies in a thread")
+                    .query::<threads::GetThreadMessagesQuery>()
+                    .returns::<Vec<Message>>(),
+                post("/api/v1/threads/:thread_id/messages", "Reply in a thread")
+                    .body::<threads::ThreadReplyRequest>()
+                    .creates::<Message>(),
+                put("/api/v1/threads/:thread_id/subscription", "Follow a thread").returns::<Object>(),
+                delete("/api/v1/threads/:thread_id/subscription", "Unfollow a thread").returns::<Object>(),
+            ],
+        ),
+        (
+            "DMs",
+            vec![
+                get("/api/v1/dms", "List conversations").returns::<Vec<DmConversation>>(),
+                post("/api/v1/dms", "Start a conversation").body::<dms::CreateDmRequest>().creates::<DmConversation>(),
+                get("/api/v1/dms/:conversation_id/participants", "Participants").returns::<Vec<DmParticipant>>(),
+                get("/api/v1/dms/:conversation_id/timer", "The disappearing-message timer")
+                    .returns::<Option<RetentionPolicy>>(),
+                put("/api/v1/dms/:conversation_id/timer", "Set the disappearing-message timer")
+                    .body::<retention::DmTimerRequest>()
+                    .returns::<RetentionPolicy>(),
+                get("/api/v1/dms/:conversation_id/messages", "A page of messages")
+                    .query::<dms::GetDmMessagesQuery>()
+                    .returns::<Vec<DmMessage>>(),
+                post("/api/v1/dms/:conversation_id/messages", "Send a message")
+                    .body::<dms::SendDmRequest>()
+                    .creates::<DmMessage>(),
+            ],
+        ),
+        (
+            "Files",
+            vec![
+                post("/api/v1/files", "Register a shared file")
+                    .body::<files::RegisterFileRequest>()
+                    .creates::<FileMetadata>(),
+                get("/api/v1/files/:file_id", "File metadata").returns::<Object>(),
+            ],
+        ),
+        (
+            "Friends",
+            vec![
+                get("/api/v1/friends", "List friends").returns::<Vec<Friend>>(),
+                post("/api/v1/friends", "Send a friend request").body::<friends::FriendRequest>().creates::<Friend>(),
+                get("/api/v1/friends/:peer_id", "Get a friend").returns::<Object>(),
+                delete("/api/v1/friends/:peer_id", "Remove a friend").returns::<Object>(),
+                post("/api/v1/friends/:peer_id/accept", "Accept a friend request").returns::<Object>(),
+                get("/api/v1/blocked", "Blocked peers").returns::<Vec<BlockedPeer>>(),
+                post("/api/v1/blocked", "Block a peer").body::<moderation::BlockRequest>().returns::<Object>(),
+                delete("/api/v1/blocked/:peer_id", "Unblock a peer").returns::<Object>(),
+            ],
+        ),
+        (
+            "Settings",
+            vec![
+                delete("/api/v1/emoji/:emoji_id", "Remove a custom emoji").returns::<Object>(),
+                get("/api/v1/settings", "All settings").returns::<Vec<Setting>>(),
+                get("/api/v1/settings/:key", "Get a setting").returns::<Object>(),
+                put("/api/v1/settings/:key", "Set a setting").body::<settings::SetSettingRequest>().returns::<Object>(),
+                delete("/api/v1/settings/:key", "Delete a setting").returns::<Object>(),
+            ],
+        ),
+        (
+            "Notifications",
+            vec![
+                get("/api/v1/notifications", "All notification levels").returns::<Vec<NotificationSetting>>(),
+                get("/api/v1/notifications/:target_type/:target_id", "A room's or channel's notification level")
+                    .returns::<Object>(),
+                put("/api/v1/notifications/:target_type/:target_id", "Set a notification level")
+                    .body::<notifications::SetNotificationRequest>()
+                    .returns::<Object>(),
+                get("/api/v1/unread", "Unread counts everywhere").returns::<UnreadSummary>(),
+            ],
+        ),
+        (
+            "Voice",
+            vec![
+                post("/api/v1/voice/join", "Join a voice channel").body::<voice::JoinVoiceRequest>().returns::<Object>(),
+                post("/api/v1/voice/leave", "Leave voice").returns::<Object>(),
+                put("/api/v1/voice/muted", "Mute or unmute").body::<voice::MutedRequest>().returns::<Object>(),
+                put("/api/v1/voice/deafened", "Deafen or undeafen").body::<voice::DeafenedRequest>().returns::<Object>(),
+                get("/api/v1/voice/devices", "Audio devices").returns::<Object>(),
+                get("/api/v1/voice/state", "Voice state").returns::<Object>(),
+                post("/api/v1/voice/camera/enable", "Turn the camera on")
+                    .body::<voice::EnableCameraRequest>()
+                    .returns::<Object>(),
+                post("/api/v1/voice/camera/disable", "Turn the camera off").returns::<Object>(),
+                get("/api/v1/voice/cameras", "Cameras").returns::<Object>(),
+                post("/api/v1/voice/screen/start", "Start sharing the screen").returns::<Object>(),
+                post("/api/v1/voice/screen/stop", "Stop sharing the screen").returns::<Object>(),
+                post("/api/v1/voice/offer", "Send a call offer (legacy signaling)")
+                    .body::<voice::CallOfferRequest>()
+                    .returns::<Object>(),
+                post("/api/v1/voice/answer", "Send a call answer (legacy signaling)")
+                    .body::<voice::CallAnswerRequest>()
+                    .returns::<Object>(),
+                post("/api/v1/voice/ice-candidate", "Send an ICE candidate (legacy signaling)")
+                    .body::<voice::IceCandidateRequest>()
+                    .returns::<Object>(),
+                post("/api/v1/voice/broadcast-state", "Broadcast voice state (legacy signaling)")
+                    .body::<voice::VoiceStateRequest>()
+                    .returns::<Object>(),
+            ],
+        ),
+    ]
+}
+
+/// Apply the generator's OpenAPI fix-ups (e.g. no boolean schemas) and serialize.
+fn to_value(gen: &mut SchemaGenerator, mut schema: Schema) -> Value {
+    for visitor in gen.visitors_mut() {
+        visitor.visit_schema(&mut schema);
+    }
+    serde_json::to_value(schema).unwrap_or(Value::Null)
+}
+
+fn json_content(schema: Value) -> Value {
+    json!({ "application/json": { "schema": schema } })
+}
+
+/// Query parameters: the properties of a query struct's object schema.
+fn query_parameters(gen: &mut SchemaGenerator, query: SchemaFn) -> Vec<Value> {
+    let Schema::Object(SchemaObject { object: Some(object), .. }) = query(gen) else {
+        return Vec::new();
+    };
+    let ObjectValidation { properties, required, .. } = *object;
+    properties
+        .into_iter()
+        .map(|(name, schema)| {
+            json!({
+                "name": name,
+                "in": "query",
+                "required": required.contains(&name),
+                "schema": to_value(gen, schema),
+            })
+        })
+        .collect()
+}
+
+fn build_operation(gen: &mut SchemaGenerator, tag: &str, operation: &Operation) -> Value {
+    let mut parameters: Vec<Value> = operation
+        .path
+        .split('/')
+        .filter_map(|segment| segment.strip_prefix(':'))
+        .map(|name| json!({ "name": name, "in": "path", "required": true, "schema": { "type": "string" } }))
+        .collect();
+    if let Some(query) = operation.query {
+        parameters.extend(query_parameters(gen, query));
+    }
+
+    let success = if operation.file {
+        json!({
+            "description": "File download",
+            "content": { "application/octet-stream": { "schema": { "type": "string", "format": "binary" } } },
+        })
+    } else {
+        let response = match operation.response {
+            Some(response) => {
+                let schema = response(gen);
+                to_value(gen, schema)
+            }
+            None => json!({}),
+        };
+        json!({ "description": "Success", "content": json_content(response) })
+    };
+    let error = json!({
+        "description": "Error",
+        "content": json_content(json!({ "$ref": "#/components/schemas/ErrorBody" })),
+    });
+
+    let mut value = json!({
+        "tags": [tag],
+        "summary": operation.summary,
+        "parameters": parameters,
+        "responses": {
+            operation.status.to_string(): success,
+            "4XX": error.clone(),
+            "5XX": error,
+        },
+    });
+    if let Some(body) = operation.body {
+        let schema = body(gen);
+        value["requestBody"] = json!({ "required": true, "content": json_content(to_value(gen, schema)) });
+    }
+    if let Some((name, _, _)) =
+        RPC_METHODS.iter().find(|(_, method, route)| *method == operation.method && *route == operation.path)
+    {
+        value["operationId"] = json!(name);
+    }
+    if operation.path.starts_with("/api/v1/hooks/") {
+        value["security"] = json!([]);
+    }
+    value
+}
+
+/// Build the document. Routes are written `{name}` rather than axum's `:name`.
+pub fn document() -> Value {
+    let mut gen = SchemaSettings::openapi3().into_generator();
+    gen.subschema_for::<ErrorBody>();
+
+    let mut paths = Map::new();
+    for (tag, operations) in operations() {
+        for operation in &operations {
+            let path = operation
+                .path
+                .split('/')
+                .map(|segment| match segment.strip_prefix(':') {
+                    Some(name) => format!("{{{}}}", name),
+                    None => segment.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join("/");
+            let item = paths.entry(path).or_insert_with(|| json!({}));
+            item[operation.method.as_str().to_lowercase()] = build_operation(&mut gen, tag, operation);
+        }
+    }
+
+    let schemas: Map<String, Value> =
+        gen.take_definitions().into_iter().map(|(name, schema)| (name, to_value(&mut gen, schema))).collect();
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Chatr local API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "REST API of a Chatr node. Send a token issued by the node as `Authorization: Bearer <token>`. \
+                Events and RPC calls go over the `/ws` WebSocket.",
+        },
+        "security": [{ "bearerAuth": [] }],
+        "paths": paths,
+        "components": {
+            "schemas": schemas,
+            "securitySchemes": { "bearerAuth": { "type": "http", "scheme": "bearer" } },
+        },
+    })
+}
+
+/// `GET /api/v1/openapi.json`. The document is built once.
+pub async fn get_openapi() -> Json<Value> {
+    static DOCUMENT: OnceLock<Value> = OnceLock::new();
+    Json(DOCUMENT.get_or_init(document).clone())
+}
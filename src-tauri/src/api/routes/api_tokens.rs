@@ -0,0 +1,42 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::models::{ApiScope, ApiToken, IssuedApiToken};
+use crate::services::{self, ServiceError};
+use crate::state::ServiceContext;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct IssueTokenRequest {
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+}
+
+pub async fn list_tokens(
+    State(ctx): State<ServiceContext>,
+) -> Result<Json<Vec<ApiToken>>, ServiceError> {
+    services::api_tokens::list_tokens(&ctx)
+        .await
+        .map(Json)
+}
+
+pub async fn issue_token(
+    State(ctx): State<ServiceContext>,
+    Json(body): Json<IssueTokenRequest>,
+) -> Result<Json<IssuedApiToken>, ServiceError> {
+    services::api_tokens::issue_token(&ctx, &body.name, body.scopes)
+        .await
+        .map(Json)
+}
+
+pub async fn revoke_token(
+    State(ctx): State<ServiceContext>,
+    Path(token_id): Path<String>,
+) -> Result<Json<serde_json::Value>, ServiceError> {
+    services::api_tokens::revoke_token(&ctx, &token_id)
+        .await
+        .map(|revoked| Json(serde_json::json!({"revoked": revoked})))
+}
@@ -0,0 +1,31 @@
+use axum::{
+    extract::State,
+    http::header,
+    response::IntoResponse,
+    Json,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::services::{self, ServiceError};
+use crate::state::ServiceContext;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CreateBackupRequest {
+    pub passphrase: String,
+}
+
+pub async fn create_backup(
+    State(ctx): State<ServiceContext>,
+    Json(body): Json<CreateBackupRequest>,
+) -> Result<impl IntoResponse, ServiceError> {
+    let bytes = services::backup::create_backup(&ctx, &body.passphrase).await?;
+    let filename = format!("chatr-{}.chatrbak", chrono::Utc::now().format("%Y%m%d-%H%M%S"));
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        bytes,
+    ))
+}
@@ -0,0 +1,101 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::api::auth::Authorized;
+use crate::models::{Bot, CreatedBot};
+use crate::services::{self, ServiceError};
+use crate::state::ServiceContext;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct BotNameRequest {
+    pub display_name: String,
+}
+
+pub async fn list_bots(
+    State(ctx): State<ServiceContext>,
+) -> Result<Json<Vec<Bot>>, ServiceError> {
+    services::bots::list_bots(&ctx)
+        .await
+        .map(Json)
+}
+
+pub async fn create_bot(
+    State(ctx): State<ServiceContext>,
+    Json(body): Json<BotNameRequest>,
+) -> Result<(StatusCode, Json<CreatedBot>), ServiceError> {
+    services::bots::create_bot(&ctx, &body.display_name)
+        .await
+        .map(|created| (StatusCode::CREATED, Json(created)))
+}
+
+pub async fn get_bot(
+    State(ctx): State<ServiceContext>,
+    Path(bot_peer_id): Path<String>,
+) -> Result<Json<Bot>, ServiceError> {
+    services::bots::get_bot(&ctx, &bot_peer_id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ServiceError::not_found("Bot not found"))
+}
+
+pub async fn update_bot(
+    State(ctx): State<ServiceContext>,
+    Path(bot_peer_id): Path<String>,
+    Json(body): Json<BotNameRequest>,
+) -> Result<Json<Bot>, ServiceError> {
+    services::bots::set_display_name(&ctx, &bot_peer_id, &body.display_name)
+        .await
+        .map(Json)
+}
+
+pub async fn delete_bot(
+    State(ctx): State<ServiceContext>,
+    Path(bot_peer_id): Path<String>,
+) -> Result<Json<serde_json::Value>, ServiceError> {
+    services::bots::delete_bot(&ctx, &bot_peer_id)
+        .await
+        .map(|deleted| Json(serde_json::json!({"deleted": deleted})))
+}
+
+pub async fn regenerate_token(
+    State(ctx): State<ServiceContext>,
+    Path(bot_peer_id): Path<String>,
+) -> Result<Json<serde_json::Value>, ServiceError> {
+    services::bots::regenerate_token(&ctx, &bot_peer_id)
+        .await
+        .map(|token| Json(serde_json::json!({"token": token})))
+}
+
+pub async fn join_room(
+    State(ctx): State<ServiceContext>,
+    Path((bot_peer_id, room_id)): Path<(String, String)>,
+) -> Result<Json<Bot>, ServiceError> {
+    services::bots::join_room(&ctx, &bot_peer_id, &room_id)
+        .await
+        .map(Json)
+}
+
+pub async fn leave_room(
+    State(ctx): State<ServiceContext>,
+    Path((bot_peer_id, room_id)): Path<(String, String)>,
+) -> Result<Json<Bot>, ServiceError> {
+    services::bots::leave_room(&ctx, &bot_peer_id, &room_id)
+        .await
+        .map(Json)
+}
+
+/// The bot a bot token acts as.
+pub async fn get_current_bot(
+    State(ctx): State<ServiceContext>,
+    authorized: Option<Extension<Authorized>>,
+) -> Result<Json<Bot>, ServiceError> {
+    let Some(bot_peer_id) = authorized.and_then(|Extension(a)| a.bot) else {
+        return Err(ServiceError::not_found("Not a bot token"));
+    };
+    get_bot(State(ctx), Path(bot_peer_id)).await
+}
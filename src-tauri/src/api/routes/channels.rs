@@ -3,29 +3,37 @@ use axum::{
     http::StatusCode,
     Json,
 };
+use schemars::JsonSchema;
 use serde::Deserialize;
 
-use crate::models::Channel;
-use crate::services;
+use crate::models::{Channel, ChannelCategory, ChannelLayout};
+use crate::services::{self, ServiceError};
 use crate::state::ServiceContext;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct CreateChannelRequest {
     pub name: String,
     pub channel_type: Option<String>,
+    pub category_id: Option<String>,
 }
 
 pub async fn create_channel(
     State(ctx): State<ServiceContext>,
     Path(room_id): Path<String>,
     Json(body): Json<CreateChannelRequest>,
-) -> Result<(StatusCode, Json<Channel>), (StatusCode, String)> {
-    services::channels::create_channel(&ctx, &room_id, &body.name, body.channel_type.as_deref())
+) -> Result<(StatusCode, Json<Channel>), ServiceError> {
+    services::channels::create_channel(
+        &ctx,
+        &room_id,
+        &body.name,
+        body.channel_type.as_deref(),
+        body.category_id.as_deref(),
+    )
+        .await
         .map(|ch| (StatusCode::CREATED, Json(ch)))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct UpdateChannelRequest {
     pub name: Option<String>,
     pub topic: Option<String>,
@@ -36,7 +44,7 @@ pub async fn update_channel(
     State(ctx): State<ServiceContext>,
     Path(channel_id): Path<String>,
     Json(body): Json<UpdateChannelRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::channels::update_channel(
         &ctx,
         &channel_id,
@@ -44,17 +52,160 @@ pub async fn update_channel(
         body.topic.as_deref(),
         body.position,
     )
+    .await
     .map(|_| Json(serde_json::json!({"ok": true})))
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn delete_channel(
     State(ctx): State<ServiceContext>,
     Path(channel_id): Path<String>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     // Look up room_id for broadcast before deleting
-    let room_id = ctx.db.get_channel_room_id(&channel_id).ok().flatten();
+    let id = channel_id.clone();
+    let room_id = ctx.db.run(move |db| db.get_channel_room_id(&id)).await.ok().flatten();
     services::channels::delete_channel(&ctx, &channel_id, room_id.as_deref())
+        .await
+        .map(|_| Json(serde_json::json!({"ok": true})))
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct MoveChannelRequest {
+    pub category_id: Option<String>,
+    pub position: Option<i32>,
+}
+
+pub async fn move_channel(
+    State(ctx): State<ServiceContext>,
+    Path(channel_id): Path<String>,
+    Json(body): Json<MoveChannelRequest>,
+) -> Result<Json<Channel>, ServiceError> {
+    services::channels::move_channel(&ctx, &channel_id, body.category_id.as_deref(), body.position)
+        .await
+        .map(Json)
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ChannelPermissionsRequest {
+    pub inherit_permissions: Option<bool>,
+    /// Absent = unchanged, `null` = clear the channel's own overwrites.
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub permissions: Option<Option<String>>,
+}
+
+pub async fn set_channel_permissions(
+    State(ctx): State<ServiceContext>,
+    Path(channel_id): Path<String>,
+    Json(body): Json<ChannelPermissionsRequest>,
+) -> Result<Json<Channel>, ServiceError> {
+    services::channels::set_channel_permissions(
+        &ctx,
+        &channel_id,
+        body.inherit_permissions,
+        body.permissions.as_ref().map(|p| p.as_deref()),
+    )
+    .await
+    .map(Json)
+}
+
+pub async fn get_effective_permissions(
+    State(ctx): State<ServiceContext>,
+    Path(channel_id): Path<String>,
+) -> Result<Json<serde_json::Value>, ServiceError> {
+    services::channels::get_effective_permissions(&ctx, &channel_id)
+        .await
+        .map(|permissions| Json(serde_json::json!({"channel_id": channel_id, "permissions": permissions})))
+}
+
+// --- Categories ---
+
+pub async fn get_channel_layout(
+    State(ctx): State<ServiceContext>,
+    Path(room_id): Path<String>,
+) -> Result<Json<ChannelLayout>, ServiceError> {
+    services::channels::get_channel_layout(&ctx, &room_id)
+        .await
+        .map(Json)
+}
+
+pub async fn get_categories(
+    State(ctx): State<ServiceContext>,
+    Path(room_id): Path<String>,
+) -> Result<Json<Vec<ChannelCategory>>, ServiceError> {
+    services::channels::get_categories(&ctx, &room_id)
+        .await
+        .map(Json)
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CreateCategoryRequest {
+    pub name: String,
+    pub position: Option<i32>,
+}
+
+pub async fn create_category(
+    State(ctx): State<ServiceContext>,
+    Path(room_id): Path<String>,
+    Json(body): Json<CreateCategoryRequest>,
+) -> Result<(StatusCode, Json<ChannelCategory>), ServiceError> {
+    services::channels::create_category(&ctx, &room_id, &body.name, body.position)
+        .await
+        .map(|c| (StatusCode::CREATED, Json(c)))
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct UpdateCategoryRequest {
+    pub name: Option<String>,
+    pub position: Option<i32>,
+    /// Absent = unchanged, `null` = clear the category's permissions.
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub permissions: Option<Option<String>>,
+}
+
+pub async fn update_category(
+    State(ctx): State<ServiceContext>,
+    Path(category_id): Path<String>,
+    Json(body): Json<UpdateCategoryRequest>,
+) -> Result<Json<ChannelCategory>, ServiceError> {
+    services::channels::update_category(
+        &ctx,
+        &category_id,
+        body.name.as_deref(),
+        body.position,
+        body.permissions.as_ref().map(|p| p.as_deref()),
+    )
+    .await
+    .map(Json)
+}
+
+pub async fn delete_category(
+    State(ctx): State<ServiceContext>,
+    Path(category_id): Path<String>,
+) -> Result<Json<serde_json::Value>, ServiceError> {
+    services::channels::delete_category(&ctx, &category_id)
+        .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CollapseCategoryRequest {
+    pub collapsed: bool,
+}
+
+pub async fn set_category_collapsed(
+    State(ctx): State<ServiceContext>,
+    Path(category_id): Path<String>,
+    Json(body): Json<CollapseCategoryRequest>,
+) -> Result<Json<serde_json::Value>, ServiceError> {
+    services::channels::set_category_collapsed(&ctx, &category_id, body.collapsed)
+        .await
+        .map(|_| Json(serde_json::json!({"ok": true})))
+}
+
+/// Distinguish an explicit `null` from a missing field.
+fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
 }
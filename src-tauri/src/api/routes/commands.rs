@@ -0,0 +1,99 @@
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::api::auth::Authorized;
+use crate::models::{CommandOption, CommandResult, CommandSuggestion, SlashCommand};
+use crate::services::{self, ServiceError};
+use crate::state::ServiceContext;
+
+pub async fn list_commands(
+    State(ctx): State<ServiceContext>,
+    Path(channel_id): Path<String>,
+) -> Result<Json<Vec<SlashCommand>>, ServiceError> {
+    services::commands::list_commands(&ctx, &channel_id)
+        .await
+        .map(Json)
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ExecuteCommandRequest {
+    pub input: String,
+}
+
+pub async fn execute_command(
+    State(ctx): State<ServiceContext>,
+    Path(channel_id): Path<String>,
+    Json(body): Json<ExecuteCommandRequest>,
+) -> Result<Json<CommandResult>, ServiceError> {
+    services::commands::execute(&ctx, &channel_id, &body.input)
+        .await
+        .map(Json)
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct AutocompleteQuery {
+    #[serde(default)]
+    pub input: String,
+}
+
+pub async fn autocomplete(
+    State(ctx): State<ServiceContext>,
+    Path(channel_id): Path<String>,
+    Query(query): Query<AutocompleteQuery>,
+) -> Result<Json<Vec<CommandSuggestion>>, ServiceError> {
+    services::commands::autocomplete(&ctx, &channel_id, &query.input)
+        .await
+        .map(Json)
+}
+
+/// The bot a request acts as; command registration is for bot tokens only.
+fn require_bot(authorized: Option<Extension<Authorized>>) -> Result<String, ServiceError> {
+    authorized
+        .and_then(|Extension(a)| a.bot)
+        .ok_or_else(|| ServiceError::forbidden("Only bot tokens can register commands"))
+}
+
+pub async fn list_bot_commands(
+    State(ctx): State<ServiceContext>,
+    authorized: Option<Extension<Authorized>>,
+) -> Result<Json<Vec<SlashCommand>>, ServiceError> {
+    let bot_peer_id = require_bot(authorized)?;
+    services::commands::list_bot_commands(&ctx, &bot_peer_id)
+        .await
+        .map(Json)
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct RegisterCommandRequest {
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub options: Vec<CommandOption>,
+}
+
+pub async fn register_bot_command(
+    State(ctx): State<ServiceContext>,
+    authorized: Option<Extension<Authorized>>,
+    Path((room_id, name)): Path<(String, String)>,
+    Json(body): Json<RegisterCommandRequest>,
+) -> Result<Json<SlashCommand>, ServiceError> {
+    let bot_peer_id = require_bot(authorized)?;
+    services::commands::register_bot_command(&ctx, &bot_peer_id, &room_id, &name, &body.description, body.options)
+        .await
+        .map(Json)
+}
+
+pub async fn unregister_bot_command(
+    State(ctx): State<ServiceContext>,
+    authorized: Option<Extension<Authorized>>,
+    Path((room_id, name)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, ServiceError> {
+    let bot_peer_id = require_bot(authorized)?;
+    services::commands::unregister_bot_command(&ctx, &bot_peer_id, &room_id, &name)
+        .await
+        .map(|deleted| Json(serde_json::json!({ "deleted": deleted })))
+}
@@ -0,0 +1,59 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::models::{DeviceCertificate, DeviceLinkRequest, LinkedDevice};
+use crate::services::{self, ServiceError};
+use crate::state::ServiceContext;
+
+pub async fn list_devices(
+    State(ctx): State<ServiceContext>,
+) -> Result<Json<Vec<LinkedDevice>>, ServiceError> {
+    services::devices::list_devices(&ctx)
+        .await
+        .map(Json)
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct LinkRequestQuery {
+    pub name: Option<String>,
+}
+
+pub async fn get_link_request(
+    State(ctx): State<ServiceContext>,
+    Query(params): Query<LinkRequestQuery>,
+) -> Result<Json<DeviceLinkRequest>, ServiceError> {
+    services::devices::get_link_request(&ctx, params.name.as_deref())
+        .await
+        .map(Json)
+}
+
+pub async fn link_device(
+    State(ctx): State<ServiceContext>,
+    Json(body): Json<DeviceLinkRequest>,
+) -> Result<Json<DeviceCertificate>, ServiceError> {
+    services::devices::link_device(&ctx, &body)
+        .await
+        .map(Json)
+}
+
+pub async fn accept_link(
+    State(ctx): State<ServiceContext>,
+    Json(body): Json<DeviceCertificate>,
+) -> Result<Json<serde_json::Value>, ServiceError> {
+    services::devices::accept_link(&ctx, &body)
+        .await
+        .map(|_| Json(serde_json::json!({"ok": true})))
+}
+
+pub async fn unlink_device(
+    State(ctx): State<ServiceContext>,
+    Path(device_peer_id): Path<String>,
+) -> Result<Json<serde_json::Value>, ServiceError> {
+    services::devices::unlink_device(&ctx, &device_peer_id)
+        .await
+        .map(|revoked| Json(serde_json::json!({"revoked": revoked})))
+}
@@ -3,13 +3,14 @@ use axum::{
     http::StatusCode,
     Json,
 };
+use schemars::JsonSchema;
 use serde::Deserialize;
 
 use crate::models::{DmConversation, DmMessage, DmParticipant};
-use crate::services;
+use crate::services::{self, ServiceError};
 use crate::state::ServiceContext;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct CreateDmRequest {
     pub peer_ids: Vec<String>,
     pub name: Option<String>,
@@ -18,30 +19,30 @@ pub struct CreateDmRequest {
 pub async fn create_dm(
     State(ctx): State<ServiceContext>,
     Json(body): Json<CreateDmRequest>,
-) -> Result<(StatusCode, Json<DmConversation>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<DmConversation>), ServiceError> {
     services::dms::create_dm(&ctx, body.peer_ids, body.name)
+        .await
         .map(|dm| (StatusCode::CREATED, Json(dm)))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn list_dms(
     State(ctx): State<ServiceContext>,
-) -> Result<Json<Vec<DmConversation>>, (StatusCode, String)> {
+) -> Result<Json<Vec<DmConversation>>, ServiceError> {
     services::dms::list_dms(&ctx)
+        .await
         .map(Json)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn get_dm_participants(
     State(ctx): State<ServiceContext>,
     Path(conversation_id): Path<String>,
-) -> Result<Json<Vec<DmParticipant>>, (StatusCode, String)> {
+) -> Result<Json<Vec<DmParticipant>>, ServiceError> {
     services::dms::get_dm_participants(&ctx, &conversation_id)
+        .await
         .map(Json)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct SendDmRequest {
     pub content: String,
 }
@@ -50,13 +51,13 @@ pub async fn send_dm_message(
     State(ctx): State<ServiceContext>,
     Path(conversation_id): Path<String>,
     Json(body): Json<SendDmRequest>,
-) -> Result<(StatusCode, Json<DmMessage>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<DmMessage>), ServiceError> {
     services::dms::send_dm_message(&ctx, &conversation_id, &body.content)
+        .await
         .map(|msg| (StatusCode::CREATED, Json(msg)))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct GetDmMessagesQuery {
     pub limit: Option<i64>,
     pub before: Option<String>,
@@ -66,8 +67,8 @@ pub async fn get_dm_messages(
     State(ctx): State<ServiceContext>,
     Path(conversation_id): Path<String>,
     Query(params): Query<GetDmMessagesQuery>,
-) -> Result<Json<Vec<DmMessage>>, (StatusCode, String)> {
+) -> Result<Json<Vec<DmMessage>>, ServiceError> {
     services::dms::get_dm_messages(&ctx, &conversation_id, params.limit, params.before.as_deref())
+        .await
         .map(Json)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
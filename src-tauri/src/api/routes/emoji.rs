@@ -3,13 +3,14 @@ use axum::{
     http::StatusCode,
     Json,
 };
+use schemars::JsonSchema;
 use serde::Deserialize;
 
 use crate::models::CustomEmoji;
-use crate::services;
+use crate::services::{self, ServiceError};
 use crate::state::ServiceContext;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct AddEmojiRequest {
     pub name: String,
     pub file_hash: String,
@@ -19,26 +20,26 @@ pub async fn add_emoji(
     State(ctx): State<ServiceContext>,
     Path(room_id): Path<String>,
     Json(body): Json<AddEmojiRequest>,
-) -> Result<(StatusCode, Json<CustomEmoji>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<CustomEmoji>), ServiceError> {
     services::emoji::add_emoji(&ctx, &room_id, &body.name, &body.file_hash)
+        .await
         .map(|e| (StatusCode::CREATED, Json(e)))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn remove_emoji(
     State(ctx): State<ServiceContext>,
     Path(emoji_id): Path<String>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::emoji::remove_emoji(&ctx, &emoji_id)
+        .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn list_emoji(
     State(ctx): State<ServiceContext>,
     Path(room_id): Path<String>,
-) -> Result<Json<Vec<CustomEmoji>>, (StatusCode, String)> {
+) -> Result<Json<Vec<CustomEmoji>>, ServiceError> {
     services::emoji::list_emoji(&ctx, &room_id)
+        .await
         .map(Json)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
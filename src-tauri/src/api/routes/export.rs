@@ -0,0 +1,34 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::header,
+    response::IntoResponse,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::models::ExportFormat;
+use crate::services::{self, ServiceError};
+use crate::state::ServiceContext;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ExportQuery {
+    pub format: Option<ExportFormat>,
+    pub channel_id: Option<String>,
+}
+
+pub async fn export_room(
+    State(ctx): State<ServiceContext>,
+    Path(room_id): Path<String>,
+    Query(params): Query<ExportQuery>,
+) -> Result<impl IntoResponse, ServiceError> {
+    let format = params.format.unwrap_or(ExportFormat::Json);
+    let body = services::export::export_room(&ctx, &room_id, params.channel_id.as_deref(), format).await?;
+    let filename = format!("chatr-{}.{}", params.channel_id.as_deref().unwrap_or(&room_id), format.extension());
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        body,
+    ))
+}
@@ -3,13 +3,14 @@ use axum::{
     http::StatusCode,
     Json,
 };
+use schemars::JsonSchema;
 use serde::Deserialize;
 
 use crate::models::FileMetadata;
-use crate::services;
+use crate::services::{self, ServiceError};
 use crate::state::ServiceContext;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct RegisterFileRequest {
     pub filename: String,
     pub size: i64,
@@ -21,7 +22,7 @@ pub struct RegisterFileRequest {
 pub async fn register_file(
     State(ctx): State<ServiceContext>,
     Json(body): Json<RegisterFileRequest>,
-) -> Result<(StatusCode, Json<FileMetadata>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<FileMetadata>), ServiceError> {
     services::files::register_file(
         &ctx,
         &body.filename,
@@ -30,20 +31,20 @@ pub async fn register_file(
         &body.sha256_hash,
         body.chunk_count,
     )
+    .await
     .map(|f| (StatusCode::CREATED, Json(f)))
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn get_file(
     State(ctx): State<ServiceContext>,
     Path(file_id): Path<String>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::files::get_file(&ctx, &file_id)
+        .await
         .map(|f| Json(serde_json::json!(f)))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct AttachFileRequest {
     pub file_id: String,
 }
@@ -52,17 +53,17 @@ pub async fn attach_file(
     State(ctx): State<ServiceContext>,
     Path(message_id): Path<String>,
     Json(body): Json<AttachFileRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::files::attach_file(&ctx, &message_id, &body.file_id)
+        .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn get_attachments(
     State(ctx): State<ServiceContext>,
     Path(message_id): Path<String>,
-) -> Result<Json<Vec<FileMetadata>>, (StatusCode, String)> {
+) -> Result<Json<Vec<FileMetadata>>, ServiceError> {
     services::files::get_attachments(&ctx, &message_id)
+        .await
         .map(Json)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
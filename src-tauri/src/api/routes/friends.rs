@@ -3,13 +3,14 @@ use axum::{
     http::StatusCode,
     Json,
 };
+use schemars::JsonSchema;
 use serde::Deserialize;
 
 use crate::models::Friend;
-use crate::services;
+use crate::services::{self, ServiceError};
 use crate::state::ServiceContext;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct FriendRequest {
     pub peer_id: String,
     pub display_name: String,
@@ -18,43 +19,43 @@ pub struct FriendRequest {
 pub async fn send_friend_request(
     State(ctx): State<ServiceContext>,
     Json(body): Json<FriendRequest>,
-) -> Result<(StatusCode, Json<Friend>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<Friend>), ServiceError> {
     services::friends::send_friend_request(&ctx, &body.peer_id, &body.display_name)
+        .await
         .map(|f| (StatusCode::CREATED, Json(f)))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn accept_friend_request(
     State(ctx): State<ServiceContext>,
     Path(peer_id): Path<String>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::friends::accept_friend_request(&ctx, &peer_id)
+        .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn remove_friend(
     State(ctx): State<ServiceContext>,
     Path(peer_id): Path<String>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::friends::remove_friend(&ctx, &peer_id)
+        .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn list_friends(
     State(ctx): State<ServiceContext>,
-) -> Result<Json<Vec<Friend>>, (StatusCode, String)> {
+) -> Result<Json<Vec<Friend>>, ServiceError> {
     services::friends::list_friends(&ctx)
+        .await
         .map(Json)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn get_friend(
     State(ctx): State<ServiceContext>,
     Path(peer_id): Path<String>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::friends::get_friend(&ctx, &peer_id)
+        .await
         .map(|f| Json(serde_json::json!(f)))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
@@ -1,19 +1,20 @@
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{extract::State, Json};
+use schemars::JsonSchema;
 use serde::Deserialize;
 
-use crate::models::Identity;
-use crate::services;
+use crate::models::{Identity, KeyRotation};
+use crate::services::{self, ServiceError};
 use crate::state::ServiceContext;
 
 pub async fn get_identity(
     State(ctx): State<ServiceContext>,
-) -> Result<Json<Identity>, (StatusCode, String)> {
+) -> Result<Json<Identity>, ServiceError> {
     services::identity::get_identity(&ctx)
+        .await
         .map(Json)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct SetDisplayNameRequest {
     pub name: String,
 }
@@ -21,13 +22,13 @@ pub struct SetDisplayNameRequest {
 pub async fn set_display_name(
     State(ctx): State<ServiceContext>,
     Json(body): Json<SetDisplayNameRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::identity::set_display_name(&ctx, &body.name)
+        .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct SetStatusRequest {
     pub message: Option<String>,
     pub status_type: Option<String>,
@@ -36,13 +37,13 @@ pub struct SetStatusRequest {
 pub async fn set_status(
     State(ctx): State<ServiceContext>,
     Json(body): Json<SetStatusRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::identity::set_status(&ctx, body.message.as_deref(), body.status_type.as_deref())
+        .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct SetAvatarRequest {
     pub hash: Option<String>,
 }
@@ -50,8 +51,69 @@ pub struct SetAvatarRequest {
 pub async fn set_avatar(
     State(ctx): State<ServiceContext>,
     Json(body): Json<SetAvatarRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::identity::set_avatar_hash(&ctx, body.hash.as_deref())
+        .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+pub async fn get_encryption(
+    State(ctx): State<ServiceContext>,
+) -> Result<Json<serde_json::Value>, ServiceError> {
+    services::identity::is_encrypted(&ctx)
+        .await
+        .map(|encrypted| Json(serde_json::json!({"encrypted": encrypted})))
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ChangePassphraseRequest {
+    pub current_passphrase: Option<String>,
+    /// `None` removes encryption.
+    pub new_passphrase: Option<String>,
+}
+
+pub async fn change_passphrase(
+    State(ctx): State<ServiceContext>,
+    Json(body): Json<ChangePassphraseRequest>,
+) -> Result<Json<serde_json::Value>, ServiceError> {
+    services::identity::change_passphrase(
+        &ctx,
+        body.current_passphrase.as_deref(),
+        body.new_passphrase.as_deref(),
+    )
+    .await
+    .map(|_| Json(serde_json::json!({"ok": true})))
+}
+
+pub async fn get_recovery_phrase(
+    State(ctx): State<ServiceContext>,
+) -> Result<Json<serde_json::Value>, ServiceError> {
+    services::identity::get_recovery_phrase(&ctx)
+        .await
+        .map(|phrase| Json(serde_json::json!({"phrase": phrase})))
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ImportIdentityRequest {
+    pub phrase: String,
+    /// Must be set to replace the current identity.
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+pub async fn import_identity(
+    State(ctx): State<ServiceContext>,
+    Json(body): Json<ImportIdentityRequest>,
+) -> Result<Json<serde_json::Value>, ServiceError> {
+    services::identity::import_identity(&ctx, &body.phrase, body.overwrite)
+        .await
+        .map(|peer_id| Json(serde_json::json!({"peer_id": peer_id, "restart_required": true})))
+}
+
+pub async fn rotate_key(
+    State(ctx): State<ServiceContext>,
+) -> Result<Json<KeyRotation>, ServiceError> {
+    services::identity::rotate_key(&ctx)
+        .await
+        .map(Json)
 }
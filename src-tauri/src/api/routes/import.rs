@@ -0,0 +1,27 @@
+use axum::{extract::State, Json};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::import::DiscordExport;
+use crate::models::ImportSummary;
+use crate::services::{self, ServiceError};
+use crate::state::ServiceContext;
+
+/// Request body limit for history imports; exports of busy channels run large.
+pub const MAX_IMPORT_BYTES: usize = 512 * 1024 * 1024;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct DiscordImportRequest {
+    pub room_name: Option<String>,
+    /// One DiscordChatExporter JSON export per channel.
+    pub exports: Vec<DiscordExport>,
+}
+
+pub async fn import_discord(
+    State(ctx): State<ServiceContext>,
+    Json(body): Json<DiscordImportRequest>,
+) -> Result<Json<ImportSummary>, ServiceError> {
+    services::import::import_discord(&ctx, body.room_name, body.exports)
+        .await
+        .map(Json)
+}
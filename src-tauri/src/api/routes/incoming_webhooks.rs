@@ -0,0 +1,70 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::models::{CreatedIncomingWebhook, IncomingWebhook, Message, MessageEmbed};
+use crate::services::{self, ServiceError};
+use crate::state::ServiceContext;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ListIncomingWebhooksQuery {
+    pub channel_id: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CreateIncomingWebhookRequest {
+    pub channel_id: String,
+    pub name: String,
+}
+
+/// Body POSTed by CI systems and other senders.
+#[derive(Deserialize, JsonSchema)]
+pub struct ExecuteWebhookRequest {
+    pub content: Option<String>,
+    /// Display name for this message instead of the webhook's name.
+    pub username: Option<String>,
+    #[serde(default)]
+    pub embeds: Vec<MessageEmbed>,
+}
+
+pub async fn list_incoming_webhooks(
+    State(ctx): State<ServiceContext>,
+    Query(params): Query<ListIncomingWebhooksQuery>,
+) -> Result<Json<Vec<IncomingWebhook>>, ServiceError> {
+    services::incoming_webhooks::list_incoming_webhooks(&ctx, params.channel_id.as_deref())
+        .await
+        .map(Json)
+}
+
+pub async fn create_incoming_webhook(
+    State(ctx): State<ServiceContext>,
+    Json(body): Json<CreateIncomingWebhookRequest>,
+) -> Result<(StatusCode, Json<CreatedIncomingWebhook>), ServiceError> {
+    services::incoming_webhooks::create_incoming_webhook(&ctx, &body.channel_id, &body.name)
+        .await
+        .map(|created| (StatusCode::CREATED, Json(created)))
+}
+
+pub async fn revoke_incoming_webhook(
+    State(ctx): State<ServiceContext>,
+    Path(webhook_id): Path<String>,
+) -> Result<Json<serde_json::Value>, ServiceError> {
+    services::incoming_webhooks::revoke_incoming_webhook(&ctx, &webhook_id)
+        .await
+        .map(|revoked| Json(serde_json::json!({"revoked": revoked})))
+}
+
+pub async fn execute_webhook(
+    State(ctx): State<ServiceContext>,
+    Path((webhook_id, token)): Path<(String, String)>,
+    Json(body): Json<ExecuteWebhookRequest>,
+) -> Result<(StatusCode, Json<Message>), ServiceError> {
+    services::incoming_webhooks::execute_incoming_webhook(&ctx, &webhook_id, &token, body.content, body.username, body.embeds)
+        .await?
+        .map(|msg| (StatusCode::CREATED, Json(msg)))
+        .ok_or_else(|| ServiceError::not_found("Unknown webhook"))
+}
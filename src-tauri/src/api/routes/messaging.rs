@@ -1,15 +1,17 @@
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
-    Json,
+    Extension, Json,
 };
+use schemars::JsonSchema;
 use serde::Deserialize;
 
+use crate::api::auth::Authorized;
 use crate::models::Message;
-use crate::services;
+use crate::services::{self, ServiceError};
 use crate::state::ServiceContext;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct GetMessagesQuery {
     pub limit: Option<i64>,
     pub before: Option<String>,
@@ -19,30 +21,36 @@ pub async fn get_messages(
     State(ctx): State<ServiceContext>,
     Path(channel_id): Path<String>,
     Query(params): Query<GetMessagesQuery>,
-) -> Result<Json<Vec<Message>>, (StatusCode, String)> {
+) -> Result<Json<Vec<Message>>, ServiceError> {
     services::messaging::get_messages(&ctx, &channel_id, params.limit, params.before.as_deref())
+        .await
         .map(Json)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct SendMessageRequest {
     pub content: String,
     pub reply_to_id: Option<String>,
+    pub thread_id: Option<String>,
 }
 
+/// Post as the node's identity, or as the bot a bot token acts as.
 pub async fn send_message(
     State(ctx): State<ServiceContext>,
     Path(channel_id): Path<String>,
+    authorized: Option<Extension<Authorized>>,
     Json(body): Json<SendMessageRequest>,
-) -> Result<(StatusCode, Json<Message>), (StatusCode, String)> {
-    services::messaging::send_message(&ctx, channel_id, body.content, body.reply_to_id)
+) -> Result<(StatusCode, Json<Message>), ServiceError> {
+    let author = match authorized.and_then(|Extension(a)| a.bot) {
+        Some(bot_peer_id) => Some(services::bots::author(&ctx, &bot_peer_id).await?),
+        None => None,
+    };
+    services::messaging::send_message(&ctx, channel_id, body.content, body.reply_to_id, body.thread_id, author)
         .await
         .map(|msg| (StatusCode::CREATED, Json(msg)))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct EditMessageRequest {
     pub content: String,
 }
@@ -51,22 +59,31 @@ pub async fn edit_message(
     State(ctx): State<ServiceContext>,
     Path(message_id): Path<String>,
     Json(body): Json<EditMessageRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::messaging::edit_message(&ctx, &message_id, &body.content)
+        .await
         .map(|updated| Json(serde_json::json!({"updated": updated})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn delete_message(
     State(ctx): State<ServiceContext>,
     Path(message_id): Path<String>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::messaging::delete_message(&ctx, &message_id)
+        .await
+        .map(|deleted| Json(serde_json::json!({"deleted": deleted})))
+}
+
+pub async fn delete_my_messages_in_room(
+    State(ctx): State<ServiceContext>,
+    Path(room_id): Path<String>,
+) -> Result<Json<serde_json::Value>, ServiceError> {
+    services::messaging::delete_my_messages_in_room(&ctx, &room_id)
+        .await
         .map(|deleted| Json(serde_json::json!({"deleted": deleted})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct ReactionRequest {
     pub emoji: String,
 }
@@ -75,31 +92,31 @@ pub async fn add_reaction(
     State(ctx): State<ServiceContext>,
     Path(message_id): Path<String>,
     Json(body): Json<ReactionRequest>,
-) -> Result<(StatusCode, Json<crate::models::Reaction>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<crate::models::Reaction>), ServiceError> {
     services::messaging::add_reaction(&ctx, &message_id, &body.emoji)
+        .await
         .map(|r| (StatusCode::CREATED, Json(r)))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn remove_reaction(
     State(ctx): State<ServiceContext>,
     Path((message_id, emoji)): Path<(String, String)>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::messaging::remove_reaction(&ctx, &message_id, &emoji)
+        .await
         .map(|removed| Json(serde_json::json!({"removed": removed})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn get_reactions(
     State(ctx): State<ServiceContext>,
     Path(message_id): Path<String>,
-) -> Result<Json<Vec<crate::models::Reaction>>, (StatusCode, String)> {
+) -> Result<Json<Vec<crate::models::Reaction>>, ServiceError> {
     services::messaging::get_reactions(&ctx, &message_id)
+        .await
         .map(Json)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct TypingRequest {
     pub typing: bool,
 }
@@ -108,13 +125,13 @@ pub async fn typing_indicator(
     State(ctx): State<ServiceContext>,
     Path(channel_id): Path<String>,
     Json(body): Json<TypingRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::messaging::typing_indicator(&ctx, &channel_id, body.typing)
+        .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct MarkReadRequest {
     pub last_read_message_id: String,
 }
@@ -123,22 +140,22 @@ pub async fn mark_read(
     State(ctx): State<ServiceContext>,
     Path(channel_id): Path<String>,
     Json(body): Json<MarkReadRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::messaging::mark_read(&ctx, &channel_id, &body.last_read_message_id)
+        .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn get_read_receipts(
     State(ctx): State<ServiceContext>,
     Path(channel_id): Path<String>,
-) -> Result<Json<Vec<crate::models::ReadReceipt>>, (StatusCode, String)> {
+) -> Result<Json<Vec<crate::models::ReadReceipt>>, ServiceError> {
     services::messaging::get_read_receipts(&ctx, &channel_id)
+        .await
         .map(Json)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct PinRequest {
     pub message_id: String,
 }
@@ -147,34 +164,35 @@ pub async fn pin_message(
     State(ctx): State<ServiceContext>,
     Path(channel_id): Path<String>,
     Json(body): Json<PinRequest>,
-) -> Result<(StatusCode, Json<crate::models::PinnedMessage>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<crate::models::PinnedMessage>), ServiceError> {
     services::messaging::pin_message(&ctx, &channel_id, &body.message_id)
+        .await
         .map(|p| (StatusCode::CREATED, Json(p)))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn unpin_message(
     State(ctx): State<ServiceContext>,
     Path((channel_id, message_id)): Path<(String, String)>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::messaging::unpin_message(&ctx, &channel_id, &message_id)
+        .await
         .map(|removed| Json(serde_json::json!({"removed": removed})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn get_pinned_messages(
     State(ctx): State<ServiceContext>,
     Path(channel_id): Path<String>,
-) -> Result<Json<Vec<crate::models::PinnedMessage>>, (StatusCode, String)> {
+) -> Result<Json<Vec<crate::models::PinnedMessage>>, ServiceError> {
     services::messaging::get_pinned_messages(&ctx, &channel_id)
+        .await
         .map(Json)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct SearchQuery {
     pub q: String,
     pub channel_id: Option<String>,
+    pub room_id: Option<String>,
     pub limit: Option<i64>,
     pub offset: Option<i64>,
 }
@@ -182,8 +200,47 @@ pub struct SearchQuery {
 pub async fn search_messages(
     State(ctx): State<ServiceContext>,
     Query(params): Query<SearchQuery>,
-) -> Result<Json<crate::models::SearchResult>, (StatusCode, String)> {
-    services::messaging::search_messages(&ctx, &params.q, params.channel_id.as_deref(), params.limit, params.offset)
-        .map(Json)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+) -> Result<Json<crate::models::SearchResult>, ServiceError> {
+    services::search::search_messages(
+        &ctx,
+        &params.q,
+        params.channel_id.as_deref(),
+        params.room_id.as_deref(),
+        params.limit,
+        params.offset,
+    )
+    .await
+    .map(Json)
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct UnifiedSearchQuery {
+    pub q: String,
+    /// Comma-separated subset of "channel", "dm", "file". Defaults to all.
+    pub sources: Option<String>,
+    pub room_id: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+pub async fn search_all(
+    State(ctx): State<ServiceContext>,
+    Query(params): Query<UnifiedSearchQuery>,
+) -> Result<Json<crate::models::UnifiedSearchResult>, ServiceError> {
+    let sources: Option<Vec<String>> = params.sources.as_deref().map(|s| {
+        s.split(',')
+            .map(|source| source.trim().to_lowercase())
+            .filter(|source| !source.is_empty())
+            .collect()
+    });
+    services::search::search_all(
+        &ctx,
+        &params.q,
+        sources.as_deref(),
+        params.room_id.as_deref(),
+        params.limit,
+        params.offset,
+    )
+    .await
+    .map(Json)
 }
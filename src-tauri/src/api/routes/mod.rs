@@ -1,14 +1,25 @@
+pub mod api_tokens;
+pub mod backup;
+pub mod bots;
 pub mod channels;
+pub mod commands;
+pub mod devices;
 pub mod dms;
 pub mod emoji;
+pub mod export;
 pub mod files;
 pub mod friends;
 pub mod identity;
+pub mod import;
+pub mod incoming_webhooks;
 pub mod messaging;
 pub mod moderation;
 pub mod notifications;
 pub mod peers;
+pub mod retention;
 pub mod roles;
 pub mod rooms;
 pub mod settings;
+pub mod threads;
 pub mod voice;
+pub mod webhooks;
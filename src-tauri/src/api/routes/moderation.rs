@@ -3,13 +3,14 @@ use axum::{
     http::StatusCode,
     Json,
 };
+use schemars::JsonSchema;
 use serde::Deserialize;
 
 use crate::models::{BlockedPeer, ModerationAction};
-use crate::services;
+use crate::services::{self, ServiceError};
 use crate::state::ServiceContext;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct ModerateRequest {
     pub action_type: String,
     pub target_peer_id: String,
@@ -21,7 +22,7 @@ pub async fn moderate(
     State(ctx): State<ServiceContext>,
     Path(room_id): Path<String>,
     Json(body): Json<ModerateRequest>,
-) -> Result<(StatusCode, Json<ModerationAction>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<ModerationAction>), ServiceError> {
     services::moderation::moderate(
         &ctx,
         &room_id,
@@ -30,20 +31,20 @@ pub async fn moderate(
         body.reason.as_deref(),
         body.expires_at.as_deref(),
     )
+    .await
     .map(|a| (StatusCode::CREATED, Json(a)))
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn get_audit_log(
     State(ctx): State<ServiceContext>,
     Path(room_id): Path<String>,
-) -> Result<Json<Vec<ModerationAction>>, (StatusCode, String)> {
+) -> Result<Json<Vec<ModerationAction>>, ServiceError> {
     services::moderation::get_audit_log(&ctx, &room_id)
+        .await
         .map(Json)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct BlockRequest {
     pub peer_id: String,
 }
@@ -51,25 +52,25 @@ pub struct BlockRequest {
 pub async fn block_peer(
     State(ctx): State<ServiceContext>,
     Json(body): Json<BlockRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::moderation::block_peer(&ctx, &body.peer_id)
+        .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn unblock_peer(
     State(ctx): State<ServiceContext>,
     Path(peer_id): Path<String>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::moderation::unblock_peer(&ctx, &peer_id)
+        .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn get_blocked_peers(
     State(ctx): State<ServiceContext>,
-) -> Result<Json<Vec<BlockedPeer>>, (StatusCode, String)> {
+) -> Result<Json<Vec<BlockedPeer>>, ServiceError> {
     services::moderation::get_blocked_peers(&ctx)
+        .await
         .map(Json)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
@@ -1,23 +1,23 @@
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
     Json,
 };
+use schemars::JsonSchema;
 use serde::Deserialize;
 
-use crate::models::NotificationSetting;
-use crate::services;
+use crate::models::{MessageMention, NotificationSetting, RoomUnread, UnreadSummary};
+use crate::services::{self, ServiceError};
 use crate::state::ServiceContext;
 
 pub async fn get_all_notification_settings(
     State(ctx): State<ServiceContext>,
-) -> Result<Json<Vec<NotificationSetting>>, (StatusCode, String)> {
+) -> Result<Json<Vec<NotificationSetting>>, ServiceError> {
     services::notifications::get_all_notification_settings(&ctx)
+        .await
         .map(Json)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct SetNotificationRequest {
     pub level: String,
 }
@@ -26,17 +26,43 @@ pub async fn set_notification_setting(
     State(ctx): State<ServiceContext>,
     Path((target_type, target_id)): Path<(String, String)>,
     Json(body): Json<SetNotificationRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::notifications::set_notification_setting(&ctx, &target_id, &target_type, &body.level)
+        .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn get_notification_setting(
     State(ctx): State<ServiceContext>,
     Path((target_type, target_id)): Path<(String, String)>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::notifications::get_notification_setting(&ctx, &target_id, &target_type)
+        .await
         .map(|level| Json(serde_json::json!({"target_id": target_id, "target_type": target_type, "level": level})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+pub async fn get_unread_summary(
+    State(ctx): State<ServiceContext>,
+) -> Result<Json<UnreadSummary>, ServiceError> {
+    services::notifications::get_unread_summary(&ctx)
+        .await
+        .map(Json)
+}
+
+pub async fn get_room_unread(
+    State(ctx): State<ServiceContext>,
+    Path(room_id): Path<String>,
+) -> Result<Json<RoomUnread>, ServiceError> {
+    services::notifications::get_room_unread(&ctx, &room_id)
+        .await
+        .map(Json)
+}
+
+pub async fn get_message_mentions(
+    State(ctx): State<ServiceContext>,
+    Path(message_id): Path<String>,
+) -> Result<Json<Vec<MessageMention>>, ServiceError> {
+    services::notifications::get_message_mentions(&ctx, &message_id)
+        .await
+        .map(Json)
 }
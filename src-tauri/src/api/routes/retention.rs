@@ -0,0 +1,84 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::models::{RetentionPolicy, RetentionScope};
+use crate::services::{self, ServiceError};
+use crate::state::ServiceContext;
+
+const SECS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Leave both unset to keep history forever.
+#[derive(Deserialize, JsonSchema)]
+pub struct RetentionRequest {
+    pub max_age_days: Option<i64>,
+    pub max_messages: Option<i64>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct DmTimerRequest {
+    /// `None` turns disappearing messages off.
+    pub timer_secs: Option<i64>,
+}
+
+pub async fn get_room_retention(
+    State(ctx): State<ServiceContext>,
+    Path(room_id): Path<String>,
+) -> Result<Json<Option<RetentionPolicy>>, ServiceError> {
+    services::retention::get_policy(&ctx, RetentionScope::Room, &room_id)
+        .await
+        .map(Json)
+}
+
+pub async fn set_room_retention(
+    State(ctx): State<ServiceContext>,
+    Path(room_id): Path<String>,
+    Json(body): Json<RetentionRequest>,
+) -> Result<Json<RetentionPolicy>, ServiceError> {
+    let max_age_secs = body.max_age_days.map(|d| d.saturating_mul(SECS_PER_DAY));
+    services::retention::set_room_policy(&ctx, &room_id, max_age_secs, body.max_messages)
+        .await
+        .map(Json)
+}
+
+pub async fn get_channel_retention(
+    State(ctx): State<ServiceContext>,
+    Path(channel_id): Path<String>,
+) -> Result<Json<Option<RetentionPolicy>>, ServiceError> {
+    services::retention::get_policy(&ctx, RetentionScope::Channel, &channel_id)
+        .await
+        .map(Json)
+}
+
+pub async fn set_channel_retention(
+    State(ctx): State<ServiceContext>,
+    Path(channel_id): Path<String>,
+    Json(body): Json<RetentionRequest>,
+) -> Result<Json<RetentionPolicy>, ServiceError> {
+    let max_age_secs = body.max_age_days.map(|d| d.saturating_mul(SECS_PER_DAY));
+    services::retention::set_channel_policy(&ctx, &channel_id, max_age_secs, body.max_messages)
+        .await
+        .map(Json)
+}
+
+pub async fn get_dm_timer(
+    State(ctx): State<ServiceContext>,
+    Path(conversation_id): Path<String>,
+) -> Result<Json<Option<RetentionPolicy>>, ServiceError> {
+    services::retention::get_policy(&ctx, RetentionScope::Dm, &conversation_id)
+        .await
+        .map(Json)
+}
+
+pub async fn set_dm_timer(
+    State(ctx): State<ServiceContext>,
+    Path(conversation_id): Path<String>,
+    Json(body): Json<DmTimerRequest>,
+) -> Result<Json<RetentionPolicy>, ServiceError> {
+    services::retention::set_dm_timer(&ctx, &conversation_id, body.timer_secs)
+        .await
+        .map(Json)
+}
@@ -3,13 +3,14 @@ use axum::{
     http::StatusCode,
     Json,
 };
+use schemars::JsonSchema;
 use serde::Deserialize;
 
 use crate::models::RoomRole;
-use crate::services;
+use crate::services::{self, ServiceError};
 use crate::state::ServiceContext;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct SetRoleRequest {
     pub peer_id: String,
     pub role: String,
@@ -19,26 +20,26 @@ pub async fn set_role(
     State(ctx): State<ServiceContext>,
     Path(room_id): Path<String>,
     Json(body): Json<SetRoleRequest>,
-) -> Result<(StatusCode, Json<RoomRole>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<RoomRole>), ServiceError> {
     services::roles::set_role(&ctx, &room_id, &body.peer_id, &body.role)
+        .await
         .map(|r| (StatusCode::CREATED, Json(r)))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn get_room_roles(
     State(ctx): State<ServiceContext>,
     Path(room_id): Path<String>,
-) -> Result<Json<Vec<RoomRole>>, (StatusCode, String)> {
+) -> Result<Json<Vec<RoomRole>>, ServiceError> {
     services::roles::get_room_roles(&ctx, &room_id)
+        .await
         .map(Json)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn remove_role(
     State(ctx): State<ServiceContext>,
     Path((room_id, peer_id)): Path<(String, String)>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::roles::remove_role(&ctx, &room_id, &peer_id)
+        .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
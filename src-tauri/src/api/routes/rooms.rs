@@ -1,23 +1,32 @@
 use axum::{
     extract::{Path, State},
     http::StatusCode,
-    Json,
+    Extension, Json,
 };
+use schemars::JsonSchema;
 use serde::Deserialize;
 
+use crate::api::auth::Authorized;
 use crate::models::{Channel, Room};
-use crate::services;
+use crate::services::{self, ServiceError};
 use crate::state::ServiceContext;
 
+/// The node's rooms; for a bot token, only the bot's.
 pub async fn list_rooms(
     State(ctx): State<ServiceContext>,
-) -> Result<Json<Vec<Room>>, (StatusCode, String)> {
-    services::rooms::list_rooms(&ctx)
-        .map(Json)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+    authorized: Option<Extension<Authorized>>,
+) -> Result<Json<Vec<Room>>, ServiceError> {
+    let mut rooms = services::rooms::list_rooms(&ctx).await?;
+    if let Some(bot_peer_id) = authorized.and_then(|Extension(a)| a.bot) {
+        let bot = services::bots::get_bot(&ctx, &bot_peer_id)
+            .await?
+            .ok_or_else(|| ServiceError::not_found("Bot not found"))?;
+        rooms.retain(|room| bot.rooms.contains(&room.id));
+    }
+    Ok(Json(rooms))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct CreateRoomRequest {
     pub name: String,
 }
@@ -25,14 +34,13 @@ pub struct CreateRoomRequest {
 pub async fn create_room(
     State(ctx): State<ServiceContext>,
     Json(body): Json<CreateRoomRequest>,
-) -> Result<(StatusCode, Json<Room>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<Room>), ServiceError> {
     services::rooms::create_room(&ctx, body.name)
         .await
         .map(|room| (StatusCode::CREATED, Json(room)))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct JoinRoomRequest {
     pub invite_code: String,
 }
@@ -40,18 +48,17 @@ pub struct JoinRoomRequest {
 pub async fn join_room(
     State(ctx): State<ServiceContext>,
     Json(body): Json<JoinRoomRequest>,
-) -> Result<Json<Room>, (StatusCode, String)> {
+) -> Result<Json<Room>, ServiceError> {
     services::rooms::join_room(&ctx, body.invite_code)
         .await
         .map(Json)
-        .map_err(|e| (StatusCode::NOT_FOUND, e))
 }
 
 pub async fn get_channels(
     State(ctx): State<ServiceContext>,
     Path(room_id): Path<String>,
-) -> Result<Json<Vec<Channel>>, (StatusCode, String)> {
+) -> Result<Json<Vec<Channel>>, ServiceError> {
     services::rooms::get_channels(&ctx, &room_id)
+        .await
         .map(Json)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
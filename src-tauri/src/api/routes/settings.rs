@@ -1,32 +1,32 @@
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
     Json,
 };
+use schemars::JsonSchema;
 use serde::Deserialize;
 
 use crate::models::Setting;
-use crate::services;
+use crate::services::{self, ServiceError};
 use crate::state::ServiceContext;
 
 pub async fn get_all_settings(
     State(ctx): State<ServiceContext>,
-) -> Result<Json<Vec<Setting>>, (StatusCode, String)> {
+) -> Result<Json<Vec<Setting>>, ServiceError> {
     services::settings::get_all_settings(&ctx)
+        .await
         .map(Json)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn get_setting(
     State(ctx): State<ServiceContext>,
     Path(key): Path<String>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::settings::get_setting(&ctx, &key)
+        .await
         .map(|v| Json(serde_json::json!({"key": key, "value": v})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct SetSettingRequest {
     pub value: String,
 }
@@ -35,17 +35,17 @@ pub async fn set_setting(
     State(ctx): State<ServiceContext>,
     Path(key): Path<String>,
     Json(body): Json<SetSettingRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::settings::set_setting(&ctx, &key, &body.value)
+        .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 pub async fn delete_setting(
     State(ctx): State<ServiceContext>,
     Path(key): Path<String>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     services::settings::delete_setting(&ctx, &key)
+        .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
@@ -0,0 +1,106 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::models::{Message, ThreadInfo};
+use crate::services::{self, ServiceError};
+use crate::state::ServiceContext;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CreateThreadRequest {
+    pub name: Option<String>,
+}
+
+pub async fn create_thread(
+    State(ctx): State<ServiceContext>,
+    Path(message_id): Path<String>,
+    Json(body): Json<CreateThreadRequest>,
+) -> Result<(StatusCode, Json<ThreadInfo>), ServiceError> {
+    services::threads::create_thread(&ctx, &message_id, body.name)
+        .await
+        .map(|thread| (StatusCode::CREATED, Json(thread)))
+}
+
+pub async fn get_thread(
+    State(ctx): State<ServiceContext>,
+    Path(thread_id): Path<String>,
+) -> Result<Json<ThreadInfo>, ServiceError> {
+    services::threads::get_thread(&ctx, &thread_id)
+        .await
+        .map(Json)
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct GetThreadMessagesQuery {
+    pub limit: Option<i64>,
+    pub before: Option<String>,
+}
+
+pub async fn get_thread_messages(
+    State(ctx): State<ServiceContext>,
+    Path(thread_id): Path<String>,
+    Query(params): Query<GetThreadMessagesQuery>,
+) -> Result<Json<Vec<Message>>, ServiceError> {
+    services::threads::get_thread_messages(&ctx, &thread_id, params.limit, params.before.as_deref())
+        .await
+        .map(Json)
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ThreadReplyRequest {
+    pub content: String,
+    pub reply_to_id: Option<String>,
+}
+
+pub async fn reply_in_thread(
+    State(ctx): State<ServiceContext>,
+    Path(thread_id): Path<String>,
+    Json(body): Json<ThreadReplyRequest>,
+) -> Result<(StatusCode, Json<Message>), ServiceError> {
+    let root_id = thread_id.clone();
+    let root = ctx.db.run(move |db| db.get_message(&root_id)).await
+        .map_err(ServiceError::internal)?
+        .ok_or_else(|| ServiceError::not_found("Thread root message not found"))?;
+    services::messaging::send_message(&ctx, root.channel_id, body.content, body.reply_to_id, Some(thread_id), None)
+        .await
+        .map(|msg| (StatusCode::CREATED, Json(msg)))
+}
+
+pub async fn subscribe(
+    State(ctx): State<ServiceContext>,
+    Path(thread_id): Path<String>,
+) -> Result<Json<serde_json::Value>, ServiceError> {
+    services::threads::set_subscription(&ctx, &thread_id, true)
+        .await
+        .map(|_| Json(serde_json::json!({"ok": true})))
+}
+
+pub async fn unsubscribe(
+    State(ctx): State<ServiceContext>,
+    Path(thread_id): Path<String>,
+) -> Result<Json<serde_json::Value>, ServiceError> {
+    services::threads::set_subscription(&ctx, &thread_id, false)
+        .await
+        .map(|_| Json(serde_json::json!({"ok": true})))
+}
+
+pub async fn list_subscribed_threads(
+    State(ctx): State<ServiceContext>,
+) -> Result<Json<Vec<ThreadInfo>>, ServiceError> {
+    services::threads::list_subscribed_threads(&ctx)
+        .await
+        .map(Json)
+}
+
+pub async fn list_channel_threads(
+    State(ctx): State<ServiceContext>,
+    Path(channel_id): Path<String>,
+) -> Result<Json<Vec<ThreadInfo>>, ServiceError> {
+    services::threads::list_channel_threads(&ctx, &channel_id)
+        .await
+        .map(Json)
+}
@@ -1,17 +1,18 @@
 use axum::{
     extract::State,
-    http::StatusCode,
     Json,
 };
+use schemars::JsonSchema;
 use serde::Deserialize;
 
 use crate::media::{audio, video, MediaCommand};
 use crate::network::NetworkCommand;
+use crate::services::ServiceError;
 use crate::state::ServiceContext;
 
 // --- Camera & Screen share routes ---
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct EnableCameraRequest {
     pub device_index: Option<u32>,
 }
@@ -19,55 +20,55 @@ pub struct EnableCameraRequest {
 pub async fn enable_camera(
     State(ctx): State<ServiceContext>,
     Json(body): Json<EnableCameraRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     ctx.media_tx
         .send(MediaCommand::EnableCamera {
             device_index: body.device_index,
         })
         .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to enable camera: {}", e)))
+        .map_err(|e| ServiceError::internal(format!("Failed to enable camera: {}", e)))
 }
 
 pub async fn disable_camera(
     State(ctx): State<ServiceContext>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     ctx.media_tx
         .send(MediaCommand::DisableCamera)
         .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to disable camera: {}", e)))
+        .map_err(|e| ServiceError::internal(format!("Failed to disable camera: {}", e)))
 }
 
 pub async fn list_cameras(
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     let cameras = video::list_cameras();
     Ok(Json(serde_json::json!({ "cameras": cameras })))
 }
 
 pub async fn start_screen_share(
     State(ctx): State<ServiceContext>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     ctx.media_tx
         .send(MediaCommand::StartScreenShare)
         .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to start screen share: {}", e)))
+        .map_err(|e| ServiceError::internal(format!("Failed to start screen share: {}", e)))
 }
 
 pub async fn stop_screen_share(
     State(ctx): State<ServiceContext>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     ctx.media_tx
         .send(MediaCommand::StopScreenShare)
         .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to stop screen share: {}", e)))
+        .map_err(|e| ServiceError::internal(format!("Failed to stop screen share: {}", e)))
 }
 
 // --- Media engine voice routes ---
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct JoinVoiceRequest {
     pub room_id: String,
     pub channel_id: String,
@@ -76,7 +77,7 @@ pub struct JoinVoiceRequest {
 pub async fn join_voice(
     State(ctx): State<ServiceContext>,
     Json(body): Json<JoinVoiceRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     ctx.media_tx
         .send(MediaCommand::JoinVoice {
             room_id: body.room_id,
@@ -84,20 +85,20 @@ pub async fn join_voice(
         })
         .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to join voice: {}", e)))
+        .map_err(|e| ServiceError::internal(format!("Failed to join voice: {}", e)))
 }
 
 pub async fn leave_voice(
     State(ctx): State<ServiceContext>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     ctx.media_tx
         .send(MediaCommand::LeaveVoice)
         .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to leave voice: {}", e)))
+        .map_err(|e| ServiceError::internal(format!("Failed to leave voice: {}", e)))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct MutedRequest {
     pub muted: bool,
 }
@@ -105,15 +106,15 @@ pub struct MutedRequest {
 pub async fn set_muted(
     State(ctx): State<ServiceContext>,
     Json(body): Json<MutedRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     ctx.media_tx
         .send(MediaCommand::SetMuted(body.muted))
         .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to set muted: {}", e)))
+        .map_err(|e| ServiceError::internal(format!("Failed to set muted: {}", e)))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct DeafenedRequest {
     pub deafened: bool,
 }
@@ -121,30 +122,30 @@ pub struct DeafenedRequest {
 pub async fn set_deafened(
     State(ctx): State<ServiceContext>,
     Json(body): Json<DeafenedRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     ctx.media_tx
         .send(MediaCommand::SetDeafened(body.deafened))
         .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to set deafened: {}", e)))
+        .map_err(|e| ServiceError::internal(format!("Failed to set deafened: {}", e)))
 }
 
 pub async fn list_devices(
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     let devices = audio::list_devices();
     Ok(Json(serde_json::json!({ "devices": devices })))
 }
 
 pub async fn get_voice_state(
     State(ctx): State<ServiceContext>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     let state = ctx.voice_state_rx.borrow().clone();
     Ok(Json(serde_json::to_value(state).unwrap_or_default()))
 }
 
 // --- Existing signaling routes (kept for backwards compatibility) ---
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct CallOfferRequest {
     pub room_id: String,
     pub to_peer_id: String,
@@ -156,7 +157,7 @@ pub struct CallOfferRequest {
 pub async fn send_call_offer(
     State(ctx): State<ServiceContext>,
     Json(body): Json<CallOfferRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     ctx.network_tx
         .send(NetworkCommand::SendCallOffer {
             room_id: body.room_id,
@@ -167,10 +168,10 @@ pub async fn send_call_offer(
         })
         .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to send call offer: {}", e)))
+        .map_err(|e| ServiceError::internal(format!("Failed to send call offer: {}", e)))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct CallAnswerRequest {
     pub room_id: String,
     pub to_peer_id: String,
@@ -182,7 +183,7 @@ pub struct CallAnswerRequest {
 pub async fn send_call_answer(
     State(ctx): State<ServiceContext>,
     Json(body): Json<CallAnswerRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     ctx.network_tx
         .send(NetworkCommand::SendCallAnswer {
             room_id: body.room_id,
@@ -193,10 +194,10 @@ pub async fn send_call_answer(
         })
         .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to send call answer: {}", e)))
+        .map_err(|e| ServiceError::internal(format!("Failed to send call answer: {}", e)))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct IceCandidateRequest {
     pub room_id: String,
     pub to_peer_id: String,
@@ -207,7 +208,7 @@ pub struct IceCandidateRequest {
 pub async fn send_ice_candidate(
     State(ctx): State<ServiceContext>,
     Json(body): Json<IceCandidateRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     ctx.network_tx
         .send(NetworkCommand::SendIceCandidate {
             room_id: body.room_id,
@@ -217,10 +218,10 @@ pub async fn send_ice_candidate(
         })
         .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to send ICE candidate: {}", e)))
+        .map_err(|e| ServiceError::internal(format!("Failed to send ICE candidate: {}", e)))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct VoiceStateRequest {
     pub room_id: String,
     pub channel_id: Option<String>,
@@ -233,7 +234,7 @@ pub struct VoiceStateRequest {
 pub async fn update_voice_state(
     State(ctx): State<ServiceContext>,
     Json(body): Json<VoiceStateRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ServiceError> {
     ctx.network_tx
         .send(NetworkCommand::SendVoiceState {
             room_id: body.room_id,
@@ -245,5 +246,5 @@ pub async fn update_voice_state(
         })
         .await
         .map(|_| Json(serde_json::json!({"ok": true})))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update voice state: {}", e)))
+        .map_err(|e| ServiceError::internal(format!("Failed to update voice state: {}", e)))
 }
@@ -0,0 +1,96 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::events::EventFilter;
+use crate::models::{CreatedWebhook, Webhook, WebhookDelivery};
+use crate::services::{self, ServiceError};
+use crate::state::ServiceContext;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    #[serde(flatten)]
+    pub filter: EventFilter,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct UpdateWebhookRequest {
+    pub url: Option<String>,
+    pub filter: Option<EventFilter>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct GetDeliveriesQuery {
+    pub limit: Option<i64>,
+}
+
+pub async fn list_webhooks(
+    State(ctx): State<ServiceContext>,
+) -> Result<Json<Vec<Webhook>>, ServiceError> {
+    services::webhooks::list_webhooks(&ctx)
+        .await
+        .map(Json)
+}
+
+pub async fn create_webhook(
+    State(ctx): State<ServiceContext>,
+    Json(body): Json<CreateWebhookRequest>,
+) -> Result<(StatusCode, Json<CreatedWebhook>), ServiceError> {
+    services::webhooks::create_webhook(&ctx, &body.url, body.filter)
+        .await
+        .map(|created| (StatusCode::CREATED, Json(created)))
+}
+
+pub async fn get_webhook(
+    State(ctx): State<ServiceContext>,
+    Path(webhook_id): Path<String>,
+) -> Result<Json<Webhook>, ServiceError> {
+    services::webhooks::get_webhook(&ctx, &webhook_id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ServiceError::not_found("Webhook not found"))
+}
+
+pub async fn update_webhook(
+    State(ctx): State<ServiceContext>,
+    Path(webhook_id): Path<String>,
+    Json(body): Json<UpdateWebhookRequest>,
+) -> Result<Json<Webhook>, ServiceError> {
+    services::webhooks::update_webhook(&ctx, &webhook_id, body.url, body.filter, body.enabled)
+        .await
+        .map(Json)
+}
+
+pub async fn delete_webhook(
+    State(ctx): State<ServiceContext>,
+    Path(webhook_id): Path<String>,
+) -> Result<Json<serde_json::Value>, ServiceError> {
+    services::webhooks::delete_webhook(&ctx, &webhook_id)
+        .await
+        .map(|deleted| Json(serde_json::json!({"deleted": deleted})))
+}
+
+pub async fn get_deliveries(
+    State(ctx): State<ServiceContext>,
+    Path(webhook_id): Path<String>,
+    Query(params): Query<GetDeliveriesQuery>,
+) -> Result<Json<Vec<WebhookDelivery>>, ServiceError> {
+    services::webhooks::get_deliveries(&ctx, &webhook_id, params.limit)
+        .await
+        .map(Json)
+}
+
+pub async fn test_webhook(
+    State(ctx): State<ServiceContext>,
+    Path(webhook_id): Path<String>,
+) -> Result<Json<WebhookDelivery>, ServiceError> {
+    services::webhooks::test_webhook(&ctx, &webhook_id)
+        .await
+        .map(Json)
+}
@@ -0,0 +1,501 @@
+//! JSON-RPC over the `/ws` socket.
+//!
+//! Clients send `{"id": 1, "method": "messages.send", "params": {...}}` and
+//! get back `{"id": 1, "result": ...}` or `{"id": 1, "error": {"code", "message"}}`,
+//! interleaved with the usual event stream. Each method is a REST route: path
+//! placeholders are taken from `params`, the rest becomes the query string
+//! (GET, DELETE) or the JSON body, and the call runs through the same router,
+//! so behaviour and token scopes match the REST API exactly.
+//!
+//! `events.subscribe` is the one socket-level method: it sets the socket's
+//! event filter and can replay the event journal (see `api::websocket`).
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, Method, Request, StatusCode},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tower::ServiceExt;
+
+use crate::api::auth::{required_scope, Authorized};
+use crate::api::error::ErrorBody;
+
+/// Largest response body an RPC call returns.
+const MAX_RESULT_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+pub struct RpcRequest {
+    #[serde(default)]
+    pub id: Value,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpcErrorCode {
+    ParseError,
+    InvalidRequest,
+    MethodNotFound,
+    InvalidParams,
+    Internal,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    Conflict,
+}
+
+impl RpcErrorCode {
+    /// JSON-RPC 2.0 codes, plus -32001.. for API errors.
+    pub fn code(self) -> i32 {
+        match self {
+            RpcErrorCode::ParseError => -32700,
+            RpcErrorCode::InvalidRequest => -32600,
+            RpcErrorCode::MethodNotFound => -32601,
+            RpcErrorCode::InvalidParams => -32602,
+            RpcErrorCode::Internal => -32603,
+            RpcErrorCode::Unauthorized => -32001,
+            RpcErrorCode::Forbidden => -32003,
+            RpcErrorCode::NotFound => -32004,
+            RpcErrorCode::Conflict => -32009,
+        }
+    }
+
+    fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY | StatusCode::UNSUPPORTED_MEDIA_TYPE
+            | StatusCode::PAYLOAD_TOO_LARGE => RpcErrorCode::InvalidParams,
+            StatusCode::UNAUTHORIZED => RpcErrorCode::Unauthorized,
+            StatusCode::FORBIDDEN => RpcErrorCode::Forbidden,
+            StatusCode::NOT_FOUND => RpcErrorCode::NotFound,
+            StatusCode::CONFLICT => RpcErrorCode::Conflict,
+            _ => RpcErrorCode::Internal,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RpcError {
+    pub code: i32,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: RpcErrorCode, message: impl Into<String>) -> Self {
+        Self { code: code.code(), message: message.into() }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RpcResponse {
+    pub id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+/// `(method, HTTP method, route)`. Backups and exports return files and stay REST-only.
+pub const RPC_METHODS: &[(&str, Method, &str)] = &[
+    // Identity
+    ("identity.get", Method::GET, "/api/v1/identity"),
+    ("identity.set_display_name", Method::PUT, "/api/v1/identity/display-name"),
+    ("identity.set_status", Method::PUT, "/api/v1/identity/status"),
+    ("identity.set_avatar", Method::PUT, "/api/v1/identity/avatar"),
+    ("identity.get_encryption", Method::GET, "/api/v1/identity/encryption"),
+    ("identity.change_passphrase", Method::PUT, "/api/v1/identity/passphrase"),
+    ("identity.get_recovery_phrase", Method::GET, "/api/v1/identity/recovery-phrase"),
+    ("identity.import", Method::POST, "/api/v1/identity/import"),
+    ("identity.rotate_key", Method::POST, "/api/v1/identity/rotate"),
+    // API tokens
+    ("tokens.list", Method::GET, "/api/v1/tokens"),
+    ("tokens.issue", Method::POST, "/api/v1/tokens"),
+    ("tokens.revoke", Method::DELETE, "/api/v1/tokens/:token_id"),
+    // Bots
+    ("bots.list", Method::GET, "/api/v1/bots"),
+    ("bots.create", Method::POST, "/api/v1/bots"),
+    ("bots.get", Method::GET, "/api/v1/bots/:bot_peer_id"),
+    ("bots.update", Method::PUT, "/api/v1/bots/:bot_peer_id"),
+    ("bots.delete", Method::DELETE, "/api/v1/bots/:bot_peer_id"),
+    ("bots.regenerate_token", Method::POST, "/api/v1/bots/:bot_peer_id/token"),
+    ("bots.join_room", Method::PUT, "/api/v1/bots/:bot_peer_id/rooms/:room_id"),
+    ("bots.leave_room", Method::DELETE, "/api/v1/bots/:bot_peer_id/rooms/:room_id"),
+    ("bot.get", Method::GET, "/api/v1/bot"),
+    ("bot.list_commands", Method::GET, "/api/v1/bot/commands"),
+    ("bot.register_command", Method::PUT, "/api/v1/bot/rooms/:room_id/commands/:name"),
+    ("bot.unregister_command", Method::DELETE, "/api/v1/bot/rooms/:room_id/commands/:name"),
+    // Webhooks
+    ("webhooks.list", Method::GET, "/api/v1/webhooks"),
+    ("webhooks.create", Method::POST, "/api/v1/webhooks"),
+    ("webhooks.get", Method::GET, "/api/v1/webhooks/:webhook_id"),
+    ("webhooks.update", Method::PUT, "/api/v1/webhooks/:webhook_id"),
+    ("webhooks.delete", Method::DELETE, "/api/v1/webhooks/:webhook_id"),
+    ("webhooks.deliveries", Method::GET, "/api/v1/webhooks/:webhook_id/deliveries"),
+    ("webhooks.test", Method::POST, "/api/v1/webhooks/:webhook_id/test"),
+    // Incoming webhooks
+    ("incoming_webhooks.list", Method::GET, "/api/v1/incoming-webhooks"),
+    ("incoming_webhooks.create", Method::POST, "/api/v1/incoming-webhooks"),
+    ("incoming_webhooks.revoke", Method::DELETE, "/api/v1/incoming-webhooks/:webhook_id"),
+    // Devices
+    ("devices.list", Method::GET, "/api/v1/devices"),
+    ("devices.link", Method::POST, "/api/v1/devices"),
+    ("devices.get_link_request", Method::GET, "/api/v1/devices/link-request"),
+    ("devices.accept", Method::POST, "/api/v1/devices/accept"),
+    ("devices.unlink", Method::DELETE, "/api/v1/devices/:device_peer_id"),
+    // Import
+    ("import.discord", Method::POST, "/api/v1/import/discord"),
+    // Rooms
+    ("rooms.list", Method::GET, "/api/v1/rooms"),
+    ("rooms.create", Method::POST, "/api/v1/rooms"),
+    ("rooms.join", Method::POST, "/api/v1/rooms/join"),
+    ("rooms.delete_my_messages", Method::DELETE, "/api/v1/rooms/:room_id/messages/mine"),
+    ("rooms.get_retention", Method::GET, "/api/v1/rooms/:room_id/retention"),
+    ("rooms.set_retention", Method::PUT, "/api/v1/rooms/:room_id/retention"),
+    ("rooms.get_channels", Method::GET, "/api/v1/rooms/:room_id/channels"),
+    ("rooms.get_categories", Method::GET, "/api/v1/rooms/:room_id/categories"),
+    ("rooms.get_layout", Method::GET, "/api/v1/rooms/:room_id/layout"),
+    ("rooms.get_unread", Method::GET, "/api/v1/rooms/:room_id/unread"),
+    ("rooms.get_peers", Method::GET, "/api/v1/rooms/:room_id/peers"),
+    ("rooms.get_roles", Method::GET, "/api/v1/rooms/:room_id/roles"),
+    ("rooms.set_role", Method::POST, "/api/v1/rooms/:room_id/roles"),
+    ("rooms.remove_role", Method::DELETE, "/api/v1/rooms/:room_id/roles/:peer_id"),
+    ("rooms.moderate", Method::POST, "/api/v1/rooms/:room_id/moderate"),
+    ("rooms.get_audit_log", Method::GET, "/api/v1/rooms/:room_id/audit-log"),
+    ("rooms.list_emoji", Method::GET, "/api/v1/rooms/:room_id/emoji"),
+    ("rooms.add_emoji", Method::POST, "/api/v1/rooms/:room_id/emoji"),
+    // Channels
+    ("channels.create", Method::POST, "/api/v1/rooms/:room_id/channels"),
+    ("channels.update", Method::PUT, "/api/v1/channels/:channel_id"),
+    ("channels.delete", Method::DELETE, "/api/v1/channels/:channel_id"),
+    ("channels.move", Method::PUT, "/api/v1/channels/:channel_id/category"),
+    ("channels.get_retention", Method::GET, "/api/v1/channels/:channel_id/retention"),
+    ("channels.set_retention", Method::PUT, "/api/v1/channels/:channel_id/retention"),
+    ("channels.get_permissions", Method::GET, "/api/v1/channels/:channel_id/permissions"),
+    ("channels.set_permissions", Method::PUT, "/api/v1/channels/:channel_id/permissions"),
+    ("channels.get_threads", Method::GET, "/api/v1/channels/:channel_id/threads"),
+    ("channels.list_commands", Method::GET, "/api/v1/channels/:channel_id/commands"),
+    ("channels.run_command", Method::POST, "/api/v1/channels/:channel_id/commands"),
+    ("channels.autocomplete_command", Method::GET, "/api/v1/channels/:channel_id/commands/autocomplete"),
+    ("channels.typing", Method::POST, "/api/v1/channels/:channel_id/typing"),
+    ("channels.mark_read", Method::POST, "/api/v1/channels/:channel_id/read"),
+    ("channels.get_read_receipts", Method::GET, "/api/v1/channels/:channel_id/read-receipts"),
+    ("channels.get_pins", Method::GET, "/api/v1/channels/:channel_id/pins"),
+    ("channels.pin", Method::POST, "/api/v1/channels/:channel_id/pins"),
+    ("channels.unpin", Method::DELETE, "/api/v1/channels/:channel_id/pins/:message_id"),
+    // Categories
+    ("categories.create", Method::POST, "/api/v1/rooms/:room_id/categories"),
+    ("categories.update", Method::PUT, "/api/v1/categories/:category_id"),
+    ("categories.delete", Method::DELETE, "/api/v1/categories/:category_id"),
+    ("categories.set_collapsed", Method::PUT, "/api/v1/categories/:category_id/collapsed"),
+    // Messages
+    ("messages.list", Method::GET, "/api/v1/channels/:channel_id/messages"),
+    ("messages.send", Method::POST, "/api/v1/channels/:channel_id/messages"),
+    ("messages.edit", Method::PUT, "/api/v1/messages/:message_id"),
+    ("messages.delete", Method::DELETE, "/api/v1/messages/:message_id"),
+    ("messages.get_reactions", Method::GET, "/api/v1/messages/:message_id/reactions"),
+    ("messages.react", Method::POST, "/api/v1/messages/:message_id/reactions"),
+    ("messages.unreact", Method::DELETE, "/api/v1/messages/:message_id/reactions/:emoji"),
+    ("messages.get_attachments", Method::GET, "/api/v1/messages/:message_id/attachments"),
+    ("messages.attach_file", Method::POST, "/api/v1/messages/:message_id/attachments"),
+    ("messages.get_mentions", Method::GET, "/api/v1/messages/:message_id/mentions"),
+    ("messages.create_thread", Method::POST, "/api/v1/messages/:message_id/thread"),
+    // Threads
+    ("threads.list_subscribed", Method::GET, "/api/v1/threads/subscribed"),
+    ("threads.get", Method::GET, "/api/v1/threads/:thread_id"),
+    ("threads.get_messages", Method::GET, "/api/v1/threads/:thread_id/messages"),
+    ("threads.reply", Method::POST, "/api/v1/threads/:thread_id/messages"),
+    ("threads.subscribe", Method::PUT, "/api/v1/threads/:thread_id/subscription"),
+    ("threads.unsubscribe", Method::DELETE, "/api/v1/threads/:thread_id/subscription"),
+    // Search
+    ("search.all", Method::GET, "/api/v1/search"),
+    ("search.messages", Method::GET, "/api/v1/search/messages"),
+    // DMs
+    ("dms.list", Method::GET, "/api/v1/dms"),
+    ("dms.create", Method::POST, "/api/v1/dms"),
+    ("dms.get_participants", Method::GET, "/api/v1/dms/:conversation_id/participants"),
+    ("dms.get_timer", Method::GET, "/api/v1/dms/:conversation_id/timer"),
+    ("dms.set_timer", Method::PUT, "/api/v1/dms/:conversation_id/timer"),
+    ("dms.get_messages", Method::GET, "/api/v1/dms/:conversation_id/messages"),
+    ("dms.send", Method::POST, "/api/v1/dms/:conversation_id/messages"),
+    // Files
+    ("files.register", Method::POST, "/api/v1/files"),
+    ("files.get", Method::GET, "/api/v1/files/:file_id"),
+    // Friends
+    ("friends.list", Method::GET, "/api/v1/friends"),
+    ("friends.request", Method::POST, "/api/v1/friends"),
+    ("friends.get", Method::GET, "/api/v1/friends/:peer_id"),
+    ("friends.remove", Method::DELETE, "/api/v1/friends/:peer_id"),
+    ("friends.accept", Method::POST, "/api/v1/friends/:peer_id/accept"),
+    // Blocked peers
+    ("blocked.list", Method::GET, "/api/v1/blocked"),
+    ("blocked.block", Method::POST, "/api/v1/blocked"),
+    ("blocked.unblock", Method::DELETE, "/api/v1/blocked/:peer_id"),
+    // Emoji
+    ("emoji.remove", Method::DELETE, "/api/v1/emoji/:emoji_id"),
+    // Settings
+    ("settings.list", Method::GET, "/api/v1/settings"),
+    ("settings.get", Method::GET, "/api/v1/settings/:key"),
+    ("settings.set", Method::PUT, "/api/v1/settings/:key"),
+    ("settings.delete", Method::DELETE, "/api/v1/settings/:key"),
+    // Notifications
+    ("notifications.list", Method::GET, "/api/v1/notifications"),
+    ("notifications.get_unread", Method::GET, "/api/v1/unread"),
+    ("notifications.get", Method::GET, "/api/v1/notifications/:target_type/:target_id"),
+    ("notifications.set", Method::PUT, "/api/v1/notifications/:target_type/:target_id"),
+    // Voice
+    ("voice.join", Method::POST, "/api/v1/voice/join"),
+    ("voice.leave", Method::POST, "/api/v1/voice/leave"),
+    ("voice.set_muted", Method::PUT, "/api/v1/voice/muted"),
+    ("voice.set_deafened", Method::PUT, "/api/v1/voice/deafened"),
+    ("voice.list_devices", Method::GET, "/api/v1/voice/devices"),
+    ("voice.get_state", Method::GET, "/api/v1/voice/state"),
+    ("voice.enable_camera", Method::POST, "/api/v1/voice/camera/enable"),
+    ("voice.disable_camera", Method::POST, "/api/v1/voice/camera/disable"),
+    ("voice.list_cameras", Method::GET, "/api/v1/voice/cameras"),
+    ("voice.start_screen_share", Method::POST, "/api/v1/voice/screen/start"),
+    ("voice.stop_screen_share", Method::POST, "/api/v1/voice/screen/stop"),
+    ("voice.send_offer", Method::POST, "/api/v1/voice/offer"),
+    ("voice.send_answer", Method::POST, "/api/v1/voice/answer"),
+    ("voice.send_ice_candidate", Method::POST, "/api/v1/voice/ice-candidate"),
+    ("voice.broadcast_state", Method::POST, "/api/v1/voice/broadcast-state"),
+];
+
+/// Percent-encode everything but RFC 3986 unreserved characters.
+fn encode_component(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn scalar_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Turn an RPC call into the request its route expects.
+fn build_request(method: &Method, route: &str, params: Value) -> Result<Request<Body>, RpcError> {
+    let mut params = match params {
+        Value::Null => Map::new(),
+        Value::Object(map) => map,
+        _ => return Err(RpcError::new(RpcErrorCode::InvalidParams, "params must be an object")),
+    };
+
+    let mut path = String::new();
+    for segment in route.split('/').skip(1) {
+        path.push('/');
+        match segment.strip_prefix(':') {
+            Some(name) => {
+                let value = params.remove(name).as_ref().and_then(scalar_to_string).ok_or_else(|| {
+                    RpcError::new(RpcErrorCode::InvalidParams, format!("missing param '{}'", name))
+                })?;
+                path.push_str(&encode_component(&value));
+            }
+            None => path.push_str(segment),
+        }
+    }
+
+    let builder = Request::builder().method(method.clone());
+    let request = if *method == Method::GET || *method == Method::DELETE {
+        let mut query = Vec::new();
+        for (key, value) in &params {
+            if value.is_null() {
+                continue;
+            }
+            let value = scalar_to_string(value).ok_or_else(|| {
+                RpcError::new(RpcErrorCode::InvalidParams, format!("param '{}' must be a string, number or bool", key))
+            })?;
+            query.push(format!("{}={}", encode_component(key), encode_component(&value)));
+        }
+        if !query.is_empty() {
+            path.push('?');
+            path.push_str(&query.join("&"));
+        }
+        builder.uri(path).body(Body::empty())
+    } else {
+        builder
+            .uri(path)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(Value::Object(params).to_string()))
+    };
+    request.map_err(|e| RpcError::new(RpcErrorCode::InvalidParams, e.to_string()))
+}
+
+async fn call(rest: Router, authorized: &Authorized, method: &str, params: Value) -> Result<Value, RpcError> {
+    let (_, http_method, route) = RPC_METHODS
+        .iter()
+        .find(|(name, _, _)| *name == method)
+        .ok_or_else(|| RpcError::new(RpcErrorCode::MethodNotFound, format!("Unknown method '{}'", method)))?;
+
+    let needed = required_scope(http_method, route);
+    if !authorized.allows(needed) {
+        return Err(RpcError::new(
+            RpcErrorCode::Forbidden,
+            format!("Token lacks the '{}' scope", needed.as_str()),
+        ));
+    }
+
+    let mut request = build_request(http_method, route, params)?;
+    request.extensions_mut().insert(authorized.clone());
+    let response = rest
+        .oneshot(request)
+        .await
+        .map_err(|e| RpcError::new(RpcErrorCode::Internal, e.to_string()))?;
+    let status = response.status();
+    let body = to_bytes(response.into_body(), MAX_RESULT_BYTES)
+        .await
+        .map_err(|e| RpcError::new(RpcErrorCode::Internal, e.to_string()))?;
+
+    if !status.is_success() {
+        // Error bodies are `api::error::ErrorBody`; extractor rejections are plain text
+        let message = match serde_json::from_slice::<ErrorBody>(&body) {
+            Ok(error) => error.message,
+            Err(_) => String::from_utf8_lossy(&body).into_owned(),
+        };
+        let message = if message.is_empty() { status.to_string() } else { message };
+        return Err(RpcError::new(RpcErrorCode::from_status(status), message));
+    }
+    if body.is_empty() {
+        return Ok(Value::Null);
+    }
+    serde_json::from_slice(&body).map_err(|e| RpcError::new(RpcErrorCode::Internal, e.to_string()))
+}
+
+/// Handle one text frame from the socket and produce the reply to send back.
+pub async fn handle_text(rest: Router, authorized: Authorized, text: &str) -> RpcResponse {
+    let request: RpcRequest = match serde_json::from_str::<Value>(text) {
+        Err(e) => {
+            return RpcResponse { id: Value::Null, result: None, error: Some(RpcError::new(RpcErrorCode::ParseError, e.to_string())) };
+        }
+        Ok(value) => {
+            let id = value.get("id").cloned().unwrap_or(Value::Null);
+            match serde_json::from_value(value) {
+                Ok(request) => request,
+                Err(e) => {
+                    return RpcResponse { id, result: None, error: Some(RpcError::new(RpcErrorCode::InvalidRequest, e.to_string())) };
+                }
+            }
+        }
+    };
+    match call(rest, &authorized, &request.method, request.params).await {
+        Ok(result) => RpcResponse { id: request.id, result: Some(result), error: None },
+        Err(error) => RpcResponse { id: request.id, result: None, error: Some(error) },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ApiScope;
+    use crate::services::ServiceError;
+    use axum::{extract::OriginalUri, routing::get, Json};
+
+    /// Stands in for the REST router: echoes what the routes received.
+    fn rest() -> Router {
+        Router::new()
+            .route(
+                "/api/v1/channels/:channel_id/messages",
+                get(|uri: OriginalUri| async move { Json(Value::String(uri.to_string())) })
+                    .post(|uri: OriginalUri, Json(body): Json<Value>| async move { Json(serde_json::json!({ "uri": uri.to_string(), "body": body })) }),
+            )
+            .route(
+                "/api/v1/messages/:message_id",
+                axum::routing::delete(|| async { ServiceError::not_found("Message not found") })
+                    .put(|| async { StatusCode::NO_CONTENT }),
+            )
+            .route("/api/v1/tokens", get(|| async { Json(Value::Array(Vec::new())) }))
+    }
+
+    async fn call(scopes: &[ApiScope], text: &str) -> RpcResponse {
+        handle_text(rest(), Authorized { scopes: scopes.to_vec(), bot: None }, text).await
+    }
+
+    fn error_code(response: &RpcResponse) -> Option<i32> {
+        response.error.as_ref().map(|e| e.code)
+    }
+
+    #[test]
+    fn method_names_are_unique() {
+        let mut names: Vec<&str> = RPC_METHODS.iter().map(|(name, _, _)| *name).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), RPC_METHODS.len());
+    }
+
+    #[test]
+    fn params_fill_the_path_then_the_query() {
+        let request = build_request(
+            &Method::GET,
+            "/api/v1/channels/:channel_id/messages",
+            serde_json::json!({ "channel_id": "a/b c", "limit": 5, "before": null }),
+        )
+        .unwrap();
+        assert_eq!(request.uri(), "/api/v1/channels/a%2Fb%20c/messages?limit=5");
+
+        let missing = build_request(&Method::GET, "/api/v1/channels/:channel_id/messages", Value::Null).unwrap_err();
+        assert_eq!(missing.code, RpcErrorCode::InvalidParams.code());
+        let not_object = build_request(&Method::GET, "/api/v1/rooms", serde_json::json!([1])).unwrap_err();
+        assert_eq!(not_object.code, RpcErrorCode::InvalidParams.code());
+    }
+
+    #[tokio::test]
+    async fn calls_run_through_the_rest_routes() {
+        let response = call(&[ApiScope::Read], r#"{"id": 1, "method": "messages.list", "params": {"channel_id": "c1", "limit": 2}}"#).await;
+        assert_eq!(response.id, Value::from(1));
+        assert_eq!(response.result, Some(Value::from("/api/v1/channels/c1/messages?limit=2")));
+
+        let response = call(&[ApiScope::Write], r#"{"id": "x", "method": "messages.send", "params": {"channel_id": "c1", "content": "hi"}}"#).await;
+        assert_eq!(
+            response.result,
+            Some(serde_json::json!({ "uri": "/api/v1/channels/c1/messages", "body": { "content": "hi" } }))
+        );
+
+        // An empty success body is null
+        let response = call(&[ApiScope::Write], r#"{"id": 2, "method": "messages.edit", "params": {"message_id": "m1"}}"#).await;
+        assert_eq!(response.result, Some(Value::Null));
+    }
+
+    #[tokio::test]
+    async fn error_codes() {
+        let response = call(&[ApiScope::Admin], "{not json").await;
+        assert_eq!((response.id.clone(), error_code(&response)), (Value::Null, Some(-32700)));
+
+        let response = call(&[ApiScope::Admin], r#"{"id": 3}"#).await;
+        assert_eq!((response.id.clone(), error_code(&response)), (Value::from(3), Some(-32600)));
+
+        let response = call(&[ApiScope::Admin], r#"{"id": 4, "method": "nope.nothing"}"#).await;
+        assert_eq!(error_code(&response), Some(-32601));
+
+        let response = call(&[ApiScope::Admin], r#"{"id": 5, "method": "messages.list"}"#).await;
+        assert_eq!(error_code(&response), Some(-32602));
+
+        // Scopes are checked before the route runs
+        let response = call(&[ApiScope::Read], r#"{"id": 6, "method": "messages.send", "params": {"channel_id": "c1"}}"#).await;
+        assert_eq!(error_code(&response), Some(-32003));
+        let response = call(&[ApiScope::Write], r#"{"id": 7, "method": "tokens.list"}"#).await;
+        assert_eq!(error_code(&response), Some(-32003));
+
+        // API errors keep their message
+        let response = call(&[ApiScope::Write], r#"{"id": 8, "method": "messages.delete", "params": {"message_id": "m1"}}"#).await;
+        let error = response.error.unwrap();
+        assert_eq!((error.code, error.message.as_str()), (-32004, "Message not found"));
+    }
+
+    #[test]
+    fn statuses_map_to_codes() {
+        assert_eq!(RpcErrorCode::from_status(StatusCode::UNPROCESSABLE_ENTITY), RpcErrorCode::InvalidParams);
+        assert_eq!(RpcErrorCode::from_status(StatusCode::UNAUTHORIZED).code(), -32001);
+        assert_eq!(RpcErrorCode::from_status(StatusCode::CONFLICT).code(), -32009);
+        assert_eq!(RpcErrorCode::from_status(StatusCode::SERVICE_UNAVAILABLE), RpcErrorCode::Internal);
+    }
+}
@@ -1,22 +1,83 @@
-use axum::{routing::{delete, get, post, put}, Router};
-use tower_http::cors::CorsLayer;
+use axum::{extract::DefaultBodyLimit, middleware, routing::{delete, get, post, put}, Extension, Router};
 use tracing::info;
 
-use crate::api::{routes, websocket};
+use crate::api::{auth, openapi, routes, websocket};
 use crate::media::frame_server::{self, FrameServerState};
 use crate::state::ServiceContext;
 
-pub fn build_router(ctx: ServiceContext, frame_server: FrameServerState) -> Router {
+/// Every `/api/v1` route. Also what WebSocket RPC calls dispatch to.
+fn rest_routes() -> Router<ServiceContext> {
     Router::new()
         // Identity
         .route("/api/v1/identity", get(routes::identity::get_identity))
         .route("/api/v1/identity/display-name", put(routes::identity::set_display_name))
         .route("/api/v1/identity/status", put(routes::identity::set_status))
         .route("/api/v1/identity/avatar", put(routes::identity::set_avatar))
+        .route("/api/v1/identity/encryption", get(routes::identity::get_encryption))
+        .route("/api/v1/identity/passphrase", put(routes::identity::change_passphrase))
+        .route("/api/v1/identity/recovery-phrase", get(routes::identity::get_recovery_phrase))
+        .route("/api/v1/identity/import", post(routes::identity::import_identity))
+        .route("/api/v1/identity/rotate", post(routes::identity::rotate_key))
+        // API tokens
+        .route("/api/v1/tokens", get(routes::api_tokens::list_tokens).post(routes::api_tokens::issue_token))
+        .route("/api/v1/tokens/:token_id", delete(routes::api_tokens::revoke_token))
+        // Bots
+        .route("/api/v1/bots", get(routes::bots::list_bots).post(routes::bots::create_bot))
+        .route(
+            "/api/v1/bots/:bot_peer_id",
+            get(routes::bots::get_bot).put(routes::bots::update_bot).delete(routes::bots::delete_bot),
+        )
+        .route("/api/v1/bots/:bot_peer_id/token", post(routes::bots::regenerate_token))
+        .route(
+            "/api/v1/bots/:bot_peer_id/rooms/:room_id",
+            put(routes::bots::join_room).delete(routes::bots::leave_room),
+        )
+        .route("/api/v1/bot", get(routes::bots::get_current_bot))
+        .route("/api/v1/bot/commands", get(routes::commands::list_bot_commands))
+        .route(
+            "/api/v1/bot/rooms/:room_id/commands/:name",
+            put(routes::commands::register_bot_command).delete(routes::commands::unregister_bot_command),
+        )
+        // Webhooks
+        .route("/api/v1/webhooks", get(routes::webhooks::list_webhooks).post(routes::webhooks::create_webhook))
+        .route(
+            "/api/v1/webhooks/:webhook_id",
+            get(routes::webhooks::get_webhook).put(routes::webhooks::update_webhook).delete(routes::webhooks::delete_webhook),
+        )
+        .route("/api/v1/webhooks/:webhook_id/deliveries", get(routes::webhooks::get_deliveries))
+        .route("/api/v1/webhooks/:webhook_id/test", post(routes::webhooks::test_webhook))
+        // Incoming webhooks
+        .route(
+            "/api/v1/incoming-webhooks",
+            get(routes::incoming_webhooks::list_incoming_webhooks).post(routes::incoming_webhooks::create_incoming_webhook),
+        )
+        .route("/api/v1/incoming-webhooks/:webhook_id", delete(routes::incoming_webhooks::revoke_incoming_webhook))
+        .route("/api/v1/hooks/:webhook_id/:token", post(routes::incoming_webhooks::execute_webhook))
+        // Devices
+        .route("/api/v1/devices", get(routes::devices::list_devices).post(routes::devices::link_device))
+        .route("/api/v1/devices/link-request", get(routes::devices::get_link_request))
+        .route("/api/v1/devices/accept", post(routes::devices::accept_link))
+        .route("/api/v1/devices/:device_peer_id", delete(routes::devices::unlink_device))
+        // Import
+        .route(
+            "/api/v1/import/discord",
+            post(routes::import::import_discord).layer(DefaultBodyLimit::max(routes::import::MAX_IMPORT_BYTES)),
+        )
+        // Backup
+        .route("/api/v1/backup", post(routes::backup::create_backup))
         // Rooms
         .route("/api/v1/rooms", get(routes::rooms::list_rooms).post(routes::rooms::create_room))
         .route("/api/v1/rooms/join", post(routes::rooms::join_room))
+        .route("/api/v1/rooms/:room_id/export", get(routes::export::export_room))
+        .route("/api/v1/rooms/:room_id/messages/mine", delete(routes::messaging::delete_my_messages_in_room))
+        .route(
+            "/api/v1/rooms/:room_id/retention",
+            get(routes::retention::get_room_retention).put(routes::retention::set_room_retention),
+        )
         .route("/api/v1/rooms/:room_id/channels", get(routes::rooms::get_channels).post(routes::channels::create_channel))
+        .route("/api/v1/rooms/:room_id/categories", get(routes::channels::get_categories).post(routes::channels::create_category))
+        .route("/api/v1/rooms/:room_id/layout", get(routes::channels::get_channel_layout))
+        .route("/api/v1/rooms/:room_id/unread", get(routes::notifications::get_room_unread))
         .route("/api/v1/rooms/:room_id/peers", get(routes::peers::get_room_peers))
         .route("/api/v1/rooms/:room_id/roles", get(routes::roles::get_room_roles).post(routes::roles::set_role))
         .route("/api/v1/rooms/:room_id/roles/:peer_id", delete(routes::roles::remove_role))
@@ -25,25 +86,55 @@ pub fn build_router(ctx: ServiceContext, frame_server: FrameServerState) -> Rout
         .route("/api/v1/rooms/:room_id/emoji", get(routes::emoji::list_emoji).post(routes::emoji::add_emoji))
         // Channels
         .route("/api/v1/channels/:channel_id", put(routes::channels::update_channel).delete(routes::channels::delete_channel))
+        .route("/api/v1/channels/:channel_id/category", put(routes::channels::move_channel))
+        .route(
+            "/api/v1/channels/:channel_id/retention",
+            get(routes::retention::get_channel_retention).put(routes::retention::set_channel_retention),
+        )
+        .route(
+            "/api/v1/channels/:channel_id/permissions",
+            get(routes::channels::get_effective_permissions).put(routes::channels::set_channel_permissions),
+        )
         .route(
             "/api/v1/channels/:channel_id/messages",
             get(routes::messaging::get_messages).post(routes::messaging::send_message),
         )
+        .route("/api/v1/channels/:channel_id/threads", get(routes::threads::list_channel_threads))
+        .route(
+            "/api/v1/channels/:channel_id/commands",
+            get(routes::commands::list_commands).post(routes::commands::execute_command),
+        )
+        .route("/api/v1/channels/:channel_id/commands/autocomplete", get(routes::commands::autocomplete))
         .route("/api/v1/channels/:channel_id/typing", post(routes::messaging::typing_indicator))
         .route("/api/v1/channels/:channel_id/read", post(routes::messaging::mark_read))
         .route("/api/v1/channels/:channel_id/read-receipts", get(routes::messaging::get_read_receipts))
         .route("/api/v1/channels/:channel_id/pins", get(routes::messaging::get_pinned_messages).post(routes::messaging::pin_message))
         .route("/api/v1/channels/:channel_id/pins/:message_id", delete(routes::messaging::unpin_message))
+        // Categories
+        .route("/api/v1/categories/:category_id", put(routes::channels::update_category).delete(routes::channels::delete_category))
+        .route("/api/v1/categories/:category_id/collapsed", put(routes::channels::set_category_collapsed))
         // Messages
         .route("/api/v1/messages/:message_id", put(routes::messaging::edit_message).delete(routes::messaging::delete_message))
         .route("/api/v1/messages/:message_id/reactions", get(routes::messaging::get_reactions).post(routes::messaging::add_reaction))
         .route("/api/v1/messages/:message_id/reactions/:emoji", delete(routes::messaging::remove_reaction))
         .route("/api/v1/messages/:message_id/attachments", get(routes::files::get_attachments).post(routes::files::attach_file))
+        .route("/api/v1/messages/:message_id/mentions", get(routes::notifications::get_message_mentions))
+        .route("/api/v1/messages/:message_id/thread", post(routes::threads::create_thread))
+        // Threads
+        .route("/api/v1/threads/subscribed", get(routes::threads::list_subscribed_threads))
+        .route("/api/v1/threads/:thread_id", get(routes::threads::get_thread))
+        .route("/api/v1/threads/:thread_id/messages", get(routes::threads::get_thread_messages).post(routes::threads::reply_in_thread))
+        .route("/api/v1/threads/:thread_id/subscription", put(routes::threads::subscribe).delete(routes::threads::unsubscribe))
         // Search
+        .route("/api/v1/search", get(routes::messaging::search_all))
         .route("/api/v1/search/messages", get(routes::messaging::search_messages))
         // DMs
         .route("/api/v1/dms", get(routes::dms::list_dms).post(routes::dms::create_dm))
         .route("/api/v1/dms/:conversation_id/participants", get(routes::dms::get_dm_participants))
+        .route(
+            "/api/v1/dms/:conversation_id/timer",
+            get(routes::retention::get_dm_timer).put(routes::retention::set_dm_timer),
+        )
         .route(
             "/api/v1/dms/:conversation_id/messages",
             get(routes::dms::get_dm_messages).post(routes::dms::send_dm_message),
@@ -65,6 +156,7 @@ pub fn build_router(ctx: ServiceContext, frame_server: FrameServerState) -> Rout
         .route("/api/v1/settings/:key", get(routes::settings::get_setting).put(routes::settings::set_setting).delete(routes::settings::delete_setting))
         // Notifications
         .route("/api/v1/notifications", get(routes::notifications::get_all_notification_settings))
+        .route("/api/v1/unread", get(routes::notifications::get_unread_summary))
         .route("/api/v1/notifications/:target_type/:target_id", get(routes::notifications::get_notification_setting).put(routes::notifications::set_notification_setting))
         // Voice (media engine)
         .route("/api/v1/voice/join", post(routes::voice::join_voice))
@@ -84,13 +176,23 @@ pub fn build_router(ctx: ServiceContext, frame_server: FrameServerState) -> Rout
         .route("/api/v1/voice/answer", post(routes::voice::send_call_answer))
         .route("/api/v1/voice/ice-candidate", post(routes::voice::send_ice_candidate))
         .route("/api/v1/voice/broadcast-state", post(routes::voice::update_voice_state))
-        // WebSocket
-        .route("/ws", get(websocket::ws_handler))
-        // Middleware
-        .layer(CorsLayer::permissive())
-        .with_state(ctx)
+}
+
+pub fn build_router(ctx: ServiceContext, frame_server: FrameServerState) -> Router {
+    let rest = rest_routes()
+        .route_layer(middleware::from_fn_with_state(ctx.clone(), auth::restrict_bot_tokens))
+        .with_state(ctx.clone());
+    Router::new()
+        // WebSocket: events out, RPC calls in
+        .route("/ws", get(websocket::ws_handler).layer(Extension(rest.clone())))
+        .route(openapi::OPENAPI_PATH, get(openapi::get_openapi))
+        .with_state(ctx.clone())
+        .merge(rest)
         // Merge frame server routes (MJPEG streams)
         .merge(frame_server::frame_server_routes(frame_server))
+        // Middleware: CORS outermost so preflights are answered without a token
+        .layer(middleware::from_fn_with_state(ctx, auth::require_token))
+        .layer(auth::cors_layer())
 }
 
 pub async fn start_api_server(ctx: ServiceContext, port: u16, frame_server: FrameServerState) {
@@ -1,49 +1,156 @@
 use axum::{
     extract::{State, ws::{Message, WebSocket, WebSocketUpgrade}},
     response::IntoResponse,
+    Extension, Router,
 };
-use tokio::sync::broadcast;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::{broadcast, mpsc};
 use tracing::{debug, warn};
 
-use crate::events::AppEvent;
+use crate::api::auth::Authorized;
+use crate::api::rpc::{self, RpcError, RpcErrorCode, RpcRequest, RpcResponse};
+use crate::events::{EventFilter, JournaledEvent};
 use crate::state::ServiceContext;
 
+/// Journal entries read per query while replaying.
+const REPLAY_BATCH: i64 = 500;
+
+/// Params of `events.subscribe`. Replaces the socket's filter; with `since`,
+/// first replays journaled events after that sequence number.
+#[derive(Debug, Deserialize)]
+struct SubscribeParams {
+    #[serde(flatten)]
+    filter: EventFilter,
+    since: Option<i64>,
+}
+
 pub async fn ws_handler(
     ws: WebSocketUpgrade,
     State(ctx): State<ServiceContext>,
+    Extension(rest): Extension<Router>,
+    Extension(authorized): Extension<Authorized>,
 ) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_socket(socket, ctx.event_tx.subscribe()))
+    ws.on_upgrade(move |socket| handle_socket(socket, ctx, rest, authorized))
+}
+
+/// Per-socket delivery state.
+struct Stream {
+    filter: EventFilter,
+    /// Highest sequence number delivered or skipped by the filter.
+    last_seq: Option<i64>,
+    /// For bot tokens: the bot's rooms, the only events it may see.
+    bot_rooms: Option<Vec<String>>,
 }
 
-async fn handle_socket(mut socket: WebSocket, mut event_rx: broadcast::Receiver<AppEvent>) {
+impl Stream {
+    /// Send `event` if it passes the filter and wasn't already replayed.
+    async fn deliver(&mut self, socket: &mut WebSocket, event: &JournaledEvent) -> Result<(), axum::Error> {
+        if let Some(seq) = event.seq {
+            if self.last_seq.is_some_and(|last| seq <= last) {
+                return Ok(());
+            }
+            self.last_seq = Some(seq);
+        }
+        let visible = self.bot_rooms.as_ref().map_or(true, |rooms| {
+            event.scope.room_id.as_ref().is_some_and(|room_id| rooms.contains(room_id))
+        });
+        if visible && self.filter.matches(event) {
+            socket.send(Message::Text(event.to_json())).await?;
+        }
+        Ok(())
+    }
+
+    /// Send everything journaled after `since`. Returns false if some of it
+    /// was already trimmed from the journal.
+    async fn replay(&mut self, socket: &mut WebSocket, ctx: &ServiceContext, since: i64) -> Result<bool, axum::Error> {
+        // Everything broadcast before now must be readable, or it could fall
+        // between the journal and the live stream
+        ctx.event_tx.flushed().await;
+        let oldest = ctx.db.run(|db| db.get_oldest_event_seq()).await.ok().flatten();
+        let complete = oldest.map_or(true, |oldest| oldest <= since + 1);
+        self.last_seq = Some(since);
+        loop {
+            let after = self.last_seq.unwrap_or(since);
+            let events = match ctx.db.run(move |db| db.get_events_since(after, REPLAY_BATCH)).await {
+                Ok(events) => events,
+                Err(e) => {
+                    warn!("Failed to read event journal: {}", e);
+                    return Ok(false);
+                }
+            };
+            if events.is_empty() {
+                return Ok(complete);
+            }
+            for event in &events {
+                self.deliver(socket, event).await?;
+            }
+        }
+    }
+
+    async fn subscribe(&mut self, socket: &mut WebSocket, ctx: &ServiceContext, params: Value) -> Result<Value, RpcError> {
+        let params: SubscribeParams = serde_json::from_value(if params.is_null() { Value::Object(Default::default()) } else { params })
+            .map_err(|e| RpcError::new(RpcErrorCode::InvalidParams, e.to_string()))?;
+        self.filter = params.filter;
+        let complete = match params.since {
+            Some(since) => self
+                .replay(socket, ctx, since)
+                .await
+                .map_err(|e| RpcError::new(RpcErrorCode::Internal, e.to_string()))?,
+            None => true,
+        };
+        Ok(serde_json::json!({ "last_seq": self.last_seq, "complete": complete }))
+    }
+}
+
+async fn handle_socket(mut socket: WebSocket, ctx: ServiceContext, rest: Router, authorized: Authorized) {
     debug!("WebSocket client connected");
+    let mut event_rx = ctx.event_tx.subscribe_journaled();
+    let bot_rooms = match authorized.bot.clone() {
+        Some(bot_peer_id) => Some(
+            ctx.db.run(move |db| db.get_bot(&bot_peer_id)).await.ok().flatten().map(|bot| bot.rooms).unwrap_or_default(),
+        ),
+        None => None,
+    };
+    let mut stream = Stream { filter: EventFilter::default(), last_seq: None, bot_rooms };
+    // RPC calls run concurrently; their replies come back through here
+    let (reply_tx, mut reply_rx) = mpsc::unbounded_channel::<String>();
 
     loop {
         tokio::select! {
-            // Forward AppEvents to the WebSocket client as JSON
+            Some(reply) = reply_rx.recv() => {
+                if socket.send(Message::Text(reply)).await.is_err() {
+                    break;
+                }
+            }
+            // Forward events to the WebSocket client as JSON
             result = event_rx.recv() => {
                 match result {
                     Ok(event) => {
-                        match serde_json::to_string(&event) {
-                            Ok(json) => {
-                                if socket.send(Message::Text(json.into())).await.is_err() {
-                                    break;
-                                }
-                            }
-                            Err(e) => {
-                                warn!("Failed to serialize event: {}", e);
-                            }
+                        if stream.deliver(&mut socket, &event).await.is_err() {
+                            break;
                         }
                     }
                     Err(broadcast::error::RecvError::Lagged(n)) => {
-                        warn!("WebSocket client lagged, skipped {} events", n);
+                        // Catch up from the journal instead of dropping events
+                        match stream.last_seq {
+                            Some(last) => {
+                                debug!("WebSocket client lagged by {} events, replaying from {}", n, last);
+                                match stream.replay(&mut socket, &ctx, last).await {
+                                    Ok(true) => {}
+                                    Ok(false) => warn!("WebSocket client lagged past the event journal"),
+                                    Err(_) => break,
+                                }
+                            }
+                            None => warn!("WebSocket client lagged, skipped {} events", n),
+                        }
                     }
                     Err(broadcast::error::RecvError::Closed) => {
                         break;
                     }
                 }
             }
-            // Handle incoming WebSocket messages (for future use, e.g. ping/pong)
+            // Incoming RPC calls, pings and close
             msg = socket.recv() => {
                 match msg {
                     Some(Ok(Message::Close(_))) | None => break,
@@ -52,7 +159,32 @@ async fn handle_socket(mut socket: WebSocket, mut event_rx: broadcast::Receiver<
                             break;
                         }
                     }
-                    Some(Ok(_)) => {} // Ignore other messages for now
+                    Some(Ok(Message::Text(text))) => {
+                        // Subscriptions change this socket's state, so they run inline
+                        if let Ok(request) = serde_json::from_str::<RpcRequest>(&text) {
+                            if request.method == "events.subscribe" {
+                                let response = match stream.subscribe(&mut socket, &ctx, request.params).await {
+                                    Ok(result) => RpcResponse { id: request.id, result: Some(result), error: None },
+                                    Err(error) => RpcResponse { id: request.id, result: None, error: Some(error) },
+                                };
+                                let json = serde_json::to_string(&response).unwrap_or_default();
+                                if socket.send(Message::Text(json)).await.is_err() {
+                                    break;
+                                }
+                                continue;
+                            }
+                        }
+                        let rest = rest.clone();
+                        let authorized = authorized.clone();
+                        let reply_tx = reply_tx.clone();
+                        tokio::spawn(async move {
+                            let response = rpc::handle_text(rest, authorized, &text).await;
+                            if let Ok(json) = serde_json::to_string(&response) {
+                                let _ = reply_tx.send(json);
+                            }
+                        });
+                    }
+                    Some(Ok(_)) => {}
                     Some(Err(_)) => break,
                 }
             }
@@ -0,0 +1,138 @@
+//! Encrypted full-node backups.
+//!
+//! An archive is a SQLCipher database encrypted with the backup passphrase:
+//! a snapshot of every table in `chatr.db` (identity, settings, rooms,
+//! channels, messages, DMs, friends, ...), plus `backup_manifest` describing
+//! the archive. Files are shared peer to peer and only their metadata is
+//! stored locally, so that is all a backup holds of them.
+
+use rusqlite::{Connection, OptionalExtension};
+use std::path::Path;
+
+use crate::db::{encryption, migrations, pool, Database};
+use crate::models::BackupManifest;
+
+/// Bumped whenever the archive layout changes; restore refuses newer formats.
+pub const ARCHIVE_FORMAT_VERSION: i64 = 1;
+
+/// Environment variable read by `--backup` / `--restore` for the archive passphrase.
+pub const BACKUP_PASSPHRASE_ENV: &str = "CHATR_BACKUP_PASSPHRASE";
+
+/// Write an encrypted archive of the whole node to `dest`.
+pub fn create_backup(db: &Database, dest: &Path, passphrase: &str) -> Result<BackupManifest, String> {
+    if passphrase.is_empty() {
+        return Err("Backup passphrase must not be empty".to_string());
+    }
+    if dest.exists() {
+        return Err(format!("{} already exists", dest.display()));
+    }
+
+    {
+        let conn = db.pool.get();
+        encryption::export(&conn, dest, Some(passphrase)).map_err(|e| e.to_string())?;
+    }
+    let written = fill_archive(dest, passphrase);
+    if written.is_err() {
+        let _ = std::fs::remove_file(dest);
+    }
+    written
+}
+
+fn fill_archive(dest: &Path, passphrase: &str) -> Result<BackupManifest, String> {
+    let mut archive = pool::connect(dest, Some(passphrase)).map_err(|e| e.to_string())?;
+    let schema_version = migrations::current_version(&archive).map_err(|e| e.to_string())?;
+    let peer_id = archive
+        .query_row("SELECT keypair_bytes FROM identity WHERE id = 1", [], |row| row.get::<_, Vec<u8>>(0))
+        .optional()
+        .map_err(|e| e.to_string())?
+        .and_then(|bytes| crate::keys::decode_keypair(&bytes))
+        .map(|kp| kp.public().to_peer_id().to_string())
+        .ok_or_else(|| "Database has no identity to back up".to_string())?;
+
+    let tx = archive.transaction().map_err(|e| e.to_string())?;
+    tx.execute_batch("CREATE TABLE backup_manifest (key TEXT PRIMARY KEY, value TEXT NOT NULL);")
+        .map_err(|e| e.to_string())?;
+
+    let manifest = BackupManifest {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        peer_id,
+        schema_version,
+    };
+    for (key, value) in [
+        ("format_version", manifest.format_version.to_string()),
+        ("created_at", manifest.created_at.clone()),
+        ("peer_id", manifest.peer_id.clone()),
+        ("schema_version", manifest.schema_version.to_string()),
+    ] {
+        tx.execute("INSERT INTO backup_manifest (key, value) VALUES (?1, ?2)", [key, &value])
+            .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(manifest)
+}
+
+fn read_manifest(archive: &Connection) -> Result<BackupManifest, String> {
+    let get = |key: &str| -> Result<String, String> {
+        archive
+            .query_row("SELECT value FROM backup_manifest WHERE key = ?1", [key], |row| row.get(0))
+            .map_err(|_| format!("Backup manifest is missing {}", key))
+    };
+    let number = |key: &str| -> Result<i64, String> {
+        get(key)?.parse().map_err(|_| format!("Backup manifest has an invalid {}", key))
+    };
+    Ok(BackupManifest {
+        format_version: number("format_version")?,
+        created_at: get("created_at")?,
+        peer_id: get("peer_id")?,
+        schema_version: number("schema_version")?,
+    })
+}
+
+/// Restore an archive into `data_dir`, which must not already hold a
+/// database. The restored `chatr.db` is encrypted with `db_passphrase` if
+/// given, and is migrated on next open if the archive is from an older build.
+pub fn restore_backup(
+    src: &Path,
+    data_dir: &Path,
+    passphrase: &str,
+    db_passphrase: Option<&str>,
+) -> Result<BackupManifest, String> {
+    let db_path = data_dir.join("chatr.db");
+    if db_path.exists() {
+        return Err(format!(
+            "{} already exists; restore into an empty data directory",
+            db_path.display()
+        ));
+    }
+
+    let archive = pool::connect(src, Some(passphrase)).map_err(|e| {
+        if encryption::is_not_a_database(&e) {
+            "Wrong passphrase, or not a Chatr backup".to_string()
+        } else {
+            e.to_string()
+        }
+    })?;
+    let manifest = read_manifest(&archive)?;
+    if manifest.format_version > ARCHIVE_FORMAT_VERSION {
+        return Err(format!(
+            "Backup format v{} is newer than this build supports (v{})",
+            manifest.format_version, ARCHIVE_FORMAT_VERSION
+        ));
+    }
+    if manifest.schema_version > migrations::latest_version() {
+        return Err(format!(
+            "Backup schema v{} is newer than this build supports (v{})",
+            manifest.schema_version,
+            migrations::latest_version()
+        ));
+    }
+
+    encryption::export(&archive, &db_path, db_passphrase).map_err(|e| e.to_string())?;
+    let restored = pool::connect(&db_path, db_passphrase).map_err(|e| e.to_string())?;
+    restored
+        // Archives from earlier builds also carry an always-empty blob table
+        .execute_batch("DROP TABLE backup_manifest; DROP TABLE IF EXISTS backup_blobs;")
+        .map_err(|e| e.to_string())?;
+    Ok(manifest)
+}
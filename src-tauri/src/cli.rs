@@ -0,0 +1,515 @@
+//! `chatr <command>`: drive a running node through its REST and WebSocket API.
+//!
+//! Every command prints a short human-readable summary, or with `--json` the
+//! API's JSON (one object per line for `tail`) for scripts. Errors go to
+//! stderr with a non-zero exit status.
+
+use std::error::Error;
+use std::io::{Read, Write};
+
+use chatr_client::{Client, EventFilter, Message, SearchHit};
+use clap::{Args, Subcommand};
+use serde::Serialize;
+use serde_json::Value;
+
+/// Where the node's API is and how to authenticate.
+#[derive(Args)]
+pub struct ApiArgs {
+    /// Node API address [default: http://127.0.0.1:<PORT>]
+    #[arg(long, env = "CHATR_API_URL", global = true)]
+    api_url: Option<String>,
+
+    /// API token, e.g. one issued with `chatr --create-token`
+    #[arg(long, env = "CHATR_API_TOKEN", hide_env_values = true, global = true)]
+    token: Option<String>,
+
+    /// Print JSON instead of text
+    #[arg(long, global = true)]
+    json: bool,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Show the node's identity
+    Whoami,
+    /// Set presence (online, idle, dnd or invisible) and an optional status message
+    Status {
+        status_type: String,
+        message: Option<String>,
+    },
+    /// List, create and inspect rooms
+    #[command(subcommand)]
+    Rooms(RoomsCommand),
+    /// Join a room by invite code
+    Join { invite_code: String },
+    /// List and create channels
+    #[command(subcommand)]
+    Channels(ChannelsCommand),
+    /// Print a channel's latest messages, oldest first
+    Messages {
+        channel_id: String,
+        #[arg(long, default_value_t = 50)]
+        limit: i64,
+        /// Only messages before this timestamp
+        #[arg(long)]
+        before: Option<String>,
+    },
+    /// Send a message; the text is read from stdin if not given
+    Send {
+        channel_id: String,
+        text: Vec<String>,
+        #[arg(long, value_name = "MESSAGE_ID")]
+        reply_to: Option<String>,
+        #[arg(long, value_name = "THREAD_ID")]
+        thread: Option<String>,
+    },
+    /// Follow new messages in channels or rooms until interrupted
+    Tail {
+        #[arg(required_unless_present = "room")]
+        channel_ids: Vec<String>,
+        /// Follow every channel of this room
+        #[arg(long, value_name = "ROOM_ID")]
+        room: Vec<String>,
+        /// First print this many earlier messages of each channel
+        #[arg(long, default_value_t = 0)]
+        history: i64,
+    },
+    /// Search messages, DMs and files
+    Search {
+        #[arg(required = true)]
+        query: Vec<String>,
+        #[arg(long)]
+        room: Option<String>,
+        /// Comma-separated subset of channel, dm, file
+        #[arg(long, value_delimiter = ',')]
+        sources: Vec<String>,
+        #[arg(long, default_value_t = 25)]
+        limit: i64,
+    },
+    /// Direct messages
+    #[command(subcommand)]
+    Dms(DmsCommand),
+    /// List friends and pending friend requests
+    Friends,
+    /// Join, leave and control voice
+    #[command(subcommand)]
+    Voice(VoiceCommand),
+    /// Run a slash command in a channel, e.g. `chatr run <CHANNEL_ID> /topic Release day`
+    Run {
+        channel_id: String,
+        #[arg(required = true, allow_hyphen_values = true)]
+        input: Vec<String>,
+    },
+    /// Full-screen terminal client
+    Tui,
+    /// Call any API route and print the JSON response
+    Api {
+        /// GET, POST, PUT or DELETE
+        method: String,
+        /// e.g. /api/v1/rooms
+        path: String,
+        /// JSON request body
+        #[arg(long)]
+        data: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum RoomsCommand {
+    /// List the rooms this node is in
+    List,
+    /// Create a room and print its invite code
+    Create { name: String },
+    /// List a room's members and whether they are online
+    Members { room_id: String },
+}
+
+#[derive(Subcommand)]
+pub enum ChannelsCommand {
+    /// List a room's channels
+    List {
+        room_id: String,
+    },
+    /// Create a text channel, or a voice channel with --voice
+    Create {
+        room_id: String,
+        name: String,
+        /// Create a voice channel
+        #[arg(long)]
+        voice: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DmsCommand {
+    /// List conversations
+    List,
+    /// Open (or reuse) a conversation with one or more peers
+    Open {
+        #[arg(required = true)]
+        peer_ids: Vec<String>,
+        /// Name of a group conversation
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Print a conversation's latest messages, oldest first
+    Messages {
+        conversation_id: String,
+        #[arg(long, default_value_t = 50)]
+        limit: i64,
+    },
+    /// Send a DM; the text is read from stdin if not given
+    Send {
+        conversation_id: String,
+        text: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum VoiceCommand {
+    /// Join a voice channel
+    Join { channel_id: String },
+    /// Leave the current voice channel
+    Leave,
+    /// Mute the microphone, or unmute with --off
+    Mute {
+        #[arg(long)]
+        off: bool,
+    },
+    /// Deafen, or undeafen with --off
+    Deafen {
+        #[arg(long)]
+        off: bool,
+    },
+    /// Show the voice session
+    Status,
+}
+
+/// Run one command against the node, then exit. `port` is the default API
+/// port when `--api-url` isn't given.
+pub fn run(args: ApiArgs, port: u16, command: Command) {
+    let rt = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
+    if let Err(e) = rt.block_on(execute(args, port, command)) {
+        eprintln!("chatr: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn execute(args: ApiArgs, port: u16, command: Command) -> Result<(), Box<dyn Error>> {
+    let url = args.api_url.unwrap_or_else(|| format!("http://127.0.0.1:{}", port));
+    let token = args.token.ok_or(
+        "No API token. Pass --token or set CHATR_API_TOKEN; issue one with `chatr --create-token cli`",
+    )?;
+    let client = Client::new(&url, &token)?;
+    let out = Output { json: args.json };
+
+    match command {
+        Command::Whoami => {
+            let identity = client.identity().await?;
+            out.print(&identity, |i| {
+                let status = i.status_type.as_deref().unwrap_or("online");
+                match &i.status_message {
+                    Some(message) => println!("{} ({}) [{}] {}", i.display_name, i.peer_id, status, message),
+                    None => println!("{} ({}) [{}]", i.display_name, i.peer_id, status),
+                }
+            });
+        }
+        Command::Status { status_type, message } => {
+            client.set_status(Some(&status_type), message.as_deref()).await?;
+            out.done(&format!("Status set to {}", status_type));
+        }
+        Command::Rooms(RoomsCommand::List) => {
+            let rooms = client.rooms().await?;
+            out.print(&rooms, |rooms| {
+                for room in rooms {
+                    println!("{}\t{}\tinvite: {}", room.id, room.name, room.invite_code);
+                }
+            });
+        }
+        Command::Rooms(RoomsCommand::Create { name }) => {
+            let room = client.create_room(&name).await?;
+            out.print(&room, |room| println!("Created {} ({}), invite code {}", room.name, room.id, room.invite_code));
+        }
+        Command::Rooms(RoomsCommand::Members { room_id }) => {
+            let peers = client.room_peers(&room_id).await?;
+            out.print(&peers, |peers| {
+                for peer in peers {
+                    let presence = if peer.is_online { "online" } else { "offline" };
+                    let bot = if peer.is_bot { " [bot]" } else { "" };
+                    println!("{}\t{}{}\t{}", peer.peer_id, peer.display_name, bot, presence);
+                }
+            });
+        }
+        Command::Join { invite_code } => {
+            let room = client.join_room(&invite_code).await?;
+            out.print(&room, |room| println!("Joined {} ({})", room.name, room.id));
+        }
+        Command::Channels(ChannelsCommand::List { room_id }) => {
+            let channels = client.channels(&room_id).await?;
+            out.print(&channels, |channels| {
+                for channel in channels {
+                    println!("{}\t#{}\t{}", channel.id, channel.name, channel.channel_type);
+                }
+            });
+        }
+        Command::Channels(ChannelsCommand::Create { room_id, name, voice }) => {
+            let channel_type = if voice { "voice" } else { "text" };
+            let channel = client.create_channel(&room_id, &name, Some(channel_type)).await?;
+            out.print(&channel, |c| println!("Created #{} ({})", c.name, c.id));
+        }
+        Command::Messages { channel_id, limit, before } => {
+            let messages = client.messages(&channel_id, Some(limit), before.as_deref()).await?;
+            out.print(&messages, |messages| messages.iter().for_each(print_message));
+        }
+        Command::Send { channel_id, text, reply_to, thread } => {
+            let content = text_or_stdin(text)?;
+            let message = client
+                .send_message_with(&channel_id, &content, reply_to.as_deref(), thread.as_deref())
+                .await?;
+            out.print(&message, |m| println!("Sent {}", m.id));
+        }
+        Command::Tail { channel_ids, room, history } => {
+            tail(&client, &out, channel_ids, room, history).await?;
+        }
+        Command::Search { query, room, sources, limit } => {
+            let sources: Vec<&str> = sources.iter().map(String::as_str).collect();
+            let results = client.search(&query.join(" "), &sources, room.as_deref(), Some(limit)).await?;
+            out.print(&results, |results| {
+                for hit in &results.results {
+                    match hit {
+                        SearchHit::Channel { message, .. } => print_message(message),
+                        SearchHit::Dm { message, .. } => println!(
+                            "{} [dm] {}: {}",
+                            local_time(&message.timestamp),
+                            message.sender_display_name,
+                            message.content
+                        ),
+                        SearchHit::File { file, .. } => println!(
+                            "[file] {} ({})",
+                            file["file_name"].as_str().unwrap_or_default(),
+                            file["id"].as_str().unwrap_or_default()
+                        ),
+                    }
+                }
+                eprintln!("{} of {} results", results.results.len(), results.total);
+            });
+        }
+        Command::Dms(DmsCommand::List) => {
+            let dms = client.dms().await?;
+            out.print(&dms, |dms| {
+                for dm in dms {
+                    let kind = if dm.is_group { "group" } else { "direct" };
+                    println!("{}\t{}\t{}", dm.id, dm.name.as_deref().unwrap_or("-"), kind);
+                }
+            });
+        }
+        Command::Dms(DmsCommand::Open { peer_ids, name }) => {
+            let peer_ids: Vec<&str> = peer_ids.iter().map(String::as_str).collect();
+            let dm = client.create_dm(&peer_ids, name.as_deref()).await?;
+            out.print(&dm, |dm| println!("{}", dm.id));
+        }
+        Command::Dms(DmsCommand::Messages { conversation_id, limit }) => {
+            let messages = client.dm_messages(&conversation_id, Some(limit), None).await?;
+            out.print(&messages, |messages| {
+                for m in messages {
+                    println!("{} {}: {}", local_time(&m.timestamp), m.sender_display_name, m.content);
+                }
+            });
+        }
+        Command::Dms(DmsCommand::Send { conversation_id, text }) => {
+            let content = text_or_stdin(text)?;
+            let message = client.send_dm(&conversation_id, &content).await?;
+            out.print(&message, |m| println!("Sent {}", m.id));
+        }
+        Command::Friends => {
+            let friends = client.friends().await?;
+            out.print(&friends, |friends| {
+                for friend in friends {
+                    println!("{}\t{}\t{}", friend.peer_id, friend.display_name, friend.status);
+                }
+            });
+        }
+        Command::Voice(VoiceCommand::Join { channel_id }) => {
+            let (room, channel) = client
+                .find_channel(&channel_id)
+                .await?
+                .ok_or_else(|| format!("Channel {} not found in any room", channel_id))?;
+            client.join_voice(&room.id, &channel.id).await?;
+            out.done(&format!("Joined voice in #{} of {}", channel.name, room.name));
+        }
+        Command::Voice(VoiceCommand::Leave) => {
+            client.leave_voice().await?;
+            out.done("Left voice");
+        }
+        Command::Voice(VoiceCommand::Mute { off }) => {
+            client.set_muted(!off).await?;
+            out.done(if off { "Unmuted" } else { "Muted" });
+        }
+        Command::Voice(VoiceCommand::Deafen { off }) => {
+            client.set_deafened(!off).await?;
+            out.done(if off { "Undeafened" } else { "Deafened" });
+        }
+        Command::Voice(VoiceCommand::Status) => {
+            let state = client.voice_state().await?;
+            out.print(&state, |s| match (&s.room_id, &s.channel_id) {
+                (Some(room_id), Some(channel_id)) if s.in_voice => println!(
+                    "In voice in channel {} of room {}; muted: {}, deafened: {}, {} peers connected",
+                    channel_id,
+                    room_id,
+                    s.muted,
+                    s.deafened,
+                    s.connected_peers.len()
+                ),
+                _ => println!("Not in voice"),
+            });
+        }
+        Command::Run { channel_id, input } => {
+            let mut input = input.join(" ");
+            if !input.starts_with('/') {
+                input.insert(0, '/');
+            }
+            let result = client.run_command(&channel_id, &input).await?;
+            out.print(&result, |r| {
+                if let Some(response) = &r.response {
+                    println!("{}", response);
+                }
+                if let Some(message) = &r.message {
+                    print_message(message);
+                }
+            });
+        }
+        Command::Tui => crate::tui::run(client).await?,
+        Command::Api { method, path, data } => {
+            let method = reqwest::Method::from_bytes(method.to_uppercase().as_bytes())
+                .map_err(|_| format!("Invalid HTTP method '{}'", method))?;
+            let body = data
+                .map(|d| serde_json::from_str::<Value>(&d))
+                .transpose()
+                .map_err(|e| format!("--data is not valid JSON: {}", e))?;
+            let response: Value = client.request(method, &path, &[], body.as_ref()).await?;
+            println!("{}", serde_json::to_string_pretty(&response).unwrap_or_default());
+        }
+    }
+    Ok(())
+}
+
+/// Subscribe to new messages of the channels and rooms and print them as they
+/// arrive. Returns when the node closes the socket.
+async fn tail(
+    client: &Client,
+    out: &Output,
+    channels: Vec<String>,
+    rooms: Vec<String>,
+    history: i64,
+) -> Result<(), Box<dyn Error>> {
+    let mut events = client.events().await?;
+    events
+        .subscribe(
+            &EventFilter {
+                rooms: rooms.clone(),
+                channels: channels.clone(),
+                types: vec!["NewMessage".to_string()],
+                ..Default::default()
+            },
+            None,
+        )
+        .await?;
+
+    if history > 0 {
+        let mut history_channels = channels;
+        for room_id in &rooms {
+            history_channels.extend(client.channels(room_id).await?.into_iter().map(|c| c.id));
+        }
+        for channel_id in &history_channels {
+            for message in client.messages(channel_id, Some(history), None).await? {
+                if out.line(&message).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    while let Some(event) = events.next_event().await? {
+        if let Some(message) = event.new_message() {
+            // Stop quietly once the reader goes away, e.g. `chatr tail ... | head`
+            if out.line(&message).is_err() {
+                return Ok(());
+            }
+        }
+    }
+    Err("The node closed the connection".into())
+}
+
+struct Output {
+    json: bool,
+}
+
+impl Output {
+    /// Print `value` as pretty JSON, or with `human`.
+    fn print<T: Serialize>(&self, value: &T, human: impl FnOnce(&T)) {
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(value).unwrap_or_default());
+        } else {
+            human(value);
+        }
+    }
+
+    /// One streamed message: a JSON line, or a text line. Fails once stdout is closed.
+    fn line(&self, message: &Message) -> std::io::Result<()> {
+        let line = if self.json {
+            serde_json::to_string(message).unwrap_or_default()
+        } else {
+            format_message(message)
+        };
+        let mut stdout = std::io::stdout().lock();
+        writeln!(stdout, "{}", line)?;
+        stdout.flush()
+    }
+
+    /// Report a command that returns nothing.
+    fn done(&self, summary: &str) {
+        if self.json {
+            println!("{}", serde_json::json!({ "ok": true }));
+        } else {
+            println!("{}", summary);
+        }
+    }
+}
+
+fn format_message(message: &Message) -> String {
+    let edited = if message.edited_at.is_some() { " (edited)" } else { "" };
+    format!(
+        "{} {}: {}{}",
+        local_time(&message.timestamp),
+        message.sender_display_name,
+        message.content,
+        edited
+    )
+}
+
+fn print_message(message: &Message) {
+    println!("{}", format_message(message));
+}
+
+/// An RFC 3339 timestamp as local `YYYY-MM-DD HH:MM`, or as given if it doesn't parse.
+fn local_time(timestamp: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|_| timestamp.to_string())
+}
+
+/// The words joined, or all of stdin if there are none or the only one is `-`.
+fn text_or_stdin(words: Vec<String>) -> Result<String, Box<dyn Error>> {
+    if !words.is_empty() && words != ["-"] {
+        return Ok(words.join(" "));
+    }
+    let mut text = String::new();
+    std::io::stdin()
+        .read_to_string(&mut text)
+        .map_err(|e| format!("Failed to read stdin: {}", e))?;
+    let text = text.trim_end().to_string();
+    if text.is_empty() {
+        return Err("Nothing to send".into());
+    }
+    Ok(text)
+}
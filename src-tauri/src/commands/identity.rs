@@ -1,7 +1,29 @@
-use tauri::State;
+use tauri::{AppHandle, Manager, State};
+use crate::db::{Database, OpenError};
 use crate::models::Identity;
 use crate::services;
-use crate::state::AppState;
+use crate::state::{AppState, LockState};
+
+/// Whether the database is encrypted and still waiting for its passphrase.
+#[tauri::command]
+pub fn is_locked(app: AppHandle) -> bool {
+    app.try_state::<AppState>().is_none()
+}
+
+#[tauri::command]
+pub async fn unlock(app: AppHandle, lock: State<'_, LockState>, passphrase: String) -> Result<(), String> {
+    let _guard = lock.unlocking.lock().await;
+    if app.try_state::<AppState>().is_some() {
+        return Ok(());
+    }
+    let db = tokio::task::block_in_place(|| Database::open(&lock.data_dir, Some(&passphrase)))
+        .map_err(|e| match e {
+            OpenError::WrongPassphrase => "Wrong passphrase".to_string(),
+            e => e.to_string(),
+        })?;
+    crate::start_services(&app, db, &lock.data_dir, lock.api_port);
+    Ok(())
+}
 
 #[tauri::command]
 pub fn get_api_port(state: State<'_, AppState>) -> Result<u16, String> {
@@ -9,21 +31,26 @@ pub fn get_api_port(state: State<'_, AppState>) -> Result<u16, String> {
 }
 
 #[tauri::command]
-pub fn get_my_peer_id(state: State<'_, AppState>) -> Result<String, String> {
-    services::identity::get_peer_id(&state.ctx)
+pub fn get_api_token(state: State<'_, AppState>) -> Result<String, String> {
+    Ok(state.api_token.clone())
+}
+
+#[tauri::command]
+pub async fn get_my_peer_id(state: State<'_, AppState>) -> Result<String, String> {
+    services::identity::get_peer_id(&state.ctx).await.map_err(String::from)
 }
 
 #[tauri::command]
-pub fn get_identity(state: State<'_, AppState>) -> Result<Identity, String> {
-    services::identity::get_identity(&state.ctx)
+pub async fn get_identity(state: State<'_, AppState>) -> Result<Identity, String> {
+    services::identity::get_identity(&state.ctx).await.map_err(String::from)
 }
 
 #[tauri::command]
-pub fn get_display_name(state: State<'_, AppState>) -> Result<String, String> {
-    services::identity::get_display_name(&state.ctx)
+pub async fn get_display_name(state: State<'_, AppState>) -> Result<String, String> {
+    services::identity::get_display_name(&state.ctx).await.map_err(String::from)
 }
 
 #[tauri::command]
-pub fn set_display_name(state: State<'_, AppState>, name: String) -> Result<(), String> {
-    services::identity::set_display_name(&state.ctx, &name)
+pub async fn set_display_name(state: State<'_, AppState>, name: String) -> Result<(), String> {
+    services::identity::set_display_name(&state.ctx, &name).await.map_err(String::from)
 }
@@ -9,18 +9,19 @@ pub async fn send_message(
     channel_id: String,
     content: String,
     reply_to_id: Option<String>,
+    thread_id: Option<String>,
 ) -> Result<Message, String> {
-    services::messaging::send_message(&state.ctx, channel_id, content, reply_to_id).await
+    services::messaging::send_message(&state.ctx, channel_id, content, reply_to_id, thread_id, None).await.map_err(String::from)
 }
 
 #[tauri::command]
-pub fn get_messages(
+pub async fn get_messages(
     state: State<'_, AppState>,
     channel_id: String,
     limit: Option<i64>,
     before: Option<String>,
 ) -> Result<Vec<Message>, String> {
-    services::messaging::get_messages(&state.ctx, &channel_id, limit, before.as_deref())
+    services::messaging::get_messages(&state.ctx, &channel_id, limit, before.as_deref()).await.map_err(String::from)
 }
 
 #[tauri::command]
@@ -28,5 +29,5 @@ pub async fn get_room_peers(
     state: State<'_, AppState>,
     room_id: String,
 ) -> Result<Vec<crate::models::PeerInfo>, String> {
-    services::peers::get_room_peers(&state.ctx, &room_id).await
+    services::peers::get_room_peers(&state.ctx, &room_id).await.map_err(String::from)
 }
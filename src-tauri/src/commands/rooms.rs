@@ -5,20 +5,20 @@ use crate::state::AppState;
 
 #[tauri::command]
 pub async fn create_room(state: State<'_, AppState>, name: String) -> Result<Room, String> {
-    services::rooms::create_room(&state.ctx, name).await
+    services::rooms::create_room(&state.ctx, name).await.map_err(String::from)
 }
 
 #[tauri::command]
 pub async fn join_room(state: State<'_, AppState>, invite_code: String) -> Result<Room, String> {
-    services::rooms::join_room(&state.ctx, invite_code).await
+    services::rooms::join_room(&state.ctx, invite_code).await.map_err(String::from)
 }
 
 #[tauri::command]
-pub fn list_rooms(state: State<'_, AppState>) -> Result<Vec<Room>, String> {
-    services::rooms::list_rooms(&state.ctx)
+pub async fn list_rooms(state: State<'_, AppState>) -> Result<Vec<Room>, String> {
+    services::rooms::list_rooms(&state.ctx).await.map_err(String::from)
 }
 
 #[tauri::command]
-pub fn get_channels(state: State<'_, AppState>, room_id: String) -> Result<Vec<Channel>, String> {
-    services::rooms::get_channels(&state.ctx, &room_id)
+pub async fn get_channels(state: State<'_, AppState>, room_id: String) -> Result<Vec<Channel>, String> {
+    services::rooms::get_channels(&state.ctx, &room_id).await.map_err(String::from)
 }
@@ -0,0 +1,78 @@
+use rusqlite::{OptionalExtension, Result};
+
+use super::Database;
+use crate::models::{ApiScope, ApiToken};
+
+fn parse_scopes(scopes: &str) -> Vec<ApiScope> {
+    scopes.split(',').filter_map(|s| s.parse().ok()).collect()
+}
+
+fn join_scopes(scopes: &[ApiScope]) -> String {
+    scopes.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(",")
+}
+
+fn token_from_row(row: &rusqlite::Row) -> Result<ApiToken> {
+    let scopes: String = row.get(2)?;
+    Ok(ApiToken {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        scopes: parse_scopes(&scopes),
+        created_at: row.get(3)?,
+        last_used_at: row.get(4)?,
+        bot_peer_id: row.get(5)?,
+    })
+}
+
+impl Database {
+    // ============================================================
+    // API tokens
+    // ============================================================
+
+    pub fn insert_api_token(&self, token: &ApiToken, token_hash: &str) -> Result<()> {
+        let conn = self.pool.get();
+        conn.execute(
+            "INSERT INTO api_tokens (id, name, token_hash, scopes, created_at, bot_peer_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![token.id, token.name, token_hash, join_scopes(&token.scopes), token.created_at, token.bot_peer_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn list_api_tokens(&self) -> Result<Vec<ApiToken>> {
+        let conn = self.pool.get();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, scopes, created_at, last_used_at, bot_peer_id FROM api_tokens ORDER BY created_at",
+        )?;
+        let rows = stmt.query_map([], token_from_row)?.collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    pub fn revoke_api_token(&self, id: &str) -> Result<bool> {
+        let conn = self.pool.get();
+        Ok(conn.execute("DELETE FROM api_tokens WHERE id = ?1", [id])? > 0)
+    }
+
+    /// Revoke every token issued under `name`.
+    pub fn revoke_api_tokens_named(&self, name: &str) -> Result<usize> {
+        let conn = self.pool.get();
+        conn.execute("DELETE FROM api_tokens WHERE name = ?1", [name])
+    }
+
+    /// Scopes of the token with this hash and the bot it acts as, recording the use.
+    pub fn authenticate_api_token(&self, token_hash: &str, now: &str) -> Result<Option<(Vec<ApiScope>, Option<String>)>> {
+        let conn = self.pool.get();
+        let row: Option<(String, Option<String>)> = conn
+            .query_row(
+                "UPDATE api_tokens SET last_used_at = ?2 WHERE token_hash = ?1 RETURNING scopes, bot_peer_id",
+                rusqlite::params![token_hash, now],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        Ok(row.map(|(scopes, bot_peer_id)| (parse_scopes(&scopes), bot_peer_id)))
+    }
+
+    /// Revoke the tokens acting as `bot_peer_id`.
+    pub fn revoke_bot_api_tokens(&self, bot_peer_id: &str) -> Result<usize> {
+        let conn = self.pool.get();
+        conn.execute("DELETE FROM api_tokens WHERE bot_peer_id = ?1", [bot_peer_id])
+    }
+}
@@ -0,0 +1,153 @@
+use rusqlite::{OptionalExtension, Result};
+
+use super::Database;
+use crate::models::{Bot, BotCertificate};
+
+impl Database {
+    // ============================================================
+    // Hosted bots
+    // ============================================================
+
+    pub fn insert_bot(&self, bot: &Bot, keypair_bytes: &[u8]) -> Result<()> {
+        let conn = self.pool.get();
+        conn.execute(
+            "INSERT INTO bots (peer_id, display_name, keypair_bytes, created_at) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![bot.peer_id, bot.display_name, keypair_bytes, bot.created_at],
+        )?;
+        Ok(())
+    }
+
+    fn get_bot_rooms(&self, peer_id: &str) -> Result<Vec<String>> {
+        let conn = self.pool.get();
+        let mut stmt = conn.prepare_cached("SELECT room_id FROM bot_rooms WHERE bot_peer_id = ?1 ORDER BY room_id")?;
+        let rows = stmt.query_map([peer_id], |row| row.get(0))?.collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    pub fn get_bot(&self, peer_id: &str) -> Result<Option<Bot>> {
+        let bot = {
+            let conn = self.pool.get();
+            conn.query_row(
+                "SELECT peer_id, display_name, created_at FROM bots WHERE peer_id = ?1",
+                [peer_id],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)),
+            )
+            .optional()?
+        };
+        match bot {
+            Some((peer_id, display_name, created_at)) => {
+                let rooms = self.get_bot_rooms(&peer_id)?;
+                Ok(Some(Bot { peer_id, display_name, rooms, created_at }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn list_bots(&self) -> Result<Vec<Bot>> {
+        let ids: Vec<String> = {
+            let conn = self.pool.get();
+            let mut stmt = conn.prepare("SELECT peer_id FROM bots ORDER BY created_at")?;
+            let rows = stmt.query_map([], |row| row.get(0))?.collect::<Result<Vec<_>>>()?;
+            rows
+        };
+        let mut bots = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(bot) = self.get_bot(&id)? {
+                bots.push(bot);
+            }
+        }
+        Ok(bots)
+    }
+
+    pub fn set_bot_display_name(&self, peer_id: &str, display_name: &str) -> Result<bool> {
+        let conn = self.pool.get();
+        Ok(conn.execute("UPDATE bots SET display_name = ?2 WHERE peer_id = ?1", [peer_id, display_name])? > 0)
+    }
+
+    pub fn get_bot_keypair(&self, peer_id: &str) -> Result<Option<Vec<u8>>> {
+        let conn = self.pool.get();
+        conn.query_row("SELECT keypair_bytes FROM bots WHERE peer_id = ?1", [peer_id], |row| row.get(0))
+            .optional()
+    }
+
+    /// Remove a bot with its room memberships, certificate and API tokens.
+    pub fn delete_bot(&self, peer_id: &str) -> Result<bool> {
+        let mut conn = self.pool.get();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM bot_rooms WHERE bot_peer_id = ?1", [peer_id])?;
+        tx.execute("DELETE FROM bot_hosts WHERE bot_peer_id = ?1", [peer_id])?;
+        tx.execute("DELETE FROM api_tokens WHERE bot_peer_id = ?1", [peer_id])?;
+        tx.execute("DELETE FROM bot_commands WHERE bot_peer_id = ?1", [peer_id])?;
+        let deleted = tx.execute("DELETE FROM bots WHERE peer_id = ?1", [peer_id])? > 0;
+        tx.commit()?;
+        Ok(deleted)
+    }
+
+    pub fn add_bot_room(&self, peer_id: &str, room_id: &str) -> Result<bool> {
+        let conn = self.pool.get();
+        Ok(conn.execute("INSERT OR IGNORE INTO bot_rooms (bot_peer_id, room_id) VALUES (?1, ?2)", [peer_id, room_id])? > 0)
+    }
+
+    pub fn remove_bot_room(&self, peer_id: &str, room_id: &str) -> Result<bool> {
+        let conn = self.pool.get();
+        conn.execute("DELETE FROM bot_commands WHERE bot_peer_id = ?1 AND room_id = ?2", [peer_id, room_id])?;
+        Ok(conn.execute("DELETE FROM bot_rooms WHERE bot_peer_id = ?1 AND room_id = ?2", [peer_id, room_id])? > 0)
+    }
+
+    /// Hosted bots in `room_id` with their certificates, for announcing them.
+    pub fn get_room_bot_announcements(&self, room_id: &str) -> Result<Vec<(String, BotCertificate)>> {
+        let conn = self.pool.get();
+        let mut stmt = conn.prepare(
+            "SELECT b.display_name, h.certificate FROM bot_rooms r
+             JOIN bots b ON b.peer_id = r.bot_peer_id
+             JOIN bot_hosts h ON h.bot_peer_id = r.bot_peer_id
+             WHERE r.room_id = ?1",
+        )?;
+        let rows = stmt
+            .query_map([room_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(name, cert)| serde_json::from_str(&cert).ok().map(|cert| (name, cert)))
+            .collect())
+    }
+
+    // ============================================================
+    // Bot hosts
+    // ============================================================
+
+    /// Record a verified certificate, replacing an older one for the same bot.
+    pub fn store_bot_certificate(&self, cert: &BotCertificate) -> Result<()> {
+        let conn = self.pool.get();
+        let certificate = serde_json::to_string(cert).unwrap_or_default();
+        conn.execute(
+            "INSERT INTO bot_hosts (bot_peer_id, host_peer_id, certificate, issued_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(bot_peer_id) DO UPDATE SET host_peer_id = ?2, certificate = ?3, issued_at = ?4
+             WHERE excluded.issued_at > bot_hosts.issued_at",
+            rusqlite::params![cert.bot_peer_id, cert.host_peer_id, certificate, cert.issued_at],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_bot_certificate(&self, bot_peer_id: &str) -> Result<Option<BotCertificate>> {
+        let conn = self.pool.get();
+        let certificate: Option<String> = conn
+            .query_row("SELECT certificate FROM bot_hosts WHERE bot_peer_id = ?1", [bot_peer_id], |row| row.get(0))
+            .optional()?;
+        Ok(certificate.and_then(|c| serde_json::from_str(&c).ok()))
+    }
+
+    /// Whether `source_peer_id` may act for `sender_peer_id`: it is the same
+    /// identity (or a device of it), or it hosts the sender as a bot.
+    pub fn acts_for(&self, source_peer_id: &str, sender_peer_id: &str) -> Result<bool> {
+        if self.resolve_identity(source_peer_id)? == self.resolve_identity(sender_peer_id)? {
+            return Ok(true);
+        }
+        let conn = self.pool.get();
+        conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM bot_hosts WHERE bot_peer_id = ?1 AND host_peer_id = ?2)",
+            [sender_peer_id, source_peer_id],
+            |row| row.get(0),
+        )
+    }
+}
@@ -0,0 +1,104 @@
+use rusqlite::{Result, Row};
+
+use super::Database;
+use crate::models::{Reminder, SlashCommand};
+
+fn bot_command_from_row(row: &Row) -> Result<SlashCommand> {
+    let options: String = row.get(4)?;
+    Ok(SlashCommand {
+        room_id: Some(row.get(0)?),
+        name: row.get(1)?,
+        bot_peer_id: Some(row.get(2)?),
+        description: row.get(3)?,
+        options: serde_json::from_str(&options).unwrap_or_default(),
+    })
+}
+
+fn reminder_from_row(row: &Row) -> Result<Reminder> {
+    Ok(Reminder {
+        id: row.get(0)?,
+        channel_id: row.get(1)?,
+        content: row.get(2)?,
+        remind_at: row.get(3)?,
+        created_at: row.get(4)?,
+    })
+}
+
+impl Database {
+    // ============================================================
+    // Bot commands
+    // ============================================================
+
+    /// Register or update a bot's command in its room. Returns false if
+    /// another bot already owns the name there.
+    pub fn upsert_bot_command(&self, command: &SlashCommand, room_id: &str, bot_peer_id: &str, created_at: &str) -> Result<bool> {
+        let conn = self.pool.get();
+        let options = serde_json::to_string(&command.options).unwrap_or_else(|_| "[]".to_string());
+        let changed = conn.execute(
+            "INSERT INTO bot_commands (room_id, name, bot_peer_id, description, options, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(room_id, name) DO UPDATE SET description = excluded.description, options = excluded.options
+             WHERE bot_commands.bot_peer_id = excluded.bot_peer_id",
+            rusqlite::params![room_id, command.name, bot_peer_id, command.description, options, created_at],
+        )?;
+        Ok(changed > 0)
+    }
+
+    pub fn delete_bot_command(&self, room_id: &str, name: &str, bot_peer_id: &str) -> Result<bool> {
+        let conn = self.pool.get();
+        Ok(conn.execute(
+            "DELETE FROM bot_commands WHERE room_id = ?1 AND name = ?2 AND bot_peer_id = ?3",
+            [room_id, name, bot_peer_id],
+        )? > 0)
+    }
+
+    pub fn list_room_bot_commands(&self, room_id: &str) -> Result<Vec<SlashCommand>> {
+        let conn = self.pool.get();
+        let mut stmt = conn.prepare(
+            "SELECT room_id, name, bot_peer_id, description, options FROM bot_commands
+             WHERE room_id = ?1 ORDER BY name",
+        )?;
+        let rows = stmt.query_map([room_id], bot_command_from_row)?.collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    pub fn list_bot_commands(&self, bot_peer_id: &str) -> Result<Vec<SlashCommand>> {
+        let conn = self.pool.get();
+        let mut stmt = conn.prepare(
+            "SELECT room_id, name, bot_peer_id, description, options FROM bot_commands
+             WHERE bot_peer_id = ?1 ORDER BY room_id, name",
+        )?;
+        let rows = stmt.query_map([bot_peer_id], bot_command_from_row)?.collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    // ============================================================
+    // Reminders
+    // ============================================================
+
+    pub fn insert_reminder(&self, reminder: &Reminder) -> Result<()> {
+        let conn = self.pool.get();
+        conn.execute(
+            "INSERT INTO reminders (id, channel_id, content, remind_at, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![reminder.id, reminder.channel_id, reminder.content, reminder.remind_at, reminder.created_at],
+        )?;
+        Ok(())
+    }
+
+    /// Remove and return the reminders due at `now`.
+    pub fn take_due_reminders(&self, now: &str) -> Result<Vec<Reminder>> {
+        let mut conn = self.pool.get();
+        let tx = conn.transaction()?;
+        let due = {
+            let mut stmt = tx.prepare(
+                "SELECT id, channel_id, content, remind_at, created_at FROM reminders
+                 WHERE remind_at <= ?1 ORDER BY remind_at",
+            )?;
+            let rows = stmt.query_map([now], reminder_from_row)?.collect::<Result<Vec<_>>>()?;
+            rows
+        };
+        tx.execute("DELETE FROM reminders WHERE remind_at <= ?1", [now])?;
+        tx.commit()?;
+        Ok(due)
+    }
+}
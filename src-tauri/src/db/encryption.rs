@@ -0,0 +1,50 @@
+//! At-rest encryption via SQLCipher.
+//!
+//! The whole database file is encrypted, including the identity keypair in
+//! `identity.keypair_bytes`. A passphrase is optional: without one the file
+//! is an ordinary SQLite database, exactly as before.
+
+use rusqlite::{Connection, ErrorCode};
+use std::path::Path;
+
+/// Environment variable read at startup to unlock the database.
+pub const PASSPHRASE_ENV: &str = "CHATR_PASSPHRASE";
+
+/// Unlock `conn` with `key`. Must be the first statement run on the connection.
+/// Fails with `SQLITE_NOTADB` if the key is wrong.
+pub fn apply_key(conn: &Connection, key: &str) -> rusqlite::Result<()> {
+    conn.pragma_update(None, "key", key)?;
+    // The key is only checked once a page is read
+    conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(()))
+}
+
+/// Whether the database at `path` exists and can't be read without a key.
+pub fn is_encrypted(path: &Path) -> bool {
+    if !path.exists() {
+        return false;
+    }
+    match Connection::open(path) {
+        Ok(conn) => conn
+            .query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(()))
+            .err()
+            .is_some_and(|e| is_not_a_database(&e)),
+        Err(e) => is_not_a_database(&e),
+    }
+}
+
+/// SQLCipher reports a wrong (or missing) key as "file is not a database".
+pub fn is_not_a_database(e: &rusqlite::Error) -> bool {
+    e.sqlite_error_code() == Some(ErrorCode::NotADatabase)
+}
+
+/// Copy the database open on `conn` into a new file at `dest`, encrypted
+/// under `key` (`None` writes plaintext).
+pub fn export(conn: &Connection, dest: &Path, key: Option<&str>) -> rusqlite::Result<()> {
+    conn.execute(
+        "ATTACH DATABASE ?1 AS rekeyed KEY ?2",
+        rusqlite::params![dest.to_string_lossy(), key.unwrap_or("")],
+    )?;
+    let exported = conn.query_row("SELECT sqlcipher_export('rekeyed')", [], |_| Ok(()));
+    conn.execute_batch("DETACH DATABASE rekeyed;")?;
+    exported
+}
@@ -0,0 +1,83 @@
+use rusqlite::Result;
+
+use super::Database;
+use crate::events::{EventScope, JournalEntry, JournaledEvent};
+
+/// Events kept in the journal; older ones are trimmed.
+pub const JOURNAL_CAPACITY: i64 = 10_000;
+
+/// Trim the journal every this many appends rather than on each one.
+const TRIM_EVERY: i64 = 100;
+
+impl Database {
+    // ============================================================
+    // Event journal
+    // ============================================================
+
+    /// Append events under the sequence numbers the event bus gave them.
+    pub fn append_events(&self, entries: &[JournalEntry]) -> Result<()> {
+        let mut conn = self.pool.get();
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO event_journal (seq, event_type, room_id, channel_id, conversation_id, payload, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for entry in entries {
+                stmt.execute(rusqlite::params![
+                    entry.seq,
+                    entry.event_type,
+                    entry.scope.room_id,
+                    entry.scope.channel_id,
+                    entry.scope.conversation_id,
+                    entry.payload,
+                    entry.created_at,
+                ])?;
+            }
+        }
+        // Trim when the batch crosses a multiple of TRIM_EVERY
+        if let (Some(first), Some(last)) = (entries.first(), entries.last()) {
+            if last.seq / TRIM_EVERY != (first.seq - 1) / TRIM_EVERY {
+                tx.execute("DELETE FROM event_journal WHERE seq <= ?1", [last.seq - JOURNAL_CAPACITY])?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// Highest sequence number ever journaled, 0 if none.
+    pub fn get_last_event_seq(&self) -> Result<i64> {
+        let conn = self.pool.get();
+        conn.query_row("SELECT COALESCE(MAX(seq), 0) FROM event_journal", [], |row| row.get(0))
+    }
+
+    /// Up to `limit` journaled events after `seq`, oldest first.
+    pub fn get_events_since(&self, seq: i64, limit: i64) -> Result<Vec<JournaledEvent>> {
+        let conn = self.pool.get();
+        let mut stmt = conn.prepare_cached(
+            "SELECT seq, event_type, room_id, channel_id, conversation_id, payload
+             FROM event_journal WHERE seq > ?1 ORDER BY seq LIMIT ?2",
+        )?;
+        let rows = stmt
+            .query_map(rusqlite::params![seq, limit], |row| {
+                let payload: String = row.get(5)?;
+                Ok(JournaledEvent {
+                    seq: Some(row.get(0)?),
+                    event_type: row.get(1)?,
+                    scope: EventScope {
+                        room_id: row.get(2)?,
+                        channel_id: row.get(3)?,
+                        conversation_id: row.get(4)?,
+                    },
+                    payload: serde_json::from_str(&payload).unwrap_or_default(),
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Oldest sequence number still in the journal.
+    pub fn get_oldest_event_seq(&self) -> Result<Option<i64>> {
+        let conn = self.pool.get();
+        conn.query_row("SELECT MIN(seq) FROM event_journal", [], |row| row.get(0))
+    }
+}
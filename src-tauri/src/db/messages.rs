@@ -1,5 +1,144 @@
+use rusqlite::{Connection, OptionalExtension};
 use crate::models::*;
 use super::Database;
+use crate::export::escape_html;
+
+/// Message columns plus the thread summary of each row (reply count, thread
+/// row presence, last reply). Used with `LAST_REPLY_JOIN` on `messages m`.
+const THREADED_MESSAGE_COLUMNS: &str =
+    "m.id, m.channel_id, m.sender_peer_id, m.sender_display_name, m.content, m.timestamp,
+     m.edited_at, m.deleted_at, m.reply_to_id, m.thread_id,
+     (SELECT COUNT(*) FROM messages r WHERE r.thread_id = m.id AND r.deleted_at IS NULL),
+     EXISTS(SELECT 1 FROM threads t WHERE t.root_message_id = m.id),
+     lr.timestamp, lr.sender_peer_id, lr.sender_display_name, m.imported_from,
+     m.webhook_id, m.embeds";
+
+const LAST_REPLY_JOIN: &str =
+    "LEFT JOIN messages lr ON lr.id = (
+        SELECT r.id FROM messages r
+        WHERE r.thread_id = m.id AND r.deleted_at IS NULL
+        ORDER BY r.timestamp DESC LIMIT 1
+     )";
+
+/// Embeds are stored as a JSON array, NULL when there are none.
+/// FTS5 `snippet()`/`highlight()` markers. Private-use characters rather
+/// than `<mark>` so the matched text can be escaped before the tags go in.
+pub(super) const MATCH_SQL_ARGS: &str = "'\u{E000}', '\u{E001}'";
+
+/// Escape an FTS snippet for HTML and turn its match markers into `<mark>`.
+pub(super) fn highlight_snippet(raw: &str) -> String {
+    escape_html(raw).replace('\u{E000}', "<mark>").replace('\u{E001}', "</mark>")
+}
+
+fn embeds_to_sql(embeds: &[MessageEmbed]) -> Option<String> {
+    if embeds.is_empty() {
+        None
+    } else {
+        serde_json::to_string(embeds).ok()
+    }
+}
+
+fn embeds_from_sql(json: Option<String>) -> Vec<MessageEmbed> {
+    json.and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default()
+}
+
+fn threaded_message_from_row(row: &rusqlite::Row) -> rusqlite::Result<Message> {
+    let reply_count: i64 = row.get(10)?;
+    let has_thread: bool = row.get(11)?;
+    let thread = if reply_count > 0 || has_thread {
+        Some(ThreadSummary {
+            reply_count,
+            last_reply_at: row.get(12)?,
+            last_reply_peer_id: row.get(13)?,
+            last_reply_display_name: row.get(14)?,
+        })
+    } else {
+        None
+    };
+    Ok(Message {
+        id: row.get(0)?,
+        channel_id: row.get(1)?,
+        sender_peer_id: row.get(2)?,
+        sender_display_name: row.get(3)?,
+        content: row.get(4)?,
+        timestamp: row.get(5)?,
+        edited_at: row.get(6)?,
+        deleted_at: row.get(7)?,
+        reply_to_id: row.get(8)?,
+        thread_id: row.get(9)?,
+        thread,
+        imported_from: row.get(15)?,
+        webhook_id: row.get(16)?,
+        embeds: embeds_from_sql(row.get(17)?),
+    })
+}
+
+/// Remove what hangs off a message: reactions, pins, mentions and attachment
+/// references. Returns the ids of files it was attached to.
+fn erase_message_dependents(conn: &Connection, message_id: &str) -> rusqlite::Result<Vec<String>> {
+    let file_ids = conn
+        .prepare_cached("SELECT file_id FROM message_attachments WHERE message_id = ?1")?
+        .query_map([message_id], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for sql in [
+        "DELETE FROM reactions WHERE message_id = ?1",
+        "DELETE FROM pinned_messages WHERE message_id = ?1",
+        "DELETE FROM message_mentions WHERE message_id = ?1",
+        "DELETE FROM message_attachments WHERE message_id = ?1",
+    ] {
+        conn.prepare_cached(sql)?.execute([message_id])?;
+    }
+    Ok(file_ids)
+}
+
+/// Drop files no message is attached to any more.
+fn release_files(conn: &Connection, mut file_ids: Vec<String>) -> rusqlite::Result<()> {
+    file_ids.sort();
+    file_ids.dedup();
+    for file_id in file_ids {
+        let still_attached: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM message_attachments WHERE file_id = ?1)",
+            [&file_id],
+            |row| row.get(0),
+        )?;
+        if !still_attached {
+            conn.execute("DELETE FROM files WHERE id = ?1", [&file_id])?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove channel messages entirely, threads they root included.
+pub(super) fn hard_delete_messages(conn: &Connection, ids: &[String]) -> rusqlite::Result<()> {
+    let mut file_ids = Vec::new();
+    for id in ids {
+        file_ids.extend(erase_message_dependents(conn, id)?);
+        for sql in [
+            "DELETE FROM threads WHERE root_message_id = ?1",
+            "DELETE FROM thread_subscriptions WHERE thread_id = ?1",
+            "DELETE FROM messages WHERE id = ?1",
+        ] {
+            conn.prepare_cached(sql)?.execute([id])?;
+        }
+    }
+    release_files(conn, file_ids)
+}
+
+/// Erase messages down to tombstones: the row keeps its id, channel, sender,
+/// timestamp, reply and thread links so ordering and references survive, but
+/// the content and everything attached to it are gone. Tombstones are not
+/// indexed for search.
+fn tombstone_messages(conn: &Connection, ids: &[String], deleted_at: &str) -> rusqlite::Result<()> {
+    let mut file_ids = Vec::new();
+    for id in ids {
+        file_ids.extend(erase_message_dependents(conn, id)?);
+        conn.prepare_cached(
+            "UPDATE messages SET content = '', edited_at = NULL, deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+        )?
+        .execute(rusqlite::params![deleted_at, id])?;
+    }
+    release_files(conn, file_ids)
+}
 
 impl Database {
     // ============================================================
@@ -7,10 +146,10 @@ impl Database {
     // ============================================================
 
     pub fn insert_message(&self, msg: &Message) -> rusqlite::Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
-            "INSERT OR IGNORE INTO messages (id, channel_id, sender_peer_id, sender_display_name, content, timestamp, edited_at, deleted_at, reply_to_id)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            "INSERT OR IGNORE INTO messages (id, channel_id, sender_peer_id, sender_display_name, content, timestamp, edited_at, deleted_at, reply_to_id, thread_id, imported_from, webhook_id, embeds)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
             rusqlite::params![
                 msg.id,
                 msg.channel_id,
@@ -21,68 +160,59 @@ impl Database {
                 msg.edited_at,
                 msg.deleted_at,
                 msg.reply_to_id,
+                msg.thread_id,
+                msg.imported_from,
+                msg.webhook_id,
+                embeds_to_sql(&msg.embeds),
             ],
         )?;
         Ok(())
     }
 
+    /// Channel messages, excluding replies posted inside threads.
+    /// Thread roots carry their reply count and last-reply metadata.
     pub fn get_messages(&self, channel_id: &str, limit: i64, before: Option<&str>) -> rusqlite::Result<Vec<Message>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut messages = if let Some(before_ts) = before {
-            let mut stmt = conn.prepare(
-                "SELECT id, channel_id, sender_peer_id, sender_display_name, content, timestamp, edited_at, deleted_at, reply_to_id
-                 FROM messages
-                 WHERE channel_id = ?1 AND timestamp < ?2 AND deleted_at IS NULL
-                 ORDER BY timestamp DESC LIMIT ?3",
-            )?;
-            let rows = stmt.query_map(rusqlite::params![channel_id, before_ts, limit], |row| {
-                Ok(Message {
-                    id: row.get(0)?,
-                    channel_id: row.get(1)?,
-                    sender_peer_id: row.get(2)?,
-                    sender_display_name: row.get(3)?,
-                    content: row.get(4)?,
-                    timestamp: row.get(5)?,
-                    edited_at: row.get(6)?,
-                    deleted_at: row.get(7)?,
-                    reply_to_id: row.get(8)?,
-                })
-            })?
-            .collect::<rusqlite::Result<Vec<_>>>()?;
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {THREADED_MESSAGE_COLUMNS}
+                 FROM messages m {LAST_REPLY_JOIN}
+                 WHERE m.channel_id = ?1 AND m.thread_id IS NULL AND m.timestamp < ?2 AND m.deleted_at IS NULL
+                 ORDER BY m.timestamp DESC LIMIT ?3",
+            ))?;
+            let rows = stmt.query_map(rusqlite::params![channel_id, before_ts, limit], threaded_message_from_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
             rows
         } else {
-            let mut stmt = conn.prepare(
-                "SELECT id, channel_id, sender_peer_id, sender_display_name, content, timestamp, edited_at, deleted_at, reply_to_id
-                 FROM messages
-                 WHERE channel_id = ?1 AND deleted_at IS NULL
-                 ORDER BY timestamp DESC LIMIT ?2",
-            )?;
-            let rows = stmt.query_map(rusqlite::params![channel_id, limit], |row| {
-                Ok(Message {
-                    id: row.get(0)?,
-                    channel_id: row.get(1)?,
-                    sender_peer_id: row.get(2)?,
-                    sender_display_name: row.get(3)?,
-                    content: row.get(4)?,
-                    timestamp: row.get(5)?,
-                    edited_at: row.get(6)?,
-                    deleted_at: row.get(7)?,
-                    reply_to_id: row.get(8)?,
-                })
-            })?
-            .collect::<rusqlite::Result<Vec<_>>>()?;
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {THREADED_MESSAGE_COLUMNS}
+                 FROM messages m {LAST_REPLY_JOIN}
+                 WHERE m.channel_id = ?1 AND m.thread_id IS NULL AND m.deleted_at IS NULL
+                 ORDER BY m.timestamp DESC LIMIT ?2",
+            ))?;
+            let rows = stmt.query_map(rusqlite::params![channel_id, limit], threaded_message_from_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
             rows
         };
         messages.reverse();
         Ok(messages)
     }
 
+    pub fn get_message(&self, message_id: &str) -> rusqlite::Result<Option<Message>> {
+        let conn = self.pool.get();
+        conn.query_row(
+            &format!("SELECT {THREADED_MESSAGE_COLUMNS} FROM messages m {LAST_REPLY_JOIN} WHERE m.id = ?1"),
+            rusqlite::params![message_id],
+            threaded_message_from_row,
+        ).optional()
+    }
+
     // ============================================================
     // Phase 1: Edit, Delete, Reactions, Read Receipts, Search
     // ============================================================
 
     pub fn edit_message(&self, message_id: &str, new_content: &str, edited_at: &str) -> rusqlite::Result<bool> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let rows_affected = conn.execute(
             "UPDATE messages SET content = ?1, edited_at = ?2 WHERE id = ?3 AND deleted_at IS NULL",
             rusqlite::params![new_content, edited_at, message_id],
@@ -90,17 +220,83 @@ impl Database {
         Ok(rows_affected > 0)
     }
 
+    /// Erase a message to a tombstone. Returns false if there was no live
+    /// message to delete.
     pub fn delete_message(&self, message_id: &str, deleted_at: &str) -> rusqlite::Result<bool> {
-        let conn = self.conn.lock().unwrap();
-        let rows_affected = conn.execute(
-            "UPDATE messages SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
-            rusqlite::params![deleted_at, message_id],
+        let mut conn = self.pool.get();
+        let tx = conn.transaction()?;
+        let live: bool = tx.query_row(
+            "SELECT EXISTS(SELECT 1 FROM messages WHERE id = ?1 AND deleted_at IS NULL)",
+            [message_id],
+            |row| row.get(0),
         )?;
-        Ok(rows_affected > 0)
+        if !live {
+            return Ok(false);
+        }
+        tombstone_messages(&tx, &[message_id.to_string()], deleted_at)?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// Erase every live message `sender_peer_id` posted in the room's channels
+    /// up to `deleted_at`. Returns `(channel_id, message_id)` pairs.
+    pub fn delete_sender_messages_in_room(
+        &self,
+        room_id: &str,
+        sender_peer_id: &str,
+        deleted_at: &str,
+    ) -> rusqlite::Result<Vec<(String, String)>> {
+        let mut conn = self.pool.get();
+        let tx = conn.transaction()?;
+        let deleted: Vec<(String, String)> = {
+            let mut stmt = tx.prepare(
+                "SELECT channel_id, id FROM messages
+                 WHERE channel_id IN (SELECT id FROM channels WHERE room_id = ?1)
+                   AND sender_peer_id = ?2 AND timestamp <= ?3 AND deleted_at IS NULL",
+            )?;
+            let rows = stmt
+                .query_map(rusqlite::params![room_id, sender_peer_id, deleted_at], |row| {
+                    Ok((row.get(0)?, row.get(1)?))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            rows
+        };
+        let ids: Vec<String> = deleted.iter().map(|(_, id)| id.clone()).collect();
+        tombstone_messages(&tx, &ids, deleted_at)?;
+        tx.execute(
+            "INSERT INTO message_purges (room_id, sender_peer_id, deleted_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(room_id, sender_peer_id) DO UPDATE SET deleted_at = excluded.deleted_at
+             WHERE excluded.deleted_at > message_purges.deleted_at",
+            rusqlite::params![room_id, sender_peer_id, deleted_at],
+        )?;
+        tx.commit()?;
+        Ok(deleted)
+    }
+
+    /// Latest "delete all my messages" cut-off recorded for a sender in a room.
+    pub fn get_message_purge(&self, room_id: &str, sender_peer_id: &str) -> rusqlite::Result<Option<String>> {
+        let conn = self.pool.get();
+        conn.query_row(
+            "SELECT deleted_at FROM message_purges WHERE room_id = ?1 AND sender_peer_id = ?2",
+            rusqlite::params![room_id, sender_peer_id],
+            |row| row.get(0),
+        )
+        .optional()
+    }
+
+    /// Whether a message arriving late was already covered by its sender's purge.
+    pub fn is_message_purged(&self, channel_id: &str, sender_peer_id: &str, timestamp: &str) -> rusqlite::Result<bool> {
+        let conn = self.pool.get();
+        conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM message_purges p JOIN channels c ON c.room_id = p.room_id
+                           WHERE c.id = ?1 AND p.sender_peer_id = ?2 AND p.deleted_at >= ?3)",
+            rusqlite::params![channel_id, sender_peer_id, timestamp],
+            |row| row.get(0),
+        )
     }
 
     pub fn add_reaction(&self, reaction: &Reaction) -> rusqlite::Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "INSERT OR IGNORE INTO reactions (id, message_id, peer_id, emoji, created_at)
              VALUES (?1, ?2, ?3, ?4, ?5)",
@@ -116,7 +312,7 @@ impl Database {
     }
 
     pub fn remove_reaction(&self, message_id: &str, peer_id: &str, emoji: &str) -> rusqlite::Result<bool> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let rows_affected = conn.execute(
             "DELETE FROM reactions WHERE message_id = ?1 AND peer_id = ?2 AND emoji = ?3",
             rusqlite::params![message_id, peer_id, emoji],
@@ -125,7 +321,7 @@ impl Database {
     }
 
     pub fn get_reactions(&self, message_id: &str) -> rusqlite::Result<Vec<Reaction>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare(
             "SELECT id, message_id, peer_id, emoji, created_at
              FROM reactions
@@ -146,7 +342,7 @@ impl Database {
     }
 
     pub fn set_read_receipt(&self, channel_id: &str, peer_id: &str, last_read_message_id: &str, updated_at: &str) -> rusqlite::Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "INSERT OR REPLACE INTO read_receipts (channel_id, peer_id, last_read_message_id, updated_at)
              VALUES (?1, ?2, ?3, ?4)",
@@ -156,7 +352,7 @@ impl Database {
     }
 
     pub fn get_read_receipts(&self, channel_id: &str) -> rusqlite::Result<Vec<ReadReceipt>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare(
             "SELECT channel_id, peer_id, last_read_message_id, updated_at
              FROM read_receipts
@@ -175,80 +371,276 @@ impl Database {
         Ok(rows)
     }
 
-    pub fn search_messages(&self, channel_id: Option<&str>, query: &str, limit: i64, offset: i64) -> rusqlite::Result<SearchResult> {
-        let conn = self.conn.lock().unwrap();
+    /// Run a parsed search. Free text goes through FTS5 `MATCH` (and gets a
+    /// highlighted snippet); everything else is plain SQL filters.
+    pub fn search_messages(&self, search: &MessageSearch, limit: i64, offset: i64) -> rusqlite::Result<SearchResult> {
+        use rusqlite::types::Value;
 
-        let (total, messages) = if let Some(ch_id) = channel_id {
-            let total: i64 = conn.query_row(
-                "SELECT COUNT(*)
-                 FROM messages_fts
-                 JOIN messages ON messages.rowid = messages_fts.rowid
-                 WHERE messages_fts MATCH ?1 AND messages_fts.channel_id = ?2 AND messages.deleted_at IS NULL",
-                rusqlite::params![query, ch_id],
-                |row| row.get(0),
-            )?;
+        let conn = self.pool.get();
 
-            let mut stmt = conn.prepare(
-                "SELECT messages.id, messages.channel_id, messages.sender_peer_id, messages.sender_display_name,
-                        messages.content, messages.timestamp, messages.edited_at, messages.deleted_at, messages.reply_to_id
-                 FROM messages_fts
-                 JOIN messages ON messages.rowid = messages_fts.rowid
-                 WHERE messages_fts MATCH ?1 AND messages_fts.channel_id = ?2 AND messages.deleted_at IS NULL
-                 ORDER BY messages.timestamp DESC
-                 LIMIT ?3 OFFSET ?4",
-            )?;
-            let msgs = stmt.query_map(rusqlite::params![query, ch_id, limit, offset], |row| {
-                Ok(Message {
-                    id: row.get(0)?,
-                    channel_id: row.get(1)?,
-                    sender_peer_id: row.get(2)?,
-                    sender_display_name: row.get(3)?,
-                    content: row.get(4)?,
-                    timestamp: row.get(5)?,
-                    edited_at: row.get(6)?,
-                    deleted_at: row.get(7)?,
-                    reply_to_id: row.get(8)?,
-                })
+        let mut clauses = vec!["m.deleted_at IS NULL".to_string()];
+        let mut params: Vec<Value> = Vec::new();
+        if let Some(fts) = &search.fts {
+            clauses.push("messages_fts MATCH ?".to_string());
+            params.push(Value::Text(fts.clone()));
+        }
+        for from in &search.from {
+            clauses.push("(m.sender_peer_id = ? OR m.sender_display_name = ? COLLATE NOCASE)".to_string());
+            params.push(Value::Text(from.clone()));
+            params.push(Value::Text(from.clone()));
+        }
+        if !search.channels.is_empty() {
+            let alternatives = vec!["m.channel_id = ? OR m.channel_id IN (SELECT id FROM channels WHERE name = ? COLLATE NOCASE)"; search.channels.len()];
+            clauses.push(format!("({})", alternatives.join(" OR ")));
+            for channel in &search.channels {
+                params.push(Value::Text(channel.clone()));
+                params.push(Value::Text(channel.clone()));
+            }
+        }
+        for peer_id in &search.mentions {
+            clauses.push(
+                "EXISTS (SELECT 1 FROM message_mentions mm WHERE mm.message_id = m.id AND mm.kind = 'user' AND mm.target = ?)".to_string(),
+            );
+            params.push(Value::Text(peer_id.clone()));
+        }
+        if search.has_attachment {
+            clauses.push("EXISTS (SELECT 1 FROM message_attachments ma WHERE ma.message_id = m.id)".to_string());
+        }
+        if let Some(before) = &search.before {
+            clauses.push("julianday(m.timestamp) < julianday(?)".to_string());
+            params.push(Value::Text(before.clone()));
+        }
+        if let Some(after) = &search.after {
+            clauses.push("julianday(m.timestamp) >= julianday(?)".to_string());
+            params.push(Value::Text(after.clone()));
+        }
+        if let Some(room_id) = &search.room_id {
+            clauses.push("m.channel_id IN (SELECT id FROM channels WHERE room_id = ?)".to_string());
+            params.push(Value::Text(room_id.clone()));
+        }
+
+        let (from_sql, snippet_sql) = if search.fts.is_some() {
+            (
+                "messages_fts JOIN messages m ON m.rowid = messages_fts.rowid",
+                format!("snippet(messages_fts, 3, {MATCH_SQL_ARGS}, '…', 16)"),
+            )
+        } else {
+            ("messages m", "NULL".to_string())
+        };
+        let where_sql = clauses.join(" AND ");
+
+        let total: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM {from_sql} WHERE {where_sql}"),
+            rusqlite::params_from_iter(params.iter()),
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT m.id, m.channel_id, m.sender_peer_id, m.sender_display_name,
+                    m.content, m.timestamp, m.edited_at, m.deleted_at, m.reply_to_id,
+                    m.thread_id, {snippet_sql}, m.imported_from, m.webhook_id, m.embeds
+             FROM {from_sql}
+             WHERE {where_sql}
+             ORDER BY m.timestamp DESC
+             LIMIT ? OFFSET ?"
+        ))?;
+        params.push(Value::Integer(limit));
+        params.push(Value::Integer(offset));
+
+        let mut snippets = std::collections::HashMap::new();
+        let messages = stmt
+            .query_map(rusqlite::params_from_iter(params.iter()), |row| {
+                Ok((
+                    Message {
+                        id: row.get(0)?,
+                        channel_id: row.get(1)?,
+                        sender_peer_id: row.get(2)?,
+                        sender_display_name: row.get(3)?,
+                        content: row.get(4)?,
+                        timestamp: row.get(5)?,
+                        edited_at: row.get(6)?,
+                        deleted_at: row.get(7)?,
+                        reply_to_id: row.get(8)?,
+                        thread_id: row.get(9)?,
+                        thread: None,
+                        imported_from: row.get(11)?,
+                        webhook_id: row.get(12)?,
+                        embeds: embeds_from_sql(row.get(13)?),
+                    },
+                    row.get::<_, Option<String>>(10)?,
+                ))
             })?
+            .map(|row| {
+                row.map(|(msg, snippet)| {
+                    if let Some(snippet) = snippet {
+                        snippets.insert(msg.id.clone(), highlight_snippet(&snippet));
+                    }
+                    msg
+                })
+            })
             .collect::<rusqlite::Result<Vec<_>>>()?;
-            (total, msgs)
-        } else {
-            let total: i64 = conn.query_row(
-                "SELECT COUNT(*)
-                 FROM messages_fts
-                 JOIN messages ON messages.rowid = messages_fts.rowid
-                 WHERE messages_fts MATCH ?1 AND messages.deleted_at IS NULL",
-                rusqlite::params![query],
-                |row| row.get(0),
-            )?;
 
-            let mut stmt = conn.prepare(
-                "SELECT messages.id, messages.channel_id, messages.sender_peer_id, messages.sender_display_name,
-                        messages.content, messages.timestamp, messages.edited_at, messages.deleted_at, messages.reply_to_id
-                 FROM messages_fts
-                 JOIN messages ON messages.rowid = messages_fts.rowid
-                 WHERE messages_fts MATCH ?1 AND messages.deleted_at IS NULL
-                 ORDER BY messages.timestamp DESC
-                 LIMIT ?2 OFFSET ?3",
-            )?;
-            let msgs = stmt.query_map(rusqlite::params![query, limit, offset], |row| {
-                Ok(Message {
+        Ok(SearchResult { messages, total, snippets })
+    }
+
+    // ============================================================
+    // Threads
+    // ============================================================
+
+    /// Record a thread. Returns false if the thread already existed.
+    pub fn create_thread(&self, thread: &Thread) -> rusqlite::Result<bool> {
+        let conn = self.pool.get();
+        let rows_affected = conn.execute(
+            "INSERT OR IGNORE INTO threads (root_message_id, channel_id, name, created_by, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![
+                thread.id,
+                thread.channel_id,
+                thread.name,
+                thread.created_by,
+                thread.created_at,
+            ],
+        )?;
+        Ok(rows_affected > 0)
+    }
+
+    pub fn get_thread(&self, thread_id: &str) -> rusqlite::Result<Option<Thread>> {
+        let conn = self.pool.get();
+        conn.query_row(
+            "SELECT root_message_id, channel_id, name, created_by, created_at
+             FROM threads WHERE root_message_id = ?1",
+            rusqlite::params![thread_id],
+            |row| {
+                Ok(Thread {
                     id: row.get(0)?,
                     channel_id: row.get(1)?,
-                    sender_peer_id: row.get(2)?,
-                    sender_display_name: row.get(3)?,
-                    content: row.get(4)?,
-                    timestamp: row.get(5)?,
-                    edited_at: row.get(6)?,
-                    deleted_at: row.get(7)?,
-                    reply_to_id: row.get(8)?,
+                    name: row.get(2)?,
+                    created_by: row.get(3)?,
+                    created_at: row.get(4)?,
                 })
-            })?
-            .collect::<rusqlite::Result<Vec<_>>>()?;
-            (total, msgs)
+            },
+        ).optional()
+    }
+
+    pub fn get_channel_threads(&self, channel_id: &str) -> rusqlite::Result<Vec<Thread>> {
+        let conn = self.pool.get();
+        let mut stmt = conn.prepare(
+            "SELECT root_message_id, channel_id, name, created_by, created_at
+             FROM threads WHERE channel_id = ?1 ORDER BY created_at DESC",
+        )?;
+        let rows = stmt.query_map(rusqlite::params![channel_id], |row| {
+            Ok(Thread {
+                id: row.get(0)?,
+                channel_id: row.get(1)?,
+                name: row.get(2)?,
+                created_by: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Replies posted in a thread, oldest first, paginated like `get_messages`.
+    pub fn get_thread_messages(&self, thread_id: &str, limit: i64, before: Option<&str>) -> rusqlite::Result<Vec<Message>> {
+        let conn = self.pool.get();
+        let mut messages = if let Some(before_ts) = before {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {THREADED_MESSAGE_COLUMNS}
+                 FROM messages m {LAST_REPLY_JOIN}
+                 WHERE m.thread_id = ?1 AND m.timestamp < ?2 AND m.deleted_at IS NULL
+                 ORDER BY m.timestamp DESC LIMIT ?3",
+            ))?;
+            let rows = stmt.query_map(rusqlite::params![thread_id, before_ts, limit], threaded_message_from_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            rows
+        } else {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {THREADED_MESSAGE_COLUMNS}
+                 FROM messages m {LAST_REPLY_JOIN}
+                 WHERE m.thread_id = ?1 AND m.deleted_at IS NULL
+                 ORDER BY m.timestamp DESC LIMIT ?2",
+            ))?;
+            let rows = stmt.query_map(rusqlite::params![thread_id, limit], threaded_message_from_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            rows
         };
+        messages.reverse();
+        Ok(messages)
+    }
 
-        Ok(SearchResult { messages, total })
+    pub fn get_thread_summary(&self, thread_id: &str) -> rusqlite::Result<ThreadSummary> {
+        let conn = self.pool.get();
+        conn.query_row(
+            "SELECT COUNT(*),
+                    (SELECT timestamp FROM messages WHERE thread_id = ?1 AND deleted_at IS NULL ORDER BY timestamp DESC LIMIT 1),
+                    (SELECT sender_peer_id FROM messages WHERE thread_id = ?1 AND deleted_at IS NULL ORDER BY timestamp DESC LIMIT 1),
+                    (SELECT sender_display_name FROM messages WHERE thread_id = ?1 AND deleted_at IS NULL ORDER BY timestamp DESC LIMIT 1)
+             FROM messages WHERE thread_id = ?1 AND deleted_at IS NULL",
+            rusqlite::params![thread_id],
+            |row| {
+                Ok(ThreadSummary {
+                    reply_count: row.get(0)?,
+                    last_reply_at: row.get(1)?,
+                    last_reply_peer_id: row.get(2)?,
+                    last_reply_display_name: row.get(3)?,
+                })
+            },
+        )
+    }
+
+    /// Explicitly (un)subscribe from a thread. Overrides any automatic subscription.
+    pub fn set_thread_subscription(&self, thread_id: &str, subscribed: bool, updated_at: &str) -> rusqlite::Result<()> {
+        let conn = self.pool.get();
+        conn.execute(
+            "INSERT OR REPLACE INTO thread_subscriptions (thread_id, subscribed, updated_at)
+             VALUES (?1, ?2, ?3)",
+            rusqlite::params![thread_id, subscribed, updated_at],
+        )?;
+        Ok(())
+    }
+
+    /// Subscribe to a thread unless the user has already chosen otherwise.
+    pub fn auto_subscribe_thread(&self, thread_id: &str, updated_at: &str) -> rusqlite::Result<()> {
+        let conn = self.pool.get();
+        conn.execute(
+            "INSERT OR IGNORE INTO thread_subscriptions (thread_id, subscribed, updated_at)
+             VALUES (?1, 1, ?2)",
+            rusqlite::params![thread_id, updated_at],
+        )?;
+        Ok(())
+    }
+
+    pub fn is_thread_subscribed(&self, thread_id: &str) -> rusqlite::Result<bool> {
+        let conn = self.pool.get();
+        let subscribed: Option<bool> = conn.query_row(
+            "SELECT subscribed FROM thread_subscriptions WHERE thread_id = ?1",
+            rusqlite::params![thread_id],
+            |row| row.get(0),
+        ).optional()?;
+        Ok(subscribed.unwrap_or(false))
+    }
+
+    pub fn get_subscribed_threads(&self) -> rusqlite::Result<Vec<Thread>> {
+        let conn = self.pool.get();
+        let mut stmt = conn.prepare(
+            "SELECT t.root_message_id, t.channel_id, t.name, t.created_by, t.created_at
+             FROM threads t
+             INNER JOIN thread_subscriptions s ON s.thread_id = t.root_message_id
+             WHERE s.subscribed = 1
+             ORDER BY t.created_at DESC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(Thread {
+                id: row.get(0)?,
+                channel_id: row.get(1)?,
+                name: row.get(2)?,
+                created_by: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
     }
 
     // ============================================================
@@ -256,7 +648,7 @@ impl Database {
     // ============================================================
 
     pub fn pin_message(&self, pin: &PinnedMessage) -> rusqlite::Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "INSERT OR IGNORE INTO pinned_messages (id, channel_id, message_id, pinned_by, pinned_at)
              VALUES (?1, ?2, ?3, ?4, ?5)",
@@ -272,7 +664,7 @@ impl Database {
     }
 
     pub fn unpin_message(&self, message_id: &str) -> rusqlite::Result<bool> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let rows_affected = conn.execute(
             "DELETE FROM pinned_messages WHERE message_id = ?1",
             rusqlite::params![message_id],
@@ -281,7 +673,7 @@ impl Database {
     }
 
     pub fn get_pinned_messages(&self, channel_id: &str) -> rusqlite::Result<Vec<PinnedMessage>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare(
             "SELECT id, channel_id, message_id, pinned_by, pinned_at
              FROM pinned_messages
@@ -301,12 +693,72 @@ impl Database {
         Ok(rows)
     }
 
+    // ============================================================
+    // Imports
+    // ============================================================
+
+    /// Insert imported history in one transaction. Returns how many messages
+    /// were new.
+    pub fn import_messages(
+        &self,
+        messages: &[Message],
+        reactions: &[Reaction],
+        pins: &[PinnedMessage],
+    ) -> rusqlite::Result<usize> {
+        let mut conn = self.pool.get();
+        let tx = conn.transaction()?;
+        let mut inserted = 0;
+        {
+            let mut insert_message = tx.prepare(
+                "INSERT OR IGNORE INTO messages (id, channel_id, sender_peer_id, sender_display_name, content, timestamp, edited_at, deleted_at, reply_to_id, thread_id, imported_from)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            )?;
+            for msg in messages {
+                inserted += insert_message.execute(rusqlite::params![
+                    msg.id,
+                    msg.channel_id,
+                    msg.sender_peer_id,
+                    msg.sender_display_name,
+                    msg.content,
+                    msg.timestamp,
+                    msg.edited_at,
+                    msg.deleted_at,
+                    msg.reply_to_id,
+                    msg.thread_id,
+                    msg.imported_from,
+                ])?;
+            }
+            let mut insert_reaction = tx.prepare(
+                "INSERT OR IGNORE INTO reactions (id, message_id, peer_id, emoji, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for reaction in reactions {
+                insert_reaction.execute(rusqlite::params![
+                    reaction.id,
+                    reaction.message_id,
+                    reaction.peer_id,
+                    reaction.emoji,
+                    reaction.created_at,
+                ])?;
+            }
+            let mut insert_pin = tx.prepare(
+                "INSERT OR IGNORE INTO pinned_messages (id, channel_id, message_id, pinned_by, pinned_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for pin in pins {
+                insert_pin.execute(rusqlite::params![pin.id, pin.channel_id, pin.message_id, pin.pinned_by, pin.pinned_at])?;
+            }
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
     // ============================================================
     // Phase 2: DM Messages
     // ============================================================
 
     pub fn insert_dm_message(&self, id: &str, conversation_id: &str, sender_peer_id: &str, sender_display_name: &str, content: &str, timestamp: &str) -> rusqlite::Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "INSERT OR IGNORE INTO dm_messages (id, conversation_id, sender_peer_id, sender_display_name, content, timestamp)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
@@ -316,7 +768,7 @@ impl Database {
     }
 
     pub fn get_dm_messages(&self, conversation_id: &str, limit: i64, before: Option<&str>) -> rusqlite::Result<Vec<DmMessage>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut messages = if let Some(before_ts) = before {
             let mut stmt = conn.prepare(
                 "SELECT id, conversation_id, sender_peer_id, sender_display_name, content, timestamp
@@ -359,4 +811,196 @@ impl Database {
         messages.reverse();
         Ok(messages)
     }
+
+    /// Search DMs with the `from:` and date filters of a parsed query. Returns
+    /// the page of hits with their snippets, plus the total match count.
+    pub fn search_dm_messages(
+        &self,
+        search: &MessageSearch,
+        limit: i64,
+        offset: i64,
+    ) -> rusqlite::Result<(Vec<(DmMessage, Option<String>)>, i64)> {
+        use rusqlite::types::Value;
+
+        let conn = self.pool.get();
+
+        let mut clauses = vec!["1 = 1".to_string()];
+        let mut params: Vec<Value> = Vec::new();
+        if let Some(fts) = &search.fts {
+            clauses.push("dm_messages_fts MATCH ?".to_string());
+            params.push(Value::Text(fts.clone()));
+        }
+        for from in &search.from {
+            clauses.push("(m.sender_peer_id = ? OR m.sender_display_name = ? COLLATE NOCASE)".to_string());
+            params.push(Value::Text(from.clone()));
+            params.push(Value::Text(from.clone()));
+        }
+        if let Some(before) = &search.before {
+            clauses.push("julianday(m.timestamp) < julianday(?)".to_string());
+            params.push(Value::Text(before.clone()));
+        }
+        if let Some(after) = &search.after {
+            clauses.push("julianday(m.timestamp) >= julianday(?)".to_string());
+            params.push(Value::Text(after.clone()));
+        }
+
+        let (from_sql, snippet_sql) = if search.fts.is_some() {
+            (
+                "dm_messages_fts JOIN dm_messages m ON m.rowid = dm_messages_fts.rowid",
+                format!("snippet(dm_messages_fts, 3, {MATCH_SQL_ARGS}, '…', 16)"),
+            )
+        } else {
+            ("dm_messages m", "NULL".to_string())
+        };
+        let where_sql = clauses.join(" AND ");
+
+        let total: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM {from_sql} WHERE {where_sql}"),
+            rusqlite::params_from_iter(params.iter()),
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT m.id, m.conversation_id, m.sender_peer_id, m.sender_display_name, m.content, m.timestamp,
+                    {snippet_sql}
+             FROM {from_sql}
+             WHERE {where_sql}
+             ORDER BY m.timestamp DESC
+             LIMIT ? OFFSET ?"
+        ))?;
+        params.push(Value::Integer(limit));
+        params.push(Value::Integer(offset));
+        let hits = stmt
+            .query_map(rusqlite::params_from_iter(params.iter()), |row| {
+                Ok((
+                    DmMessage {
+                        id: row.get(0)?,
+                        conversation_id: row.get(1)?,
+                        sender_peer_id: row.get(2)?,
+                        sender_display_name: row.get(3)?,
+                        content: row.get(4)?,
+                        timestamp: row.get(5)?,
+                    },
+                    row.get::<_, Option<String>>(6)?.map(|s| highlight_snippet(&s)),
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok((hits, total))
+    }
+
+    // ============================================================
+    // Mentions & Unread Counters
+    // ============================================================
+
+    pub fn insert_message_mentions(&self, message_id: &str, mentions: &[MessageMention]) -> rusqlite::Result<()> {
+        let conn = self.pool.get();
+        let mut stmt = conn.prepare(
+            "INSERT OR IGNORE INTO message_mentions (message_id, kind, target) VALUES (?1, ?2, ?3)",
+        )?;
+        for mention in mentions {
+            stmt.execute(rusqlite::params![message_id, mention.kind, mention.target])?;
+        }
+        Ok(())
+    }
+
+    pub fn get_message_mentions(&self, message_id: &str) -> rusqlite::Result<Vec<MessageMention>> {
+        let conn = self.pool.get();
+        let mut stmt = conn.prepare(
+            "SELECT kind, target FROM message_mentions WHERE message_id = ?1 ORDER BY kind, target",
+        )?;
+        let rows = stmt
+            .query_map([message_id], |row| {
+                Ok(MessageMention {
+                    kind: row.get(0)?,
+                    target: row.get(1)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Unread and mention counters per channel for `peer_id`, derived from its read
+    /// receipts. Thread replies count towards mentions but not towards unread.
+    /// Returns `(room_id, counters)` pairs ordered by room and channel position.
+    pub fn get_channel_unreads(
+        &self,
+        peer_id: &str,
+        room_id: Option<&str>,
+    ) -> rusqlite::Result<Vec<(String, ChannelUnread)>> {
+        let conn = self.pool.get();
+        let mut stmt = conn.prepare(
+            "WITH last_read AS (
+                SELECT rr.channel_id, lm.timestamp AS ts
+                FROM read_receipts rr
+                JOIN messages lm ON lm.id = rr.last_read_message_id
+                WHERE rr.peer_id = ?1
+             )
+             SELECT c.room_id, c.id,
+                (SELECT COUNT(*) FROM messages m
+                 WHERE m.channel_id = c.id AND m.thread_id IS NULL AND m.deleted_at IS NULL
+                   AND m.sender_peer_id != ?1 AND m.timestamp > COALESCE(lr.ts, '')),
+                (SELECT COUNT(*) FROM messages m
+                 WHERE m.channel_id = c.id AND m.deleted_at IS NULL
+                   AND m.sender_peer_id != ?1 AND m.timestamp > COALESCE(lr.ts, '')
+                   AND EXISTS (
+                       SELECT 1 FROM message_mentions mm
+                       WHERE mm.message_id = m.id
+                         AND (mm.kind = 'everyone'
+                              OR (mm.kind = 'user' AND mm.target = ?1)
+                              OR (mm.kind = 'role' AND mm.target IN (
+                                  SELECT role FROM room_roles WHERE room_id = c.room_id AND peer_id = ?1)))
+                   )),
+                (SELECT MAX(m.timestamp) FROM messages m
+                 WHERE m.channel_id = c.id AND m.thread_id IS NULL AND m.deleted_at IS NULL)
+             FROM channels c
+             LEFT JOIN last_read lr ON lr.channel_id = c.id
+             WHERE ?2 IS NULL OR c.room_id = ?2
+             ORDER BY c.room_id, c.position, c.name",
+        )?;
+        let rows = stmt
+            .query_map(rusqlite::params![peer_id, room_id], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    ChannelUnread {
+                        channel_id: row.get(1)?,
+                        unread: row.get(2)?,
+                        mentions: row.get(3)?,
+                        last_message_at: row.get(4)?,
+                    },
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Unread counters per DM conversation. Read receipts for DMs are keyed by conversation ID.
+    pub fn get_dm_unreads(&self, peer_id: &str) -> rusqlite::Result<Vec<DmUnread>> {
+        let conn = self.pool.get();
+        let mut stmt = conn.prepare(
+            "WITH last_read AS (
+                SELECT rr.channel_id AS conversation_id, lm.timestamp AS ts
+                FROM read_receipts rr
+                JOIN dm_messages lm ON lm.id = rr.last_read_message_id
+                WHERE rr.peer_id = ?1
+             )
+             SELECT d.id,
+                (SELECT COUNT(*) FROM dm_messages m
+                 WHERE m.conversation_id = d.id AND m.sender_peer_id != ?1
+                   AND m.timestamp > COALESCE(lr.ts, '')),
+                (SELECT MAX(m.timestamp) FROM dm_messages m WHERE m.conversation_id = d.id)
+             FROM dm_conversations d
+             LEFT JOIN last_read lr ON lr.conversation_id = d.id
+             ORDER BY d.created_at",
+        )?;
+        let rows = stmt
+            .query_map([peer_id], |row| {
+                Ok(DmUnread {
+                    conversation_id: row.get(0)?,
+                    unread: row.get(1)?,
+                    last_message_at: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
 }
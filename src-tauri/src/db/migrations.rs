@@ -0,0 +1,799 @@
+//! Versioned schema migrations.
+//!
+//! Each migration runs in its own transaction together with the bump of
+//! `schema_version`, so a failure leaves the database at the previous
+//! version. Migrations are append-only: never edit one that has shipped, add
+//! a new one instead. Columns are added with `ALTER TABLE` in a new migration,
+//! not by editing the baseline.
+
+use rusqlite::Connection;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+#[derive(Debug)]
+pub enum MigrationError {
+    Sqlite(rusqlite::Error),
+    /// The database was written by a newer build of the app.
+    Downgrade { db_version: i64, app_version: i64 },
+    Failed { version: i64, source: rusqlite::Error },
+    Backup(String),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::Sqlite(e) => write!(f, "database error: {}", e),
+            MigrationError::Downgrade { db_version, app_version } => write!(
+                f,
+                "database schema version {} is newer than this build supports ({}); refusing to open it",
+                db_version, app_version
+            ),
+            MigrationError::Failed { version, source } => write!(f, "migration v{} failed: {}", version, source),
+            MigrationError::Backup(e) => write!(f, "database backup failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+impl From<rusqlite::Error> for MigrationError {
+    fn from(e: rusqlite::Error) -> Self {
+        MigrationError::Sqlite(e)
+    }
+}
+
+/// Number of pre-migration backups kept next to the database.
+const BACKUPS_KEPT: usize = 5;
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Baseline schema",
+        sql: "
+            CREATE TABLE IF NOT EXISTS identity (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                keypair_bytes BLOB NOT NULL,
+                display_name TEXT NOT NULL DEFAULT 'Anonymous',
+                avatar_hash TEXT,
+                status_message TEXT,
+                status_type TEXT DEFAULT 'online'
+            );
+
+            CREATE TABLE IF NOT EXISTS rooms (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                invite_code TEXT NOT NULL UNIQUE,
+                created_at TEXT NOT NULL,
+                owner_peer_id TEXT
+            );
+
+            CREATE TABLE IF NOT EXISTS channels (
+                id TEXT PRIMARY KEY,
+                room_id TEXT NOT NULL REFERENCES rooms(id),
+                name TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                channel_type TEXT NOT NULL DEFAULT 'text',
+                topic TEXT,
+                position INTEGER NOT NULL DEFAULT 0
+            );
+
+            CREATE TABLE IF NOT EXISTS messages (
+                id TEXT PRIMARY KEY,
+                channel_id TEXT NOT NULL REFERENCES channels(id),
+                sender_peer_id TEXT NOT NULL,
+                sender_display_name TEXT NOT NULL,
+                content TEXT NOT NULL,
+                timestamp TEXT NOT NULL,
+                edited_at TEXT,
+                deleted_at TEXT,
+                reply_to_id TEXT
+            );
+
+            CREATE TABLE IF NOT EXISTS reactions (
+                id TEXT PRIMARY KEY,
+                message_id TEXT NOT NULL REFERENCES messages(id),
+                peer_id TEXT NOT NULL,
+                emoji TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                UNIQUE(message_id, peer_id, emoji)
+            );
+
+            CREATE TABLE IF NOT EXISTS read_receipts (
+                channel_id TEXT NOT NULL,
+                peer_id TEXT NOT NULL,
+                last_read_message_id TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (channel_id, peer_id)
+            );
+
+            CREATE TABLE IF NOT EXISTS dm_conversations (
+                id TEXT PRIMARY KEY,
+                is_group INTEGER NOT NULL DEFAULT 0,
+                name TEXT,
+                created_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS dm_participants (
+                conversation_id TEXT NOT NULL REFERENCES dm_conversations(id),
+                peer_id TEXT NOT NULL,
+                joined_at TEXT NOT NULL,
+                PRIMARY KEY (conversation_id, peer_id)
+            );
+
+            CREATE TABLE IF NOT EXISTS dm_messages (
+                id TEXT PRIMARY KEY,
+                conversation_id TEXT NOT NULL REFERENCES dm_conversations(id),
+                sender_peer_id TEXT NOT NULL,
+                sender_display_name TEXT NOT NULL,
+                content TEXT NOT NULL,
+                timestamp TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS pinned_messages (
+                id TEXT PRIMARY KEY,
+                channel_id TEXT NOT NULL,
+                message_id TEXT NOT NULL UNIQUE,
+                pinned_by TEXT NOT NULL,
+                pinned_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS room_roles (
+                id TEXT PRIMARY KEY,
+                room_id TEXT NOT NULL REFERENCES rooms(id),
+                peer_id TEXT NOT NULL,
+                role TEXT NOT NULL DEFAULT 'member',
+                assigned_by TEXT NOT NULL,
+                assigned_at TEXT NOT NULL,
+                UNIQUE(room_id, peer_id)
+            );
+
+            CREATE TABLE IF NOT EXISTS moderation_actions (
+                id TEXT PRIMARY KEY,
+                room_id TEXT NOT NULL REFERENCES rooms(id),
+                action_type TEXT NOT NULL,
+                target_peer_id TEXT NOT NULL,
+                moderator_peer_id TEXT NOT NULL,
+                reason TEXT,
+                created_at TEXT NOT NULL,
+                expires_at TEXT
+            );
+
+            CREATE TABLE IF NOT EXISTS blocked_peers (
+                peer_id TEXT PRIMARY KEY,
+                blocked_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS files (
+                id TEXT PRIMARY KEY,
+                filename TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                mime_type TEXT NOT NULL,
+                sha256_hash TEXT NOT NULL,
+                chunk_count INTEGER NOT NULL,
+                uploader_peer_id TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS message_attachments (
+                message_id TEXT NOT NULL,
+                file_id TEXT NOT NULL REFERENCES files(id),
+                PRIMARY KEY (message_id, file_id)
+            );
+
+            CREATE TABLE IF NOT EXISTS friends (
+                peer_id TEXT PRIMARY KEY,
+                display_name TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'pending_outgoing',
+                created_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS custom_emoji (
+                id TEXT PRIMARY KEY,
+                room_id TEXT NOT NULL REFERENCES rooms(id),
+                name TEXT NOT NULL,
+                file_hash TEXT NOT NULL,
+                uploaded_by TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                UNIQUE(room_id, name)
+            );
+
+            CREATE TABLE IF NOT EXISTS notification_settings (
+                target_id TEXT NOT NULL,
+                target_type TEXT NOT NULL,
+                level TEXT NOT NULL DEFAULT 'all',
+                PRIMARY KEY (target_id, target_type)
+            );
+
+            -- Indexes
+            CREATE INDEX IF NOT EXISTS idx_messages_channel ON messages(channel_id, timestamp);
+            CREATE INDEX IF NOT EXISTS idx_channels_room ON channels(room_id);
+            CREATE INDEX IF NOT EXISTS idx_reactions_message ON reactions(message_id);
+            CREATE INDEX IF NOT EXISTS idx_dm_messages_conv ON dm_messages(conversation_id, timestamp);
+            CREATE INDEX IF NOT EXISTS idx_moderation_room ON moderation_actions(room_id, created_at);
+            CREATE INDEX IF NOT EXISTS idx_pinned_channel ON pinned_messages(channel_id);
+            CREATE INDEX IF NOT EXISTS idx_files_hash ON files(sha256_hash);
+
+            -- FTS5 for full-text search
+            CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5(
+                id UNINDEXED,
+                channel_id UNINDEXED,
+                sender_display_name,
+                content,
+                content=messages,
+                content_rowid=rowid
+            );
+
+            -- Triggers to keep FTS in sync
+            CREATE TRIGGER IF NOT EXISTS messages_ai AFTER INSERT ON messages BEGIN
+                INSERT INTO messages_fts(rowid, id, channel_id, sender_display_name, content)
+                VALUES (new.rowid, new.id, new.channel_id, new.sender_display_name, new.content);
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS messages_ad AFTER DELETE ON messages BEGIN
+                INSERT INTO messages_fts(messages_fts, rowid, id, channel_id, sender_display_name, content)
+                VALUES ('delete', old.rowid, old.id, old.channel_id, old.sender_display_name, old.content);
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS messages_au AFTER UPDATE ON messages BEGIN
+                INSERT INTO messages_fts(messages_fts, rowid, id, channel_id, sender_display_name, content)
+                VALUES ('delete', old.rowid, old.id, old.channel_id, old.sender_display_name, old.content);
+                INSERT INTO messages_fts(rowid, id, channel_id, sender_display_name, content)
+                VALUES (new.rowid, new.id, new.channel_id, new.sender_display_name, new.content);
+            END;
+        ",
+    },
+    Migration {
+        version: 2,
+        description: "Channel categories",
+        sql: "
+            CREATE TABLE IF NOT EXISTS channel_categories (
+                id TEXT PRIMARY KEY,
+                room_id TEXT NOT NULL REFERENCES rooms(id),
+                name TEXT NOT NULL,
+                position INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL,
+                permissions TEXT,
+                collapsed INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE INDEX IF NOT EXISTS idx_categories_room ON channel_categories(room_id, position);
+            ALTER TABLE channels ADD COLUMN category_id TEXT;
+            ALTER TABLE channels ADD COLUMN inherit_permissions INTEGER NOT NULL DEFAULT 1;
+            ALTER TABLE channels ADD COLUMN permissions TEXT;
+        ",
+    },
+    Migration {
+        version: 3,
+        description: "Threads",
+        sql: "
+            ALTER TABLE messages ADD COLUMN thread_id TEXT;
+            CREATE INDEX IF NOT EXISTS idx_messages_thread ON messages(thread_id, timestamp);
+            CREATE TABLE IF NOT EXISTS threads (
+                root_message_id TEXT PRIMARY KEY,
+                channel_id TEXT NOT NULL,
+                name TEXT,
+                created_by TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_threads_channel ON threads(channel_id, created_at);
+            CREATE TABLE IF NOT EXISTS thread_subscriptions (
+                thread_id TEXT PRIMARY KEY,
+                subscribed INTEGER NOT NULL DEFAULT 1,
+                updated_at TEXT NOT NULL
+            );
+        ",
+    },
+    Migration {
+        version: 4,
+        description: "Parsed mentions, used for mention counters and notifications",
+        sql: "
+            CREATE TABLE IF NOT EXISTS message_mentions (
+                message_id TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                target TEXT NOT NULL DEFAULT '',
+                PRIMARY KEY (message_id, kind, target)
+            );
+            CREATE INDEX IF NOT EXISTS idx_mentions_target ON message_mentions(kind, target);
+        ",
+    },
+    Migration {
+        version: 5,
+        description: "Full-text search over DMs and file names",
+        sql: "
+            CREATE VIRTUAL TABLE IF NOT EXISTS dm_messages_fts USING fts5(
+                id UNINDEXED,
+                conversation_id UNINDEXED,
+                sender_display_name,
+                content,
+                content=dm_messages,
+                content_rowid=rowid
+            );
+            CREATE TRIGGER IF NOT EXISTS dm_messages_ai AFTER INSERT ON dm_messages BEGIN
+                INSERT INTO dm_messages_fts(rowid, id, conversation_id, sender_display_name, content)
+                VALUES (new.rowid, new.id, new.conversation_id, new.sender_display_name, new.content);
+            END;
+            CREATE TRIGGER IF NOT EXISTS dm_messages_ad AFTER DELETE ON dm_messages BEGIN
+                INSERT INTO dm_messages_fts(dm_messages_fts, rowid, id, conversation_id, sender_display_name, content)
+                VALUES ('delete', old.rowid, old.id, old.conversation_id, old.sender_display_name, old.content);
+            END;
+            CREATE TRIGGER IF NOT EXISTS dm_messages_au AFTER UPDATE ON dm_messages BEGIN
+                INSERT INTO dm_messages_fts(dm_messages_fts, rowid, id, conversation_id, sender_display_name, content)
+                VALUES ('delete', old.rowid, old.id, old.conversation_id, old.sender_display_name, old.content);
+                INSERT INTO dm_messages_fts(rowid, id, conversation_id, sender_display_name, content)
+                VALUES (new.rowid, new.id, new.conversation_id, new.sender_display_name, new.content);
+            END;
+            INSERT INTO dm_messages_fts(dm_messages_fts) VALUES ('rebuild');
+
+            CREATE VIRTUAL TABLE IF NOT EXISTS files_fts USING fts5(
+                id UNINDEXED,
+                filename,
+                content=files,
+                content_rowid=rowid
+            );
+            CREATE TRIGGER IF NOT EXISTS files_ai AFTER INSERT ON files BEGIN
+                INSERT INTO files_fts(rowid, id, filename) VALUES (new.rowid, new.id, new.filename);
+            END;
+            CREATE TRIGGER IF NOT EXISTS files_ad AFTER DELETE ON files BEGIN
+                INSERT INTO files_fts(files_fts, rowid, id, filename) VALUES ('delete', old.rowid, old.id, old.filename);
+            END;
+            CREATE TRIGGER IF NOT EXISTS files_au AFTER UPDATE ON files BEGIN
+                INSERT INTO files_fts(files_fts, rowid, id, filename) VALUES ('delete', old.rowid, old.id, old.filename);
+                INSERT INTO files_fts(rowid, id, filename) VALUES (new.rowid, new.id, new.filename);
+            END;
+            INSERT INTO files_fts(files_fts) VALUES ('rebuild');
+        ",
+    },
+    Migration {
+        version: 6,
+        description: "Signed identity key rotations",
+        sql: "
+            CREATE TABLE IF NOT EXISTS peer_key_rotations (
+                old_peer_id TEXT PRIMARY KEY,
+                new_peer_id TEXT NOT NULL,
+                rotated_at TEXT NOT NULL,
+                statement TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_key_rotations_new ON peer_key_rotations(new_peer_id);
+        ",
+    },
+    Migration {
+        version: 7,
+        description: "Device certificates linking device keys to an identity",
+        sql: "
+            CREATE TABLE IF NOT EXISTS device_certificates (
+                device_peer_id TEXT PRIMARY KEY,
+                identity_peer_id TEXT NOT NULL,
+                device_name TEXT NOT NULL,
+                issued_at TEXT NOT NULL,
+                revoked_at TEXT,
+                certificate TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_device_certificates_identity ON device_certificates(identity_peer_id);
+        ",
+    },
+    Migration {
+        version: 8,
+        description: "Mark messages imported from other chat services",
+        sql: "
+            ALTER TABLE messages ADD COLUMN imported_from TEXT;
+        ",
+    },
+    Migration {
+        version: 9,
+        description: "Retention policies and disappearing DM timers",
+        sql: "
+            CREATE TABLE IF NOT EXISTS retention_policies (
+                scope TEXT NOT NULL,
+                target_id TEXT NOT NULL,
+                max_age_secs INTEGER,
+                max_messages INTEGER,
+                updated_by TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (scope, target_id)
+            );
+        ",
+    },
+    Migration {
+        version: 10,
+        description: "Deleted messages become tombstones outside the search index",
+        sql: "
+            -- Erase what soft deletes left behind (the old trigger re-indexes the empty row)
+            DELETE FROM reactions WHERE message_id IN (SELECT id FROM messages WHERE deleted_at IS NOT NULL);
+            DELETE FROM pinned_messages WHERE message_id IN (SELECT id FROM messages WHERE deleted_at IS NOT NULL);
+            DELETE FROM message_mentions WHERE message_id IN (SELECT id FROM messages WHERE deleted_at IS NOT NULL);
+            DELETE FROM message_attachments WHERE message_id IN (SELECT id FROM messages WHERE deleted_at IS NOT NULL);
+            UPDATE messages SET content = '', edited_at = NULL WHERE deleted_at IS NOT NULL;
+
+            -- Only live messages are indexed
+            DROP TRIGGER IF EXISTS messages_ai;
+            DROP TRIGGER IF EXISTS messages_ad;
+            DROP TRIGGER IF EXISTS messages_au;
+            CREATE TRIGGER messages_ai AFTER INSERT ON messages WHEN new.deleted_at IS NULL BEGIN
+                INSERT INTO messages_fts(rowid, id, channel_id, sender_display_name, content)
+                VALUES (new.rowid, new.id, new.channel_id, new.sender_display_name, new.content);
+            END;
+            CREATE TRIGGER messages_ad AFTER DELETE ON messages WHEN old.deleted_at IS NULL BEGIN
+                INSERT INTO messages_fts(messages_fts, rowid, id, channel_id, sender_display_name, content)
+                VALUES ('delete', old.rowid, old.id, old.channel_id, old.sender_display_name, old.content);
+            END;
+            CREATE TRIGGER messages_au AFTER UPDATE ON messages BEGIN
+                INSERT INTO messages_fts(messages_fts, rowid, id, channel_id, sender_display_name, content)
+                SELECT 'delete', old.rowid, old.id, old.channel_id, old.sender_display_name, old.content
+                WHERE old.deleted_at IS NULL;
+                INSERT INTO messages_fts(rowid, id, channel_id, sender_display_name, content)
+                SELECT new.rowid, new.id, new.channel_id, new.sender_display_name, new.content
+                WHERE new.deleted_at IS NULL;
+            END;
+            INSERT INTO messages_fts(messages_fts, rowid, id, channel_id, sender_display_name, content)
+            SELECT 'delete', rowid, id, channel_id, sender_display_name, content
+            FROM messages WHERE deleted_at IS NOT NULL;
+
+            CREATE TABLE IF NOT EXISTS message_purges (
+                room_id TEXT NOT NULL,
+                sender_peer_id TEXT NOT NULL,
+                deleted_at TEXT NOT NULL,
+                PRIMARY KEY (room_id, sender_peer_id)
+            );
+        ",
+    },
+    Migration {
+        version: 11,
+        description: "Local API tokens",
+        sql: "
+            CREATE TABLE IF NOT EXISTS api_tokens (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                token_hash TEXT NOT NULL UNIQUE,
+                scopes TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                last_used_at TEXT
+            );
+        ",
+    },
+    Migration {
+        version: 12,
+        description: "Event journal for resumable event streams",
+        sql: "
+            CREATE TABLE IF NOT EXISTS event_journal (
+                seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                event_type TEXT NOT NULL,
+                room_id TEXT,
+                channel_id TEXT,
+                conversation_id TEXT,
+                payload TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+        ",
+    },
+    Migration {
+        version: 13,
+        description: "Outbound webhooks and their delivery log",
+        sql: "
+            CREATE TABLE IF NOT EXISTS webhooks (
+                id TEXT PRIMARY KEY,
+                url TEXT NOT NULL,
+                secret TEXT NOT NULL,
+                filter TEXT NOT NULL,
+                enabled INTEGER NOT NULL DEFAULT 1,
+                created_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS webhook_deliveries (
+                id TEXT PRIMARY KEY,
+                webhook_id TEXT NOT NULL REFERENCES webhooks(id) ON DELETE CASCADE,
+                event_seq INTEGER,
+                event_type TEXT NOT NULL,
+                attempt INTEGER NOT NULL,
+                status_code INTEGER,
+                error TEXT,
+                success INTEGER NOT NULL,
+                attempted_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_webhook ON webhook_deliveries(webhook_id, attempted_at);
+        ",
+    },
+    Migration {
+        version: 14,
+        description: "Incoming webhooks and webhook-posted messages",
+        sql: "
+            CREATE TABLE IF NOT EXISTS incoming_webhooks (
+                id TEXT PRIMARY KEY,
+                channel_id TEXT NOT NULL,
+                name TEXT NOT NULL,
+                token_hash TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_incoming_webhooks_channel ON incoming_webhooks(channel_id);
+            ALTER TABLE messages ADD COLUMN webhook_id TEXT;
+            ALTER TABLE messages ADD COLUMN embeds TEXT;
+        ",
+    },
+    Migration {
+        version: 15,
+        description: "Hosted bot identities and known bot hosts",
+        sql: "
+            CREATE TABLE IF NOT EXISTS bots (
+                peer_id TEXT PRIMARY KEY,
+                display_name TEXT NOT NULL,
+                keypair_bytes BLOB NOT NULL,
+                created_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS bot_rooms (
+                bot_peer_id TEXT NOT NULL,
+                room_id TEXT NOT NULL,
+                PRIMARY KEY (bot_peer_id, room_id)
+            );
+            CREATE TABLE IF NOT EXISTS bot_hosts (
+                bot_peer_id TEXT PRIMARY KEY,
+                host_peer_id TEXT NOT NULL,
+                certificate TEXT NOT NULL,
+                issued_at TEXT NOT NULL
+            );
+            ALTER TABLE api_tokens ADD COLUMN bot_peer_id TEXT;
+        ",
+    },
+    Migration {
+        version: 16,
+        description: "Bot slash commands and reminders",
+        sql: "
+            CREATE TABLE IF NOT EXISTS bot_commands (
+                room_id TEXT NOT NULL,
+                name TEXT NOT NULL,
+                bot_peer_id TEXT NOT NULL,
+                description TEXT NOT NULL,
+                options TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                PRIMARY KEY (room_id, name)
+            );
+            CREATE INDEX IF NOT EXISTS idx_bot_commands_bot ON bot_commands(bot_peer_id);
+            CREATE TABLE IF NOT EXISTS reminders (
+                id TEXT PRIMARY KEY,
+                channel_id TEXT NOT NULL,
+                content TEXT NOT NULL,
+                remind_at TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_reminders_due ON reminders(remind_at);
+        ",
+    },
+    Migration {
+        version: 17,
+        description: "Identity keys replaced by an import or rotation",
+        sql: "
+            CREATE TABLE IF NOT EXISTS retired_keypairs (
+                peer_id TEXT PRIMARY KEY,
+                keypair_bytes BLOB NOT NULL,
+                retired_at TEXT NOT NULL
+            );
+        ",
+    },
+];
+
+/// Highest schema version this build knows about.
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Current schema version of a database, 0 for a fresh one.
+pub fn current_version(conn: &Connection) -> Result<i64, MigrationError> {
+    conn.execute_batch("CREATE TABLE IF NOT EXISTS schema_version (version INTEGER PRIMARY KEY);")?;
+    let version = conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_version", [], |row| row.get(0))?;
+    Ok(version)
+}
+
+/// Migrations that would run on this database.
+pub fn pending(conn: &Connection) -> Result<Vec<&'static Migration>, MigrationError> {
+    let version = current_version(conn)?;
+    Ok(MIGRATIONS.iter().filter(|m| m.version > version).collect())
+}
+
+/// Bring a database up to the latest schema.
+pub fn migrate(conn: &mut Connection) -> Result<i64, MigrationError> {
+    migrate_to(conn, latest_version())
+}
+
+/// Apply migrations up to and including `target`. Stopping early is how
+/// fixture databases of older schema versions are produced.
+pub fn migrate_to(conn: &mut Connection, target: i64) -> Result<i64, MigrationError> {
+    apply(conn, MIGRATIONS, target)
+}
+
+fn apply(conn: &mut Connection, migrations: &[Migration], target: i64) -> Result<i64, MigrationError> {
+    let mut version = current_version(conn)?;
+    let known = migrations.last().map(|m| m.version).unwrap_or(0);
+    if version > known {
+        return Err(MigrationError::Downgrade { db_version: version, app_version: known });
+    }
+
+    let pending: Vec<&Migration> = migrations.iter().filter(|m| m.version > version && m.version <= target).collect();
+    for migration in pending {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration.sql)
+            .map_err(|source| MigrationError::Failed { version: migration.version, source })?;
+        tx.execute(
+            "INSERT OR REPLACE INTO schema_version (version) VALUES (?1)",
+            [migration.version],
+        )?;
+        tx.commit()?;
+        tracing::info!("Applied migration v{}: {}", migration.version, migration.description);
+        version = migration.version;
+    }
+    Ok(version)
+}
+
+/// Snapshot the database before migrating it, using `VACUUM INTO` so the copy
+/// is consistent. Backups go to `<data_dir>/backups/` and only the newest
+/// [`BACKUPS_KEPT`] are kept. Returns the backup path.
+pub fn backup(conn: &Connection, data_dir: &Path, version: i64) -> Result<PathBuf, MigrationError> {
+    let dir = data_dir.join("backups");
+    std::fs::create_dir_all(&dir).map_err(|e| MigrationError::Backup(e.to_string()))?;
+    let path = dir.join(format!(
+        "chatr-v{}-{}.db",
+        version,
+        chrono::Utc::now().format("%Y%m%dT%H%M%S")
+    ));
+    conn.execute("VACUUM INTO ?1", [path.to_string_lossy()])?;
+
+    let mut backups: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .map_err(|e| MigrationError::Backup(e.to_string()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("chatr-v") && n.ends_with(".db"))
+        })
+        .collect();
+    // Names embed the timestamp, but sort on mtime so the version prefix doesn't
+    // matter; backups taken within one mtime tick fall back to name order
+    backups.sort_by_key(|p| (std::fs::metadata(p).and_then(|m| m.modified()).ok(), p.clone()));
+    while backups.len() > BACKUPS_KEPT {
+        let _ = std::fs::remove_file(backups.remove(0));
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns(conn: &Connection, table: &str) -> Vec<String> {
+        let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1)").unwrap();
+        stmt.query_map([table], |row| row.get(0)).unwrap().collect::<rusqlite::Result<_>>().unwrap()
+    }
+
+    fn has_table(conn: &Connection, name: &str) -> bool {
+        conn.query_row("SELECT COUNT(*) FROM sqlite_master WHERE name = ?1", [name], |row| row.get::<_, i64>(0))
+            .unwrap()
+            > 0
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("chatr-migrations-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn migrates_v1_fixture_to_latest() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert_eq!(migrate_to(&mut conn, 1).unwrap(), 1);
+        assert!(!columns(&conn, "channels").contains(&"category_id".to_string()));
+        conn.execute_batch(
+            "INSERT INTO rooms (id, name, invite_code, created_at) VALUES ('r1', 'Room', 'invite', '2024-01-01T00:00:00Z');
+             INSERT INTO channels (id, room_id, name, created_at) VALUES ('c1', 'r1', 'general', '2024-01-01T00:00:00Z');
+             INSERT INTO messages (id, channel_id, sender_peer_id, sender_display_name, content, timestamp)
+             VALUES ('m1', 'c1', 'peer', 'Alice', 'hello from v1', '2024-01-01T00:00:00Z');",
+        )
+        .unwrap();
+
+        assert_eq!(migrate(&mut conn).unwrap(), latest_version());
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+        assert!(pending(&conn).unwrap().is_empty());
+        let channel_columns = columns(&conn, "channels");
+        for column in ["category_id", "inherit_permissions", "permissions"] {
+            assert!(channel_columns.contains(&column.to_string()), "channels.{} missing", column);
+        }
+        let message_columns = columns(&conn, "messages");
+        for column in ["thread_id", "imported_from", "webhook_id", "embeds"] {
+            assert!(message_columns.contains(&column.to_string()), "messages.{} missing", column);
+        }
+        for table in ["channel_categories", "threads", "event_journal", "bot_commands", "reminders", "retired_keypairs"] {
+            assert!(has_table(&conn, table), "table {} missing", table);
+        }
+        let (content, category, inherit): (String, Option<String>, i64) = conn
+            .query_row(
+                "SELECT m.content, c.category_id, c.inherit_permissions FROM messages m JOIN channels c ON c.id = m.channel_id",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(content, "hello from v1");
+        assert_eq!(category, None);
+        assert_eq!(inherit, 1);
+    }
+
+    #[test]
+    fn migrates_pre_framework_database() {
+        // Before versioned migrations the baseline schema was created on every
+        // start and `schema_version` stayed empty
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE schema_version (version INTEGER PRIMARY KEY);").unwrap();
+        conn.execute_batch(MIGRATIONS[0].sql).unwrap();
+        assert_eq!(current_version(&conn).unwrap(), 0);
+
+        assert_eq!(migrate(&mut conn).unwrap(), latest_version());
+        assert!(columns(&conn, "messages").contains(&"thread_id".to_string()));
+        // Running again is a no-op
+        assert_eq!(migrate(&mut conn).unwrap(), latest_version());
+    }
+
+    #[test]
+    fn refuses_database_from_newer_build() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [latest_version() + 1]).unwrap();
+
+        match migrate(&mut conn) {
+            Err(MigrationError::Downgrade { db_version, app_version }) => {
+                assert_eq!(db_version, latest_version() + 1);
+                assert_eq!(app_version, latest_version());
+            }
+            other => panic!("expected a downgrade error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn failed_migration_rolls_back() {
+        let migrations = [
+            Migration { version: 1, description: "ok", sql: "CREATE TABLE a (x INTEGER);" },
+            Migration {
+                version: 2,
+                description: "fails halfway",
+                sql: "CREATE TABLE b (x INTEGER); INSERT INTO a (x) VALUES (1); INSERT INTO missing (x) VALUES (1);",
+            },
+        ];
+        let mut conn = Connection::open_in_memory().unwrap();
+
+        match apply(&mut conn, &migrations, 2) {
+            Err(MigrationError::Failed { version, .. }) => assert_eq!(version, 2),
+            other => panic!("expected migration v2 to fail, got {:?}", other),
+        }
+        assert_eq!(current_version(&conn).unwrap(), 1);
+        assert!(!has_table(&conn, "b"));
+        let rows: i64 = conn.query_row("SELECT COUNT(*) FROM a", [], |row| row.get(0)).unwrap();
+        assert_eq!(rows, 0);
+    }
+
+    #[test]
+    fn backups_are_rotated() {
+        let dir = temp_dir();
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+
+        let mut paths = Vec::new();
+        for version in 1..=BACKUPS_KEPT as i64 + 2 {
+            let path = backup(&conn, &dir, version).unwrap();
+            assert!(path.exists());
+            paths.push(path);
+        }
+
+        let kept = std::fs::read_dir(dir.join("backups")).unwrap().count();
+        assert_eq!(kept, BACKUPS_KEPT);
+        assert!(!paths[0].exists() && !paths[1].exists());
+        assert!(paths[2..].iter().all(|p| p.exists()));
+        let backup_conn = Connection::open(paths.last().unwrap()).unwrap();
+        assert_eq!(current_version(&backup_conn).unwrap(), latest_version());
+
+        drop(backup_conn);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
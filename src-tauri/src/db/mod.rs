@@ -1,254 +1,146 @@
+pub mod api_tokens;
+pub mod bots;
+pub mod commands;
+pub mod encryption;
+pub mod events;
 pub mod messages;
+pub mod migrations;
+pub mod pool;
+pub mod retention;
 pub mod rooms;
+pub mod webhooks;
 
-use rusqlite::{Connection, Result};
+use rusqlite::{Connection, OptionalExtension, Result};
+use std::fmt;
 use std::path::Path;
-use std::sync::Mutex;
+use std::sync::Arc;
+
+use migrations::MigrationError;
+use pool::ConnectionPool;
 
 pub struct Database {
-    pub conn: Mutex<Connection>,
+    pub pool: ConnectionPool,
+}
+
+#[derive(Debug)]
+pub enum OpenError {
+    /// The database is encrypted and no passphrase was given.
+    Locked,
+    WrongPassphrase,
+    Migration(MigrationError),
+}
+
+impl fmt::Display for OpenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpenError::Locked => write!(f, "database is encrypted; a passphrase is required"),
+            OpenError::WrongPassphrase => write!(f, "wrong passphrase"),
+            OpenError::Migration(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for OpenError {}
+
+impl From<MigrationError> for OpenError {
+    fn from(e: MigrationError) -> Self {
+        OpenError::Migration(e)
+    }
+}
+
+impl From<rusqlite::Error> for OpenError {
+    fn from(e: rusqlite::Error) -> Self {
+        OpenError::Migration(MigrationError::Sqlite(e))
+    }
 }
 
 impl Database {
-    pub fn new(data_dir: &Path) -> Result<Self> {
+    /// Open an unencrypted `chatr.db` in `data_dir`.
+    pub fn new(data_dir: &Path) -> std::result::Result<Self, OpenError> {
+        Self::open(data_dir, None)
+    }
+
+    /// Open `chatr.db` in `data_dir`, backing it up first if migrations are pending.
+    ///
+    /// A new database is created encrypted if `passphrase` is given. An
+    /// existing plaintext database stays plaintext until
+    /// [`Database::change_passphrase`] is used.
+    pub fn open(data_dir: &Path, passphrase: Option<&str>) -> std::result::Result<Self, OpenError> {
         std::fs::create_dir_all(data_dir).ok();
         let db_path = data_dir.join("chatr.db");
-        let conn = Connection::open(db_path)?;
-        let db = Database {
-            conn: Mutex::new(conn),
+
+        let key = if encryption::is_encrypted(&db_path) {
+            Some(passphrase.ok_or(OpenError::Locked)?)
+        } else if db_path.exists() {
+            if passphrase.is_some() {
+                tracing::warn!("Database is not encrypted; ignoring passphrase (set one with a passphrase change)");
+            }
+            None
+        } else {
+            passphrase
         };
-        db.init_schema()?;
-        db.run_migrations()?;
-        Ok(db)
-    }
-
-    fn init_schema(&self) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute_batch(
-            "
-            CREATE TABLE IF NOT EXISTS schema_version (
-                version INTEGER PRIMARY KEY
-            );
-
-            CREATE TABLE IF NOT EXISTS identity (
-                id INTEGER PRIMARY KEY CHECK (id = 1),
-                keypair_bytes BLOB NOT NULL,
-                display_name TEXT NOT NULL DEFAULT 'Anonymous',
-                avatar_hash TEXT,
-                status_message TEXT,
-                status_type TEXT DEFAULT 'online'
-            );
-
-            CREATE TABLE IF NOT EXISTS rooms (
-                id TEXT PRIMARY KEY,
-                name TEXT NOT NULL,
-                invite_code TEXT NOT NULL UNIQUE,
-                created_at TEXT NOT NULL,
-                owner_peer_id TEXT
-            );
-
-            CREATE TABLE IF NOT EXISTS channels (
-                id TEXT PRIMARY KEY,
-                room_id TEXT NOT NULL REFERENCES rooms(id),
-                name TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                channel_type TEXT NOT NULL DEFAULT 'text',
-                topic TEXT,
-                position INTEGER NOT NULL DEFAULT 0
-            );
-
-            CREATE TABLE IF NOT EXISTS messages (
-                id TEXT PRIMARY KEY,
-                channel_id TEXT NOT NULL REFERENCES channels(id),
-                sender_peer_id TEXT NOT NULL,
-                sender_display_name TEXT NOT NULL,
-                content TEXT NOT NULL,
-                timestamp TEXT NOT NULL,
-                edited_at TEXT,
-                deleted_at TEXT,
-                reply_to_id TEXT
-            );
-
-            CREATE TABLE IF NOT EXISTS reactions (
-                id TEXT PRIMARY KEY,
-                message_id TEXT NOT NULL REFERENCES messages(id),
-                peer_id TEXT NOT NULL,
-                emoji TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                UNIQUE(message_id, peer_id, emoji)
-            );
-
-            CREATE TABLE IF NOT EXISTS read_receipts (
-                channel_id TEXT NOT NULL,
-                peer_id TEXT NOT NULL,
-                last_read_message_id TEXT NOT NULL,
-                updated_at TEXT NOT NULL,
-                PRIMARY KEY (channel_id, peer_id)
-            );
-
-            CREATE TABLE IF NOT EXISTS dm_conversations (
-                id TEXT PRIMARY KEY,
-                is_group INTEGER NOT NULL DEFAULT 0,
-                name TEXT,
-                created_at TEXT NOT NULL
-            );
-
-            CREATE TABLE IF NOT EXISTS dm_participants (
-                conversation_id TEXT NOT NULL REFERENCES dm_conversations(id),
-                peer_id TEXT NOT NULL,
-                joined_at TEXT NOT NULL,
-                PRIMARY KEY (conversation_id, peer_id)
-            );
-
-            CREATE TABLE IF NOT EXISTS dm_messages (
-                id TEXT PRIMARY KEY,
-                conversation_id TEXT NOT NULL REFERENCES dm_conversations(id),
-                sender_peer_id TEXT NOT NULL,
-                sender_display_name TEXT NOT NULL,
-                content TEXT NOT NULL,
-                timestamp TEXT NOT NULL
-            );
-
-            CREATE TABLE IF NOT EXISTS pinned_messages (
-                id TEXT PRIMARY KEY,
-                channel_id TEXT NOT NULL,
-                message_id TEXT NOT NULL UNIQUE,
-                pinned_by TEXT NOT NULL,
-                pinned_at TEXT NOT NULL
-            );
-
-            CREATE TABLE IF NOT EXISTS room_roles (
-                id TEXT PRIMARY KEY,
-                room_id TEXT NOT NULL REFERENCES rooms(id),
-                peer_id TEXT NOT NULL,
-                role TEXT NOT NULL DEFAULT 'member',
-                assigned_by TEXT NOT NULL,
-                assigned_at TEXT NOT NULL,
-                UNIQUE(room_id, peer_id)
-            );
-
-            CREATE TABLE IF NOT EXISTS moderation_actions (
-                id TEXT PRIMARY KEY,
-                room_id TEXT NOT NULL REFERENCES rooms(id),
-                action_type TEXT NOT NULL,
-                target_peer_id TEXT NOT NULL,
-                moderator_peer_id TEXT NOT NULL,
-                reason TEXT,
-                created_at TEXT NOT NULL,
-                expires_at TEXT
-            );
-
-            CREATE TABLE IF NOT EXISTS blocked_peers (
-                peer_id TEXT PRIMARY KEY,
-                blocked_at TEXT NOT NULL
-            );
-
-            CREATE TABLE IF NOT EXISTS files (
-                id TEXT PRIMARY KEY,
-                filename TEXT NOT NULL,
-                size INTEGER NOT NULL,
-                mime_type TEXT NOT NULL,
-                sha256_hash TEXT NOT NULL,
-                chunk_count INTEGER NOT NULL,
-                uploader_peer_id TEXT NOT NULL,
-                created_at TEXT NOT NULL
-            );
-
-            CREATE TABLE IF NOT EXISTS message_attachments (
-                message_id TEXT NOT NULL,
-                file_id TEXT NOT NULL REFERENCES files(id),
-                PRIMARY KEY (message_id, file_id)
-            );
-
-            CREATE TABLE IF NOT EXISTS friends (
-                peer_id TEXT PRIMARY KEY,
-                display_name TEXT NOT NULL,
-                status TEXT NOT NULL DEFAULT 'pending_outgoing',
-                created_at TEXT NOT NULL
-            );
-
-            CREATE TABLE IF NOT EXISTS settings (
-                key TEXT PRIMARY KEY,
-                value TEXT NOT NULL
-            );
-
-            CREATE TABLE IF NOT EXISTS custom_emoji (
-                id TEXT PRIMARY KEY,
-                room_id TEXT NOT NULL REFERENCES rooms(id),
-                name TEXT NOT NULL,
-                file_hash TEXT NOT NULL,
-                uploaded_by TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                UNIQUE(room_id, name)
-            );
-
-            CREATE TABLE IF NOT EXISTS notification_settings (
-                target_id TEXT NOT NULL,
-                target_type TEXT NOT NULL,
-                level TEXT NOT NULL DEFAULT 'all',
-                PRIMARY KEY (target_id, target_type)
-            );
-
-            -- Indexes
-            CREATE INDEX IF NOT EXISTS idx_messages_channel ON messages(channel_id, timestamp);
-            CREATE INDEX IF NOT EXISTS idx_channels_room ON channels(room_id);
-            CREATE INDEX IF NOT EXISTS idx_reactions_message ON reactions(message_id);
-            CREATE INDEX IF NOT EXISTS idx_dm_messages_conv ON dm_messages(conversation_id, timestamp);
-            CREATE INDEX IF NOT EXISTS idx_moderation_room ON moderation_actions(room_id, created_at);
-            CREATE INDEX IF NOT EXISTS idx_pinned_channel ON pinned_messages(channel_id);
-            CREATE INDEX IF NOT EXISTS idx_files_hash ON files(sha256_hash);
-
-            -- FTS5 for full-text search
-            CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5(
-                id UNINDEXED,
-                channel_id UNINDEXED,
-                sender_display_name,
-                content,
-                content=messages,
-                content_rowid=rowid
-            );
-
-            -- Triggers to keep FTS in sync
-            CREATE TRIGGER IF NOT EXISTS messages_ai AFTER INSERT ON messages BEGIN
-                INSERT INTO messages_fts(rowid, id, channel_id, sender_display_name, content)
-                VALUES (new.rowid, new.id, new.channel_id, new.sender_display_name, new.content);
-            END;
-
-            CREATE TRIGGER IF NOT EXISTS messages_ad AFTER DELETE ON messages BEGIN
-                INSERT INTO messages_fts(messages_fts, rowid, id, channel_id, sender_display_name, content)
-                VALUES ('delete', old.rowid, old.id, old.channel_id, old.sender_display_name, old.content);
-            END;
-
-            CREATE TRIGGER IF NOT EXISTS messages_au AFTER UPDATE ON messages BEGIN
-                INSERT INTO messages_fts(messages_fts, rowid, id, channel_id, sender_display_name, content)
-                VALUES ('delete', old.rowid, old.id, old.channel_id, old.sender_display_name, old.content);
-                INSERT INTO messages_fts(rowid, id, channel_id, sender_display_name, content)
-                VALUES (new.rowid, new.id, new.channel_id, new.sender_display_name, new.content);
-            END;
-            ",
-        )?;
-        Ok(())
+        let mut conn = pool::connect(&db_path, key).map_err(|e| {
+            if encryption::is_not_a_database(&e) {
+                OpenError::WrongPassphrase
+            } else {
+                e.into()
+            }
+        })?;
+
+        let version = migrations::current_version(&conn)?;
+        if version > 0 && !migrations::pending(&conn)?.is_empty() {
+            let path = migrations::backup(&conn, data_dir, version)?;
+            tracing::info!("Backed up database to {}", path.display());
+        }
+        migrations::migrate(&mut conn)?;
+
+        Ok(Database {
+            pool: ConnectionPool::open(&db_path, conn, pool::DEFAULT_POOL_SIZE, key)?,
+        })
     }
 
-    fn run_migrations(&self) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
-        let version: i64 = conn
-            .query_row(
-                "SELECT COALESCE(MAX(version), 0) FROM schema_version",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0);
+    /// Wrap an already-open connection (e.g. a fixture database) and migrate it.
+    pub fn from_connection(mut conn: Connection) -> std::result::Result<Self, MigrationError> {
+        migrations::migrate(&mut conn)?;
+        Ok(Database {
+            pool: ConnectionPool::single(conn)?,
+        })
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.pool.is_encrypted()
+    }
 
-        if version < 1 {
-            conn.execute("INSERT OR REPLACE INTO schema_version (version) VALUES (1)", [])?;
+    /// Set, change or (with `new: None`) remove the database passphrase.
+    /// `current` must match the passphrase the database was unlocked with.
+    pub fn change_passphrase(&self, current: Option<&str>, new: Option<&str>) -> std::result::Result<(), String> {
+        if !self.pool.key_matches(current) {
+            return Err("Current passphrase is incorrect".to_string());
+        }
+        if new.is_some_and(str::is_empty) {
+            return Err("Passphrase must not be empty".to_string());
         }
+        self.pool.rekey(new).map_err(|e| e.to_string())
+    }
 
-        Ok(())
+    /// Run database work from async code. The closure runs on the blocking
+    /// thread pool, so neither SQLite nor waiting for a pooled connection
+    /// holds up a runtime worker, whatever the runtime flavor.
+    pub async fn run<T, F>(self: &Arc<Self>, f: F) -> T
+    where
+        F: FnOnce(&Database) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let db = Arc::clone(self);
+        match tokio::task::spawn_blocking(move || f(&db)).await {
+            Ok(value) => value,
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            Err(e) => panic!("database task did not complete: {}", e),
+        }
     }
 
     pub fn save_keypair(&self, keypair_bytes: &[u8]) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "INSERT OR REPLACE INTO identity (id, keypair_bytes, display_name)
              VALUES (1, ?1, COALESCE((SELECT display_name FROM identity WHERE id = 1), 'Anonymous'))",
@@ -257,8 +149,18 @@ impl Database {
         Ok(())
     }
 
+    /// Keep an identity key that is being replaced, so it isn't lost.
+    pub fn retire_keypair(&self, peer_id: &str, keypair_bytes: &[u8], retired_at: &str) -> Result<()> {
+        let conn = self.pool.get();
+        conn.execute(
+            "INSERT OR REPLACE INTO retired_keypairs (peer_id, keypair_bytes, retired_at) VALUES (?1, ?2, ?3)",
+            rusqlite::params![peer_id, keypair_bytes, retired_at],
+        )?;
+        Ok(())
+    }
+
     pub fn load_keypair(&self) -> Result<Option<Vec<u8>>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare("SELECT keypair_bytes FROM identity WHERE id = 1")?;
         let result = stmt.query_row([], |row| row.get(0));
         match result {
@@ -269,7 +171,7 @@ impl Database {
     }
 
     pub fn get_display_name(&self) -> Result<String> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare("SELECT display_name FROM identity WHERE id = 1")?;
         let result = stmt.query_row([], |row| row.get::<_, String>(0));
         match result {
@@ -279,7 +181,7 @@ impl Database {
     }
 
     pub fn set_display_name(&self, name: &str) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "UPDATE identity SET display_name = ?1 WHERE id = 1",
             [name],
@@ -288,7 +190,7 @@ impl Database {
     }
 
     pub fn get_identity_profile(&self) -> Result<(String, Option<String>, Option<String>, Option<String>)> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare(
             "SELECT display_name, avatar_hash, status_message, status_type FROM identity WHERE id = 1"
         )?;
@@ -307,7 +209,7 @@ impl Database {
     }
 
     pub fn set_status(&self, status_message: Option<&str>, status_type: Option<&str>) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "UPDATE identity SET status_message = ?1, status_type = ?2 WHERE id = 1",
             rusqlite::params![status_message, status_type],
@@ -316,7 +218,7 @@ impl Database {
     }
 
     pub fn set_avatar_hash(&self, hash: Option<&str>) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "UPDATE identity SET avatar_hash = ?1 WHERE id = 1",
             [hash],
@@ -324,12 +226,138 @@ impl Database {
         Ok(())
     }
 
+    /// Record a verified key rotation and move room ownership, roles,
+    /// friendship, blocks and DM membership from the old peer id to the new
+    /// one. Returns false if the rotation was already known.
+    pub fn apply_key_rotation(&self, rotation: &crate::models::KeyRotation) -> Result<bool> {
+        let statement = serde_json::to_string(rotation).unwrap_or_default();
+        let mut conn = self.pool.get();
+        let tx = conn.transaction()?;
+        let inserted = tx.execute(
+            "INSERT OR IGNORE INTO peer_key_rotations (old_peer_id, new_peer_id, rotated_at, statement)
+             VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![rotation.old_peer_id, rotation.new_peer_id, rotation.rotated_at, statement],
+        )?;
+        if inserted == 0 {
+            return Ok(false);
+        }
+        for sql in [
+            "UPDATE rooms SET owner_peer_id = ?1 WHERE owner_peer_id = ?2",
+            "UPDATE OR REPLACE room_roles SET peer_id = ?1 WHERE peer_id = ?2",
+            "UPDATE OR REPLACE friends SET peer_id = ?1 WHERE peer_id = ?2",
+            "UPDATE OR REPLACE blocked_peers SET peer_id = ?1 WHERE peer_id = ?2",
+            "UPDATE OR IGNORE dm_participants SET peer_id = ?1 WHERE peer_id = ?2",
+        ] {
+            tx.execute(sql, [&rotation.new_peer_id, &rotation.old_peer_id])?;
+        }
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// Rotations that ended at `peer_id`, oldest first.
+    pub fn get_key_rotations_to(&self, peer_id: &str) -> Result<Vec<crate::models::KeyRotation>> {
+        let conn = self.pool.get();
+        let mut stmt = conn.prepare(
+            "SELECT statement FROM peer_key_rotations WHERE new_peer_id = ?1 ORDER BY rotated_at"
+        )?;
+        let rows = stmt
+            .query_map([peer_id], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(rows.iter().filter_map(|s| serde_json::from_str(s).ok()).collect())
+    }
+
+    /// Store a verified device certificate. Returns false if the device was
+    /// already known (including revoked devices, which can't be re-linked
+    /// with an old certificate).
+    pub fn insert_device_certificate(&self, cert: &crate::models::DeviceCertificate) -> Result<bool> {
+        let certificate = serde_json::to_string(cert).unwrap_or_default();
+        let conn = self.pool.get();
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO device_certificates (device_peer_id, identity_peer_id, device_name, issued_at, certificate)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![cert.device_peer_id, cert.identity_peer_id, cert.device_name, cert.issued_at, certificate],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    pub fn revoke_device(&self, identity_peer_id: &str, device_peer_id: &str, revoked_at: &str) -> Result<bool> {
+        let conn = self.pool.get();
+        let updated = conn.execute(
+            "UPDATE device_certificates SET revoked_at = ?3
+             WHERE device_peer_id = ?2 AND identity_peer_id = ?1 AND revoked_at IS NULL",
+            [identity_peer_id, device_peer_id, revoked_at],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// The identity a peer acts for: its certifying identity if it is a
+    /// linked device, otherwise the peer itself; then whatever key that
+    /// identity has rotated to, so retired keys and their devices carry over.
+    pub fn resolve_identity(&self, peer_id: &str) -> Result<String> {
+        const MAX_ROTATIONS: usize = 16;
+
+        let conn = self.pool.get();
+        let mut identity = conn
+            .query_row(
+                "SELECT identity_peer_id FROM device_certificates WHERE device_peer_id = ?1 AND revoked_at IS NULL",
+                [peer_id],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?
+            .unwrap_or_else(|| peer_id.to_string());
+        let mut stmt = conn.prepare_cached("SELECT new_peer_id FROM peer_key_rotations WHERE old_peer_id = ?1")?;
+        let mut seen = vec![identity.clone()];
+        while seen.len() <= MAX_ROTATIONS {
+            match stmt.query_row([&identity], |row| row.get::<_, String>(0)).optional()? {
+                Some(next) if !seen.contains(&next) => {
+                    seen.push(next.clone());
+                    identity = next;
+                }
+                _ => break,
+            }
+        }
+        Ok(identity)
+    }
+
+    /// Certificates of the devices currently linked to `identity_peer_id`.
+    pub fn get_device_certificates(&self, identity_peer_id: &str) -> Result<Vec<crate::models::DeviceCertificate>> {
+        let conn = self.pool.get();
+        let mut stmt = conn.prepare(
+            "SELECT certificate FROM device_certificates
+             WHERE identity_peer_id = ?1 AND revoked_at IS NULL ORDER BY issued_at"
+        )?;
+        let rows = stmt
+            .query_map([identity_peer_id], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(rows.iter().filter_map(|s| serde_json::from_str(s).ok()).collect())
+    }
+
+    /// All devices ever linked to `identity_peer_id`, including revoked ones.
+    pub fn list_devices(&self, identity_peer_id: &str) -> Result<Vec<crate::models::LinkedDevice>> {
+        let conn = self.pool.get();
+        let mut stmt = conn.prepare(
+            "SELECT device_peer_id, device_name, issued_at, revoked_at FROM device_certificates
+             WHERE identity_peer_id = ?1 ORDER BY issued_at"
+        )?;
+        let rows = stmt.query_map([identity_peer_id], |row| {
+            Ok(crate::models::LinkedDevice {
+                device_peer_id: row.get(0)?,
+                device_name: row.get(1)?,
+                issued_at: row.get(2)?,
+                revoked_at: row.get(3)?,
+                is_current: false,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
     // ============================================================
     // Settings (Phase 6)
     // ============================================================
 
     pub fn get_setting(&self, key: &str) -> Result<Option<String>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare("SELECT value FROM settings WHERE key = ?1")?;
         match stmt.query_row([key], |row| row.get::<_, String>(0)) {
             Ok(v) => Ok(Some(v)),
@@ -339,7 +367,7 @@ impl Database {
     }
 
     pub fn set_setting(&self, key: &str, value: &str) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
             [key, value],
@@ -348,7 +376,7 @@ impl Database {
     }
 
     pub fn get_all_settings(&self) -> Result<Vec<(String, String)>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare("SELECT key, value FROM settings ORDER BY key")?;
         let rows = stmt.query_map([], |row| {
             Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
@@ -358,7 +386,7 @@ impl Database {
     }
 
     pub fn delete_setting(&self, key: &str) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute("DELETE FROM settings WHERE key = ?1", [key])?;
         Ok(())
     }
@@ -368,7 +396,7 @@ impl Database {
     // ============================================================
 
     pub fn get_notification_setting(&self, target_id: &str, target_type: &str) -> Result<Option<String>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare(
             "SELECT level FROM notification_settings WHERE target_id = ?1 AND target_type = ?2"
         )?;
@@ -380,7 +408,7 @@ impl Database {
     }
 
     pub fn set_notification_setting(&self, target_id: &str, target_type: &str, level: &str) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "INSERT OR REPLACE INTO notification_settings (target_id, target_type, level) VALUES (?1, ?2, ?3)",
             rusqlite::params![target_id, target_type, level],
@@ -389,7 +417,7 @@ impl Database {
     }
 
     pub fn get_all_notification_settings(&self) -> Result<Vec<crate::models::NotificationSetting>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare("SELECT target_id, target_type, level FROM notification_settings")?;
         let rows = stmt.query_map([], |row| {
             Ok(crate::models::NotificationSetting {
@@ -402,3 +430,95 @@ impl Database {
         Ok(rows)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn run_works_on_a_current_thread_runtime() {
+        let dir = std::env::temp_dir().join(format!("chatr-db-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = Arc::new(Database::new(&dir).unwrap());
+        db.run(|db| db.set_setting("theme", "dark")).await.unwrap();
+        assert_eq!(db.run(|db| db.get_setting("theme")).await.unwrap().as_deref(), Some("dark"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn search_snippets_escape_the_matched_text() {
+        use messages::{highlight_snippet, MATCH_SQL_ARGS};
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE VIRTUAL TABLE t USING fts5(content)").unwrap();
+        conn.execute("INSERT INTO t (content) VALUES (?1)", ["<img src=x onerror=alert(1)> deploy & \"ship\""]).unwrap();
+        let raw: String = conn
+            .query_row(&format!("SELECT highlight(t, 0, {MATCH_SQL_ARGS}) FROM t WHERE t MATCH 'deploy'"), [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(
+            highlight_snippet(&raw),
+            "&lt;img src=x onerror=alert(1)&gt; <mark>deploy</mark> &amp; &quot;ship&quot;"
+        );
+    }
+
+    #[test]
+    fn search_bounds_compare_instants_not_strings() {
+        use crate::models::{DmConversation, MessageSearch};
+
+        let dir = std::env::temp_dir().join(format!("chatr-db-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = Database::new(&dir).unwrap();
+        db.create_dm_conversation(&DmConversation {
+            id: "conv".into(),
+            is_group: false,
+            name: None,
+            created_at: "2024-01-01T00:00:00Z".into(),
+        })
+        .unwrap();
+        // 23:30 on 1 January in UTC-5 is 04:30 on 2 January in UTC
+        db.insert_dm_message("late", "conv", "peer", "Peer", "hello", "2024-01-01T23:30:00-05:00").unwrap();
+
+        let found = |before: Option<&str>, after: Option<&str>| {
+            let search = MessageSearch {
+                before: before.map(String::from),
+                after: after.map(String::from),
+                ..Default::default()
+            };
+            db.search_dm_messages(&search, 10, 0).unwrap().1
+        };
+        assert_eq!(found(None, Some("2024-01-02")), 1);
+        assert_eq!(found(Some("2024-01-02"), None), 0);
+        assert_eq!(found(Some("2024-01-02T05:00:00+00:00"), Some("2024-01-02T04:00:00+00:00")), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn resolve_identity_follows_key_rotations() {
+        use libp2p::identity::Keypair;
+
+        let dir = std::env::temp_dir().join(format!("chatr-db-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = Database::new(&dir).unwrap();
+        let first = Keypair::generate_ed25519();
+        let device = Keypair::generate_ed25519();
+        let mut cert = crate::keys::sign_device_certificate(&first, &crate::keys::device_link_request(&device, "Laptop")).unwrap();
+        crate::keys::countersign_device_certificate(&device, &mut cert).unwrap();
+        db.insert_device_certificate(&cert).unwrap();
+
+        db.save_keypair(&crate::keys::encode_keypair(&first)).unwrap();
+        let second = Keypair::generate_ed25519();
+        let rotation = crate::services::identity::replace_identity(&db, &second).unwrap().unwrap();
+        let third = Keypair::generate_ed25519();
+        crate::services::identity::replace_identity(&db, &third).unwrap();
+
+        let latest = third.public().to_peer_id().to_string();
+        assert_eq!(db.resolve_identity(&rotation.old_peer_id).unwrap(), latest);
+        assert_eq!(db.resolve_identity(&rotation.new_peer_id).unwrap(), latest);
+        assert_eq!(db.resolve_identity(&device.public().to_peer_id().to_string()).unwrap(), latest);
+        // Replaced keys are kept
+        let retired: i64 = db.pool.get().query_row("SELECT COUNT(*) FROM retired_keypairs", [], |row| row.get(0)).unwrap();
+        assert_eq!(retired, 2);
+        assert!(crate::services::identity::replace_identity(&db, &third).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,179 @@
+//! A small fixed-size SQLite connection pool.
+//!
+//! Every connection runs in WAL mode, so readers never wait for a writer and
+//! a writer never waits for readers; only writers serialize against each
+//! other, inside SQLite, bounded by `busy_timeout`.
+
+use rusqlite::Connection;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+use super::encryption;
+
+/// Connections opened for a file database.
+pub const DEFAULT_POOL_SIZE: usize = 4;
+
+/// How long a writer waits for another writer before failing with SQLITE_BUSY.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct ConnectionPool {
+    idle: Mutex<Vec<Connection>>,
+    available: Condvar,
+    size: usize,
+    /// `None` for in-memory databases.
+    path: Option<PathBuf>,
+    key: Mutex<Option<String>>,
+}
+
+/// Open a connection to `path`, unlocking it with `key` if the database is encrypted.
+pub fn connect(path: &Path, key: Option<&str>) -> rusqlite::Result<Connection> {
+    let conn = Connection::open(path)?;
+    if let Some(key) = key {
+        encryption::apply_key(&conn, key)?;
+    }
+    Ok(conn)
+}
+
+impl ConnectionPool {
+    /// Open `size` connections to `path`, the first of which may be a
+    /// connection the caller already opened (and migrated) with the same `key`.
+    pub fn open(path: &Path, first: Connection, size: usize, key: Option<&str>) -> rusqlite::Result<Self> {
+        let size = size.max(1);
+        configure(&first)?;
+        let mut conns = vec![first];
+        conns.extend(connect_all(path, key, size - 1)?);
+        Ok(ConnectionPool {
+            path: Some(path.to_path_buf()),
+            key: Mutex::new(key.map(str::to_string)),
+            ..Self::from_connections(conns)
+        })
+    }
+
+    /// A pool around a single connection, e.g. an in-memory fixture database
+    /// (in-memory databases cannot be shared between connections).
+    pub fn single(conn: Connection) -> rusqlite::Result<Self> {
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        Ok(Self::from_connections(vec![conn]))
+    }
+
+    fn from_connections(conns: Vec<Connection>) -> Self {
+        let size = conns.len();
+        ConnectionPool {
+            idle: Mutex::new(conns),
+            available: Condvar::new(),
+            size,
+            path: None,
+            key: Mutex::new(None),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.key.lock().unwrap().is_some()
+    }
+
+    pub fn key_matches(&self, key: Option<&str>) -> bool {
+        self.key.lock().unwrap().as_deref() == key
+    }
+
+    /// Re-encrypt the database under `key` (`None` stores it as plaintext).
+    ///
+    /// Waits until every connection is back in the pool and keeps hold of
+    /// them, so nothing else touches the database while it is exported to a
+    /// new file, swapped in and reopened.
+    pub fn rekey(&self, key: Option<&str>) -> rusqlite::Result<()> {
+        let path = self
+            .path
+            .as_deref()
+            .ok_or_else(|| rusqlite::Error::InvalidPath(PathBuf::from(":memory:")))?;
+        let mut idle = self.idle.lock().unwrap();
+        while idle.len() < self.size {
+            idle = self.available.wait(idle).unwrap();
+        }
+
+        let tmp = path.with_extension("db.rekey");
+        let _ = std::fs::remove_file(&tmp);
+        if let Err(e) = encryption::export(&idle[0], &tmp, key) {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(e);
+        }
+
+        let mut current_key = self.key.lock().unwrap();
+        idle.clear();
+        let swapped = std::fs::rename(&tmp, path);
+        if swapped.is_ok() {
+            // The export already includes everything in the old WAL; a
+            // leftover -wal/-shm must not be replayed against the new file.
+            for suffix in ["db-wal", "db-shm"] {
+                let _ = std::fs::remove_file(path.with_extension(suffix));
+            }
+            *current_key = key.map(str::to_string);
+        }
+        *idle = connect_all(path, current_key.as_deref(), self.size)?;
+        self.available.notify_all();
+        swapped.map_err(|_| rusqlite::Error::InvalidPath(path.to_path_buf()))
+    }
+
+    /// Check out a connection, waiting for one to be returned if all are busy.
+    /// The connection goes back to the pool when the guard is dropped.
+    pub fn get(&self) -> PooledConnection<'_> {
+        let mut idle = self.idle.lock().unwrap();
+        loop {
+            if let Some(conn) = idle.pop() {
+                return PooledConnection { pool: self, conn: Some(conn) };
+            }
+            idle = self.available.wait(idle).unwrap();
+        }
+    }
+}
+
+fn connect_all(path: &Path, key: Option<&str>, count: usize) -> rusqlite::Result<Vec<Connection>> {
+    (0..count)
+        .map(|_| {
+            let conn = connect(path, key)?;
+            configure(&conn)?;
+            Ok(conn)
+        })
+        .collect()
+}
+
+fn configure(conn: &Connection) -> rusqlite::Result<()> {
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    // journal_mode returns the resulting mode as a row, so it can't go through execute_batch
+    conn.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+    conn.execute_batch("PRAGMA synchronous = NORMAL;")?;
+    Ok(())
+}
+
+pub struct PooledConnection<'a> {
+    pool: &'a ConnectionPool,
+    conn: Option<Connection>,
+}
+
+impl Deref for PooledConnection<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("connection already returned")
+    }
+}
+
+impl DerefMut for PooledConnection<'_> {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.conn.as_mut().expect("connection already returned")
+    }
+}
+
+impl Drop for PooledConnection<'_> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool.idle.lock().unwrap().push(conn);
+            self.pool.available.notify_one();
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{OptionalExtension, Result};
+
+use super::messages::hard_delete_messages;
+use super::Database;
+use crate::models::{RetentionPolicy, RetentionScope};
+
+/// What one pruning pass removed.
+#[derive(Debug, Default)]
+pub struct PruneReport {
+    /// `(channel_id, message ids)` per channel that lost messages.
+    pub channels: Vec<(String, Vec<String>)>,
+    /// `(conversation_id, message ids)` per DM that lost messages.
+    pub conversations: Vec<(String, Vec<String>)>,
+}
+
+fn policy_from_row(row: &rusqlite::Row) -> Result<RetentionPolicy> {
+    let scope: String = row.get(0)?;
+    Ok(RetentionPolicy {
+        scope: scope.parse().map_err(|e: String| {
+            rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, e.into())
+        })?,
+        target_id: row.get(1)?,
+        max_age_secs: row.get(2)?,
+        max_messages: row.get(3)?,
+        updated_by: row.get(4)?,
+        updated_at: row.get(5)?,
+    })
+}
+
+fn cutoff(now: DateTime<Utc>, max_age_secs: Option<i64>) -> Option<String> {
+    max_age_secs.map(|secs| (now - Duration::seconds(secs)).to_rfc3339())
+}
+
+impl Database {
+    // ============================================================
+    // Retention
+    // ============================================================
+
+    /// Store `policy` unless a newer one for the same target is already known.
+    /// Returns whether it was applied.
+    pub fn upsert_retention_policy(&self, policy: &RetentionPolicy) -> Result<bool> {
+        let conn = self.pool.get();
+        let changed = conn.execute(
+            "INSERT INTO retention_policies (scope, target_id, max_age_secs, max_messages, updated_by, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(scope, target_id) DO UPDATE SET
+                max_age_secs = excluded.max_age_secs,
+                max_messages = excluded.max_messages,
+                updated_by = excluded.updated_by,
+                updated_at = excluded.updated_at
+             WHERE julianday(excluded.updated_at) > julianday(retention_policies.updated_at)",
+            rusqlite::params![
+                policy.scope.as_str(),
+                policy.target_id,
+                policy.max_age_secs,
+                policy.max_messages,
+                policy.updated_by,
+                policy.updated_at,
+            ],
+        )?;
+        Ok(changed > 0)
+    }
+
+    pub fn get_retention_policy(&self, scope: RetentionScope, target_id: &str) -> Result<Option<RetentionPolicy>> {
+        let conn = self.pool.get();
+        conn.query_row(
+            "SELECT scope, target_id, max_age_secs, max_messages, updated_by, updated_at
+             FROM retention_policies WHERE scope = ?1 AND target_id = ?2",
+            rusqlite::params![scope.as_str(), target_id],
+            policy_from_row,
+        )
+        .optional()
+    }
+
+    /// The room's policy and those of its channels.
+    pub fn get_room_retention_policies(&self, room_id: &str) -> Result<Vec<RetentionPolicy>> {
+        let conn = self.pool.get();
+        let mut stmt = conn.prepare(
+            "SELECT scope, target_id, max_age_secs, max_messages, updated_by, updated_at
+             FROM retention_policies
+             WHERE (scope = 'room' AND target_id = ?1)
+                OR (scope = 'channel' AND target_id IN (SELECT id FROM channels WHERE room_id = ?1))",
+        )?;
+        let rows = stmt.query_map([room_id], policy_from_row)?.collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Hard-delete every message that has outlived its channel's (or room's)
+    /// policy, and DM messages past their conversation's timer.
+    pub fn prune_expired(&self, now: DateTime<Utc>) -> Result<PruneReport> {
+        let mut conn = self.pool.get();
+        let tx = conn.transaction()?;
+        let mut report = PruneReport::default();
+
+        let channel_policies: Vec<(String, Option<i64>, Option<i64>)> = {
+            let mut stmt = tx.prepare(
+                "SELECT c.id,
+                        CASE WHEN cp.target_id IS NOT NULL THEN cp.max_age_secs ELSE rp.max_age_secs END,
+                        CASE WHEN cp.target_id IS NOT NULL THEN cp.max_messages ELSE rp.max_messages END
+                 FROM channels c
+                 LEFT JOIN retention_policies cp ON cp.scope = 'channel' AND cp.target_id = c.id
+                 LEFT JOIN retention_policies rp ON rp.scope = 'room' AND rp.target_id = c.room_id",
+            )?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                .collect::<Result<Vec<_>>>()?;
+            rows
+        };
+        for (channel_id, max_age_secs, max_messages) in channel_policies {
+            if max_age_secs.is_none() && max_messages.is_none() {
+                continue;
+            }
+            let ids: Vec<String> = {
+                let mut stmt = tx.prepare_cached(
+                    "SELECT id FROM messages
+                     WHERE channel_id = ?1
+                       AND ((?2 IS NOT NULL AND timestamp < ?2)
+                         OR id NOT IN (SELECT id FROM messages WHERE channel_id = ?1
+                                       ORDER BY timestamp DESC LIMIT COALESCE(?3, -1)))",
+                )?;
+                let rows = stmt
+                    .query_map(rusqlite::params![channel_id, cutoff(now, max_age_secs), max_messages], |row| {
+                        row.get(0)
+                    })?
+                    .collect::<Result<Vec<_>>>()?;
+                rows
+            };
+            if ids.is_empty() {
+                continue;
+            }
+            hard_delete_messages(&tx, &ids)?;
+            report.channels.push((channel_id, ids));
+        }
+
+        let dm_timers: Vec<(String, Option<i64>, Option<i64>)> = {
+            let mut stmt = tx.prepare(
+                "SELECT target_id, max_age_secs, max_messages FROM retention_policies
+                 WHERE scope = 'dm' AND (max_age_secs IS NOT NULL OR max_messages IS NOT NULL)",
+            )?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                .collect::<Result<Vec<_>>>()?;
+            rows
+        };
+        for (conversation_id, max_age_secs, max_messages) in dm_timers {
+            let ids: Vec<String> = {
+                let mut stmt = tx.prepare_cached(
+                    "SELECT id FROM dm_messages
+                     WHERE conversation_id = ?1
+                       AND ((?2 IS NOT NULL AND timestamp < ?2)
+                         OR id NOT IN (SELECT id FROM dm_messages WHERE conversation_id = ?1
+                                       ORDER BY timestamp DESC LIMIT COALESCE(?3, -1)))",
+                )?;
+                let rows = stmt
+                    .query_map(rusqlite::params![conversation_id, cutoff(now, max_age_secs), max_messages], |row| {
+                        row.get(0)
+                    })?
+                    .collect::<Result<Vec<_>>>()?;
+                rows
+            };
+            if ids.is_empty() {
+                continue;
+            }
+            for id in &ids {
+                // FTS rows go with it via the dm_messages_ad trigger
+                tx.prepare_cached("DELETE FROM dm_messages WHERE id = ?1")?.execute([id])?;
+            }
+            report.conversations.push((conversation_id, ids));
+        }
+
+        tx.commit()?;
+        Ok(report)
+    }
+}
@@ -1,6 +1,7 @@
 use rusqlite::OptionalExtension;
 use crate::models::*;
 use super::Database;
+use super::messages::{highlight_snippet, MATCH_SQL_ARGS};
 
 impl Database {
     // ============================================================
@@ -8,7 +9,7 @@ impl Database {
     // ============================================================
 
     pub fn create_room(&self, room: &Room) -> rusqlite::Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "INSERT INTO rooms (id, name, invite_code, created_at, owner_peer_id)
              VALUES (?1, ?2, ?3, ?4, ?5)",
@@ -24,7 +25,7 @@ impl Database {
     }
 
     pub fn list_rooms(&self) -> rusqlite::Result<Vec<Room>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare(
             "SELECT id, name, invite_code, created_at, owner_peer_id
              FROM rooms ORDER BY created_at",
@@ -44,7 +45,7 @@ impl Database {
     }
 
     pub fn get_room_by_invite(&self, invite_code: &str) -> rusqlite::Result<Option<Room>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare(
             "SELECT id, name, invite_code, created_at, owner_peer_id
              FROM rooms WHERE invite_code = ?1",
@@ -70,10 +71,10 @@ impl Database {
     // ============================================================
 
     pub fn create_channel(&self, channel: &Channel) -> rusqlite::Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
-            "INSERT INTO channels (id, room_id, name, created_at, channel_type, topic, position)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            "INSERT INTO channels (id, room_id, name, created_at, channel_type, topic, position, category_id, inherit_permissions, permissions)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
             rusqlite::params![
                 channel.id,
                 channel.room_id,
@@ -82,13 +83,26 @@ impl Database {
                 channel.channel_type,
                 channel.topic,
                 channel.position,
+                channel.category_id,
+                channel.inherit_permissions,
+                channel.permissions,
             ],
         )?;
         Ok(())
     }
 
+    /// Every channel with the room it belongs to.
+    pub fn get_channel_rooms(&self) -> rusqlite::Result<Vec<(String, String)>> {
+        let conn = self.pool.get();
+        let mut stmt = conn.prepare("SELECT id, room_id FROM channels")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
     pub fn get_room_id_for_channel(&self, channel_id: &str) -> rusqlite::Result<Option<String>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare("SELECT room_id FROM channels WHERE id = ?1")?;
         let result = stmt.query_row(rusqlite::params![channel_id], |row| row.get::<_, String>(0));
         match result {
@@ -99,9 +113,9 @@ impl Database {
     }
 
     pub fn get_channels(&self, room_id: &str) -> rusqlite::Result<Vec<Channel>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare(
-            "SELECT id, room_id, name, created_at, channel_type, topic, position
+            "SELECT id, room_id, name, created_at, channel_type, topic, position, category_id, inherit_permissions, permissions
              FROM channels WHERE room_id = ?1 ORDER BY position, created_at",
         )?;
         let channels = stmt
@@ -114,12 +128,38 @@ impl Database {
                     channel_type: row.get(4)?,
                     topic: row.get(5)?,
                     position: row.get(6)?,
+                    category_id: row.get(7)?,
+                    inherit_permissions: row.get(8)?,
+                    permissions: row.get(9)?,
                 })
             })?
             .collect::<rusqlite::Result<Vec<_>>>()?;
         Ok(channels)
     }
 
+    pub fn get_channel(&self, channel_id: &str) -> rusqlite::Result<Option<Channel>> {
+        let conn = self.pool.get();
+        conn.query_row(
+            "SELECT id, room_id, name, created_at, channel_type, topic, position, category_id, inherit_permissions, permissions
+             FROM channels WHERE id = ?1",
+            rusqlite::params![channel_id],
+            |row| {
+                Ok(Channel {
+                    id: row.get(0)?,
+                    room_id: row.get(1)?,
+                    name: row.get(2)?,
+                    created_at: row.get(3)?,
+                    channel_type: row.get(4)?,
+                    topic: row.get(5)?,
+                    position: row.get(6)?,
+                    category_id: row.get(7)?,
+                    inherit_permissions: row.get(8)?,
+                    permissions: row.get(9)?,
+                })
+            },
+        ).optional()
+    }
+
     // ============================================================
     // Phase 2: Channel Management
     // ============================================================
@@ -130,31 +170,18 @@ impl Database {
         name: Option<&str>,
         topic: Option<&str>,
         position: Option<i32>,
-    ) -> rusqlite::Result<()> {
-        let conn = self.conn.lock().unwrap();
-        if let Some(name) = name {
-            conn.execute(
-                "UPDATE channels SET name = ?1 WHERE id = ?2",
-                rusqlite::params![name, channel_id],
-            )?;
-        }
-        if let Some(topic) = topic {
-            conn.execute(
-                "UPDATE channels SET topic = ?1 WHERE id = ?2",
-                rusqlite::params![topic, channel_id],
-            )?;
-        }
-        if let Some(position) = position {
-            conn.execute(
-                "UPDATE channels SET position = ?1 WHERE id = ?2",
-                rusqlite::params![position, channel_id],
-            )?;
-        }
-        Ok(())
+    ) -> rusqlite::Result<bool> {
+        let conn = self.pool.get();
+        let changed = conn.execute(
+            "UPDATE channels SET name = COALESCE(?1, name), topic = COALESCE(?2, topic), position = COALESCE(?3, position)
+             WHERE id = ?4",
+            rusqlite::params![name, topic, position, channel_id],
+        )?;
+        Ok(changed > 0)
     }
 
     pub fn get_channel_room_id(&self, channel_id: &str) -> rusqlite::Result<Option<String>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.query_row(
             "SELECT room_id FROM channels WHERE id = ?1",
             rusqlite::params![channel_id],
@@ -163,7 +190,7 @@ impl Database {
     }
 
     pub fn delete_channel(&self, channel_id: &str) -> rusqlite::Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         // Delete messages in the channel first (cascade manually for safety)
         conn.execute(
             "DELETE FROM messages WHERE channel_id = ?1",
@@ -173,6 +200,14 @@ impl Database {
             "DELETE FROM pinned_messages WHERE channel_id = ?1",
             rusqlite::params![channel_id],
         )?;
+        conn.execute(
+            "DELETE FROM incoming_webhooks WHERE channel_id = ?1",
+            rusqlite::params![channel_id],
+        )?;
+        conn.execute(
+            "DELETE FROM reminders WHERE channel_id = ?1",
+            rusqlite::params![channel_id],
+        )?;
         conn.execute(
             "DELETE FROM channels WHERE id = ?1",
             rusqlite::params![channel_id],
@@ -180,12 +215,122 @@ impl Database {
         Ok(())
     }
 
+    // ============================================================
+    // Channel Categories
+    // ============================================================
+
+    /// Insert a category, or update its synced fields if it already exists.
+    /// The local `collapsed` flag is preserved on update.
+    pub fn upsert_category(&self, category: &ChannelCategory) -> rusqlite::Result<()> {
+        let conn = self.pool.get();
+        conn.execute(
+            "INSERT INTO channel_categories (id, room_id, name, position, created_at, permissions, collapsed)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                position = excluded.position,
+                permissions = excluded.permissions",
+            rusqlite::params![
+                category.id,
+                category.room_id,
+                category.name,
+                category.position,
+                category.created_at,
+                category.permissions,
+                category.collapsed,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_categories(&self, room_id: &str) -> rusqlite::Result<Vec<ChannelCategory>> {
+        let conn = self.pool.get();
+        let mut stmt = conn.prepare(
+            "SELECT id, room_id, name, position, created_at, permissions, collapsed
+             FROM channel_categories WHERE room_id = ?1 ORDER BY position, created_at",
+        )?;
+        let categories = stmt
+            .query_map(rusqlite::params![room_id], |row| {
+                Ok(ChannelCategory {
+                    id: row.get(0)?,
+                    room_id: row.get(1)?,
+                    name: row.get(2)?,
+                    position: row.get(3)?,
+                    created_at: row.get(4)?,
+                    permissions: row.get(5)?,
+                    collapsed: row.get(6)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(categories)
+    }
+
+    pub fn get_category(&self, category_id: &str) -> rusqlite::Result<Option<ChannelCategory>> {
+        let conn = self.pool.get();
+        conn.query_row(
+            "SELECT id, room_id, name, position, created_at, permissions, collapsed
+             FROM channel_categories WHERE id = ?1",
+            rusqlite::params![category_id],
+            |row| {
+                Ok(ChannelCategory {
+                    id: row.get(0)?,
+                    room_id: row.get(1)?,
+                    name: row.get(2)?,
+                    position: row.get(3)?,
+                    created_at: row.get(4)?,
+                    permissions: row.get(5)?,
+                    collapsed: row.get(6)?,
+                })
+            },
+        ).optional()
+    }
+
+    pub fn set_category_collapsed(&self, category_id: &str, collapsed: bool) -> rusqlite::Result<()> {
+        let conn = self.pool.get();
+        conn.execute(
+            "UPDATE channel_categories SET collapsed = ?1 WHERE id = ?2",
+            rusqlite::params![collapsed, category_id],
+        )?;
+        Ok(())
+    }
+
+    /// Delete a category. Its channels are kept and moved out of the category.
+    pub fn delete_category(&self, category_id: &str) -> rusqlite::Result<()> {
+        let conn = self.pool.get();
+        conn.execute(
+            "UPDATE channels SET category_id = NULL WHERE category_id = ?1",
+            rusqlite::params![category_id],
+        )?;
+        conn.execute(
+            "DELETE FROM channel_categories WHERE id = ?1",
+            rusqlite::params![category_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_channel_layout(
+        &self,
+        channel_id: &str,
+        category_id: Option<&str>,
+        position: i32,
+        inherit_permissions: bool,
+        permissions: Option<&str>,
+    ) -> rusqlite::Result<()> {
+        let conn = self.pool.get();
+        conn.execute(
+            "UPDATE channels SET category_id = ?1, position = ?2, inherit_permissions = ?3, permissions = ?4
+             WHERE id = ?5",
+            rusqlite::params![category_id, position, inherit_permissions, permissions, channel_id],
+        )?;
+        Ok(())
+    }
+
     // ============================================================
     // Phase 2: DM Conversations
     // ============================================================
 
     pub fn create_dm_conversation(&self, conv: &DmConversation) -> rusqlite::Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "INSERT INTO dm_conversations (id, is_group, name, created_at)
              VALUES (?1, ?2, ?3, ?4)",
@@ -195,7 +340,7 @@ impl Database {
     }
 
     pub fn list_dm_conversations(&self) -> rusqlite::Result<Vec<DmConversation>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare(
             "SELECT id, is_group, name, created_at
              FROM dm_conversations ORDER BY created_at DESC",
@@ -217,7 +362,7 @@ impl Database {
         &self,
         conversation_id: &str,
     ) -> rusqlite::Result<Vec<DmParticipant>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare(
             "SELECT conversation_id, peer_id, joined_at
              FROM dm_participants WHERE conversation_id = ?1 ORDER BY joined_at",
@@ -235,7 +380,7 @@ impl Database {
     }
 
     pub fn add_dm_participant(&self, participant: &DmParticipant) -> rusqlite::Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "INSERT OR IGNORE INTO dm_participants (conversation_id, peer_id, joined_at)
              VALUES (?1, ?2, ?3)",
@@ -253,7 +398,7 @@ impl Database {
     // ============================================================
 
     pub fn set_role(&self, role: &RoomRole) -> rusqlite::Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "INSERT OR REPLACE INTO room_roles (id, room_id, peer_id, role, assigned_by, assigned_at)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
@@ -270,7 +415,7 @@ impl Database {
     }
 
     pub fn get_role(&self, room_id: &str, peer_id: &str) -> rusqlite::Result<Option<RoomRole>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare(
             "SELECT id, room_id, peer_id, role, assigned_by, assigned_at
              FROM room_roles WHERE room_id = ?1 AND peer_id = ?2",
@@ -293,7 +438,7 @@ impl Database {
     }
 
     pub fn get_room_roles(&self, room_id: &str) -> rusqlite::Result<Vec<RoomRole>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare(
             "SELECT id, room_id, peer_id, role, assigned_by, assigned_at
              FROM room_roles WHERE room_id = ?1 ORDER BY assigned_at",
@@ -314,7 +459,7 @@ impl Database {
     }
 
     pub fn remove_role(&self, room_id: &str, peer_id: &str) -> rusqlite::Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "DELETE FROM room_roles WHERE room_id = ?1 AND peer_id = ?2",
             rusqlite::params![room_id, peer_id],
@@ -322,12 +467,25 @@ impl Database {
         Ok(())
     }
 
+    /// Whether `peer_id` (or the identity it is a device of) may arrange the
+    /// room's channels and categories: it owns the room or is an owner or admin.
+    pub fn can_manage_channels(&self, room_id: &str, peer_id: &str) -> rusqlite::Result<bool> {
+        let identity = self.resolve_identity(peer_id)?;
+        let conn = self.pool.get();
+        conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM rooms WHERE id = ?1 AND owner_peer_id = ?2)
+                 OR EXISTS(SELECT 1 FROM room_roles WHERE room_id = ?1 AND peer_id = ?2 AND role IN ('owner', 'admin'))",
+            rusqlite::params![room_id, identity],
+            |row| row.get(0),
+        )
+    }
+
     // ============================================================
     // Phase 2: Moderation
     // ============================================================
 
     pub fn add_moderation_action(&self, action: &ModerationAction) -> rusqlite::Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "INSERT INTO moderation_actions (id, room_id, action_type, target_peer_id, moderator_peer_id, reason, created_at, expires_at)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
@@ -349,7 +507,7 @@ impl Database {
         &self,
         room_id: &str,
     ) -> rusqlite::Result<Vec<ModerationAction>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare(
             "SELECT id, room_id, action_type, target_peer_id, moderator_peer_id, reason, created_at, expires_at
              FROM moderation_actions WHERE room_id = ?1 ORDER BY created_at DESC",
@@ -372,7 +530,7 @@ impl Database {
     }
 
     pub fn block_peer(&self, peer_id: &str, blocked_at: &str) -> rusqlite::Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "INSERT OR IGNORE INTO blocked_peers (peer_id, blocked_at) VALUES (?1, ?2)",
             rusqlite::params![peer_id, blocked_at],
@@ -381,7 +539,7 @@ impl Database {
     }
 
     pub fn unblock_peer(&self, peer_id: &str) -> rusqlite::Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "DELETE FROM blocked_peers WHERE peer_id = ?1",
             rusqlite::params![peer_id],
@@ -390,7 +548,7 @@ impl Database {
     }
 
     pub fn get_blocked_peers(&self) -> rusqlite::Result<Vec<BlockedPeer>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare(
             "SELECT peer_id, blocked_at FROM blocked_peers ORDER BY blocked_at DESC",
         )?;
@@ -406,7 +564,7 @@ impl Database {
     }
 
     pub fn is_peer_banned(&self, room_id: &str, peer_id: &str) -> rusqlite::Result<bool> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare(
             "SELECT COUNT(*) FROM moderation_actions
              WHERE room_id = ?1 AND target_peer_id = ?2 AND action_type = 'ban'
@@ -421,7 +579,7 @@ impl Database {
     // ============================================================
 
     pub fn insert_file(&self, file: &FileMetadata) -> rusqlite::Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "INSERT INTO files (id, filename, size, mime_type, sha256_hash, chunk_count, uploader_peer_id, created_at)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
@@ -440,7 +598,7 @@ impl Database {
     }
 
     pub fn get_file(&self, file_id: &str) -> rusqlite::Result<Option<FileMetadata>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare(
             "SELECT id, filename, size, mime_type, sha256_hash, chunk_count, uploader_peer_id, created_at
              FROM files WHERE id = ?1",
@@ -464,11 +622,87 @@ impl Database {
         }
     }
 
+    /// Search file names, with the `from:` (uploader) and date filters of a parsed query.
+    pub fn search_files(
+        &self,
+        search: &MessageSearch,
+        limit: i64,
+        offset: i64,
+    ) -> rusqlite::Result<(Vec<(FileMetadata, Option<String>)>, i64)> {
+        use rusqlite::types::Value;
+
+        let conn = self.pool.get();
+
+        let mut clauses = vec!["1 = 1".to_string()];
+        let mut params: Vec<Value> = Vec::new();
+        if let Some(fts) = &search.fts {
+            clauses.push("files_fts MATCH ?".to_string());
+            params.push(Value::Text(fts.clone()));
+        }
+        for from in &search.from {
+            clauses.push("f.uploader_peer_id = ?".to_string());
+            params.push(Value::Text(from.clone()));
+        }
+        if let Some(before) = &search.before {
+            clauses.push("julianday(f.created_at) < julianday(?)".to_string());
+            params.push(Value::Text(before.clone()));
+        }
+        if let Some(after) = &search.after {
+            clauses.push("julianday(f.created_at) >= julianday(?)".to_string());
+            params.push(Value::Text(after.clone()));
+        }
+
+        let (from_sql, snippet_sql) = if search.fts.is_some() {
+            (
+                "files_fts JOIN files f ON f.rowid = files_fts.rowid",
+                format!("highlight(files_fts, 1, {MATCH_SQL_ARGS})"),
+            )
+        } else {
+            ("files f", "NULL".to_string())
+        };
+        let where_sql = clauses.join(" AND ");
+
+        let total: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM {from_sql} WHERE {where_sql}"),
+            rusqlite::params_from_iter(params.iter()),
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT f.id, f.filename, f.size, f.mime_type, f.sha256_hash, f.chunk_count, f.uploader_peer_id, f.created_at,
+                    {snippet_sql}
+             FROM {from_sql}
+             WHERE {where_sql}
+             ORDER BY f.created_at DESC
+             LIMIT ? OFFSET ?"
+        ))?;
+        params.push(Value::Integer(limit));
+        params.push(Value::Integer(offset));
+        let hits = stmt
+            .query_map(rusqlite::params_from_iter(params.iter()), |row| {
+                Ok((
+                    FileMetadata {
+                        id: row.get(0)?,
+                        filename: row.get(1)?,
+                        size: row.get(2)?,
+                        mime_type: row.get(3)?,
+                        sha256_hash: row.get(4)?,
+                        chunk_count: row.get(5)?,
+                        uploader_peer_id: row.get(6)?,
+                        created_at: row.get(7)?,
+                    },
+                    row.get::<_, Option<String>>(8)?.map(|s| highlight_snippet(&s)),
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok((hits, total))
+    }
+
     pub fn insert_message_attachment(
         &self,
         attachment: &MessageAttachment,
     ) -> rusqlite::Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "INSERT OR IGNORE INTO message_attachments (message_id, file_id) VALUES (?1, ?2)",
             rusqlite::params![attachment.message_id, attachment.file_id],
@@ -480,7 +714,7 @@ impl Database {
         &self,
         message_id: &str,
     ) -> rusqlite::Result<Vec<FileMetadata>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare(
             "SELECT f.id, f.filename, f.size, f.mime_type, f.sha256_hash, f.chunk_count, f.uploader_peer_id, f.created_at
              FROM files f
@@ -509,7 +743,7 @@ impl Database {
     // ============================================================
 
     pub fn add_friend(&self, friend: &Friend) -> rusqlite::Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "INSERT OR REPLACE INTO friends (peer_id, display_name, status, created_at)
              VALUES (?1, ?2, ?3, ?4)",
@@ -524,7 +758,7 @@ impl Database {
     }
 
     pub fn update_friend_status(&self, peer_id: &str, status: &str) -> rusqlite::Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "UPDATE friends SET status = ?1 WHERE peer_id = ?2",
             rusqlite::params![status, peer_id],
@@ -533,7 +767,7 @@ impl Database {
     }
 
     pub fn remove_friend(&self, peer_id: &str) -> rusqlite::Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "DELETE FROM friends WHERE peer_id = ?1",
             rusqlite::params![peer_id],
@@ -542,7 +776,7 @@ impl Database {
     }
 
     pub fn list_friends(&self) -> rusqlite::Result<Vec<Friend>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare(
             "SELECT peer_id, display_name, status, created_at
              FROM friends ORDER BY created_at DESC",
@@ -561,7 +795,7 @@ impl Database {
     }
 
     pub fn get_friend(&self, peer_id: &str) -> rusqlite::Result<Option<Friend>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare(
             "SELECT peer_id, display_name, status, created_at
              FROM friends WHERE peer_id = ?1",
@@ -586,7 +820,7 @@ impl Database {
     // ============================================================
 
     pub fn add_custom_emoji(&self, emoji: &CustomEmoji) -> rusqlite::Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "INSERT INTO custom_emoji (id, room_id, name, file_hash, uploaded_by, created_at)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
@@ -603,7 +837,7 @@ impl Database {
     }
 
     pub fn remove_custom_emoji(&self, emoji_id: &str) -> rusqlite::Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         conn.execute(
             "DELETE FROM custom_emoji WHERE id = ?1",
             rusqlite::params![emoji_id],
@@ -612,7 +846,7 @@ impl Database {
     }
 
     pub fn list_custom_emoji(&self, room_id: &str) -> rusqlite::Result<Vec<CustomEmoji>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.get();
         let mut stmt = conn.prepare(
             "SELECT id, room_id, name, file_hash, uploaded_by, created_at
              FROM custom_emoji WHERE room_id = ?1 ORDER BY name",
@@ -0,0 +1,195 @@
+use rusqlite::{OptionalExtension, Result};
+
+use super::Database;
+use crate::models::{IncomingWebhook, Webhook, WebhookDelivery};
+
+/// Delivery attempts kept per webhook.
+const DELIVERIES_KEPT: i64 = 500;
+
+fn webhook_from_row(row: &rusqlite::Row) -> Result<Webhook> {
+    let filter: String = row.get(2)?;
+    Ok(Webhook {
+        id: row.get(0)?,
+        url: row.get(1)?,
+        filter: serde_json::from_str(&filter).unwrap_or_default(),
+        enabled: row.get(3)?,
+        created_at: row.get(4)?,
+    })
+}
+
+fn incoming_webhook_from_row(row: &rusqlite::Row) -> Result<IncomingWebhook> {
+    Ok(IncomingWebhook {
+        id: row.get(0)?,
+        channel_id: row.get(1)?,
+        name: row.get(2)?,
+        created_at: row.get(3)?,
+    })
+}
+
+impl Database {
+    // ============================================================
+    // Webhooks
+    // ============================================================
+
+    pub fn insert_webhook(&self, webhook: &Webhook, secret: &str) -> Result<()> {
+        let conn = self.pool.get();
+        conn.execute(
+            "INSERT INTO webhooks (id, url, secret, filter, enabled, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                webhook.id,
+                webhook.url,
+                secret,
+                serde_json::to_string(&webhook.filter).unwrap_or_default(),
+                webhook.enabled,
+                webhook.created_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_webhook(&self, webhook: &Webhook) -> Result<bool> {
+        let conn = self.pool.get();
+        let changed = conn.execute(
+            "UPDATE webhooks SET url = ?2, filter = ?3, enabled = ?4 WHERE id = ?1",
+            rusqlite::params![
+                webhook.id,
+                webhook.url,
+                serde_json::to_string(&webhook.filter).unwrap_or_default(),
+                webhook.enabled,
+            ],
+        )?;
+        Ok(changed > 0)
+    }
+
+    pub fn delete_webhook(&self, id: &str) -> Result<bool> {
+        let conn = self.pool.get();
+        conn.execute("DELETE FROM webhook_deliveries WHERE webhook_id = ?1", [id])?;
+        Ok(conn.execute("DELETE FROM webhooks WHERE id = ?1", [id])? > 0)
+    }
+
+    pub fn get_webhook(&self, id: &str) -> Result<Option<Webhook>> {
+        let conn = self.pool.get();
+        conn.query_row(
+            "SELECT id, url, filter, enabled, created_at FROM webhooks WHERE id = ?1",
+            [id],
+            webhook_from_row,
+        )
+        .optional()
+    }
+
+    pub fn list_webhooks(&self) -> Result<Vec<Webhook>> {
+        let conn = self.pool.get();
+        let mut stmt = conn.prepare("SELECT id, url, filter, enabled, created_at FROM webhooks ORDER BY created_at")?;
+        let rows = stmt.query_map([], webhook_from_row)?.collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Enabled webhooks with their signing secrets.
+    pub fn get_active_webhooks(&self) -> Result<Vec<(Webhook, String)>> {
+        let conn = self.pool.get();
+        let mut stmt = conn.prepare(
+            "SELECT id, url, filter, enabled, created_at, secret FROM webhooks WHERE enabled = 1",
+        )?;
+        let rows = stmt
+            .query_map([], |row| Ok((webhook_from_row(row)?, row.get(5)?)))?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    pub fn get_webhook_secret(&self, id: &str) -> Result<Option<String>> {
+        let conn = self.pool.get();
+        conn.query_row("SELECT secret FROM webhooks WHERE id = ?1", [id], |row| row.get(0)).optional()
+    }
+
+    /// Record an attempt, keeping the newest [`DELIVERIES_KEPT`] per webhook.
+    pub fn insert_webhook_delivery(&self, delivery: &WebhookDelivery) -> Result<()> {
+        let conn = self.pool.get();
+        conn.execute(
+            "INSERT INTO webhook_deliveries
+                (id, webhook_id, event_seq, event_type, attempt, status_code, error, success, attempted_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            rusqlite::params![
+                delivery.id,
+                delivery.webhook_id,
+                delivery.event_seq,
+                delivery.event_type,
+                delivery.attempt,
+                delivery.status_code,
+                delivery.error,
+                delivery.success,
+                delivery.attempted_at,
+            ],
+        )?;
+        conn.execute(
+            "DELETE FROM webhook_deliveries WHERE webhook_id = ?1 AND id NOT IN (
+                SELECT id FROM webhook_deliveries WHERE webhook_id = ?1 ORDER BY attempted_at DESC LIMIT ?2)",
+            rusqlite::params![delivery.webhook_id, DELIVERIES_KEPT],
+        )?;
+        Ok(())
+    }
+
+    /// Newest attempts first.
+    pub fn get_webhook_deliveries(&self, webhook_id: &str, limit: i64) -> Result<Vec<WebhookDelivery>> {
+        let conn = self.pool.get();
+        let mut stmt = conn.prepare(
+            "SELECT id, webhook_id, event_seq, event_type, attempt, status_code, error, success, attempted_at
+             FROM webhook_deliveries WHERE webhook_id = ?1 ORDER BY attempted_at DESC LIMIT ?2",
+        )?;
+        let rows = stmt
+            .query_map(rusqlite::params![webhook_id, limit], |row| {
+                Ok(WebhookDelivery {
+                    id: row.get(0)?,
+                    webhook_id: row.get(1)?,
+                    event_seq: row.get(2)?,
+                    event_type: row.get(3)?,
+                    attempt: row.get(4)?,
+                    status_code: row.get(5)?,
+                    error: row.get(6)?,
+                    success: row.get(7)?,
+                    attempted_at: row.get(8)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    // ============================================================
+    // Incoming webhooks
+    // ============================================================
+
+    pub fn insert_incoming_webhook(&self, webhook: &IncomingWebhook, token_hash: &str) -> Result<()> {
+        let conn = self.pool.get();
+        conn.execute(
+            "INSERT INTO incoming_webhooks (id, channel_id, name, token_hash, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![webhook.id, webhook.channel_id, webhook.name, token_hash, webhook.created_at],
+        )?;
+        Ok(())
+    }
+
+    /// Incoming webhooks, all or only those posting into `channel_id`.
+    pub fn list_incoming_webhooks(&self, channel_id: Option<&str>) -> Result<Vec<IncomingWebhook>> {
+        let conn = self.pool.get();
+        let mut stmt = conn.prepare(
+            "SELECT id, channel_id, name, created_at FROM incoming_webhooks
+             WHERE ?1 IS NULL OR channel_id = ?1 ORDER BY created_at",
+        )?;
+        let rows = stmt.query_map([channel_id], incoming_webhook_from_row)?.collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    pub fn delete_incoming_webhook(&self, id: &str) -> Result<bool> {
+        let conn = self.pool.get();
+        Ok(conn.execute("DELETE FROM incoming_webhooks WHERE id = ?1", [id])? > 0)
+    }
+
+    /// The incoming webhook `id`, if `token_hash` is its token's.
+    pub fn authenticate_incoming_webhook(&self, id: &str, token_hash: &str) -> Result<Option<IncomingWebhook>> {
+        let conn = self.pool.get();
+        conn.query_row(
+            "SELECT id, channel_id, name, created_at FROM incoming_webhooks WHERE id = ?1 AND token_hash = ?2",
+            [id, token_hash],
+            incoming_webhook_from_row,
+        )
+        .optional()
+    }
+}
@@ -1,7 +1,13 @@
-use serde::Serialize;
-use tokio::sync::broadcast;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
 
-use crate::models::{Message, PeerInfo, PinnedMessage, DmMessage};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, mpsc, watch};
+use tracing::warn;
+
+use crate::db::Database;
+use crate::models::{ChannelCategory, CommandInvocation, DeviceCertificate, Message, Notification, PeerInfo, PinnedMessage, DmMessage, RetentionPolicy, RetentionScope, Thread};
 
 /// Transport-agnostic application events.
 /// Emitted by the network swarm, consumed by Tauri bridge and WebSocket API.
@@ -40,13 +46,392 @@ pub enum AppEvent {
     VoiceDisconnected { peer_id: String },
     SpeakingChanged { peer_id: String, speaking: bool },
     // Channel sync
-    ChannelCreated { room_id: String, channel_id: String, name: String, channel_type: String, created_at: String },
+    ChannelCreated { room_id: String, channel_id: String, name: String, channel_type: String, created_at: String, category_id: Option<String> },
     ChannelDeleted { room_id: String, channel_id: String },
+    // Channel categories
+    CategoryUpserted(ChannelCategory),
+    CategoryDeleted { room_id: String, category_id: String },
+    ChannelLayoutChanged { room_id: String, channel_id: String, category_id: Option<String>, position: i32, inherit_permissions: bool },
+    // Threads
+    ThreadCreated(Thread),
+    /// A reply was posted in a thread we are subscribed to.
+    ThreadReply { thread_id: String, message: Message },
+    // Notifications
+    /// A message passed the notification level configured for its channel, room or DM.
+    NotificationRaised(Notification),
+    // Identity
+    /// A peer proved that `old_peer_id` now goes by `new_peer_id`.
+    PeerKeyRotated { old_peer_id: String, new_peer_id: String },
+    // Devices
+    DeviceLinked(DeviceCertificate),
+    DeviceUnlinked { identity_peer_id: String, device_peer_id: String },
+    // Retention
+    RetentionPolicyUpdated(RetentionPolicy),
+    /// Messages hard-deleted because they outlived their retention policy.
+    MessagesExpired { channel_id: String, message_ids: Vec<String> },
+    DmMessagesExpired { conversation_id: String, message_ids: Vec<String> },
+    /// A sender deleted all of their messages in a room.
+    MessagesPurged { room_id: String, sender_peer_id: String, message_ids: Vec<String> },
+    // Slash commands
+    /// Someone ran a bot's command; the bot answers through the API.
+    CommandInvoked(CommandInvocation),
+}
+
+/// Where an event happened, for subscription filters. Channel events also
+/// carry their room.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventScope {
+    pub room_id: Option<String>,
+    pub channel_id: Option<String>,
+    pub conversation_id: Option<String>,
+}
+
+impl EventScope {
+    fn room(room_id: &str) -> Self {
+        Self { room_id: Some(room_id.to_string()), ..Default::default() }
+    }
+
+    fn channel(channel_id: &str) -> Self {
+        // Some events are sent before their channel is known
+        let channel_id = Some(channel_id.to_string()).filter(|c| !c.is_empty());
+        Self { channel_id, ..Default::default() }
+    }
+
+    fn conversation(conversation_id: &str) -> Self {
+        Self { conversation_id: Some(conversation_id.to_string()), ..Default::default() }
+    }
+}
+
+impl AppEvent {
+    pub fn scope(&self) -> EventScope {
+        match self {
+            AppEvent::NewMessage(message) | AppEvent::ThreadReply { message, .. } => EventScope::channel(&message.channel_id),
+            AppEvent::PeerJoinedRoom { room_id, .. }
+            | AppEvent::PeerLeftRoom { room_id, .. }
+            | AppEvent::CategoryDeleted { room_id, .. }
+            | AppEvent::MessagesPurged { room_id, .. } => EventScope::room(room_id),
+            AppEvent::CategoryUpserted(category) => EventScope::room(&category.room_id),
+            AppEvent::MessageEdited { channel_id, .. }
+            | AppEvent::MessageDeleted { channel_id, .. }
+            | AppEvent::ReactionAdded { channel_id, .. }
+            | AppEvent::ReactionRemoved { channel_id, .. }
+            | AppEvent::TypingStarted { channel_id, .. }
+            | AppEvent::TypingStopped { channel_id, .. }
+            | AppEvent::ReadReceiptUpdated { channel_id, .. }
+            | AppEvent::MessageUnpinned { channel_id, .. }
+            | AppEvent::CallOfferReceived { channel_id, .. }
+            | AppEvent::CallAnswerReceived { channel_id, .. }
+            | AppEvent::IceCandidateReceived { channel_id, .. }
+            | AppEvent::MessagesExpired { channel_id, .. } => EventScope::channel(channel_id),
+            AppEvent::MessagePinned(pin) => EventScope::channel(&pin.channel_id),
+            AppEvent::ThreadCreated(thread) => EventScope::channel(&thread.channel_id),
+            AppEvent::ChannelCreated { room_id, channel_id, .. }
+            | AppEvent::ChannelDeleted { room_id, channel_id }
+            | AppEvent::ChannelLayoutChanged { room_id, channel_id, .. } => EventScope {
+                room_id: Some(room_id.clone()),
+                channel_id: Some(channel_id.clone()),
+                conversation_id: None,
+            },
+            AppEvent::CommandInvoked(invocation) => EventScope {
+                room_id: Some(invocation.room_id.clone()),
+                channel_id: Some(invocation.channel_id.clone()),
+                conversation_id: None,
+            },
+            AppEvent::VoiceStateChanged { room_id, channel_id, .. } => EventScope {
+                room_id: Some(room_id.clone()),
+                channel_id: channel_id.clone(),
+                conversation_id: None,
+            },
+            AppEvent::NewDmMessage(dm) => EventScope::conversation(&dm.conversation_id),
+            AppEvent::DmMessagesExpired { conversation_id, .. } => EventScope::conversation(conversation_id),
+            AppEvent::NotificationRaised(notification) => EventScope {
+                room_id: notification.room_id.clone(),
+                channel_id: notification.channel_id.clone(),
+                conversation_id: notification.conversation_id.clone(),
+            },
+            AppEvent::RetentionPolicyUpdated(policy) => match policy.scope {
+                RetentionScope::Room => EventScope::room(&policy.target_id),
+                RetentionScope::Channel => EventScope::channel(&policy.target_id),
+                RetentionScope::Dm => EventScope::conversation(&policy.target_id),
+            },
+            _ => EventScope::default(),
+        }
+    }
+
+    /// High-frequency events that are delivered live but never journaled,
+    /// so they have no sequence number and are not replayed.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            AppEvent::TypingStarted { .. }
+                | AppEvent::TypingStopped { .. }
+                | AppEvent::SpeakingChanged { .. }
+                | AppEvent::IceCandidateReceived { .. }
+        )
+    }
+}
+
+/// An event as WebSocket clients see it: `{"seq", "type", "data"}`.
+#[derive(Debug, Clone)]
+pub struct JournaledEvent {
+    /// `None` for transient events and if the journal could not be written.
+    pub seq: Option<i64>,
+    pub event_type: String,
+    pub scope: EventScope,
+    /// The serialized [`AppEvent`].
+    pub payload: serde_json::Value,
+}
+
+impl JournaledEvent {
+    pub fn to_json(&self) -> String {
+        let mut payload = self.payload.clone();
+        if let serde_json::Value::Object(map) = &mut payload {
+            map.insert("seq".to_string(), self.seq.into());
+        }
+        payload.to_string()
+    }
+}
+
+/// Which events a subscriber (socket or webhook) receives. Empty lists don't filter. With any of
+/// `rooms`, `channels` or `dms` set, only events in one of them pass.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct EventFilter {
+    #[serde(default)]
+    pub rooms: Vec<String>,
+    #[serde(default)]
+    pub channels: Vec<String>,
+    #[serde(default)]
+    pub dms: Vec<String>,
+    /// Event type names, e.g. `NewMessage`.
+    #[serde(default)]
+    pub types: Vec<String>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &JournaledEvent) -> bool {
+        if !self.types.is_empty() && !self.types.contains(&event.event_type) {
+            return false;
+        }
+        if self.rooms.is_empty() && self.channels.is_empty() && self.dms.is_empty() {
+            return true;
+        }
+        let in_list = |list: &[String], id: &Option<String>| id.as_ref().is_some_and(|id| list.contains(id));
+        in_list(&self.rooms, &event.scope.room_id)
+            || in_list(&self.channels, &event.scope.channel_id)
+            || in_list(&self.dms, &event.scope.conversation_id)
+    }
+}
+
+/// A journaled event on its way to the journal writer.
+#[derive(Debug)]
+pub struct JournalEntry {
+    pub seq: i64,
+    pub event_type: String,
+    pub scope: EventScope,
+    pub payload: String,
+    pub created_at: String,
+}
+
+/// Journal entries written per transaction.
+const JOURNAL_BATCH: usize = 256;
+
+/// Numbering and hand-off of journaled events.
+#[derive(Clone)]
+struct Journal {
+    /// Last sequence number given out. Locked while broadcasting, so
+    /// subscribers see events in sequence order.
+    last_seq: Arc<Mutex<i64>>,
+    tx: mpsc::UnboundedSender<JournalEntry>,
+    /// Last sequence number the writer has stored.
+    written: watch::Receiver<i64>,
+}
+
+/// Event bus. Internal consumers get every [`AppEvent`] as is; the journaled
+/// stream gets them numbered and broadcast at once, while a [`JournalWriter`]
+/// stores them in the event journal so clients that fall behind can catch up.
+#[derive(Clone)]
+pub struct EventSender {
+    live: broadcast::Sender<AppEvent>,
+    journaled: broadcast::Sender<JournaledEvent>,
+    journal: Option<Journal>,
+    /// Room of each known channel, for scoping channel events without a query.
+    channel_rooms: Arc<RwLock<HashMap<String, String>>>,
+}
+
+impl EventSender {
+    pub fn send(&self, event: AppEvent) -> Result<usize, broadcast::error::SendError<AppEvent>> {
+        match &event {
+            AppEvent::ChannelCreated { room_id, channel_id, .. } | AppEvent::ChannelLayoutChanged { room_id, channel_id, .. } => {
+                self.track_channel(channel_id, room_id)
+            }
+            AppEvent::ChannelDeleted { channel_id, .. } => {
+                self.channel_rooms.write().unwrap_or_else(|e| e.into_inner()).remove(channel_id);
+            }
+            _ => {}
+        }
+        let payload = serde_json::to_value(&event).unwrap_or_default();
+        let event_type = payload.get("type").and_then(|t| t.as_str()).unwrap_or_default().to_string();
+        let mut scope = event.scope();
+        if scope.room_id.is_none() {
+            if let Some(channel_id) = &scope.channel_id {
+                scope.room_id = self.channel_rooms.read().unwrap_or_else(|e| e.into_inner()).get(channel_id).cloned();
+            }
+        }
+        match &self.journal {
+            Some(journal) if !event.is_transient() => {
+                let mut last_seq = journal.last_seq.lock().unwrap_or_else(|e| e.into_inner());
+                *last_seq += 1;
+                let seq = *last_seq;
+                let entry = JournalEntry {
+                    seq,
+                    event_type: event_type.clone(),
+                    scope: scope.clone(),
+                    payload: payload.to_string(),
+                    created_at: chrono::Utc::now().to_rfc3339(),
+                };
+                if journal.tx.send(entry).is_err() {
+                    warn!("Event journal writer stopped, {} event {} not journaled", event_type, seq);
+                }
+                let _ = self.journaled.send(JournaledEvent { seq: Some(seq), event_type, scope, payload });
+            }
+            _ => {
+                let _ = self.journaled.send(JournaledEvent { seq: None, event_type, scope, payload });
+            }
+        }
+        self.live.send(event)
+    }
+
+    pub fn subscribe(&self) -> EventReceiver {
+        self.live.subscribe()
+    }
+
+    pub fn subscribe_journaled(&self) -> broadcast::Receiver<JournaledEvent> {
+        self.journaled.subscribe()
+    }
+
+    /// Record a channel's room, for channels created without a
+    /// [`AppEvent::ChannelCreated`].
+    pub fn track_channel(&self, channel_id: &str, room_id: &str) {
+        self.channel_rooms
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(channel_id.to_string(), room_id.to_string());
+    }
+
+    /// Wait until every event sent so far is in the journal. Replay calls
+    /// this before reading it, so the journal and the live stream meet
+    /// without a gap.
+    pub async fn flushed(&self) {
+        let Some(journal) = &self.journal else { return };
+        let target = *journal.last_seq.lock().unwrap_or_else(|e| e.into_inner());
+        let mut written = journal.written.clone();
+        // Errs only if the writer is gone, and then there is nothing to wait for
+        let _ = written.wait_for(|&seq| seq >= target).await;
+    }
 }
 
-pub type EventSender = broadcast::Sender<AppEvent>;
 pub type EventReceiver = broadcast::Receiver<AppEvent>;
 
-pub fn create_event_bus() -> (EventSender, EventReceiver) {
-    broadcast::channel(256)
+/// Stores journaled events in the database, off the sender's path.
+pub struct JournalWriter {
+    db: Arc<Database>,
+    rx: mpsc::UnboundedReceiver<JournalEntry>,
+    written: watch::Sender<i64>,
+}
+
+impl JournalWriter {
+    /// Write events in batches until the event bus is dropped.
+    pub async fn run(mut self) {
+        let mut batch = Vec::with_capacity(JOURNAL_BATCH);
+        while self.rx.recv_many(&mut batch, JOURNAL_BATCH).await > 0 {
+            let mut entries = std::mem::take(&mut batch);
+            let last_seq = entries.last().map_or(0, |entry| entry.seq);
+            let count = entries.len();
+            let result = self
+                .db
+                .run(move |db| {
+                    // Channels the sender didn't know yet
+                    for entry in &mut entries {
+                        if entry.scope.room_id.is_none() {
+                            if let Some(channel_id) = &entry.scope.channel_id {
+                                entry.scope.room_id = db.get_room_id_for_channel(channel_id).ok().flatten();
+                            }
+                        }
+                    }
+                    db.append_events(&entries)
+                })
+                .await;
+            if let Err(e) = result {
+                warn!("Failed to journal {} events: {}", count, e);
+            }
+            // Advance even on failure, so replay doesn't wait on lost events
+            self.written.send_replace(last_seq);
+        }
+    }
+}
+
+/// Create the event bus. With a database, events are journaled by the
+/// returned [`JournalWriter`], which the caller spawns.
+pub fn create_event_bus(db: Option<Arc<Database>>) -> (EventSender, EventReceiver, Option<JournalWriter>) {
+    let (live, live_rx) = broadcast::channel(256);
+    let (journaled, _) = broadcast::channel(256);
+    let mut channel_rooms = HashMap::new();
+    let (journal, writer) = match db {
+        Some(db) => {
+            let last_seq = db.get_last_event_seq().unwrap_or_else(|e| {
+                warn!("Failed to read event journal: {}", e);
+                0
+            });
+            match db.get_channel_rooms() {
+                Ok(rows) => channel_rooms.extend(rows),
+                Err(e) => warn!("Failed to load channels: {}", e),
+            }
+            let (tx, rx) = mpsc::unbounded_channel();
+            let (written_tx, written) = watch::channel(last_seq);
+            let journal = Journal { last_seq: Arc::new(Mutex::new(last_seq)), tx, written };
+            (Some(journal), Some(JournalWriter { db, rx, written: written_tx }))
+        }
+        None => (None, None),
+    };
+    let sender = EventSender { live, journaled, journal, channel_rooms: Arc::new(RwLock::new(channel_rooms)) };
+    (sender, live_rx, writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn journal_catches_up_with_the_live_stream() {
+        let dir = std::env::temp_dir().join(format!("chatr-events-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = Arc::new(Database::new(&dir).unwrap());
+        let (event_tx, _live_rx, writer) = create_event_bus(Some(db.clone()));
+        tokio::spawn(writer.unwrap().run());
+        let mut journaled_rx = event_tx.subscribe_journaled();
+
+        event_tx.track_channel("c1", "r1");
+        for i in 0..200 {
+            let _ = event_tx.send(AppEvent::MessageDeleted { message_id: i.to_string(), channel_id: "c1".to_string() });
+        }
+        let _ = event_tx.send(AppEvent::TypingStopped { channel_id: "c1".to_string(), peer_id: "p".to_string() });
+
+        // Broadcast in sequence order, transient events unnumbered
+        let mut seqs = Vec::new();
+        while let Ok(event) = journaled_rx.try_recv() {
+            assert_eq!(event.scope.room_id.as_deref(), Some("r1"));
+            seqs.push(event.seq);
+        }
+        assert_eq!(seqs.len(), 201);
+        assert!(seqs[..200].iter().zip(1..).all(|(seq, n)| *seq == Some(n)));
+        assert_eq!(seqs[200], None);
+
+        // After flushing, the journal holds everything that was broadcast
+        event_tx.flushed().await;
+        let journal = db.run(|db| db.get_events_since(0, 1000)).await.unwrap();
+        assert_eq!(journal.len(), 200);
+        assert_eq!(journal.last().and_then(|e| e.seq), Some(200));
+        assert!(journal.iter().all(|e| e.scope.room_id.as_deref() == Some("r1")));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
@@ -0,0 +1,289 @@
+//! Room and channel history export to JSON, Markdown or a self-contained
+//! HTML transcript.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use crate::db::Database;
+use crate::models::{ChannelExport, ExportFormat, ExportedMessage, Message, Reaction, RoomExport};
+
+/// Messages fetched per page while walking channel and thread history.
+const PAGE_SIZE: i64 = 200;
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Html => "html",
+            ExportFormat::Markdown => "md",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "application/json",
+            ExportFormat::Html => "text/html; charset=utf-8",
+            ExportFormat::Markdown => "text/markdown; charset=utf-8",
+        }
+    }
+}
+
+/// Page backwards through `fetch` until it runs dry, oldest message first.
+fn fetch_all(
+    mut fetch: impl FnMut(Option<&str>) -> rusqlite::Result<Vec<Message>>,
+) -> Result<Vec<Message>, String> {
+    let mut all = Vec::new();
+    let mut before: Option<String> = None;
+    loop {
+        let page = fetch(before.as_deref()).map_err(|e| e.to_string())?;
+        let Some(oldest) = page.first() else {
+            break;
+        };
+        before = Some(oldest.timestamp.clone());
+        let full = page.len() as i64 == PAGE_SIZE;
+        all.splice(0..0, page);
+        if !full {
+            break;
+        }
+    }
+    Ok(all)
+}
+
+fn export_message(db: &Database, message: Message, pinned: &HashSet<String>) -> Result<ExportedMessage, String> {
+    let reactions = db.get_reactions(&message.id).map_err(|e| e.to_string())?;
+    let attachments = db.get_message_attachments(&message.id).map_err(|e| e.to_string())?;
+    let thread_replies = if message.thread.is_some() {
+        fetch_all(|before| db.get_thread_messages(&message.id, PAGE_SIZE, before))?
+            .into_iter()
+            .map(|reply| export_message(db, reply, pinned))
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        Vec::new()
+    };
+    Ok(ExportedMessage {
+        pinned: pinned.contains(&message.id),
+        message,
+        reactions,
+        attachments,
+        thread_replies,
+    })
+}
+
+/// Gather a room's history, or just `channel_id` within it.
+pub fn collect(db: &Database, room_id: &str, channel_id: Option<&str>) -> Result<RoomExport, String> {
+    let room = db
+        .list_rooms()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|r| r.id == room_id)
+        .ok_or_else(|| "Room not found".to_string())?;
+
+    let mut channels = db.get_channels(room_id).map_err(|e| e.to_string())?;
+    if let Some(channel_id) = channel_id {
+        channels.retain(|c| c.id == channel_id);
+        if channels.is_empty() {
+            return Err("Channel not found in this room".to_string());
+        }
+    }
+
+    let mut exported = Vec::with_capacity(channels.len());
+    for channel in channels {
+        let pinned: HashSet<String> = db
+            .get_pinned_messages(&channel.id)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|p| p.message_id)
+            .collect();
+        let messages = fetch_all(|before| db.get_messages(&channel.id, PAGE_SIZE, before))?
+            .into_iter()
+            .map(|m| export_message(db, m, &pinned))
+            .collect::<Result<Vec<_>, _>>()?;
+        exported.push(ChannelExport { channel, messages });
+    }
+
+    Ok(RoomExport {
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        room,
+        channels: exported,
+    })
+}
+
+pub fn render(export: &RoomExport, format: ExportFormat) -> Result<String, String> {
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(export).map_err(|e| e.to_string()),
+        ExportFormat::Html => Ok(render_html(export)),
+        ExportFormat::Markdown => Ok(render_markdown(export)),
+    }
+}
+
+/// Reactions collapsed to `(emoji, count)` in first-use order.
+fn reaction_counts(reactions: &[Reaction]) -> Vec<(&str, usize)> {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for reaction in reactions {
+        match counts.iter_mut().find(|(emoji, _)| *emoji == reaction.emoji) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((&reaction.emoji, 1)),
+        }
+    }
+    counts
+}
+
+// ============================================================
+// Markdown
+// ============================================================
+
+fn render_markdown(export: &RoomExport) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# {}\n\nExported {}\n", export.room.name, export.exported_at);
+    for channel in &export.channels {
+        let _ = writeln!(out, "## #{}\n", channel.channel.name);
+        if let Some(topic) = &channel.channel.topic {
+            let _ = writeln!(out, "_{}_\n", topic);
+        }
+        for message in &channel.messages {
+            markdown_message(&mut out, message, "");
+        }
+    }
+    out
+}
+
+fn markdown_message(out: &mut String, message: &ExportedMessage, indent: &str) {
+    let m = &message.message;
+    let _ = write!(out, "{}**{}** · {}", indent, m.sender_display_name, m.timestamp);
+    if let Some(edited_at) = &m.edited_at {
+        let _ = write!(out, " · edited {}", edited_at);
+    }
+    if message.pinned {
+        out.push_str(" · 📌 pinned");
+    }
+    let _ = writeln!(out, "\n{}", indent);
+    for line in m.content.lines() {
+        let _ = writeln!(out, "{}> {}", indent, line);
+    }
+    for file in &message.attachments {
+        let _ = writeln!(
+            out,
+            "{}>\n{}> 📎 {} ({} bytes, sha256 `{}`)",
+            indent, indent, file.filename, file.size, file.sha256_hash
+        );
+    }
+    let reactions = reaction_counts(&message.reactions);
+    if !reactions.is_empty() {
+        let summary: Vec<String> = reactions.iter().map(|(emoji, n)| format!("{} {}", emoji, n)).collect();
+        let _ = writeln!(out, "{}>\n{}> {}", indent, indent, summary.join("  "));
+    }
+    out.push('\n');
+    if !message.thread_replies.is_empty() {
+        let nested = format!("{}> ", indent);
+        let _ = writeln!(out, "{}Thread ({} replies):\n", indent, message.thread_replies.len());
+        for reply in &message.thread_replies {
+            markdown_message(out, reply, &nested);
+        }
+    }
+}
+
+// ============================================================
+// HTML
+// ============================================================
+
+const HTML_STYLE: &str = "body{font-family:system-ui,sans-serif;background:#111827;color:#e5e7eb;max-width:860px;margin:2rem auto;padding:0 1rem}\
+h1{margin-bottom:0}h2{border-bottom:1px solid #374151;padding-bottom:.25rem;margin-top:2rem}\
+.meta{color:#9ca3af;font-size:.8rem}.msg{padding:.5rem 0;border-bottom:1px solid #1f2937}\
+.author{font-weight:600}.content{white-space:pre-wrap;margin:.25rem 0}\
+.pin{color:#fbbf24}.reactions span{display:inline-block;background:#1f2937;border-radius:9px;padding:0 .4rem;margin-right:.25rem;font-size:.85rem}\
+.attachment{font-size:.85rem;color:#93c5fd}.thread{margin-left:1.5rem;border-left:2px solid #374151;padding-left:.75rem}";
+
+pub(crate) fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn render_html(export: &RoomExport) -> String {
+    let mut out = String::new();
+    let title = escape_html(&export.room.name);
+    let _ = write!(
+        out,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<h1>{}</h1>\n<p class=\"meta\">Exported {}</p>\n",
+        title,
+        HTML_STYLE,
+        title,
+        escape_html(&export.exported_at)
+    );
+    for channel in &export.channels {
+        let _ = writeln!(out, "<section id=\"{}\">", escape_html(&channel.channel.id));
+        let _ = writeln!(out, "<h2>#{}</h2>", escape_html(&channel.channel.name));
+        if let Some(topic) = &channel.channel.topic {
+            let _ = writeln!(out, "<p class=\"meta\">{}</p>", escape_html(topic));
+        }
+        for message in &channel.messages {
+            html_message(&mut out, message);
+        }
+        out.push_str("</section>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn html_message(out: &mut String, message: &ExportedMessage) {
+    let m = &message.message;
+    let _ = write!(
+        out,
+        "<div class=\"msg\" id=\"msg-{}\">\n<div><span class=\"author\" title=\"{}\">{}</span> <span class=\"meta\">{}",
+        escape_html(&m.id),
+        escape_html(&m.sender_peer_id),
+        escape_html(&m.sender_display_name),
+        escape_html(&m.timestamp)
+    );
+    if let Some(edited_at) = &m.edited_at {
+        let _ = write!(out, " · edited {}", escape_html(edited_at));
+    }
+    out.push_str("</span>");
+    if message.pinned {
+        out.push_str(" <span class=\"pin\">📌 pinned</span>");
+    }
+    out.push_str("</div>\n");
+    if let Some(reply_to) = &m.reply_to_id {
+        let _ = writeln!(
+            out,
+            "<div class=\"meta\">↪ <a href=\"#msg-{}\">in reply</a></div>",
+            escape_html(reply_to)
+        );
+    }
+    let _ = writeln!(out, "<div class=\"content\">{}</div>", escape_html(&m.content));
+    for file in &message.attachments {
+        let _ = writeln!(
+            out,
+            "<div class=\"attachment\">📎 {} <span class=\"meta\">({} bytes, {}, sha256 {})</span></div>",
+            escape_html(&file.filename),
+            file.size,
+            escape_html(&file.mime_type),
+            escape_html(&file.sha256_hash)
+        );
+    }
+    let reactions = reaction_counts(&message.reactions);
+    if !reactions.is_empty() {
+        out.push_str("<div class=\"reactions\">");
+        for (emoji, count) in reactions {
+            let _ = write!(out, "<span>{} {}</span>", escape_html(emoji), count);
+        }
+        out.push_str("</div>\n");
+    }
+    if !message.thread_replies.is_empty() {
+        out.push_str("<div class=\"thread\">\n");
+        for reply in &message.thread_replies {
+            html_message(out, reply);
+        }
+        out.push_str("</div>\n");
+    }
+    out.push_str("</div>\n");
+}
@@ -0,0 +1,242 @@
+//! History import from other chat services.
+//!
+//! Discord history is read from DiscordChatExporter's JSON format, one file
+//! per channel. Authors are kept as display names under a `discord:<user id>`
+//! sender id, and every message is tagged with `imported_from` so it stays
+//! local history and is never broadcast as new.
+
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::models::{Message, PinnedMessage, Reaction};
+
+/// Value of `messages.imported_from` for Discord history.
+pub const DISCORD_SOURCE: &str = "discord";
+
+/// Reactions created per emoji at most; exports only carry a count for the
+/// users they didn't list.
+const MAX_REACTIONS_PER_EMOJI: i64 = 1000;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscordExport {
+    pub guild: DiscordGuild,
+    pub channel: DiscordChannel,
+    #[serde(default)]
+    pub messages: Vec<DiscordMessage>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscordGuild {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscordChannel {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub topic: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscordMessage {
+    pub id: String,
+    #[serde(rename = "type", default)]
+    pub kind: String,
+    pub timestamp: String,
+    #[serde(default)]
+    pub timestamp_edited: Option<String>,
+    #[serde(default)]
+    pub is_pinned: bool,
+    #[serde(default)]
+    pub content: String,
+    pub author: DiscordUser,
+    #[serde(default)]
+    pub attachments: Vec<DiscordAttachment>,
+    #[serde(default)]
+    pub reactions: Vec<DiscordReaction>,
+    #[serde(default)]
+    pub reference: Option<DiscordReference>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscordUser {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub nickname: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscordAttachment {
+    pub url: String,
+    pub file_name: String,
+    #[serde(default)]
+    pub file_size_bytes: i64,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscordReaction {
+    pub emoji: DiscordEmoji,
+    pub count: i64,
+    #[serde(default)]
+    pub users: Vec<DiscordUser>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscordEmoji {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscordReference {
+    #[serde(default)]
+    pub message_id: Option<String>,
+}
+
+/// One channel's history, ready for `Database::import_messages`.
+#[derive(Debug, Default)]
+pub struct ImportedHistory {
+    pub messages: Vec<Message>,
+    pub reactions: Vec<Reaction>,
+    pub pins: Vec<PinnedMessage>,
+    pub skipped: usize,
+}
+
+fn discord_peer_id(user_id: &str) -> String {
+    format!("discord:{}", user_id)
+}
+
+/// Discord timestamps carry the server's offset; store UTC like everything else
+/// so they sort with local messages.
+fn normalize_timestamp(timestamp: &str) -> Option<String> {
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|t| t.with_timezone(&chrono::Utc).to_rfc3339())
+}
+
+/// Only regular messages and replies carry history; joins, pins, boosts and
+/// other system messages are dropped.
+fn is_chat_message(message: &DiscordMessage) -> bool {
+    matches!(message.kind.as_str(), "" | "Default" | "Reply")
+}
+
+/// Assign local ids to every importable message across all exports, so
+/// replies can point into other channels of the same import.
+pub fn assign_ids(exports: &[DiscordExport]) -> HashMap<String, String> {
+    exports
+        .iter()
+        .flat_map(|export| export.messages.iter())
+        .filter(|m| is_chat_message(m))
+        .map(|m| (m.id.clone(), Uuid::new_v4().to_string()))
+        .collect()
+}
+
+/// Convert one exported channel into `channel_id`. `ids` comes from
+/// [`assign_ids`]; pins are recorded as made by `importer_peer_id`.
+pub fn convert_channel(
+    export: &DiscordExport,
+    channel_id: &str,
+    ids: &HashMap<String, String>,
+    importer_peer_id: &str,
+) -> ImportedHistory {
+    let mut history = ImportedHistory::default();
+    for discord in &export.messages {
+        let (Some(id), Some(timestamp)) = (ids.get(&discord.id), normalize_timestamp(&discord.timestamp)) else {
+            history.skipped += 1;
+            continue;
+        };
+
+        let mut content = discord.content.clone();
+        for attachment in &discord.attachments {
+            if !content.is_empty() {
+                content.push('\n');
+            }
+            content.push_str(&format!(
+                "📎 [{}]({}) ({} bytes)",
+                attachment.file_name, attachment.url, attachment.file_size_bytes
+            ));
+        }
+        if content.is_empty() {
+            history.skipped += 1;
+            continue;
+        }
+
+        let reply_to_id = discord
+            .reference
+            .as_ref()
+            .and_then(|r| r.message_id.as_ref())
+            .and_then(|discord_id| ids.get(discord_id))
+            .cloned();
+
+        history.messages.push(Message {
+            id: id.clone(),
+            channel_id: channel_id.to_string(),
+            sender_peer_id: discord_peer_id(&discord.author.id),
+            sender_display_name: discord
+                .author
+                .nickname
+                .clone()
+                .filter(|n| !n.is_empty())
+                .unwrap_or_else(|| discord.author.name.clone()),
+            content,
+            timestamp: timestamp.clone(),
+            edited_at: discord.timestamp_edited.as_deref().and_then(normalize_timestamp),
+            deleted_at: None,
+            reply_to_id,
+            thread_id: None,
+            thread: None,
+            imported_from: Some(DISCORD_SOURCE.to_string()),
+            webhook_id: None,
+            embeds: Vec::new(),
+        });
+
+        for reaction in &discord.reactions {
+            let emoji = match reaction.emoji.id.as_deref() {
+                // Custom server emoji aren't available here; keep the name
+                Some(emoji_id) if !emoji_id.is_empty() => format!(":{}:", reaction.emoji.name),
+                _ => reaction.emoji.name.clone(),
+            };
+            let named = reaction.users.iter().map(|u| discord_peer_id(&u.id));
+            let unnamed = (reaction.users.len() as i64..reaction.count.min(MAX_REACTIONS_PER_EMOJI))
+                .map(|i| format!("discord:unknown-{}", i));
+            for peer_id in named.chain(unnamed) {
+                history.reactions.push(Reaction {
+                    id: Uuid::new_v4().to_string(),
+                    message_id: id.clone(),
+                    peer_id,
+                    emoji: emoji.clone(),
+                    created_at: timestamp.clone(),
+                });
+            }
+        }
+
+        if discord.is_pinned {
+            history.pins.push(PinnedMessage {
+                id: Uuid::new_v4().to_string(),
+                channel_id: channel_id.to_string(),
+                message_id: id.clone(),
+                pinned_by: importer_peer_id.to_string(),
+                pinned_at: timestamp,
+            });
+        }
+    }
+    history
+}
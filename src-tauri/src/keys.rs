@@ -0,0 +1,292 @@
+//! Identity keypair storage, recovery phrases, signed key rotation, device
+//! certificates and bot certificates.
+
+use bip39::Mnemonic;
+use libp2p::identity::{ed25519, Keypair, PublicKey};
+
+use crate::models::{BotCertificate, DeviceCertificate, DeviceLinkRequest, DeviceRevocation, KeyRotation};
+
+/// Encode a keypair for `identity.keypair_bytes` (64-byte ed25519 secret + public).
+pub fn encode_keypair(keypair: &Keypair) -> Vec<u8> {
+    keypair
+        .clone()
+        .try_into_ed25519()
+        .expect("identity keys are ed25519")
+        .to_bytes()
+        .to_vec()
+}
+
+/// Decode `identity.keypair_bytes`. Accepts the stored 64-byte form and a bare
+/// 32-byte secret key.
+pub fn decode_keypair(bytes: &[u8]) -> Option<Keypair> {
+    let mut bytes = bytes.to_vec();
+    match bytes.len() {
+        64 => ed25519::Keypair::try_from_bytes(&mut bytes).ok().map(Keypair::from),
+        32 => Keypair::ed25519_from_bytes(bytes).ok(),
+        _ => None,
+    }
+}
+
+/// The 24-word BIP39 phrase encoding the 32-byte secret key.
+pub fn recovery_phrase(keypair: &Keypair) -> String {
+    let secret = keypair
+        .clone()
+        .try_into_ed25519()
+        .expect("identity keys are ed25519")
+        .secret();
+    Mnemonic::from_entropy(secret.as_ref())
+        .expect("32 bytes is valid BIP39 entropy")
+        .to_string()
+}
+
+pub fn keypair_from_phrase(phrase: &str) -> Result<Keypair, String> {
+    let normalized = phrase.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    let mnemonic = Mnemonic::parse_normalized(&normalized).map_err(|e| format!("Invalid recovery phrase: {}", e))?;
+    let entropy = mnemonic.to_entropy();
+    if entropy.len() != 32 {
+        return Err("Recovery phrase must be 24 words".to_string());
+    }
+    Keypair::ed25519_from_bytes(entropy).map_err(|e| e.to_string())
+}
+
+fn rotation_payload(old_peer_id: &str, new_peer_id: &str, rotated_at: &str) -> Vec<u8> {
+    format!("chatr-key-rotation\n{}\n{}\n{}", old_peer_id, new_peer_id, rotated_at).into_bytes()
+}
+
+/// Build a rotation statement from `old` to `new`, signed by both keys: the
+/// old key authorizes the move, the new key proves it is held by the same owner.
+pub fn sign_rotation(old: &Keypair, new: &Keypair) -> Result<KeyRotation, String> {
+    let old_peer_id = old.public().to_peer_id().to_string();
+    let new_peer_id = new.public().to_peer_id().to_string();
+    let rotated_at = chrono::Utc::now().to_rfc3339();
+    let payload = rotation_payload(&old_peer_id, &new_peer_id, &rotated_at);
+    Ok(KeyRotation {
+        old_signature: old.sign(&payload).map_err(|e| e.to_string())?,
+        new_signature: new.sign(&payload).map_err(|e| e.to_string())?,
+        old_public_key: old.public().encode_protobuf(),
+        new_public_key: new.public().encode_protobuf(),
+        old_peer_id,
+        new_peer_id,
+        rotated_at,
+    })
+}
+
+/// Check that `key` belongs to `peer_id` and signed `payload`.
+fn verify_signed(peer_id: &str, key: &[u8], payload: &[u8], signature: &[u8]) -> Result<(), String> {
+    let key = PublicKey::try_decode_protobuf(key).map_err(|e| e.to_string())?;
+    if key.to_peer_id().to_string() != peer_id {
+        return Err(format!("Public key does not match peer id {}", peer_id));
+    }
+    if !key.verify(payload, signature) {
+        return Err(format!("Invalid signature from {}", peer_id));
+    }
+    Ok(())
+}
+
+pub fn verify_rotation(rotation: &KeyRotation) -> Result<(), String> {
+    if rotation.old_peer_id == rotation.new_peer_id {
+        return Err("Key rotation to the same peer id".to_string());
+    }
+    let payload = rotation_payload(&rotation.old_peer_id, &rotation.new_peer_id, &rotation.rotated_at);
+    verify_signed(&rotation.old_peer_id, &rotation.old_public_key, &payload, &rotation.old_signature)?;
+    verify_signed(&rotation.new_peer_id, &rotation.new_public_key, &payload, &rotation.new_signature)
+}
+
+/// A link request for this device, to be certified by the primary.
+pub fn device_link_request(device: &Keypair, device_name: &str) -> DeviceLinkRequest {
+    DeviceLinkRequest {
+        device_peer_id: device.public().to_peer_id().to_string(),
+        device_public_key: device.public().encode_protobuf(),
+        device_name: device_name.to_string(),
+    }
+}
+
+fn certificate_payload(cert: &DeviceCertificate) -> Vec<u8> {
+    format!(
+        "chatr-device-certificate\n{}\n{}\n{}\n{}",
+        cert.identity_peer_id, cert.device_peer_id, cert.device_name, cert.issued_at
+    )
+    .into_bytes()
+}
+
+/// What the device signs: the identity's certificate, signature included,
+/// so the device agrees to act for exactly that identity.
+fn countersignature_payload(cert: &DeviceCertificate) -> Vec<u8> {
+    let mut payload = b"chatr-device-countersignature\n".to_vec();
+    payload.extend(certificate_payload(cert));
+    payload.push(b'\n');
+    payload.extend(&cert.signature);
+    payload
+}
+
+pub fn sign_device_certificate(identity: &Keypair, request: &DeviceLinkRequest) -> Result<DeviceCertificate, String> {
+    let device_key = PublicKey::try_decode_protobuf(&request.device_public_key).map_err(|e| e.to_string())?;
+    if device_key.to_peer_id().to_string() != request.device_peer_id {
+        return Err("Device public key does not match its peer id".to_string());
+    }
+    let mut cert = DeviceCertificate {
+        identity_peer_id: identity.public().to_peer_id().to_string(),
+        device_peer_id: request.device_peer_id.clone(),
+        identity_public_key: identity.public().encode_protobuf(),
+        device_public_key: request.device_public_key.clone(),
+        device_name: request.device_name.clone(),
+        issued_at: chrono::Utc::now().to_rfc3339(),
+        signature: Vec::new(),
+        device_signature: Vec::new(),
+    };
+    if cert.device_peer_id == cert.identity_peer_id {
+        return Err("A device can't certify itself".to_string());
+    }
+    cert.signature = identity.sign(&certificate_payload(&cert)).map_err(|e| e.to_string())?;
+    Ok(cert)
+}
+
+/// Sign a certificate issued for `device`, completing it. Without this
+/// anyone could certify a peer id they don't hold as one of their devices.
+pub fn countersign_device_certificate(device: &Keypair, cert: &mut DeviceCertificate) -> Result<(), String> {
+    if device.public().to_peer_id().to_string() != cert.device_peer_id {
+        return Err("Certificate was issued for a different device".to_string());
+    }
+    cert.device_signature = device.sign(&countersignature_payload(cert)).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Check both signatures: the identity's and the device's countersignature.
+pub fn verify_device_certificate(cert: &DeviceCertificate) -> Result<(), String> {
+    verify_signed(&cert.identity_peer_id, &cert.identity_public_key, &certificate_payload(cert), &cert.signature)?;
+    verify_signed(&cert.device_peer_id, &cert.device_public_key, &countersignature_payload(cert), &cert.device_signature)
+        .map_err(|e| format!("Device has not countersigned the certificate: {}", e))
+}
+
+fn revocation_payload(identity_peer_id: &str, device_peer_id: &str, revoked_at: &str) -> Vec<u8> {
+    format!("chatr-device-revocation\n{}\n{}\n{}", identity_peer_id, device_peer_id, revoked_at).into_bytes()
+}
+
+pub fn sign_device_revocation(identity: &Keypair, device_peer_id: &str) -> Result<DeviceRevocation, String> {
+    let identity_peer_id = identity.public().to_peer_id().to_string();
+    let revoked_at = chrono::Utc::now().to_rfc3339();
+    let payload = revocation_payload(&identity_peer_id, device_peer_id, &revoked_at);
+    Ok(DeviceRevocation {
+        signature: identity.sign(&payload).map_err(|e| e.to_string())?,
+        identity_public_key: identity.public().encode_protobuf(),
+        identity_peer_id,
+        device_peer_id: device_peer_id.to_string(),
+        revoked_at,
+    })
+}
+
+pub fn verify_device_revocation(revocation: &DeviceRevocation) -> Result<(), String> {
+    let payload = revocation_payload(&revocation.identity_peer_id, &revocation.device_peer_id, &revocation.revoked_at);
+    verify_signed(&revocation.identity_peer_id, &revocation.identity_public_key, &payload, &revocation.signature)
+}
+
+fn bot_certificate_payload(bot_peer_id: &str, host_peer_id: &str, issued_at: &str) -> Vec<u8> {
+    format!("chatr-bot-certificate\n{}\n{}\n{}", bot_peer_id, host_peer_id, issued_at).into_bytes()
+}
+
+/// Let `host_peer_id` publish on behalf of the bot.
+pub fn sign_bot_certificate(bot: &Keypair, host_peer_id: &str) -> Result<BotCertificate, String> {
+    let bot_peer_id = bot.public().to_peer_id().to_string();
+    let issued_at = chrono::Utc::now().to_rfc3339();
+    let payload = bot_certificate_payload(&bot_peer_id, host_peer_id, &issued_at);
+    Ok(BotCertificate {
+        signature: bot.sign(&payload).map_err(|e| e.to_string())?,
+        bot_public_key: bot.public().encode_protobuf(),
+        bot_peer_id,
+        host_peer_id: host_peer_id.to_string(),
+        issued_at,
+    })
+}
+
+pub fn verify_bot_certificate(cert: &BotCertificate) -> Result<(), String> {
+    let payload = bot_certificate_payload(&cert.bot_peer_id, &cert.host_peer_id, &cert.issued_at);
+    verify_signed(&cert.bot_peer_id, &cert.bot_public_key, &payload, &cert.signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovery_phrase_round_trips() {
+        let keypair = Keypair::generate_ed25519();
+        let phrase = recovery_phrase(&keypair);
+        assert_eq!(phrase.split_whitespace().count(), 24);
+
+        let restored = keypair_from_phrase(&phrase).unwrap();
+        assert_eq!(restored.public(), keypair.public());
+        assert_eq!(encode_keypair(&restored), encode_keypair(&keypair));
+        // Case and spacing don't matter
+        let messy = format!("  {}\n", phrase.to_uppercase().replace(' ', "   "));
+        assert_eq!(keypair_from_phrase(&messy).unwrap().public(), keypair.public());
+    }
+
+    #[test]
+    fn recovery_phrase_known_answer() {
+        // All-zero entropy: 23 times "abandon" and the checksum word "art"
+        let phrase = format!("{} art", ["abandon"; 23].join(" "));
+        let keypair = keypair_from_phrase(&phrase).unwrap();
+        assert_eq!(keypair.public(), Keypair::ed25519_from_bytes([0u8; 32]).unwrap().public());
+        assert_eq!(recovery_phrase(&keypair), phrase);
+
+        // Wrong checksum word, too short, not BIP39 words
+        assert!(keypair_from_phrase(&["abandon"; 24].join(" ")).is_err());
+        assert!(keypair_from_phrase(&format!("{} about", ["abandon"; 11].join(" "))).is_err());
+        assert!(keypair_from_phrase("not a recovery phrase").is_err());
+    }
+
+    #[test]
+    fn rotation_is_signed_by_both_keys() {
+        let old = Keypair::generate_ed25519();
+        let new = Keypair::generate_ed25519();
+        let rotation = sign_rotation(&old, &new).unwrap();
+        assert_eq!(rotation.old_peer_id, old.public().to_peer_id().to_string());
+        assert_eq!(rotation.new_peer_id, new.public().to_peer_id().to_string());
+        verify_rotation(&rotation).unwrap();
+
+        let mut redirected = rotation.clone();
+        redirected.new_peer_id = Keypair::generate_ed25519().public().to_peer_id().to_string();
+        assert!(verify_rotation(&redirected).is_err());
+
+        let mut backdated = rotation.clone();
+        backdated.rotated_at = "2000-01-01T00:00:00+00:00".to_string();
+        assert!(verify_rotation(&backdated).is_err());
+
+        // The new key's signature alone can't claim someone else's identity
+        let mut hijacked = rotation.clone();
+        let attacker = Keypair::generate_ed25519();
+        hijacked.old_public_key = attacker.public().encode_protobuf();
+        hijacked.old_signature = attacker
+            .sign(&rotation_payload(&rotation.old_peer_id, &rotation.new_peer_id, &rotation.rotated_at))
+            .unwrap();
+        assert!(verify_rotation(&hijacked).is_err());
+
+        assert!(sign_rotation(&old, &old).and_then(|r| verify_rotation(&r)).is_err());
+    }
+
+    #[test]
+    fn device_certificate_needs_the_device_countersignature() {
+        let identity = Keypair::generate_ed25519();
+        let device = Keypair::generate_ed25519();
+        let mut cert = sign_device_certificate(&identity, &device_link_request(&device, "Laptop")).unwrap();
+        assert!(verify_device_certificate(&cert).is_err());
+
+        countersign_device_certificate(&device, &mut cert).unwrap();
+        verify_device_certificate(&cert).unwrap();
+
+        let mut renamed = cert.clone();
+        renamed.device_name = "Phone".to_string();
+        assert!(verify_device_certificate(&renamed).is_err());
+    }
+
+    #[test]
+    fn cannot_certify_someone_elses_peer_id() {
+        let attacker = Keypair::generate_ed25519();
+        let victim = Keypair::generate_ed25519();
+        // Everything in a link request can be derived from the victim's peer id
+        let mut cert = sign_device_certificate(&attacker, &device_link_request(&victim, "Victim")).unwrap();
+        assert!(countersign_device_certificate(&attacker, &mut cert).is_err());
+        cert.device_signature = attacker.sign(&countersignature_payload(&cert)).unwrap();
+        assert!(verify_device_certificate(&cert).is_err());
+    }
+}
@@ -1,13 +1,21 @@
 mod api;
+mod backup;
+pub mod cli;
 mod commands;
-mod db;
+pub mod db;
 mod events;
+mod export;
+mod import;
+mod keys;
 pub mod media;
 mod models;
 mod network;
 mod services;
 mod state;
+mod tui;
 
+use std::io::{BufRead, Write};
+use std::path::Path;
 use std::sync::Arc;
 use tokio::sync::{mpsc, watch};
 use directories::ProjectDirs;
@@ -15,11 +23,11 @@ use libp2p::identity::Keypair;
 use tracing::info;
 use tauri::{Emitter, Manager};
 
-use crate::db::Database;
-use crate::events::{AppEvent, create_event_bus};
+use crate::db::{Database, OpenError};
+use crate::events::{AppEvent, JournalWriter, create_event_bus};
 use crate::media::{MediaCommand, VoiceState};
 use crate::media::frame_server::FrameServerState;
-use crate::state::{AppState, ServiceContext};
+use crate::state::{AppState, LockState, ServiceContext};
 
 fn get_data_dir(custom_dir: Option<&str>) -> std::path::PathBuf {
     if let Some(dir) = custom_dir {
@@ -31,46 +39,234 @@ fn get_data_dir(custom_dir: Option<&str>) -> std::path::PathBuf {
     }
 }
 
+/// Load the identity keypair, generating one on first run. A keypair that
+/// exists but can't be decoded is an error: replacing it would silently lose
+/// the peer id.
 fn get_or_create_keypair(db: &Database) -> Keypair {
-    if let Ok(Some(bytes)) = db.load_keypair() {
-        if let Ok(kp) = Keypair::ed25519_from_bytes(bytes) {
-            info!("Loaded existing keypair");
-            return kp;
-        }
+    if let Some(bytes) = db.load_keypair().expect("Failed to read identity keypair") {
+        let kp = keys::decode_keypair(&bytes)
+            .expect("Stored identity keypair is corrupt; restore it with `chatr --import-identity`");
+        info!("Loaded existing keypair");
+        return kp;
     }
 
     let kp = Keypair::generate_ed25519();
-    let bytes = kp.clone().try_into_ed25519().unwrap().to_bytes().to_vec();
-    db.save_keypair(&bytes).expect("Failed to save keypair");
+    db.save_keypair(&keys::encode_keypair(&kp)).expect("Failed to save keypair");
     info!("Generated new keypair");
+    eprintln!(
+        "New identity created. Write down this recovery phrase; it is the only way to restore it:\n\n    {}\n",
+        keys::recovery_phrase(&kp)
+    );
     kp
 }
 
+/// Report a failed one-shot command the way `chatr <command>` does, and exit.
+fn exit_with_error(message: impl std::fmt::Display) -> ! {
+    eprintln!("chatr: {}", message);
+    std::process::exit(1);
+}
+
+/// Archive passphrase from `CHATR_BACKUP_PASSPHRASE`, or else read from stdin.
+fn backup_passphrase() -> String {
+    let passphrase = std::env::var(backup::BACKUP_PASSPHRASE_ENV).ok().filter(|p| !p.is_empty());
+    std::env::remove_var(backup::BACKUP_PASSPHRASE_ENV);
+    passphrase.or_else(|| {
+        eprint!("Backup passphrase: ");
+        std::io::stderr().flush().ok();
+        let mut line = String::new();
+        match std::io::stdin().lock().read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(line.trim_end_matches(['\r', '\n']).to_string()),
+        }
+    })
+    .unwrap_or_else(|| exit_with_error(format!("Set {} or pass the backup passphrase on stdin", backup::BACKUP_PASSPHRASE_ENV)))
+}
+
+/// Write an encrypted backup of the node to `dest`, then exit. Used by `chatr --backup`.
+pub fn backup_node(data_dir: Option<&str>, dest: &str) {
+    tracing_subscriber::fmt::init();
+
+    let data_dir = get_data_dir(data_dir);
+    let db = open_database_headless(&data_dir);
+    let passphrase = backup_passphrase();
+    match backup::create_backup(&db, Path::new(dest), &passphrase) {
+        Ok(manifest) => println!("Backed up {} (schema v{}) to {}", manifest.peer_id, manifest.schema_version, dest),
+        Err(e) => exit_with_error(format!("Backup failed: {}", e)),
+    }
+}
+
+/// Restore an encrypted backup into the data directory, then exit. Used by
+/// `chatr --restore`. The restored database is encrypted with
+/// `CHATR_PASSPHRASE` if it is set.
+pub fn restore_node(data_dir: Option<&str>, src: &str) {
+    tracing_subscriber::fmt::init();
+
+    let data_dir = get_data_dir(data_dir);
+    let db_passphrase = passphrase_from_env();
+    let passphrase = backup_passphrase();
+    match backup::restore_backup(Path::new(src), &data_dir, &passphrase, db_passphrase.as_deref()) {
+        Ok(manifest) => println!(
+            "Restored {} from backup taken {} into {}",
+            manifest.peer_id,
+            manifest.created_at,
+            data_dir.display()
+        ),
+        Err(e) => exit_with_error(format!("Restore failed: {}", e)),
+    }
+}
+
+/// Export a room's history (or one channel of it) to `output`, or stdout if
+/// not given, then exit. Used by `chatr --export`.
+pub fn export_room(
+    data_dir: Option<&str>,
+    room_id: &str,
+    channel_id: Option<&str>,
+    format: &str,
+    output: Option<&str>,
+) {
+    // Logs go to stderr so the export can be piped
+    tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+
+    let format: models::ExportFormat = format.parse().unwrap_or_else(|e: String| exit_with_error(e));
+    let data_dir = get_data_dir(data_dir);
+    let db = open_database_headless(&data_dir);
+    let rendered = export::collect(&db, room_id, channel_id)
+        .and_then(|export| export::render(&export, format))
+        .unwrap_or_else(|e| exit_with_error(format!("Export failed: {}", e)));
+    match output {
+        Some(path) => {
+            std::fs::write(path, rendered).unwrap_or_else(|e| exit_with_error(format!("Failed to write {}: {}", path, e)));
+            eprintln!("Exported room {} to {}", room_id, path);
+        }
+        None => print!("{}", rendered),
+    }
+}
+
+/// Issue a local API token and print its secret, then exit. Used by
+/// `chatr --create-token`, mainly for headless nodes.
+pub fn create_api_token(data_dir: Option<&str>, name: &str, scopes: &str) {
+    tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+
+    let scopes = scopes
+        .split(',')
+        .map(|s| s.trim().parse::<models::ApiScope>())
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap_or_else(|e| exit_with_error(e));
+    let data_dir = get_data_dir(data_dir);
+    let db = open_database_headless(&data_dir);
+    let issued = api::auth::issue_token(&db, name, scopes, None)
+        .unwrap_or_else(|e| exit_with_error(format!("Failed to issue token: {}", e)));
+    eprintln!("Issued token {} ({}); it will not be shown again", issued.token.id, issued.token.name);
+    println!("{}", issued.secret);
+}
+
+/// Replace the identity with one restored from a recovery phrase read from
+/// stdin, then exit. Used by `chatr --import-identity`; an existing identity
+/// is only replaced with `--overwrite`.
+pub fn import_identity(data_dir: Option<&str>, overwrite: bool) {
+    tracing_subscriber::fmt::init();
+
+    let data_dir = get_data_dir(data_dir);
+    let db = open_database_headless(&data_dir);
+    match db.load_keypair() {
+        Ok(Some(_)) if !overwrite => {
+            exit_with_error("This node already has an identity; pass --overwrite to replace it")
+        }
+        Ok(_) => {}
+        Err(e) => exit_with_error(format!("Failed to read identity keypair: {}", e)),
+    }
+
+    eprint!("Recovery phrase: ");
+    std::io::stderr().flush().ok();
+    let mut phrase = String::new();
+    if let Err(e) = std::io::stdin().lock().read_line(&mut phrase) {
+        exit_with_error(format!("Failed to read recovery phrase: {}", e));
+    }
+
+    let kp = keys::keypair_from_phrase(&phrase).unwrap_or_else(|e| exit_with_error(e));
+    match services::identity::replace_identity(&db, &kp) {
+        Ok(Some(rotation)) => println!(
+            "Imported identity {}; {} was kept and peers will learn of the rotation on next start",
+            rotation.new_peer_id, rotation.old_peer_id
+        ),
+        Ok(None) => println!("Imported identity {}", kp.public().to_peer_id()),
+        Err(e) => exit_with_error(format!("Failed to import identity: {}", e)),
+    }
+}
+
+/// Passphrase from `CHATR_PASSPHRASE`. It is removed from the environment so
+/// child processes don't inherit it.
+fn passphrase_from_env() -> Option<String> {
+    let passphrase = std::env::var(db::encryption::PASSPHRASE_ENV)
+        .ok()
+        .filter(|p| !p.is_empty());
+    std::env::remove_var(db::encryption::PASSPHRASE_ENV);
+    passphrase
+}
+
+/// Read a passphrase line from stdin, prompting on stderr.
+fn read_passphrase_from_stdin() -> Option<String> {
+    eprint!("Database passphrase: ");
+    std::io::stderr().flush().ok();
+    let mut line = String::new();
+    match std::io::stdin().lock().read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim_end_matches(['\r', '\n']).to_string()),
+    }
+}
+
+/// Open the database in headless mode. An encrypted database is unlocked
+/// with `CHATR_PASSPHRASE`, or else with a passphrase read from stdin.
+fn open_database_headless(data_dir: &Path) -> Database {
+    const ATTEMPTS: usize = 3;
+
+    let mut passphrase = passphrase_from_env();
+    let mut attempts = 0;
+    loop {
+        match Database::open(data_dir, passphrase.as_deref()) {
+            Ok(db) => return db,
+            Err(e @ (OpenError::Locked | OpenError::WrongPassphrase)) if attempts < ATTEMPTS => {
+                if passphrase.is_some() {
+                    eprintln!("{}", e);
+                }
+                attempts += 1;
+                passphrase = Some(read_passphrase_from_stdin().unwrap_or_else(|| {
+                    exit_with_error(format!(
+                        "Database is encrypted: set {} or pass the passphrase on stdin",
+                        db::encryption::PASSPHRASE_ENV
+                    ))
+                }));
+            }
+            Err(e) => exit_with_error(format!("Failed to initialize database: {}", e)),
+        }
+    }
+}
+
 /// Create a ServiceContext with all shared state.
 fn create_service_context(
-    data_dir: Option<&str>,
+    db: Database,
+    data_dir: &Path,
 ) -> (
     ServiceContext,
     Keypair,
     mpsc::Receiver<network::NetworkCommand>,
     mpsc::Receiver<MediaCommand>,
     watch::Sender<VoiceState>,
+    Option<JournalWriter>,
 ) {
-    let data_dir = get_data_dir(data_dir);
-    info!("Data directory: {:?}", data_dir);
-
-    let db = Arc::new(Database::new(&data_dir).expect("Failed to initialize database"));
+    let db = Arc::new(db);
     let keypair = get_or_create_keypair(&db);
     let peer_id = libp2p::PeerId::from(keypair.public()).to_string();
     info!("My peer ID: {}", peer_id);
 
     let (network_tx, network_rx) = mpsc::channel::<network::NetworkCommand>(256);
-    let (event_tx, _event_rx) = create_event_bus();
+    let (event_tx, _event_rx, journal_writer) = create_event_bus(Some(db.clone()));
     let (media_tx, media_rx) = mpsc::channel::<MediaCommand>(64);
     let (voice_state_tx, voice_state_rx) = watch::channel(VoiceState::default());
 
     let ctx = ServiceContext {
         db,
+        data_dir: data_dir.to_path_buf(),
         peer_id,
         network_tx,
         peers: Default::default(),
@@ -80,7 +276,7 @@ fn create_service_context(
         voice_state_rx,
     };
 
-    (ctx, keypair, network_rx, media_rx, voice_state_tx)
+    (ctx, keypair, network_rx, media_rx, voice_state_tx, journal_writer)
 }
 
 /// Spawn the network swarm event loop.
@@ -255,11 +451,11 @@ fn spawn_tauri_event_bridge(app_handle: tauri::AppHandle, ctx: &ServiceContext)
                                 "peer_id": peer_id, "speaking": speaking,
                             }))
                         }
-                        AppEvent::ChannelCreated { room_id, channel_id, name, channel_type, created_at } => {
+                        AppEvent::ChannelCreated { room_id, channel_id, name, channel_type, created_at, category_id } => {
                             app_handle.emit("channel-created", serde_json::json!({
                                 "room_id": room_id, "channel_id": channel_id,
                                 "name": name, "channel_type": channel_type,
-                                "created_at": created_at,
+                                "created_at": created_at, "category_id": category_id,
                             }))
                         }
                         AppEvent::ChannelDeleted { room_id, channel_id } => {
@@ -267,6 +463,56 @@ fn spawn_tauri_event_bridge(app_handle: tauri::AppHandle, ctx: &ServiceContext)
                                 "room_id": room_id, "channel_id": channel_id,
                             }))
                         }
+                        AppEvent::CategoryUpserted(category) => app_handle.emit("category-upserted", category),
+                        AppEvent::CategoryDeleted { room_id, category_id } => {
+                            app_handle.emit("category-deleted", serde_json::json!({
+                                "room_id": room_id, "category_id": category_id,
+                            }))
+                        }
+                        AppEvent::ChannelLayoutChanged { room_id, channel_id, category_id, position, inherit_permissions } => {
+                            app_handle.emit("channel-layout-changed", serde_json::json!({
+                                "room_id": room_id, "channel_id": channel_id,
+                                "category_id": category_id, "position": position,
+                                "inherit_permissions": inherit_permissions,
+                            }))
+                        }
+                        AppEvent::ThreadCreated(thread) => app_handle.emit("thread-created", thread),
+                        AppEvent::ThreadReply { thread_id, message } => {
+                            app_handle.emit("thread-reply", serde_json::json!({
+                                "thread_id": thread_id, "message": message,
+                            }))
+                        }
+                        AppEvent::NotificationRaised(notification) => app_handle.emit("notification-raised", notification),
+                        AppEvent::PeerKeyRotated { old_peer_id, new_peer_id } => {
+                            app_handle.emit("peer-key-rotated", serde_json::json!({
+                                "old_peer_id": old_peer_id, "new_peer_id": new_peer_id,
+                            }))
+                        }
+                        AppEvent::DeviceLinked(certificate) => app_handle.emit("device-linked", certificate),
+                        AppEvent::DeviceUnlinked { identity_peer_id, device_peer_id } => {
+                            app_handle.emit("device-unlinked", serde_json::json!({
+                                "identity_peer_id": identity_peer_id, "device_peer_id": device_peer_id,
+                            }))
+                        }
+                        AppEvent::RetentionPolicyUpdated(policy) => {
+                            app_handle.emit("retention-policy-updated", &policy)
+                        }
+                        AppEvent::MessagesExpired { channel_id, message_ids } => {
+                            app_handle.emit("messages-expired", serde_json::json!({
+                                "channel_id": channel_id, "message_ids": message_ids,
+                            }))
+                        }
+                        AppEvent::DmMessagesExpired { conversation_id, message_ids } => {
+                            app_handle.emit("dm-messages-expired", serde_json::json!({
+                                "conversation_id": conversation_id, "message_ids": message_ids,
+                            }))
+                        }
+                        AppEvent::MessagesPurged { room_id, sender_peer_id, message_ids } => {
+                            app_handle.emit("messages-purged", serde_json::json!({
+                                "room_id": room_id, "sender_peer_id": sender_peer_id, "message_ids": message_ids,
+                            }))
+                        }
+                        AppEvent::CommandInvoked(invocation) => app_handle.emit("command-invoked", invocation),
                     };
                     if let Err(e) = result {
                         tracing::warn!("Failed to emit Tauri event: {}", e);
@@ -283,6 +529,48 @@ fn spawn_tauri_event_bridge(app_handle: tauri::AppHandle, ctx: &ServiceContext)
     });
 }
 
+/// Start everything that needs the database: network, media engine, event
+/// bridge and API server. Runs from `setup`, or from `unlock` once the user
+/// has entered the passphrase.
+fn start_services(app: &tauri::AppHandle, db: Database, data_dir: &Path, api_port: u16) {
+    let (ctx, keypair, network_rx, media_rx, voice_state_tx, journal_writer) = create_service_context(db, data_dir);
+
+    // Spawn the event journal writer before anything can send events
+    if let Some(writer) = journal_writer {
+        tauri::async_runtime::spawn(writer.run());
+    }
+
+    // The GUI gets a fresh token for the local API on every start
+    let api_token = api::auth::issue_gui_token(&ctx.db).expect("Failed to issue API token");
+
+    // Manage Tauri state
+    app.manage(AppState { ctx: ctx.clone(), api_port, api_token });
+
+    // Spawn network
+    spawn_network(keypair, network_rx, &ctx);
+
+    // Create frame server state (shared between media engine and API)
+    let frame_server = FrameServerState::new();
+
+    // Spawn media engine
+    spawn_media_engine(media_rx, voice_state_tx, frame_server.clone(), &ctx);
+
+    // Spawn Tauri event bridge
+    spawn_tauri_event_bridge(app.clone(), &ctx);
+
+    // Spawn retention pruner
+    tauri::async_runtime::spawn(services::retention::run_pruner(ctx.clone()));
+    tauri::async_runtime::spawn(services::webhooks::run_dispatcher(ctx.clone()));
+    tauri::async_runtime::spawn(services::commands::run_reminders(ctx.clone()));
+
+    // Spawn API server (with frame server routes)
+    let api_ctx = ctx.clone();
+    let api_frame_server = frame_server.clone();
+    tauri::async_runtime::spawn(async move {
+        api::server::start_api_server(api_ctx, api_port, api_frame_server).await;
+    });
+}
+
 /// Run the GUI application (Tauri + API server).
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -297,37 +585,32 @@ pub fn run_with_opts(data_dir: Option<&str>, api_port: u16) {
 
     tauri::Builder::default()
         .setup(move |app| {
-            let app_handle = app.handle().clone();
-
-            let (ctx, keypair, network_rx, media_rx, voice_state_tx) =
-                create_service_context(data_dir_owned.as_deref());
-
-            // Manage Tauri state
-            app.manage(AppState { ctx: ctx.clone(), api_port });
+            let data_dir = get_data_dir(data_dir_owned.as_deref());
+            info!("Data directory: {:?}", data_dir);
 
-            // Spawn network
-            spawn_network(keypair, network_rx, &ctx);
-
-            // Create frame server state (shared between media engine and API)
-            let frame_server = FrameServerState::new();
-
-            // Spawn media engine
-            spawn_media_engine(media_rx, voice_state_tx, frame_server.clone(), &ctx);
-
-            // Spawn Tauri event bridge
-            spawn_tauri_event_bridge(app_handle, &ctx);
-
-            // Spawn API server (with frame server routes)
-            let api_ctx = ctx.clone();
-            let api_frame_server = frame_server.clone();
-            tauri::async_runtime::spawn(async move {
-                api::server::start_api_server(api_ctx, api_port, api_frame_server).await;
+            app.manage(LockState {
+                data_dir: data_dir.clone(),
+                api_port,
+                unlocking: Default::default(),
             });
 
+            // An encrypted database without CHATR_PASSPHRASE waits for the
+            // frontend to call `unlock`
+            match Database::open(&data_dir, passphrase_from_env().as_deref()) {
+                Ok(db) => start_services(app.handle(), db, &data_dir, api_port),
+                Err(e @ (OpenError::Locked | OpenError::WrongPassphrase)) => {
+                    info!("Database locked ({}), waiting for passphrase", e);
+                }
+                Err(e) => return Err(e.into()),
+            }
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
+            commands::identity::is_locked,
+            commands::identity::unlock,
             commands::identity::get_api_port,
+            commands::identity::get_api_token,
             commands::identity::get_my_peer_id,
             commands::identity::get_identity,
             commands::identity::get_display_name,
@@ -363,7 +646,16 @@ pub fn run_with_opts(data_dir: Option<&str>, api_port: u16) {
 pub async fn run_headless(data_dir: Option<&str>, api_port: u16) {
     tracing_subscriber::fmt::init();
 
-    let (ctx, keypair, network_rx, media_rx, voice_state_tx) = create_service_context(data_dir);
+    let data_dir = get_data_dir(data_dir);
+    info!("Data directory: {:?}", data_dir);
+    let db = open_database_headless(&data_dir);
+
+    let (ctx, keypair, network_rx, media_rx, voice_state_tx, journal_writer) = create_service_context(db, &data_dir);
+
+    // Spawn the event journal writer before anything can send events
+    if let Some(writer) = journal_writer {
+        tokio::spawn(writer.run());
+    }
 
     // Spawn network (tokio::spawn since we have our own runtime in headless mode)
     let db = ctx.db.clone();
@@ -396,6 +688,11 @@ pub async fn run_headless(data_dir: Option<&str>, api_port: u16) {
         .await;
     });
 
+    // Spawn retention pruner
+    tokio::spawn(services::retention::run_pruner(ctx.clone()));
+    tokio::spawn(services::webhooks::run_dispatcher(ctx.clone()));
+    tokio::spawn(services::commands::run_reminders(ctx.clone()));
+
     info!("Running in headless mode");
 
     // Run API server (blocks until shutdown)
@@ -6,23 +6,92 @@ use clap::Parser;
 #[derive(Parser)]
 #[command(name = "chatr", about = "P2P Decentralized Chat")]
 struct Cli {
-    /// Run without GUI (API server only)
+    /// Talk to a running node instead of starting one
+    #[command(subcommand)]
+    command: Option<chatr_lib::cli::Command>,
+
+    #[command(flatten)]
+    api: chatr_lib::cli::ApiArgs,
+
+    /// Run without GUI (API server only). An encrypted database is unlocked
+    /// with CHATR_PASSPHRASE, or a passphrase read from stdin
     #[arg(long)]
     headless: bool,
 
-    /// API server port
-    #[arg(long, default_value = "9847")]
+    /// API server port; also the port commands connect to
+    #[arg(long, default_value = "9847", global = true)]
     port: u16,
 
     /// Custom data directory
     #[arg(long)]
     data_dir: Option<String>,
+
+    /// Restore an identity from a recovery phrase read from stdin, then exit
+    #[arg(long)]
+    import_identity: bool,
+
+    /// Let --import-identity replace this node's existing identity. The old
+    /// key is kept and a signed rotation to the imported one is announced
+    #[arg(long, requires = "import_identity")]
+    overwrite: bool,
+
+    /// Write an encrypted backup of this node to PATH, then exit. The archive
+    /// passphrase comes from CHATR_BACKUP_PASSPHRASE or stdin
+    #[arg(long, value_name = "PATH", conflicts_with = "restore")]
+    backup: Option<String>,
+
+    /// Restore an encrypted backup from PATH into an empty data directory, then exit
+    #[arg(long, value_name = "PATH")]
+    restore: Option<String>,
+
+    /// Export the history of ROOM_ID, then exit
+    #[arg(long, value_name = "ROOM_ID")]
+    export: Option<String>,
+
+    /// Export only this channel of the room
+    #[arg(long, value_name = "CHANNEL_ID", requires = "export")]
+    channel: Option<String>,
+
+    /// Export format: json, html or markdown
+    #[arg(long, default_value = "json", requires = "export")]
+    format: String,
+
+    /// Write the export to PATH instead of stdout
+    #[arg(long, short, value_name = "PATH", requires = "export")]
+    output: Option<String>,
+
+    /// Issue a local API token called NAME, print it, then exit. Extra CORS
+    /// origins for the API can be allowed with CHATR_API_ORIGINS
+    #[arg(long, value_name = "NAME")]
+    create_token: Option<String>,
+
+    /// Comma-separated token scopes: read, write, admin
+    #[arg(long, default_value = "read,write", requires = "create_token")]
+    scopes: String,
 }
 
 fn main() {
     let cli = Cli::parse();
 
-    if cli.headless {
+    if let Some(command) = cli.command {
+        chatr_lib::cli::run(cli.api, cli.port, command);
+    } else if let Some(dest) = cli.backup.as_deref() {
+        chatr_lib::backup_node(cli.data_dir.as_deref(), dest);
+    } else if let Some(src) = cli.restore.as_deref() {
+        chatr_lib::restore_node(cli.data_dir.as_deref(), src);
+    } else if let Some(room_id) = cli.export.as_deref() {
+        chatr_lib::export_room(
+            cli.data_dir.as_deref(),
+            room_id,
+            cli.channel.as_deref(),
+            &cli.format,
+            cli.output.as_deref(),
+        );
+    } else if let Some(name) = cli.create_token.as_deref() {
+        chatr_lib::create_api_token(cli.data_dir.as_deref(), name, &cli.scopes);
+    } else if cli.import_identity {
+        chatr_lib::import_identity(cli.data_dir.as_deref(), cli.overwrite);
+    } else if cli.headless {
         let rt = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
         rt.block_on(chatr_lib::run_headless(
             cli.data_dir.as_deref(),
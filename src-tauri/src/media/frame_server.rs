@@ -151,7 +151,6 @@ async fn serve_mjpeg_stream(
     axum::response::Response::builder()
         .header("Content-Type", "multipart/x-mixed-replace; boundary=frame")
         .header("Cache-Control", "no-cache, no-store, must-revalidate")
-        .header("Access-Control-Allow-Origin", "*")
         .body(axum::body::Body::from_stream(stream))
         .unwrap()
 }
@@ -182,7 +181,6 @@ async fn serve_single_frame(
         Some(jpeg_data) => axum::response::Response::builder()
             .header("Content-Type", "image/jpeg")
             .header("Cache-Control", "no-cache, no-store, must-revalidate")
-            .header("Access-Control-Allow-Origin", "*")
             .body(axum::body::Body::from(jpeg_data.as_ref().clone()))
             .unwrap(),
         None => axum::response::Response::builder()
@@ -1,10 +1,13 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 // ============================================================
 // Core Models (Phase 0)
 // ============================================================
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Message {
     pub id: String,
     pub channel_id: String,
@@ -18,9 +21,51 @@ pub struct Message {
     pub deleted_at: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reply_to_id: Option<String>,
+    /// Root message id of the thread this message was posted in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_id: Option<String>,
+    /// Reply count and last-reply metadata, set on thread root messages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread: Option<ThreadSummary>,
+    /// Service the message was imported from (e.g. "discord"). Imported
+    /// messages are local history only and are never broadcast.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub imported_from: Option<String>,
+    /// Incoming webhook that posted the message. `sender_display_name` is
+    /// then the webhook's name (or the payload's username override).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub embeds: Vec<MessageEmbed>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Rich content block attached to webhook messages.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct MessageEmbed {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// RGB colour of the embed's accent bar, e.g. `0x2ecc71`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<EmbedField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub footer: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EmbedField {
+    pub name: String,
+    pub value: String,
+    #[serde(default)]
+    pub inline: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Room {
     pub id: String,
     pub name: String,
@@ -30,7 +75,7 @@ pub struct Room {
     pub owner_peer_id: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Channel {
     pub id: String,
     pub room_id: String,
@@ -42,20 +87,65 @@ pub struct Channel {
     pub topic: Option<String>,
     #[serde(default)]
     pub position: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category_id: Option<String>,
+    /// When true (and the channel is in a category), the category's permissions apply.
+    #[serde(default = "default_true")]
+    pub inherit_permissions: bool,
+    /// JSON-encoded permission overwrites, interpreted by clients.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<String>,
 }
 
 fn default_channel_type() -> String {
     "text".to_string()
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+fn default_true() -> bool {
+    true
+}
+
+/// Collapsible group of text and voice channels within a room.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ChannelCategory {
+    pub id: String,
+    pub room_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub position: i32,
+    pub created_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<String>,
+    /// Local UI state, never synced to peers.
+    #[serde(default)]
+    pub collapsed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CategoryLayout {
+    #[serde(flatten)]
+    pub category: ChannelCategory,
+    pub channels: Vec<Channel>,
+}
+
+/// Nested channel layout of a room: ordered categories plus channels outside any category.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ChannelLayout {
+    pub room_id: String,
+    pub uncategorized: Vec<Channel>,
+    pub categories: Vec<CategoryLayout>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PeerInfo {
     pub peer_id: String,
     pub display_name: String,
     pub is_online: bool,
+    #[serde(default)]
+    pub is_bot: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Identity {
     pub peer_id: String,
     pub display_name: String,
@@ -67,11 +157,475 @@ pub struct Identity {
     pub status_type: Option<String>,
 }
 
+/// Statement that `old_peer_id` now goes by `new_peer_id`, signed by both
+/// keys. Public keys are protobuf-encoded libp2p keys.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct KeyRotation {
+    pub old_peer_id: String,
+    pub new_peer_id: String,
+    pub old_public_key: Vec<u8>,
+    pub new_public_key: Vec<u8>,
+    pub rotated_at: String,
+    pub old_signature: Vec<u8>,
+    pub new_signature: Vec<u8>,
+}
+
 // ============================================================
-// Phase 1: Reactions, Read Receipts, Search
+// Multi-device
 // ============================================================
 
+/// Shown by a new device so the primary can certify its key.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeviceLinkRequest {
+    pub device_peer_id: String,
+    pub device_public_key: Vec<u8>,
+    pub device_name: String,
+}
+
+/// Signed by the identity key and countersigned by the device key:
+/// `device_peer_id` acts on behalf of `identity_peer_id`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeviceCertificate {
+    pub identity_peer_id: String,
+    pub device_peer_id: String,
+    pub identity_public_key: Vec<u8>,
+    pub device_public_key: Vec<u8>,
+    pub device_name: String,
+    pub issued_at: String,
+    pub signature: Vec<u8>,
+    /// Empty until the device accepts the link.
+    #[serde(default)]
+    pub device_signature: Vec<u8>,
+}
+
+/// Signed by the identity key: the device no longer acts on its behalf.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeviceRevocation {
+    pub identity_peer_id: String,
+    pub device_peer_id: String,
+    pub identity_public_key: Vec<u8>,
+    pub revoked_at: String,
+    pub signature: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LinkedDevice {
+    pub device_peer_id: String,
+    pub device_name: String,
+    pub issued_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<String>,
+    pub is_current: bool,
+}
+
+/// State mirrored between devices of the same identity.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DeviceSyncItem {
+    Conversation {
+        conversation: DmConversation,
+        participants: Vec<DmParticipant>,
+    },
+    DmMessage(DmMessageNet),
+    /// Read position in a channel or DM conversation.
+    Read {
+        target_id: String,
+        last_read_message_id: String,
+    },
+    /// Disappearing-message timer of a DM conversation.
+    Retention(RetentionPolicy),
+}
+
+// ============================================================
+// Retention
+// ============================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RetentionScope {
+    Room,
+    Channel,
+    Dm,
+}
+
+impl RetentionScope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RetentionScope::Room => "room",
+            RetentionScope::Channel => "channel",
+            RetentionScope::Dm => "dm",
+        }
+    }
+}
+
+impl std::str::FromStr for RetentionScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "room" => Ok(RetentionScope::Room),
+            "channel" => Ok(RetentionScope::Channel),
+            "dm" => Ok(RetentionScope::Dm),
+            other => Err(format!("Unknown retention scope '{}'", other)),
+        }
+    }
+}
+
+/// How long history lives in a room, channel or DM. With neither limit set,
+/// messages are kept forever. A channel's policy, once set, overrides its
+/// room's. Replicas keep the policy with the latest `updated_at`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RetentionPolicy {
+    pub scope: RetentionScope,
+    pub target_id: String,
+    /// Messages older than this are deleted. For DMs, the disappearing-message timer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_secs: Option<i64>,
+    /// Only the newest N messages are kept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_messages: Option<i64>,
+    pub updated_by: String,
+    pub updated_at: String,
+}
+
+// ============================================================
+// API tokens
+// ============================================================
+
+/// What a local API token may do. Each scope includes the ones below it:
+/// `admin` can also write, `write` can also read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiScope {
+    Read,
+    Write,
+    Admin,
+}
+
+impl ApiScope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ApiScope::Read => "read",
+            ApiScope::Write => "write",
+            ApiScope::Admin => "admin",
+        }
+    }
+}
+
+impl std::str::FromStr for ApiScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(ApiScope::Read),
+            "write" => Ok(ApiScope::Write),
+            "admin" => Ok(ApiScope::Admin),
+            other => Err(format!("Unknown API scope '{}'", other)),
+        }
+    }
+}
+
+/// A token for the local REST/WebSocket API. Only a hash of the secret is stored.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ApiToken {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+    pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<String>,
+    /// Bot the token acts as; see [`Bot`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bot_peer_id: Option<String>,
+}
+
+/// A newly issued token. The secret is shown this once.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct IssuedApiToken {
+    #[serde(flatten)]
+    pub token: ApiToken,
+    pub secret: String,
+}
+
+// ============================================================
+// Webhooks
+// ============================================================
+
+/// Outbound webhook: matching events are POSTed to `url`, signed with the
+/// webhook's secret.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    #[serde(flatten)]
+    pub filter: crate::events::EventFilter,
+    pub enabled: bool,
+    pub created_at: String,
+}
+
+/// A newly created webhook. The signing secret is shown this once.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    pub secret: String,
+}
+
+/// One delivery attempt.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub webhook_id: String,
+    /// Journal sequence number of the event; `None` for test pings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_seq: Option<i64>,
+    pub event_type: String,
+    pub attempt: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_code: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub success: bool,
+    pub attempted_at: String,
+}
+
+/// Incoming webhook: anyone holding its token can post into `channel_id`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct IncomingWebhook {
+    pub id: String,
+    pub channel_id: String,
+    /// Default display name of the messages it posts.
+    pub name: String,
+    pub created_at: String,
+}
+
+/// A newly created incoming webhook. The token is shown this once.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CreatedIncomingWebhook {
+    #[serde(flatten)]
+    pub webhook: IncomingWebhook,
+    pub token: String,
+    /// Path to POST payloads to, relative to the API server.
+    pub url: String,
+}
+
+/// Who a message is posted as when it isn't the node's own identity.
+#[derive(Debug, Clone)]
+pub enum MessageAuthor {
+    /// An incoming webhook; see [`Message::webhook_id`].
+    Webhook {
+        webhook_id: String,
+        display_name: String,
+        embeds: Vec<MessageEmbed>,
+    },
+    /// A bot hosted by this node.
+    Bot { peer_id: String, display_name: String },
+}
+
+// ============================================================
+// Bots
+// ============================================================
+
+/// A bot identity hosted by this node, with its own keypair and API token.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Bot {
+    pub peer_id: String,
+    pub display_name: String,
+    /// Rooms the bot is a member of; a subset of the node's rooms.
+    pub rooms: Vec<String>,
+    pub created_at: String,
+}
+
+/// A newly created bot. Its API token is shown this once.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CreatedBot {
+    #[serde(flatten)]
+    pub bot: Bot,
+    pub token: String,
+}
+
+/// Signed by the bot key: `host_peer_id` publishes on behalf of `bot_peer_id`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BotCertificate {
+    pub bot_peer_id: String,
+    pub host_peer_id: String,
+    pub bot_public_key: Vec<u8>,
+    pub issued_at: String,
+    pub signature: Vec<u8>,
+}
+
+// ============================================================
+// Slash commands
+// ============================================================
+
+/// How a slash command argument is parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandOptionType {
+    /// One word, or a "quoted phrase".
+    String,
+    /// The rest of the input. Only valid as the last option.
+    Text,
+    Integer,
+    /// `@name`, `<@peer_id>` or a peer ID; parsed to the peer ID.
+    User,
+    /// `30s`, `10m`, `1h30m`, `2d`, `1w`; parsed to seconds.
+    Duration,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CommandOption {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(rename = "type")]
+    pub option_type: CommandOptionType,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// A command the message box accepts as `/name args`. Built-in commands have
+/// no `bot_peer_id`; bot commands are registered per room.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SlashCommand {
+    pub name: String,
+    pub description: String,
+    #[serde(default)]
+    pub options: Vec<CommandOption>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bot_peer_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub room_id: Option<String>,
+}
+
+/// A bot command someone ran, delivered to bots as a `CommandInvoked` event.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CommandInvocation {
+    pub id: String,
+    pub command: String,
+    pub bot_peer_id: String,
+    pub room_id: String,
+    pub channel_id: String,
+    pub invoker_peer_id: String,
+    pub invoker_display_name: String,
+    /// Parsed arguments by option name: strings, integers, peer IDs and seconds.
+    pub options: serde_json::Map<String, serde_json::Value>,
+    pub created_at: String,
+}
+
+/// What running a command did.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct CommandResult {
+    /// A message the command posted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<Message>,
+    /// Feedback for the invoker only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    /// Set when a bot handles the command.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invocation: Option<CommandInvocation>,
+}
+
+/// An autocomplete suggestion: `insert` replaces the word being typed.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CommandSuggestion {
+    pub kind: String, // "command", "user"
+    pub insert: String,
+    pub label: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// A `/remind` reminder, raised as a notification when due.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Reminder {
+    pub id: String,
+    pub channel_id: String,
+    pub content: String,
+    pub remind_at: String,
+    pub created_at: String,
+}
+
+// ============================================================
+// Backups
+// ============================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BackupManifest {
+    pub format_version: i64,
+    pub created_at: String,
+    pub peer_id: String,
+    pub schema_version: i64,
+}
+
+// ============================================================
+// Export
+// ============================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Json,
+    Html,
+    Markdown,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json),
+            "html" => Ok(ExportFormat::Html),
+            "md" | "markdown" => Ok(ExportFormat::Markdown),
+            other => Err(format!("Unknown export format '{}' (expected json, html or markdown)", other)),
+        }
+    }
+}
+
+/// A message with everything attached to it, as written to an export.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ExportedMessage {
+    #[serde(flatten)]
+    pub message: Message,
+    pub reactions: Vec<Reaction>,
+    pub pinned: bool,
+    pub attachments: Vec<FileMetadata>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub thread_replies: Vec<ExportedMessage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ChannelExport {
+    pub channel: Channel,
+    pub messages: Vec<ExportedMessage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RoomExport {
+    pub exported_at: String,
+    pub room: Room,
+    pub channels: Vec<ChannelExport>,
+}
+
+// ============================================================
+// Import
+// ============================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ImportSummary {
+    pub room: Room,
+    pub channels: Vec<Channel>,
+    pub messages_imported: usize,
+    pub messages_skipped: usize,
+}
+
+// ============================================================
+// Phase 1: Reactions, Read Receipts, Search
+// ============================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Reaction {
     pub id: String,
     pub message_id: String,
@@ -80,7 +634,7 @@ pub struct Reaction {
     pub created_at: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ReadReceipt {
     pub channel_id: String,
     pub peer_id: String,
@@ -88,17 +642,114 @@ pub struct ReadReceipt {
     pub updated_at: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SearchResult {
     pub messages: Vec<Message>,
     pub total: i64,
+    /// Highlighted excerpt per message ID; HTML-escaped, with matches wrapped in `<mark>`.
+    /// Empty when the query only used filters.
+    #[serde(default)]
+    pub snippets: HashMap<String, String>,
+}
+
+/// One hit of the unified search, tagged by where it was found.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum SearchHit {
+    Channel {
+        message: Message,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        snippet: Option<String>,
+    },
+    Dm {
+        message: DmMessage,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        snippet: Option<String>,
+    },
+    File {
+        file: FileMetadata,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        snippet: Option<String>,
+    },
+}
+
+impl SearchHit {
+    /// Timestamp used to interleave hits from different sources, newest first.
+    pub fn timestamp(&self) -> &str {
+        match self {
+            SearchHit::Channel { message, .. } => &message.timestamp,
+            SearchHit::Dm { message, .. } => &message.timestamp,
+            SearchHit::File { file, .. } => &file.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UnifiedSearchResult {
+    pub results: Vec<SearchHit>,
+    pub total: i64,
+}
+
+/// A parsed search query: FTS5 expression plus structured filters.
+#[derive(Debug, Clone, Default)]
+pub struct MessageSearch {
+    /// Safe FTS5 MATCH expression, `None` when the query only has filters.
+    pub fts: Option<String>,
+    /// Sender peer IDs or display names (`from:`).
+    pub from: Vec<String>,
+    /// Channel IDs or names (`in:`).
+    pub channels: Vec<String>,
+    /// Peer IDs that must be mentioned (`mentions:`).
+    pub mentions: Vec<String>,
+    pub has_attachment: bool,
+    /// Exclusive upper bound on the RFC 3339 timestamp (`before:`).
+    pub before: Option<String>,
+    /// Inclusive lower bound on the RFC 3339 timestamp (`after:`).
+    pub after: Option<String>,
+    pub room_id: Option<String>,
+}
+
+// ============================================================
+// Threads
+// ============================================================
+
+/// A thread is identified by the id of the message it was started from.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Thread {
+    pub id: String,
+    pub channel_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub created_by: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ThreadSummary {
+    pub reply_count: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_reply_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_reply_peer_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_reply_display_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ThreadInfo {
+    #[serde(flatten)]
+    pub thread: Thread,
+    pub summary: ThreadSummary,
+    pub subscribed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root: Option<Message>,
 }
 
 // ============================================================
 // Phase 2: DMs, Roles, Moderation, Pins
 // ============================================================
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DmConversation {
     pub id: String,
     pub is_group: bool,
@@ -106,14 +757,14 @@ pub struct DmConversation {
     pub created_at: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DmParticipant {
     pub conversation_id: String,
     pub peer_id: String,
     pub joined_at: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DmMessage {
     pub id: String,
     pub conversation_id: String,
@@ -123,7 +774,7 @@ pub struct DmMessage {
     pub timestamp: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RoomRole {
     pub id: String,
     pub room_id: String,
@@ -133,7 +784,7 @@ pub struct RoomRole {
     pub assigned_at: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ModerationAction {
     pub id: String,
     pub room_id: String,
@@ -146,7 +797,7 @@ pub struct ModerationAction {
     pub expires_at: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PinnedMessage {
     pub id: String,
     pub channel_id: String,
@@ -155,7 +806,7 @@ pub struct PinnedMessage {
     pub pinned_at: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct BlockedPeer {
     pub peer_id: String,
     pub blocked_at: String,
@@ -165,7 +816,7 @@ pub struct BlockedPeer {
 // Phase 4: File Sharing
 // ============================================================
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct FileMetadata {
     pub id: String,
     pub filename: String,
@@ -177,13 +828,13 @@ pub struct FileMetadata {
     pub created_at: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct MessageAttachment {
     pub message_id: String,
     pub file_id: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct LinkPreview {
     pub url: String,
     pub title: Option<String>,
@@ -196,7 +847,7 @@ pub struct LinkPreview {
 // Phase 5: Friends
 // ============================================================
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Friend {
     pub peer_id: String,
     pub display_name: String,
@@ -208,13 +859,13 @@ pub struct Friend {
 // Phase 6: Settings, Custom Emoji
 // ============================================================
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Setting {
     pub key: String,
     pub value: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CustomEmoji {
     pub id: String,
     pub room_id: String,
@@ -228,13 +879,75 @@ pub struct CustomEmoji {
 // Phase 7: Notification Settings
 // ============================================================
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct NotificationSetting {
-    pub target_id: String,   // channel_id or room_id
-    pub target_type: String, // "channel" or "room"
+    pub target_id: String,   // channel_id, room_id or conversation_id
+    pub target_type: String, // "channel", "room" or "dm"
     pub level: String,       // "all", "mentions", "none"
 }
 
+// ============================================================
+// Mentions, Notifications & Unread Counters
+// ============================================================
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct MessageMention {
+    pub kind: String, // "user", "role", "everyone"
+    /// Peer ID for user mentions, role name for role mentions, empty for @everyone.
+    pub target: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Notification {
+    pub message_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub room_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conversation_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thread_id: Option<String>,
+    pub sender_peer_id: String,
+    pub sender_display_name: String,
+    pub content: String,
+    pub timestamp: String,
+    pub reason: String, // "message", "mention", "dm", "reminder"
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ChannelUnread {
+    pub channel_id: String,
+    pub unread: i64,
+    pub mentions: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_message_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RoomUnread {
+    pub room_id: String,
+    pub unread: i64,
+    pub mentions: i64,
+    pub channels: Vec<ChannelUnread>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DmUnread {
+    pub conversation_id: String,
+    pub unread: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_message_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct UnreadSummary {
+    pub total_unread: i64,
+    pub total_mentions: i64,
+    pub rooms: Vec<RoomUnread>,
+    pub dms: Vec<DmUnread>,
+}
+
 // ============================================================
 // Network Messages (over GossipSub)
 // ============================================================
@@ -251,6 +964,12 @@ pub struct ChatMessage {
     pub reply_to_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub attachments: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub embeds: Vec<MessageEmbed>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -258,6 +977,11 @@ pub struct PeerAnnouncement {
     pub peer_id: String,
     pub display_name: String,
     pub room_id: String,
+    #[serde(default)]
+    pub is_bot: bool,
+    /// Proves the publishing node hosts the bot; required when `is_bot`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bot_certificate: Option<BotCertificate>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -291,6 +1015,15 @@ pub struct MessageDeleteNet {
     pub deleted_at: String,
 }
 
+/// Everything `sender_peer_id` posted in the room up to `deleted_at` is
+/// deleted. Peers keep the cut-off so messages that arrive late stay deleted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessagesPurgeNet {
+    pub room_id: String,
+    pub sender_peer_id: String,
+    pub deleted_at: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReactionNet {
     pub message_id: String,
@@ -385,6 +1118,7 @@ pub enum NetworkMessage {
     RoomFound(RoomLookupResponse),
     MessageEdit(MessageEditNet),
     MessageDelete(MessageDeleteNet),
+    MessagesPurged(MessagesPurgeNet),
     Reaction(ReactionNet),
     TypingIndicator(TypingIndicatorNet),
     ReadReceipt(ReadReceiptNet),
@@ -396,7 +1130,38 @@ pub enum NetworkMessage {
     VoiceState(VoiceStateNet),
     ChannelCreated(ChannelCreatedNet),
     ChannelDeleted(ChannelDeletedNet),
-    ChannelSync { room_id: String, channels: Vec<ChannelSyncNet> },
+    ChannelSync {
+        room_id: String,
+        channels: Vec<ChannelSyncNet>,
+        #[serde(default)]
+        categories: Vec<CategorySyncNet>,
+    },
+    CategoryUpserted(CategorySyncNet),
+    CategoryDeleted(CategoryDeletedNet),
+    ChannelLayout(ChannelLayoutNet),
+    ThreadCreated(ThreadCreatedNet),
+    KeyRotated(KeyRotation),
+    DeviceLinked(DeviceCertificate),
+    DeviceUnlinked(DeviceRevocation),
+    RetentionPolicyUpdated {
+        room_id: String,
+        policy: RetentionPolicy,
+    },
+    /// A bot hosted by the sender registered or replaced one of its commands.
+    BotCommandRegistered(SlashCommand),
+    BotCommandUnregistered {
+        room_id: String,
+        bot_peer_id: String,
+        name: String,
+    },
+    /// A bot command run on another node, for the node hosting the bot.
+    CommandInvoked(CommandInvocation),
+    /// Published on `chatr/devices/{identity_peer_id}`, only to our own devices.
+    DeviceSync {
+        identity_peer_id: String,
+        from_device: String,
+        item: DeviceSyncItem,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -406,6 +1171,8 @@ pub struct ChannelCreatedNet {
     pub name: String,
     pub channel_type: String,
     pub created_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -424,4 +1191,51 @@ pub struct ChannelSyncNet {
     pub topic: Option<String>,
     #[serde(default)]
     pub position: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category_id: Option<String>,
+    #[serde(default = "default_true")]
+    pub inherit_permissions: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategorySyncNet {
+    pub room_id: String,
+    pub category_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub position: i32,
+    pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryDeletedNet {
+    pub room_id: String,
+    pub category_id: String,
+}
+
+/// Placement and permission-sync state of a channel (category move, reorder, overwrites).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelLayoutNet {
+    pub room_id: String,
+    pub channel_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category_id: Option<String>,
+    pub position: i32,
+    pub inherit_permissions: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadCreatedNet {
+    pub thread_id: String,
+    pub channel_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub created_by: String,
+    pub created_at: String,
 }
@@ -2,7 +2,7 @@ pub mod behaviour;
 pub mod swarm;
 pub mod bootstrap;
 
-use crate::models::Message;
+use crate::models::{Channel, ChannelCategory, CommandInvocation, DeviceCertificate, DeviceRevocation, DeviceSyncItem, KeyRotation, Message, MessageDeleteNet, MessagesPurgeNet, RetentionPolicy, SlashCommand, Thread, TypingIndicatorNet};
 
 /// The global discovery topic for room lookups
 pub const DISCOVERY_TOPIC: &str = "chatr/discovery";
@@ -35,6 +35,12 @@ pub enum NetworkCommand {
         room_id: String,
         display_name: String,
     },
+    /// Announce a bot hosted by this node in a room.
+    AnnounceBot {
+        room_id: String,
+        display_name: String,
+        certificate: crate::models::BotCertificate,
+    },
     SendCallOffer {
         room_id: String,
         to_peer_id: String,
@@ -69,9 +75,80 @@ pub enum NetworkCommand {
         name: String,
         channel_type: String,
         created_at: String,
+        category_id: Option<String>,
     },
     BroadcastChannelDeleted {
         room_id: String,
         channel_id: String,
     },
+    /// Started or stopped typing in one of the room's channels
+    BroadcastTyping {
+        room_id: String,
+        indicator: TypingIndicatorNet,
+    },
+    /// Category created or changed (name, position, permissions)
+    BroadcastCategoryUpserted {
+        category: ChannelCategory,
+    },
+    BroadcastCategoryDeleted {
+        room_id: String,
+        category_id: String,
+    },
+    /// Channel moved between categories, reordered, or its permission sync changed
+    BroadcastChannelLayout {
+        channel: Channel,
+    },
+    BroadcastThreadCreated {
+        room_id: String,
+        thread: Thread,
+    },
+    /// Sent to every joined room
+    BroadcastKeyRotation {
+        rotation: KeyRotation,
+    },
+    /// Sent to every joined room
+    BroadcastDeviceCertificate {
+        certificate: DeviceCertificate,
+    },
+    BroadcastDeviceRevocation {
+        revocation: DeviceRevocation,
+    },
+    /// Switch the device sync topic to `chatr/devices/{identity_peer_id}`
+    SubscribeDevices {
+        identity_peer_id: String,
+    },
+    /// Mirror local state to our other devices
+    SyncToDevices {
+        item: DeviceSyncItem,
+    },
+    /// Room or channel retention policy changed
+    BroadcastRetentionPolicy {
+        room_id: String,
+        policy: RetentionPolicy,
+    },
+    BroadcastMessageDelete {
+        room_id: String,
+        delete: MessageDeleteNet,
+    },
+    BroadcastMessagesPurged {
+        purge: MessagesPurgeNet,
+    },
+    /// A bot we host registered a command in its room
+    BroadcastBotCommand {
+        room_id: String,
+        command: SlashCommand,
+    },
+    BroadcastBotCommandRemoved {
+        room_id: String,
+        bot_peer_id: String,
+        name: String,
+    },
+    /// Hand a command to the node hosting its bot
+    BroadcastCommandInvocation {
+        invocation: CommandInvocation,
+    },
+}
+
+pub fn device_topic(identity_peer_id: &str) -> String {
+    format!("chatr/devices/{}", identity_peer_id)
 }
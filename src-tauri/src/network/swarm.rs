@@ -15,13 +15,16 @@ use tracing::{info, warn, debug, error};
 
 use crate::db::Database;
 use crate::events::{AppEvent, EventSender};
-use crate::models::{ChatMessage, NetworkMessage, PeerInfo, CallOfferNet, CallAnswerNet, IceCandidateNet, VoiceStateNet, ChannelCreatedNet, ChannelDeletedNet, ChannelSyncNet};
+use crate::models::{ChatMessage, DeviceSyncItem, RetentionPolicy, RetentionScope, NetworkMessage, PeerAnnouncement, PeerInfo, CallOfferNet, CallAnswerNet, IceCandidateNet, VoiceStateNet, ChannelCreatedNet, ChannelDeletedNet, ChannelSyncNet, CategorySyncNet, CategoryDeletedNet, ChannelLayoutNet, MessagesPurgeNet, ThreadCreatedNet};
 use crate::network::behaviour::{ChatrBehaviour, ChatrBehaviourEvent};
 use crate::network::bootstrap;
 use crate::network::NetworkCommand;
 
 const PROTOCOL_VERSION: &str = "chatr/0.1.0";
 
+/// How far ahead of our clock a gossiped timestamp may be.
+const MAX_CLOCK_SKEW: chrono::Duration = chrono::Duration::minutes(5);
+
 pub fn build_swarm(keypair: &Keypair) -> Result<Swarm<ChatrBehaviour>, Box<dyn std::error::Error>> {
     let peer_id = PeerId::from(keypair.public());
 
@@ -131,6 +134,13 @@ pub async fn run_event_loop(
         info!("Subscribed to discovery topic");
     }
 
+    // Our own devices share a topic for mirroring DMs and read state
+    let me = my_peer_id.clone();
+    let mut device_identity = db.run(move |db| db.resolve_identity(&me)).await.unwrap_or_else(|_| my_peer_id.clone());
+    if let Err(e) = swarm.behaviour_mut().gossipsub.subscribe(&gossipsub::IdentTopic::new(crate::network::device_topic(&device_identity))) {
+        warn!("Failed to subscribe to device topic: {}", e);
+    }
+
     // Track subscribed room channel topics
     let mut subscribed_topics: HashSet<String> = HashSet::new();
     // Track known peer display names (from PeerAnnounce messages)
@@ -165,33 +175,23 @@ pub async fn run_event_loop(
                         debug!("GossipSub message from {}", propagation_source);
                         if let Ok(net_msg) = serde_json::from_slice::<NetworkMessage>(&message.data) {
                             match net_msg {
-                                NetworkMessage::Chat(chat_msg) => {
-                                    info!("Received chat message from {} in channel {}: {}", chat_msg.sender_display_name, chat_msg.channel_id, chat_msg.content);
-                                    if chat_msg.sender_peer_id != my_peer_id {
-                                        let msg = crate::models::Message {
-                                            id: chat_msg.id.clone(),
-                                            channel_id: chat_msg.channel_id.clone(),
-                                            sender_peer_id: chat_msg.sender_peer_id.clone(),
-                                            sender_display_name: chat_msg.sender_display_name.clone(),
-                                            content: chat_msg.content.clone(),
-                                            timestamp: chat_msg.timestamp.clone(),
-                                            edited_at: None,
-                                            deleted_at: None,
-                                            reply_to_id: chat_msg.reply_to_id.clone(),
-                                        };
-                                        if let Err(e) = db.insert_message(&msg) {
-                                            error!("Failed to insert message: {}", e);
-                                        }
-                                        let _ = event_tx.send(AppEvent::NewMessage(msg));
-                                    }
+                                NetworkMessage::PeerAnnounce(announce) if announce.is_bot && !bot_announcement_certified(&announce, message.source.as_ref()) => {
+                                    warn!("Ignoring uncertified bot announcement for {}", announce.peer_id);
                                 }
                                 NetworkMessage::PeerAnnounce(announce) => {
+                                    if let Some(cert) = announce.bot_certificate.clone() {
+                                        let bot_peer_id = cert.bot_peer_id.clone();
+                                        if let Err(e) = db.run(move |db| db.store_bot_certificate(&cert)).await {
+                                            warn!("Failed to store bot certificate for {}: {}", bot_peer_id, e);
+                                        }
+                                    }
                                     info!("Peer announced: {} ({})", announce.display_name, announce.peer_id);
                                     peer_names.insert(announce.peer_id.clone(), announce.display_name.clone());
                                     let peer_info = PeerInfo {
                                         peer_id: announce.peer_id.clone(),
                                         display_name: announce.display_name.clone(),
                                         is_online: true,
+                                        is_bot: announce.is_bot,
                                     };
                                     // Update shared peers map so API/services see correct names
                                     {
@@ -211,7 +211,8 @@ pub async fn run_event_loop(
                                     // Someone is looking for a room by invite code - check if we have it
                                     if req.requester_peer_id != my_peer_id {
                                         info!("Received room lookup for invite code: {}", req.invite_code);
-                                        if let Ok(Some(room)) = db.get_room_by_invite(&req.invite_code) {
+                                        let invite_code = req.invite_code.clone();
+                                        if let Ok(Some(room)) = db.run(move |db| db.get_room_by_invite(&invite_code)).await {
                                             // We have this room, respond on the discovery topic
                                             let response = NetworkMessage::RoomFound(crate::models::RoomLookupResponse {
                                                 invite_code: req.invite_code,
@@ -235,56 +236,6 @@ pub async fn run_event_loop(
                                         }
                                     }
                                 }
-                                NetworkMessage::MessageEdit(edit) => {
-                                    if edit.sender_peer_id != my_peer_id {
-                                        info!("Received message edit from {}: {}", edit.sender_peer_id, edit.message_id);
-                                        let _ = db.edit_message(&edit.message_id, &edit.new_content, &edit.edited_at);
-                                        let _ = event_tx.send(AppEvent::MessageEdited {
-                                            message_id: edit.message_id,
-                                            channel_id: edit.channel_id,
-                                            new_content: edit.new_content,
-                                            edited_at: edit.edited_at,
-                                        });
-                                    }
-                                }
-                                NetworkMessage::MessageDelete(del) => {
-                                    if del.sender_peer_id != my_peer_id {
-                                        info!("Received message delete from {}: {}", del.sender_peer_id, del.message_id);
-                                        let _ = db.delete_message(&del.message_id, &del.deleted_at);
-                                        let _ = event_tx.send(AppEvent::MessageDeleted {
-                                            message_id: del.message_id,
-                                            channel_id: del.channel_id,
-                                        });
-                                    }
-                                }
-                                NetworkMessage::Reaction(reaction) => {
-                                    if reaction.peer_id != my_peer_id {
-                                        if reaction.add {
-                                            let r = crate::models::Reaction {
-                                                id: uuid::Uuid::new_v4().to_string(),
-                                                message_id: reaction.message_id.clone(),
-                                                peer_id: reaction.peer_id.clone(),
-                                                emoji: reaction.emoji.clone(),
-                                                created_at: chrono::Utc::now().to_rfc3339(),
-                                            };
-                                            let _ = db.add_reaction(&r);
-                                            let _ = event_tx.send(AppEvent::ReactionAdded {
-                                                message_id: reaction.message_id,
-                                                channel_id: reaction.channel_id,
-                                                peer_id: reaction.peer_id,
-                                                emoji: reaction.emoji,
-                                            });
-                                        } else {
-                                            let _ = db.remove_reaction(&reaction.message_id, &reaction.peer_id, &reaction.emoji);
-                                            let _ = event_tx.send(AppEvent::ReactionRemoved {
-                                                message_id: reaction.message_id,
-                                                channel_id: reaction.channel_id,
-                                                peer_id: reaction.peer_id,
-                                                emoji: reaction.emoji,
-                                            });
-                                        }
-                                    }
-                                }
                                 NetworkMessage::TypingIndicator(ti) => {
                                     if ti.peer_id != my_peer_id {
                                         if ti.typing {
@@ -301,59 +252,6 @@ pub async fn run_event_loop(
                                         }
                                     }
                                 }
-                                NetworkMessage::ReadReceipt(rr) => {
-                                    if rr.peer_id != my_peer_id {
-                                        let _ = db.set_read_receipt(&rr.channel_id, &rr.peer_id, &rr.last_read_message_id, &chrono::Utc::now().to_rfc3339());
-                                        let _ = event_tx.send(AppEvent::ReadReceiptUpdated {
-                                            channel_id: rr.channel_id,
-                                            peer_id: rr.peer_id,
-                                            last_read_message_id: rr.last_read_message_id,
-                                        });
-                                    }
-                                }
-                                NetworkMessage::DmMessage(dm) => {
-                                    if dm.sender_peer_id != my_peer_id {
-                                        let _ = db.insert_dm_message(&dm.id, &dm.conversation_id, &dm.sender_peer_id, &dm.sender_display_name, &dm.content, &dm.timestamp);
-                                        let msg = crate::models::DmMessage {
-                                            id: dm.id,
-                                            conversation_id: dm.conversation_id,
-                                            sender_peer_id: dm.sender_peer_id,
-                                            sender_display_name: dm.sender_display_name,
-                                            content: dm.content,
-                                            timestamp: dm.timestamp,
-                                        };
-                                        let _ = event_tx.send(AppEvent::NewDmMessage(msg));
-                                    }
-                                }
-                                NetworkMessage::FriendRequest(fr) => {
-                                    if fr.to_peer_id == my_peer_id {
-                                        match fr.action.as_str() {
-                                            "request" => {
-                                                let friend = crate::models::Friend {
-                                                    peer_id: fr.from_peer_id.clone(),
-                                                    display_name: fr.from_display_name.clone(),
-                                                    status: "pending_incoming".to_string(),
-                                                    created_at: chrono::Utc::now().to_rfc3339(),
-                                                };
-                                                let _ = db.add_friend(&friend);
-                                                let _ = event_tx.send(AppEvent::FriendRequestReceived {
-                                                    from_peer_id: fr.from_peer_id,
-                                                    from_display_name: fr.from_display_name,
-                                                });
-                                            }
-                                            "accept" => {
-                                                let _ = db.update_friend_status(&fr.from_peer_id, "accepted");
-                                                let _ = event_tx.send(AppEvent::FriendRequestAccepted {
-                                                    peer_id: fr.from_peer_id,
-                                                });
-                                            }
-                                            "remove" => {
-                                                let _ = db.remove_friend(&fr.from_peer_id);
-                                            }
-                                            _ => {}
-                                        }
-                                    }
-                                }
                                 NetworkMessage::CallOffer(offer) => {
                                     if offer.to_peer_id == my_peer_id {
                                         info!("Received call offer from {}", offer.from_peer_id);
@@ -401,59 +299,19 @@ pub async fn run_event_loop(
                                         });
                                     }
                                 }
-                                NetworkMessage::ChannelCreated(ch) => {
-                                    info!("Received channel created: {} in room {}", ch.name, ch.room_id);
-                                    // Save to local DB if we're in this room
-                                    let channel = crate::models::Channel {
-                                        id: ch.channel_id.clone(),
-                                        room_id: ch.room_id.clone(),
-                                        name: ch.name.clone(),
-                                        created_at: ch.created_at.clone(),
-                                        channel_type: ch.channel_type.clone(),
-                                        topic: None,
-                                        position: 0,
+                                other => {
+                                    let known_peers = match &other {
+                                        NetworkMessage::Chat(_) => peers.lock().await.values().cloned().collect(),
+                                        _ => Vec::new(),
                                     };
-                                    let _ = db.create_channel(&channel);
-                                    let _ = event_tx.send(AppEvent::ChannelCreated {
-                                        room_id: ch.room_id,
-                                        channel_id: ch.channel_id,
-                                        name: ch.name,
-                                        channel_type: ch.channel_type,
-                                        created_at: ch.created_at,
-                                    });
-                                }
-                                NetworkMessage::ChannelDeleted(ch) => {
-                                    info!("Received channel deleted: {} in room {}", ch.channel_id, ch.room_id);
-                                    let _ = db.delete_channel(&ch.channel_id);
-                                    let _ = event_tx.send(AppEvent::ChannelDeleted {
-                                        room_id: ch.room_id,
-                                        channel_id: ch.channel_id,
-                                    });
-                                }
-                                NetworkMessage::ChannelSync { room_id, channels } => {
-                                    info!("Received channel sync for room {} with {} channels", room_id, channels.len());
-                                    for ch in channels {
-                                        // Only insert if we don't already have this channel
-                                        if db.get_channels(&room_id).map(|chs| !chs.iter().any(|c| c.id == ch.channel_id)).unwrap_or(false) {
-                                            let channel = crate::models::Channel {
-                                                id: ch.channel_id.clone(),
-                                                room_id: room_id.clone(),
-                                                name: ch.name.clone(),
-                                                created_at: ch.created_at.clone(),
-                                                channel_type: ch.channel_type.clone(),
-                                                topic: ch.topic.clone(),
-                                                position: ch.position,
-                                            };
-                                            let _ = db.create_channel(&channel);
-                                            let _ = event_tx.send(AppEvent::ChannelCreated {
-                                                room_id: room_id.clone(),
-                                                channel_id: ch.channel_id,
-                                                name: ch.name,
-                                                channel_type: ch.channel_type,
-                                                created_at: ch.created_at,
-                                            });
-                                        }
-                                    }
+                                    let inbound = Inbound {
+                                        event_tx: event_tx.clone(),
+                                        my_peer_id: my_peer_id.clone(),
+                                        device_identity: device_identity.clone(),
+                                        source: message.source,
+                                        known_peers,
+                                    };
+                                    db.run(move |db| apply_network_message(db, inbound, other)).await;
                                 }
                             }
                         }
@@ -472,6 +330,7 @@ pub async fn run_event_loop(
                                 peer_id: pid.clone(),
                                 display_name: name,
                                 is_online: true,
+                                is_bot: false,
                             };
                             // Update shared room_peers map
                             {
@@ -488,38 +347,13 @@ pub async fn run_event_loop(
                                 peer: peer_info,
                             });
 
-                            // Re-announce our presence so the new peer learns our display name
+                            // Re-announce our presence (and our bots') so the new peer learns our display names
                             if subscribed_topics.contains(&topic_str) {
-                                let display_name = db.get_display_name().unwrap_or_else(|_| "Anonymous".to_string());
-                                let net_msg = NetworkMessage::PeerAnnounce(crate::models::PeerAnnouncement {
-                                    peer_id: my_peer_id.clone(),
-                                    display_name,
-                                    room_id: room_id.to_string(),
-                                });
-                                if let Ok(data) = serde_json::to_vec(&net_msg) {
-                                    let announce_topic = gossipsub::IdentTopic::new(&topic_str);
-                                    let _ = swarm.behaviour_mut().gossipsub.publish(announce_topic, data);
-                                }
-
-                                // Also send channel sync so new peer gets all channels
-                                if let Ok(channels) = db.get_channels(room_id) {
-                                    let channel_list: Vec<ChannelSyncNet> = channels.into_iter().map(|ch| ChannelSyncNet {
-                                        channel_id: ch.id,
-                                        name: ch.name,
-                                        channel_type: ch.channel_type,
-                                        created_at: ch.created_at,
-                                        topic: ch.topic,
-                                        position: ch.position,
-                                    }).collect();
-                                    if !channel_list.is_empty() {
-                                        let sync_msg = NetworkMessage::ChannelSync {
-                                            room_id: room_id.to_string(),
-                                            channels: channel_list,
-                                        };
-                                        if let Ok(data) = serde_json::to_vec(&sync_msg) {
-                                            let sync_topic = gossipsub::IdentTopic::new(&topic_str);
-                                            let _ = swarm.behaviour_mut().gossipsub.publish(sync_topic, data);
-                                        }
+                                let (me, identity, rid) = (my_peer_id.clone(), device_identity.clone(), room_id.to_string());
+                                let catch_up = db.run(move |db| room_catch_up(db, &me, &identity, &rid)).await;
+                                for net_msg in catch_up {
+                                    if let Ok(data) = serde_json::to_vec(&net_msg) {
+                                        let _ = swarm.behaviour_mut().gossipsub.publish(gossipsub::IdentTopic::new(&topic_str), data);
                                     }
                                 }
                             }
@@ -611,6 +445,7 @@ pub async fn run_event_loop(
                             peer_id: pid.clone(),
                             display_name: name,
                             is_online: true,
+                            is_bot: false,
                         };
                         // Update shared peers map
                         {
@@ -650,6 +485,9 @@ pub async fn run_event_loop(
                             timestamp: message.timestamp,
                             reply_to_id: message.reply_to_id,
                             attachments: None,
+                            thread_id: message.thread_id,
+                            webhook_id: message.webhook_id,
+                            embeds: message.embeds,
                         });
                         if let Ok(data) = serde_json::to_vec(&net_msg) {
                             match swarm.behaviour_mut().gossipsub.publish(topic, data) {
@@ -668,16 +506,13 @@ pub async fn run_event_loop(
                                 subscribed_topics.insert(topic_str.clone());
                                 info!("Subscribed to room topic: chatr/room/{}", room_id);
 
-                                // Auto-announce presence with display name
-                                let display_name = db.get_display_name().unwrap_or_else(|_| "Anonymous".to_string());
-                                let net_msg = NetworkMessage::PeerAnnounce(crate::models::PeerAnnouncement {
-                                    peer_id: my_peer_id.clone(),
-                                    display_name,
-                                    room_id: room_id.clone(),
-                                });
-                                if let Ok(data) = serde_json::to_vec(&net_msg) {
-                                    let announce_topic = gossipsub::IdentTopic::new(&topic_str);
-                                    let _ = swarm.behaviour_mut().gossipsub.publish(announce_topic, data);
+                                // Auto-announce presence with display name, and our bots in the room
+                                let (me, rid) = (my_peer_id.clone(), room_id.clone());
+                                for announcement in db.run(move |db| room_announcements(db, &me, &rid)).await {
+                                    if let Ok(data) = serde_json::to_vec(&NetworkMessage::PeerAnnounce(announcement)) {
+                                        let announce_topic = gossipsub::IdentTopic::new(&topic_str);
+                                        let _ = swarm.behaviour_mut().gossipsub.publish(announce_topic, data);
+                                    }
                                 }
                             }
                         }
@@ -728,10 +563,26 @@ pub async fn run_event_loop(
                     NetworkCommand::AnnouncePresence { room_id, display_name } => {
                         let topic_str = format!("chatr/room/{}", room_id);
                         let topic = gossipsub::IdentTopic::new(&topic_str);
-                        let net_msg = NetworkMessage::PeerAnnounce(crate::models::PeerAnnouncement {
+                        let net_msg = NetworkMessage::PeerAnnounce(PeerAnnouncement {
                             peer_id: my_peer_id.clone(),
                             display_name,
                             room_id,
+                            is_bot: false,
+                            bot_certificate: None,
+                        });
+                        if let Ok(data) = serde_json::to_vec(&net_msg) {
+                            let _ = swarm.behaviour_mut().gossipsub.publish(topic, data);
+                        }
+                    }
+                    NetworkCommand::AnnounceBot { room_id, display_name, certificate } => {
+                        let topic_str = format!("chatr/room/{}", room_id);
+                        let topic = gossipsub::IdentTopic::new(&topic_str);
+                        let net_msg = NetworkMessage::PeerAnnounce(PeerAnnouncement {
+                            peer_id: certificate.bot_peer_id.clone(),
+                            display_name,
+                            room_id,
+                            is_bot: true,
+                            bot_certificate: Some(certificate),
                         });
                         if let Ok(data) = serde_json::to_vec(&net_msg) {
                             let _ = swarm.behaviour_mut().gossipsub.publish(topic, data);
@@ -787,7 +638,7 @@ pub async fn run_event_loop(
                     NetworkCommand::SendVoiceState { room_id, channel_id, muted, deafened, video, screen_sharing } => {
                         let topic_str = format!("chatr/room/{}", room_id);
                         let topic = gossipsub::IdentTopic::new(&topic_str);
-                        let display_name = db.get_display_name().unwrap_or_else(|_| "Anonymous".to_string());
+                        let display_name = db.run(|db| db.get_display_name()).await.unwrap_or_else(|_| "Anonymous".to_string());
                         let net_msg = NetworkMessage::VoiceState(VoiceStateNet {
                             peer_id: my_peer_id.clone(),
                             display_name,
@@ -802,7 +653,7 @@ pub async fn run_event_loop(
                             let _ = swarm.behaviour_mut().gossipsub.publish(topic, data);
                         }
                     }
-                    NetworkCommand::BroadcastChannelCreated { room_id, channel_id, name, channel_type, created_at } => {
+                    NetworkCommand::BroadcastChannelCreated { room_id, channel_id, name, channel_type, created_at, category_id } => {
                         let topic_str = format!("chatr/room/{}", room_id);
                         let topic = gossipsub::IdentTopic::new(&topic_str);
                         let net_msg = NetworkMessage::ChannelCreated(ChannelCreatedNet {
@@ -811,6 +662,7 @@ pub async fn run_event_loop(
                             name,
                             channel_type,
                             created_at,
+                            category_id,
                         });
                         if let Ok(data) = serde_json::to_vec(&net_msg) {
                             let _ = swarm.behaviour_mut().gossipsub.publish(topic, data);
@@ -827,8 +679,819 @@ pub async fn run_event_loop(
                             let _ = swarm.behaviour_mut().gossipsub.publish(topic, data);
                         }
                     }
+                    NetworkCommand::BroadcastTyping { room_id, indicator } => {
+                        let topic = gossipsub::IdentTopic::new(format!("chatr/room/{}", room_id));
+                        let net_msg = NetworkMessage::TypingIndicator(indicator);
+                        if let Ok(data) = serde_json::to_vec(&net_msg) {
+                            let _ = swarm.behaviour_mut().gossipsub.publish(topic, data);
+                        }
+                    }
+                    NetworkCommand::BroadcastCategoryUpserted { category } => {
+                        let topic_str = format!("chatr/room/{}", category.room_id);
+                        let topic = gossipsub::IdentTopic::new(&topic_str);
+                        let net_msg = NetworkMessage::CategoryUpserted(CategorySyncNet {
+                            room_id: category.room_id,
+                            category_id: category.id,
+                            name: category.name,
+                            position: category.position,
+                            created_at: category.created_at,
+                            permissions: category.permissions,
+                        });
+                        if let Ok(data) = serde_json::to_vec(&net_msg) {
+                            let _ = swarm.behaviour_mut().gossipsub.publish(topic, data);
+                        }
+                    }
+                    NetworkCommand::BroadcastCategoryDeleted { room_id, category_id } => {
+                        let topic_str = format!("chatr/room/{}", room_id);
+                        let topic = gossipsub::IdentTopic::new(&topic_str);
+                        let net_msg = NetworkMessage::CategoryDeleted(CategoryDeletedNet {
+                            room_id,
+                            category_id,
+                        });
+                        if let Ok(data) = serde_json::to_vec(&net_msg) {
+                            let _ = swarm.behaviour_mut().gossipsub.publish(topic, data);
+                        }
+                    }
+                    NetworkCommand::BroadcastThreadCreated { room_id, thread } => {
+                        let topic_str = format!("chatr/room/{}", room_id);
+                        let topic = gossipsub::IdentTopic::new(&topic_str);
+                        let net_msg = NetworkMessage::ThreadCreated(ThreadCreatedNet {
+                            thread_id: thread.id,
+                            channel_id: thread.channel_id,
+                            name: thread.name,
+                            created_by: thread.created_by,
+                            created_at: thread.created_at,
+                        });
+                        if let Ok(data) = serde_json::to_vec(&net_msg) {
+                            let _ = swarm.behaviour_mut().gossipsub.publish(topic, data);
+                        }
+                    }
+                    NetworkCommand::BroadcastChannelLayout { channel } => {
+                        let topic_str = format!("chatr/room/{}", channel.room_id);
+                        let topic = gossipsub::IdentTopic::new(&topic_str);
+                        let net_msg = NetworkMessage::ChannelLayout(ChannelLayoutNet {
+                            room_id: channel.room_id,
+                            channel_id: channel.id,
+                            category_id: channel.category_id,
+                            position: channel.position,
+                            inherit_permissions: channel.inherit_permissions,
+                            permissions: channel.permissions,
+                        });
+                        if let Ok(data) = serde_json::to_vec(&net_msg) {
+                            let _ = swarm.behaviour_mut().gossipsub.publish(topic, data);
+                        }
+                    }
+                    NetworkCommand::BroadcastDeviceCertificate { certificate } => {
+                        let net_msg = NetworkMessage::DeviceLinked(certificate);
+                        if let Ok(data) = serde_json::to_vec(&net_msg) {
+                            let device_topic = crate::network::device_topic(&device_identity);
+                            for topic_str in subscribed_topics.iter().chain(std::iter::once(&device_topic)) {
+                                let topic = gossipsub::IdentTopic::new(topic_str);
+                                let _ = swarm.behaviour_mut().gossipsub.publish(topic, data.clone());
+                            }
+                        }
+                    }
+                    NetworkCommand::BroadcastDeviceRevocation { revocation } => {
+                        let net_msg = NetworkMessage::DeviceUnlinked(revocation);
+                        if let Ok(data) = serde_json::to_vec(&net_msg) {
+                            let device_topic = crate::network::device_topic(&device_identity);
+                            for topic_str in subscribed_topics.iter().chain(std::iter::once(&device_topic)) {
+                                let topic = gossipsub::IdentTopic::new(topic_str);
+                                let _ = swarm.behaviour_mut().gossipsub.publish(topic, data.clone());
+                            }
+                        }
+                    }
+                    NetworkCommand::SubscribeDevices { identity_peer_id } => {
+                        if identity_peer_id != device_identity {
+                            let old_topic = gossipsub::IdentTopic::new(crate::network::device_topic(&device_identity));
+                            let _ = swarm.behaviour_mut().gossipsub.unsubscribe(&old_topic);
+                            let new_topic = gossipsub::IdentTopic::new(crate::network::device_topic(&identity_peer_id));
+                            if let Err(e) = swarm.behaviour_mut().gossipsub.subscribe(&new_topic) {
+                                warn!("Failed to subscribe to device topic: {}", e);
+                            }
+                            device_identity = identity_peer_id;
+                        }
+                    }
+                    NetworkCommand::SyncToDevices { item } => {
+                        let topic = gossipsub::IdentTopic::new(crate::network::device_topic(&device_identity));
+                        let net_msg = NetworkMessage::DeviceSync {
+                            identity_peer_id: device_identity.clone(),
+                            from_device: my_peer_id.clone(),
+                            item,
+                        };
+                        if let Ok(data) = serde_json::to_vec(&net_msg) {
+                            // Fails with InsufficientPeers when no other device is online
+                            let _ = swarm.behaviour_mut().gossipsub.publish(topic, data);
+                        }
+                    }
+                    NetworkCommand::BroadcastRetentionPolicy { room_id, policy } => {
+                        let topic = gossipsub::IdentTopic::new(format!("chatr/room/{}", room_id));
+                        let net_msg = NetworkMessage::RetentionPolicyUpdated { room_id, policy };
+                        if let Ok(data) = serde_json::to_vec(&net_msg) {
+                            let _ = swarm.behaviour_mut().gossipsub.publish(topic, data);
+                        }
+                    }
+                    NetworkCommand::BroadcastMessageDelete { room_id, delete } => {
+                        let topic = gossipsub::IdentTopic::new(format!("chatr/room/{}", room_id));
+                        if let Ok(data) = serde_json::to_vec(&NetworkMessage::MessageDelete(delete)) {
+                            let _ = swarm.behaviour_mut().gossipsub.publish(topic, data);
+                        }
+                    }
+                    NetworkCommand::BroadcastMessagesPurged { purge } => {
+                        let topic = gossipsub::IdentTopic::new(format!("chatr/room/{}", purge.room_id));
+                        if let Ok(data) = serde_json::to_vec(&NetworkMessage::MessagesPurged(purge)) {
+                            let _ = swarm.behaviour_mut().gossipsub.publish(topic, data);
+                        }
+                    }
+                    NetworkCommand::BroadcastBotCommand { room_id, command } => {
+                        let topic = gossipsub::IdentTopic::new(format!("chatr/room/{}", room_id));
+                        if let Ok(data) = serde_json::to_vec(&NetworkMessage::BotCommandRegistered(command)) {
+                            let _ = swarm.behaviour_mut().gossipsub.publish(topic, data);
+                        }
+                    }
+                    NetworkCommand::BroadcastBotCommandRemoved { room_id, bot_peer_id, name } => {
+                        let topic = gossipsub::IdentTopic::new(format!("chatr/room/{}", room_id));
+                        let net_msg = NetworkMessage::BotCommandUnregistered { room_id, bot_peer_id, name };
+                        if let Ok(data) = serde_json::to_vec(&net_msg) {
+                            let _ = swarm.behaviour_mut().gossipsub.publish(topic, data);
+                        }
+                    }
+                    NetworkCommand::BroadcastCommandInvocation { invocation } => {
+                        let topic = gossipsub::IdentTopic::new(format!("chatr/room/{}", invocation.room_id));
+                        if let Ok(data) = serde_json::to_vec(&NetworkMessage::CommandInvoked(invocation)) {
+                            let _ = swarm.behaviour_mut().gossipsub.publish(topic, data);
+                        }
+                    }
+                    NetworkCommand::BroadcastKeyRotation { rotation } => {
+                        info!("Announcing key rotation to {}", rotation.new_peer_id);
+                        let net_msg = NetworkMessage::KeyRotated(rotation);
+                        if let Ok(data) = serde_json::to_vec(&net_msg) {
+                            for topic_str in &subscribed_topics {
+                                let topic = gossipsub::IdentTopic::new(topic_str);
+                                let _ = swarm.behaviour_mut().gossipsub.publish(topic, data.clone());
+                            }
+                        }
+                    }
                 }
             }
         }
     }
 }
+
+/// State the event loop hands over with a gossip message applied on the blocking pool.
+struct Inbound {
+    event_tx: EventSender,
+    my_peer_id: String,
+    device_identity: String,
+    source: Option<PeerId>,
+    known_peers: Vec<PeerInfo>,
+}
+
+/// Apply a room or device message that only touches the database and the event bus.
+fn apply_network_message(db: &Database, inbound: Inbound, net_msg: NetworkMessage) {
+    let Inbound { event_tx, my_peer_id, device_identity, source, known_peers } = inbound;
+    match net_msg {
+        NetworkMessage::Chat(chat_msg) => {
+            info!("Received chat message from {} in channel {}: {}", chat_msg.sender_display_name, chat_msg.channel_id, chat_msg.content);
+            // The signed source must be (a device of) the claimed sender, or host it as a bot
+            let authentic = source
+                .is_some_and(|source| db.acts_for(&source.to_string(), &chat_msg.sender_peer_id).unwrap_or(false));
+            if !authentic {
+                warn!("Ignoring chat message {} not signed by {}", chat_msg.id, chat_msg.sender_peer_id);
+                return;
+            }
+            // Messages from linked devices are stored under their identity
+            let sender_peer_id = db.resolve_identity(&chat_msg.sender_peer_id)
+                .unwrap_or_else(|_| chat_msg.sender_peer_id.clone());
+            // Late copies of messages their author has since purged stay deleted
+            let purged = db.is_message_purged(&chat_msg.channel_id, &sender_peer_id, &chat_msg.timestamp)
+                .unwrap_or(false);
+            if chat_msg.sender_peer_id != my_peer_id && !purged {
+                let msg = crate::models::Message {
+                    id: chat_msg.id.clone(),
+                    channel_id: chat_msg.channel_id.clone(),
+                    sender_peer_id,
+                    sender_display_name: chat_msg.sender_display_name.clone(),
+                    content: chat_msg.content.clone(),
+                    timestamp: chat_msg.timestamp.clone(),
+                    edited_at: None,
+                    deleted_at: None,
+                    reply_to_id: chat_msg.reply_to_id.clone(),
+                    thread_id: chat_msg.thread_id.clone(),
+                    thread: None,
+                    imported_from: None,
+                    webhook_id: chat_msg.webhook_id.clone(),
+                    embeds: chat_msg.embeds.clone(),
+                };
+                if let Err(e) = db.insert_message(&msg) {
+                    error!("Failed to insert message: {}", e);
+                }
+                if let Some(thread_id) = &msg.thread_id {
+                    // Replies may arrive before (or without) the ThreadCreated announcement
+                    let _ = db.create_thread(&crate::models::Thread {
+                        id: thread_id.clone(),
+                        channel_id: msg.channel_id.clone(),
+                        name: None,
+                        created_by: msg.sender_peer_id.clone(),
+                        created_at: msg.timestamp.clone(),
+                    });
+                    // Authors of the root message follow their threads by default
+                    if let Ok(Some(root)) = db.get_message(thread_id) {
+                        if root.sender_peer_id == my_peer_id {
+                            let _ = db.auto_subscribe_thread(thread_id, &msg.timestamp);
+                        }
+                    }
+                    if db.is_thread_subscribed(thread_id).unwrap_or(false) {
+                        let _ = event_tx.send(AppEvent::ThreadReply {
+                            thread_id: thread_id.clone(),
+                            message: msg.clone(),
+                        });
+                    }
+                }
+                                if let Err(e) = crate::services::notifications::process_channel_message(db, &event_tx, &my_peer_id, &known_peers, &msg) {
+                    warn!("Failed to process notifications for message {}: {}", msg.id, e);
+                }
+                let _ = event_tx.send(AppEvent::NewMessage(msg));
+            }
+        }
+        NetworkMessage::MessageEdit(edit) if edit.sender_peer_id != my_peer_id => {
+            info!("Received message edit from {}: {}", edit.sender_peer_id, edit.message_id);
+            let _ = db.edit_message(&edit.message_id, &edit.new_content, &edit.edited_at);
+            let _ = event_tx.send(AppEvent::MessageEdited {
+                message_id: edit.message_id,
+                channel_id: edit.channel_id,
+                new_content: edit.new_content,
+                edited_at: edit.edited_at,
+            });
+        }
+        NetworkMessage::MessageDelete(del) => {
+            // Only the author may delete: the signed source must be (a device of) the sender, or host it as a bot
+            let stored = db.get_message(&del.message_id).ok().flatten();
+            let allowed = match (source, &stored) {
+                (Some(source), Some(stored)) => {
+                    db.acts_for(&source.to_string(), &stored.sender_peer_id).unwrap_or(false)
+                }
+                _ => false,
+            };
+            if del.sender_peer_id != my_peer_id && allowed {
+                info!("Received message delete from {}: {}", del.sender_peer_id, del.message_id);
+                match db.delete_message(&del.message_id, &del.deleted_at) {
+                    Ok(true) => {
+                        let _ = event_tx.send(AppEvent::MessageDeleted {
+                            message_id: del.message_id,
+                            channel_id: del.channel_id,
+                        });
+                    }
+                    Ok(false) => {}
+                    Err(e) => error!("Failed to delete message {}: {}", del.message_id, e),
+                }
+            }
+        }
+        NetworkMessage::MessagesPurged(purge) => {
+            let author = source.map(|source| db.resolve_identity(&source.to_string()));
+            let sender = db.resolve_identity(&purge.sender_peer_id).unwrap_or_else(|_| purge.sender_peer_id.clone());
+            let allowed = matches!(author, Some(Ok(ref author)) if *author == sender);
+            if purge.sender_peer_id != my_peer_id && allowed {
+                info!("Received purge of {}'s messages in room {}", sender, purge.room_id);
+                match db.delete_sender_messages_in_room(&purge.room_id, &sender, &purge.deleted_at) {
+                    Ok(deleted) => {
+                        let _ = event_tx.send(AppEvent::MessagesPurged {
+                            room_id: purge.room_id,
+                            sender_peer_id: sender,
+                            message_ids: deleted.into_iter().map(|(_, id)| id).collect(),
+                        });
+                    }
+                    Err(e) => error!("Failed to purge messages in room {}: {}", purge.room_id, e),
+                }
+            }
+        }
+        NetworkMessage::Reaction(reaction) if reaction.peer_id != my_peer_id => {
+            if reaction.add {
+                let r = crate::models::Reaction {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    message_id: reaction.message_id.clone(),
+                    peer_id: reaction.peer_id.clone(),
+                    emoji: reaction.emoji.clone(),
+                    created_at: chrono::Utc::now().to_rfc3339(),
+                };
+                let _ = db.add_reaction(&r);
+                let _ = event_tx.send(AppEvent::ReactionAdded {
+                    message_id: reaction.message_id,
+                    channel_id: reaction.channel_id,
+                    peer_id: reaction.peer_id,
+                    emoji: reaction.emoji,
+                });
+            } else {
+                let _ = db.remove_reaction(&reaction.message_id, &reaction.peer_id, &reaction.emoji);
+                let _ = event_tx.send(AppEvent::ReactionRemoved {
+                    message_id: reaction.message_id,
+                    channel_id: reaction.channel_id,
+                    peer_id: reaction.peer_id,
+                    emoji: reaction.emoji,
+                });
+            }
+        }
+        NetworkMessage::ReadReceipt(rr) if rr.peer_id != my_peer_id => {
+            let rr = crate::models::ReadReceiptNet {
+                peer_id: db.resolve_identity(&rr.peer_id).unwrap_or(rr.peer_id),
+                ..rr
+            };
+            let _ = db.set_read_receipt(&rr.channel_id, &rr.peer_id, &rr.last_read_message_id, &chrono::Utc::now().to_rfc3339());
+            let _ = event_tx.send(AppEvent::ReadReceiptUpdated {
+                channel_id: rr.channel_id,
+                peer_id: rr.peer_id,
+                last_read_message_id: rr.last_read_message_id,
+            });
+        }
+        NetworkMessage::DmMessage(dm) if dm.sender_peer_id != my_peer_id => {
+            let dm = crate::models::DmMessageNet {
+                sender_peer_id: db.resolve_identity(&dm.sender_peer_id).unwrap_or(dm.sender_peer_id),
+                ..dm
+            };
+            let _ = db.insert_dm_message(&dm.id, &dm.conversation_id, &dm.sender_peer_id, &dm.sender_display_name, &dm.content, &dm.timestamp);
+            let msg = crate::models::DmMessage {
+                id: dm.id,
+                conversation_id: dm.conversation_id,
+                sender_peer_id: dm.sender_peer_id,
+                sender_display_name: dm.sender_display_name,
+                content: dm.content,
+                timestamp: dm.timestamp,
+            };
+            if let Err(e) = crate::services::notifications::process_dm_message(db, &event_tx, &my_peer_id, &msg) {
+                warn!("Failed to process notifications for DM {}: {}", msg.id, e);
+            }
+            let _ = event_tx.send(AppEvent::NewDmMessage(msg));
+        }
+        NetworkMessage::FriendRequest(fr) if fr.to_peer_id == my_peer_id => {
+            match fr.action.as_str() {
+                "request" => {
+                    let friend = crate::models::Friend {
+                        peer_id: fr.from_peer_id.clone(),
+                        display_name: fr.from_display_name.clone(),
+                        status: "pending_incoming".to_string(),
+                        created_at: chrono::Utc::now().to_rfc3339(),
+                    };
+                    let _ = db.add_friend(&friend);
+                    let _ = event_tx.send(AppEvent::FriendRequestReceived {
+                        from_peer_id: fr.from_peer_id,
+                        from_display_name: fr.from_display_name,
+                    });
+                }
+                "accept" => {
+                    let _ = db.update_friend_status(&fr.from_peer_id, "accepted");
+                    let _ = event_tx.send(AppEvent::FriendRequestAccepted {
+                        peer_id: fr.from_peer_id,
+                    });
+                }
+                "remove" => {
+                    let _ = db.remove_friend(&fr.from_peer_id);
+                }
+                _ => {}
+            }
+        }
+        NetworkMessage::ChannelCreated(ch) => {
+            info!("Received channel created: {} in room {}", ch.name, ch.room_id);
+            // Anyone may add a channel, but only channel managers file it under a category
+            let category_id = ch.category_id.filter(|_| may_manage_channels(db, source, &ch.room_id));
+            // Save to local DB if we're in this room
+            let channel = crate::models::Channel {
+                id: ch.channel_id.clone(),
+                room_id: ch.room_id.clone(),
+                name: ch.name.clone(),
+                created_at: ch.created_at.clone(),
+                channel_type: ch.channel_type.clone(),
+                topic: None,
+                position: 0,
+                category_id: category_id.clone(),
+                inherit_permissions: true,
+                permissions: None,
+            };
+            let _ = db.create_channel(&channel);
+            let _ = event_tx.send(AppEvent::ChannelCreated {
+                room_id: ch.room_id,
+                channel_id: ch.channel_id,
+                name: ch.name,
+                channel_type: ch.channel_type,
+                created_at: ch.created_at,
+                category_id,
+            });
+        }
+        NetworkMessage::ChannelDeleted(ch) => {
+            info!("Received channel deleted: {} in room {}", ch.channel_id, ch.room_id);
+            let _ = db.delete_channel(&ch.channel_id);
+            let _ = event_tx.send(AppEvent::ChannelDeleted {
+                room_id: ch.room_id,
+                channel_id: ch.channel_id,
+            });
+        }
+        NetworkMessage::ChannelSync { room_id, channels, categories } => {
+            info!("Received channel sync for room {} with {} channels, {} categories", room_id, channels.len(), categories.len());
+            // The sync carries layout and permissions, so only channel managers' is applied
+            if !may_manage_channels(db, source, &room_id) {
+                debug!("Ignoring channel sync for room {} from a peer that cannot manage channels", room_id);
+                return;
+            }
+            // Categories first so channels can reference them
+            for cat in categories.into_iter().filter(|c| c.room_id == room_id) {
+                let mut category = category_from_sync(cat);
+                match db.get_category(&category.id) {
+                    Ok(None) => {}
+                    Ok(Some(existing)) if existing.room_id == room_id => {
+                        if existing.name == category.name
+                            && existing.position == category.position
+                            && existing.permissions == category.permissions
+                        {
+                            continue;
+                        }
+                        category.collapsed = existing.collapsed;
+                    }
+                    _ => continue,
+                }
+                let _ = db.upsert_category(&category);
+                let _ = event_tx.send(AppEvent::CategoryUpserted(category));
+            }
+            let existing = db.get_channels(&room_id).unwrap_or_default();
+            for ch in channels {
+                if let Some(current) = existing.iter().find(|c| c.id == ch.channel_id) {
+                    let changed = current.category_id != ch.category_id
+                        || current.position != ch.position
+                        || current.inherit_permissions != ch.inherit_permissions
+                        || current.permissions != ch.permissions;
+                    if changed {
+                        let _ = db.set_channel_layout(
+                            &ch.channel_id,
+                            ch.category_id.as_deref(),
+                            ch.position,
+                            ch.inherit_permissions,
+                            ch.permissions.as_deref(),
+                        );
+                        let _ = event_tx.send(AppEvent::ChannelLayoutChanged {
+                            room_id: room_id.clone(),
+                            channel_id: ch.channel_id,
+                            category_id: ch.category_id,
+                            position: ch.position,
+                            inherit_permissions: ch.inherit_permissions,
+                        });
+                    }
+                } else {
+                    let channel = crate::models::Channel {
+                        id: ch.channel_id.clone(),
+                        room_id: room_id.clone(),
+                        name: ch.name.clone(),
+                        created_at: ch.created_at.clone(),
+                        channel_type: ch.channel_type.clone(),
+                        topic: ch.topic.clone(),
+                        position: ch.position,
+                        category_id: ch.category_id.clone(),
+                        inherit_permissions: ch.inherit_permissions,
+                        permissions: ch.permissions.clone(),
+                    };
+                    let _ = db.create_channel(&channel);
+                    let _ = event_tx.send(AppEvent::ChannelCreated {
+                        room_id: room_id.clone(),
+                        channel_id: ch.channel_id,
+                        name: ch.name,
+                        channel_type: ch.channel_type,
+                        created_at: ch.created_at,
+                        category_id: ch.category_id,
+                    });
+                }
+            }
+        }
+        NetworkMessage::ThreadCreated(tc) if tc.created_by != my_peer_id => {
+            info!("Thread {} created in channel {}", tc.thread_id, tc.channel_id);
+            let thread = crate::models::Thread {
+                id: tc.thread_id,
+                channel_id: tc.channel_id,
+                name: tc.name,
+                created_by: tc.created_by,
+                created_at: tc.created_at,
+            };
+            if db.create_thread(&thread).unwrap_or(false) {
+                if let Ok(Some(root)) = db.get_message(&thread.id) {
+                    if root.sender_peer_id == my_peer_id {
+                        let _ = db.auto_subscribe_thread(&thread.id, &thread.created_at);
+                    }
+                }
+                let _ = event_tx.send(AppEvent::ThreadCreated(thread));
+            }
+        }
+        NetworkMessage::CategoryUpserted(cat) => {
+            info!("Received category {} in room {}", cat.name, cat.room_id);
+            let mut category = category_from_sync(cat);
+            let existing = db.get_category(&category.id).ok().flatten();
+            let same_room = existing.as_ref().map_or(true, |c| c.room_id == category.room_id);
+            if !same_room || !may_manage_channels(db, source, &category.room_id) {
+                warn!("Ignoring category {} from a peer that cannot manage channels in room {}", category.id, category.room_id);
+                return;
+            }
+            // Keep the local collapsed state
+            if let Some(existing) = existing {
+                category.collapsed = existing.collapsed;
+            }
+            let _ = db.upsert_category(&category);
+            let _ = event_tx.send(AppEvent::CategoryUpserted(category));
+        }
+        NetworkMessage::RetentionPolicyUpdated { room_id, policy } => {
+            info!("Received {} retention policy for {} in room {}", policy.scope.as_str(), policy.target_id, room_id);
+            // The author must manage the room; managers may also pass on each other's policies
+            let authorized = may_manage_channels(db, source, &room_id)
+                && db.can_manage_channels(&room_id, &policy.updated_by).unwrap_or(false);
+            if !authorized || !retention_target_in_room(db, &policy, &room_id) {
+                warn!("Ignoring retention policy for {} from a peer that cannot manage room {}", policy.target_id, room_id);
+                return;
+            }
+            if !timestamp_plausible(&policy.updated_at) {
+                warn!("Ignoring retention policy for {} stamped {}", policy.target_id, policy.updated_at);
+                return;
+            }
+            if db.upsert_retention_policy(&policy).unwrap_or(false) {
+                let _ = event_tx.send(AppEvent::RetentionPolicyUpdated(policy));
+            }
+        }
+        NetworkMessage::CategoryDeleted(cat) => {
+            info!("Received category deleted: {} in room {}", cat.category_id, cat.room_id);
+            let same_room = db.get_category(&cat.category_id).ok().flatten().is_some_and(|c| c.room_id == cat.room_id);
+            if !same_room || !may_manage_channels(db, source, &cat.room_id) {
+                warn!("Ignoring deletion of category {} from a peer that cannot manage channels in room {}", cat.category_id, cat.room_id);
+                return;
+            }
+            let _ = db.delete_category(&cat.category_id);
+            let _ = event_tx.send(AppEvent::CategoryDeleted {
+                room_id: cat.room_id,
+                category_id: cat.category_id,
+            });
+        }
+        NetworkMessage::ChannelLayout(layout) => {
+            debug!("Received channel layout for {} in room {}", layout.channel_id, layout.room_id);
+            let same_room = db.get_room_id_for_channel(&layout.channel_id).ok().flatten().as_deref() == Some(layout.room_id.as_str());
+            if !same_room || !may_manage_channels(db, source, &layout.room_id) {
+                warn!("Ignoring layout of channel {} from a peer that cannot manage channels in room {}", layout.channel_id, layout.room_id);
+                return;
+            }
+            let _ = db.set_channel_layout(
+                &layout.channel_id,
+                layout.category_id.as_deref(),
+                layout.position,
+                layout.inherit_permissions,
+                layout.permissions.as_deref(),
+            );
+            let _ = event_tx.send(AppEvent::ChannelLayoutChanged {
+                room_id: layout.room_id,
+                channel_id: layout.channel_id,
+                category_id: layout.category_id,
+                position: layout.position,
+                inherit_permissions: layout.inherit_permissions,
+            });
+        }
+        NetworkMessage::DeviceLinked(certificate) => {
+            if let Err(e) = crate::keys::verify_device_certificate(&certificate) {
+                warn!("Ignoring device certificate for {}: {}", certificate.device_peer_id, e);
+            } else if db.insert_device_certificate(&certificate).unwrap_or(false) {
+                info!("Device {} linked to {}", certificate.device_peer_id, certificate.identity_peer_id);
+                let _ = event_tx.send(AppEvent::DeviceLinked(certificate));
+            }
+        }
+        NetworkMessage::DeviceUnlinked(revocation) => {
+            if let Err(e) = crate::keys::verify_device_revocation(&revocation) {
+                warn!("Ignoring device revocation for {}: {}", revocation.device_peer_id, e);
+            } else if db.revoke_device(&revocation.identity_peer_id, &revocation.device_peer_id, &revocation.revoked_at).unwrap_or(false) {
+                info!("Device {} unlinked from {}", revocation.device_peer_id, revocation.identity_peer_id);
+                let _ = event_tx.send(AppEvent::DeviceUnlinked {
+                    identity_peer_id: revocation.identity_peer_id,
+                    device_peer_id: revocation.device_peer_id,
+                });
+            }
+        }
+        NetworkMessage::DeviceSync { identity_peer_id, from_device, item } => {
+            // Only accept state from devices certified for our own identity
+            let trusted = from_device != my_peer_id
+                && identity_peer_id == device_identity
+                && db.resolve_identity(&from_device).map(|id| id == device_identity).unwrap_or(false);
+            if trusted {
+                apply_device_sync(db, &event_tx, &my_peer_id, item);
+            } else {
+                debug!("Ignoring device sync from {}", from_device);
+            }
+        }
+        NetworkMessage::KeyRotated(rotation) => {
+            if let Err(e) = crate::keys::verify_rotation(&rotation) {
+                warn!("Ignoring key rotation for {}: {}", rotation.old_peer_id, e);
+            } else if db.apply_key_rotation(&rotation).unwrap_or(false) {
+                info!("Peer {} rotated its key to {}", rotation.old_peer_id, rotation.new_peer_id);
+                let _ = event_tx.send(AppEvent::PeerKeyRotated {
+                    old_peer_id: rotation.old_peer_id,
+                    new_peer_id: rotation.new_peer_id,
+                });
+            }
+        }
+        NetworkMessage::BotCommandRegistered(command) => {
+            let (Some(room_id), Some(bot_peer_id)) = (&command.room_id, &command.bot_peer_id) else {
+                return;
+            };
+            // Only the bot's host may register its commands
+            if !source.is_some_and(|source| db.acts_for(&source.to_string(), bot_peer_id).unwrap_or(false)) {
+                warn!("Ignoring command /{} for bot {} from a peer that does not host it", command.name, bot_peer_id);
+                return;
+            }
+            debug!("Bot {} registered /{} in room {}", bot_peer_id, command.name, room_id);
+            let _ = db.upsert_bot_command(&command, room_id, bot_peer_id, &chrono::Utc::now().to_rfc3339());
+        }
+        NetworkMessage::BotCommandUnregistered { room_id, bot_peer_id, name }
+            if source.is_some_and(|source| db.acts_for(&source.to_string(), &bot_peer_id).unwrap_or(false)) =>
+        {
+            debug!("Bot {} unregistered /{} in room {}", bot_peer_id, name, room_id);
+            let _ = db.delete_bot_command(&room_id, &name, &bot_peer_id);
+        }
+        NetworkMessage::CommandInvoked(invocation) => {
+            // Invocations are for the host of the bot, and must come from the invoker
+            let hosted = db
+                .get_bot(&invocation.bot_peer_id)
+                .ok()
+                .flatten()
+                .is_some_and(|bot| bot.rooms.contains(&invocation.room_id));
+            let authentic = source
+                .is_some_and(|source| db.acts_for(&source.to_string(), &invocation.invoker_peer_id).unwrap_or(false));
+            if hosted && authentic {
+                info!("/{} invoked for bot {} by {}", invocation.command, invocation.bot_peer_id, invocation.invoker_peer_id);
+                let _ = event_tx.send(AppEvent::CommandInvoked(invocation));
+            }
+        }
+        // The rest need the swarm or the loop's own state
+        _ => {}
+    }
+}
+
+/// Announcements of ourselves and of the bots we host in `room_id`.
+fn room_announcements(db: &Database, my_peer_id: &str, room_id: &str) -> Vec<PeerAnnouncement> {
+    let display_name = db.get_display_name().unwrap_or_else(|_| "Anonymous".to_string());
+    let mut announcements = vec![PeerAnnouncement {
+        peer_id: my_peer_id.to_string(),
+        display_name,
+        room_id: room_id.to_string(),
+        is_bot: false,
+        bot_certificate: None,
+    }];
+    for (display_name, certificate) in db.get_room_bot_announcements(room_id).unwrap_or_default() {
+        announcements.push(PeerAnnouncement {
+            peer_id: certificate.bot_peer_id.clone(),
+            display_name,
+            room_id: room_id.to_string(),
+            is_bot: true,
+            bot_certificate: Some(certificate),
+        });
+    }
+    announcements
+}
+
+/// Everything a peer that just subscribed to `room_id` should hear from us.
+fn room_catch_up(db: &Database, my_peer_id: &str, device_identity: &str, room_id: &str) -> Vec<NetworkMessage> {
+    // Re-announce our presence (and our bots') so the new peer learns our display names
+    let mut messages: Vec<NetworkMessage> = room_announcements(db, my_peer_id, room_id)
+        .into_iter()
+        .map(NetworkMessage::PeerAnnounce)
+        .collect();
+    // ...and the commands of the bots we host there
+    for command in db.list_room_bot_commands(room_id).unwrap_or_default() {
+        let hosted = command.bot_peer_id.as_deref().is_some_and(|bot| db.get_bot(bot).ok().flatten().is_some());
+        if hosted {
+            messages.push(NetworkMessage::BotCommandRegistered(command));
+        }
+    }
+
+    // Peers that missed our key rotations or device links learn of them here
+    messages.extend(db.get_key_rotations_to(my_peer_id).unwrap_or_default().into_iter().map(NetworkMessage::KeyRotated));
+    messages.extend(db.get_device_certificates(device_identity).unwrap_or_default().into_iter().map(NetworkMessage::DeviceLinked));
+
+    // New members prune on the same schedule as everyone else
+    for policy in db.get_room_retention_policies(room_id).unwrap_or_default() {
+        messages.push(NetworkMessage::RetentionPolicyUpdated { room_id: room_id.to_string(), policy });
+    }
+
+    // New members (and peers that were offline) learn which of our messages are gone
+    if let Ok(Some(deleted_at)) = db.get_message_purge(room_id, device_identity) {
+        messages.push(NetworkMessage::MessagesPurged(MessagesPurgeNet {
+            room_id: room_id.to_string(),
+            sender_peer_id: my_peer_id.to_string(),
+            deleted_at,
+        }));
+    }
+
+    // Also send channel sync so new peer gets all channels
+    if let Ok(channels) = db.get_channels(room_id) {
+        let channel_list: Vec<ChannelSyncNet> = channels.into_iter().map(|ch| ChannelSyncNet {
+            channel_id: ch.id,
+            name: ch.name,
+            channel_type: ch.channel_type,
+            created_at: ch.created_at,
+            topic: ch.topic,
+            position: ch.position,
+            category_id: ch.category_id,
+            inherit_permissions: ch.inherit_permissions,
+            permissions: ch.permissions,
+        }).collect();
+        let category_list: Vec<CategorySyncNet> = db.get_categories(room_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|c| CategorySyncNet {
+                room_id: c.room_id,
+                category_id: c.id,
+                name: c.name,
+                position: c.position,
+                created_at: c.created_at,
+                permissions: c.permissions,
+            })
+            .collect();
+        if !channel_list.is_empty() {
+            messages.push(NetworkMessage::ChannelSync {
+                room_id: room_id.to_string(),
+                channels: channel_list,
+                categories: category_list,
+            });
+        }
+    }
+    messages
+}
+
+/// Whether the signed `source` may manage channels in `room_id`.
+fn may_manage_channels(db: &Database, source: Option<PeerId>, room_id: &str) -> bool {
+    source.is_some_and(|source| db.can_manage_channels(room_id, &source.to_string()).unwrap_or(false))
+}
+
+/// A room-topic policy may only target the room itself or one of its channels.
+fn retention_target_in_room(db: &Database, policy: &RetentionPolicy, room_id: &str) -> bool {
+    match policy.scope {
+        RetentionScope::Room => policy.target_id == room_id,
+        RetentionScope::Channel => {
+            db.get_room_id_for_channel(&policy.target_id).ok().flatten().as_deref() == Some(room_id)
+        }
+        RetentionScope::Dm => false,
+    }
+}
+
+/// Whether an RFC 3339 timestamp parses and isn't meaningfully in the future.
+fn timestamp_plausible(timestamp: &str) -> bool {
+    chrono::DateTime::parse_from_rfc3339(timestamp).is_ok_and(|t| t <= chrono::Utc::now() + MAX_CLOCK_SKEW)
+}
+
+/// A bot announcement must come from the host its certificate names.
+fn bot_announcement_certified(announce: &PeerAnnouncement, source: Option<&PeerId>) -> bool {
+    match (&announce.bot_certificate, source) {
+        (Some(cert), Some(source)) => {
+            cert.bot_peer_id == announce.peer_id
+                && cert.host_peer_id == source.to_string()
+                && crate::keys::verify_bot_certificate(cert).is_ok()
+        }
+        _ => false,
+    }
+}
+
+/// Apply state mirrored from another of our devices.
+fn apply_device_sync(db: &Database, event_tx: &EventSender, my_peer_id: &str, item: DeviceSyncItem) {
+    match item {
+        DeviceSyncItem::Conversation { conversation, participants } => {
+            let _ = db.create_dm_conversation(&conversation);
+            for participant in &participants {
+                let _ = db.add_dm_participant(participant);
+            }
+        }
+        DeviceSyncItem::DmMessage(dm) => {
+            if db.insert_dm_message(&dm.id, &dm.conversation_id, &dm.sender_peer_id, &dm.sender_display_name, &dm.content, &dm.timestamp).is_ok() {
+                let _ = event_tx.send(AppEvent::NewDmMessage(crate::models::DmMessage {
+                    id: dm.id,
+                    conversation_id: dm.conversation_id,
+                    sender_peer_id: dm.sender_peer_id,
+                    sender_display_name: dm.sender_display_name,
+                    content: dm.content,
+                    timestamp: dm.timestamp,
+                }));
+            }
+        }
+        DeviceSyncItem::Retention(policy) => {
+            if timestamp_plausible(&policy.updated_at) && db.upsert_retention_policy(&policy).unwrap_or(false) {
+                let _ = event_tx.send(AppEvent::RetentionPolicyUpdated(policy));
+            }
+        }
+        DeviceSyncItem::Read { target_id, last_read_message_id } => {
+            // Unread counters are keyed by this device's peer id
+            let _ = db.set_read_receipt(&target_id, my_peer_id, &last_read_message_id, &chrono::Utc::now().to_rfc3339());
+            let _ = event_tx.send(AppEvent::ReadReceiptUpdated {
+                channel_id: target_id,
+                peer_id: my_peer_id.to_string(),
+                last_read_message_id,
+            });
+        }
+    }
+}
+
+fn category_from_sync(cat: CategorySyncNet) -> crate::models::ChannelCategory {
+    crate::models::ChannelCategory {
+        id: cat.category_id,
+        room_id: cat.room_id,
+        name: cat.name,
+        position: cat.position,
+        created_at: cat.created_at,
+        permissions: cat.permissions,
+        collapsed: false,
+    }
+}
@@ -0,0 +1,22 @@
+use crate::models::{ApiScope, ApiToken, IssuedApiToken};
+use crate::services::ServiceError;
+use crate::state::ServiceContext;
+
+pub async fn list_tokens(ctx: &ServiceContext) -> Result<Vec<ApiToken>, ServiceError> {
+    ctx.db.run(|db| db.list_api_tokens()).await.map_err(ServiceError::internal)
+}
+
+/// Issue a token for a script or client. The secret is only returned here.
+pub async fn issue_token(ctx: &ServiceContext, name: &str, scopes: Vec<ApiScope>) -> Result<IssuedApiToken, ServiceError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(ServiceError::bad_request("Token name cannot be empty"));
+    }
+    let name = name.to_string();
+    ctx.db.run(move |db| crate::api::auth::issue_token(db, &name, scopes, None)).await
+}
+
+pub async fn revoke_token(ctx: &ServiceContext, token_id: &str) -> Result<bool, ServiceError> {
+    let token_id = token_id.to_string();
+    ctx.db.run(move |db| db.revoke_api_token(&token_id)).await.map_err(ServiceError::internal)
+}
@@ -0,0 +1,20 @@
+use crate::services::ServiceError;
+use crate::state::ServiceContext;
+
+/// Build an encrypted backup archive of this node and return its bytes.
+pub async fn create_backup(ctx: &ServiceContext, passphrase: &str) -> Result<Vec<u8>, ServiceError> {
+    if passphrase.is_empty() {
+        return Err(ServiceError::bad_request("Backup passphrase must not be empty"));
+    }
+    let dir = ctx.data_dir.join("backups");
+    std::fs::create_dir_all(&dir).map_err(ServiceError::internal)?;
+    let path = dir.join(format!("{}.chatrbak", uuid::Uuid::new_v4()));
+
+    let (archive, passphrase) = (path.clone(), passphrase.to_string());
+    ctx.db
+        .run(move |db| crate::backup::create_backup(db, &archive, &passphrase))
+        .await?;
+    let bytes = std::fs::read(&path).map_err(ServiceError::internal);
+    let _ = std::fs::remove_file(&path);
+    bytes
+}
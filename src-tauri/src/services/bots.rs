@@ -0,0 +1,135 @@
+use chrono::Utc;
+use libp2p::identity::Keypair;
+
+use crate::models::{ApiScope, Bot, CreatedBot, MessageAuthor};
+use crate::network::NetworkCommand;
+use crate::services::ServiceError;
+use crate::state::ServiceContext;
+
+/// Scopes of bot tokens. Bots never get admin.
+const BOT_SCOPES: [ApiScope; 2] = [ApiScope::Read, ApiScope::Write];
+
+fn validate_name(display_name: &str) -> Result<&str, ServiceError> {
+    let display_name = display_name.trim();
+    if display_name.is_empty() {
+        return Err(ServiceError::bad_request("Bot name cannot be empty"));
+    }
+    Ok(display_name)
+}
+
+async fn require_bot(ctx: &ServiceContext, bot_peer_id: &str) -> Result<Bot, ServiceError> {
+    get_bot(ctx, bot_peer_id).await?.ok_or_else(|| ServiceError::not_found("Bot not found"))
+}
+
+/// Issue the bot a fresh token, revoking its old ones.
+async fn issue_bot_token(ctx: &ServiceContext, bot: &Bot) -> Result<String, ServiceError> {
+    let bot = bot.clone();
+    ctx.db
+        .run(move |db| {
+            db.revoke_bot_api_tokens(&bot.peer_id).map_err(ServiceError::internal)?;
+            let name = format!("bot:{}", bot.display_name);
+            crate::api::auth::issue_token(db, &name, BOT_SCOPES.to_vec(), Some(&bot.peer_id))
+        })
+        .await
+        .map(|issued| issued.secret)
+}
+
+/// Announce the bot in `room_id` so peers learn its name and that we host it.
+async fn announce(ctx: &ServiceContext, bot: &Bot, room_id: &str) -> Result<(), ServiceError> {
+    let bot_peer_id = bot.peer_id.clone();
+    let Some(certificate) = ctx.db.run(move |db| db.get_bot_certificate(&bot_peer_id)).await.map_err(ServiceError::internal)? else {
+        return Err(ServiceError::not_found("Bot certificate missing"));
+    };
+    ctx.network_tx
+        .send(NetworkCommand::AnnounceBot {
+            room_id: room_id.to_string(),
+            display_name: bot.display_name.clone(),
+            certificate,
+        })
+        .await
+        .map_err(ServiceError::internal)
+}
+
+pub async fn list_bots(ctx: &ServiceContext) -> Result<Vec<Bot>, ServiceError> {
+    ctx.db.run(|db| db.list_bots()).await.map_err(ServiceError::internal)
+}
+
+pub async fn get_bot(ctx: &ServiceContext, bot_peer_id: &str) -> Result<Option<Bot>, ServiceError> {
+    let bot_peer_id = bot_peer_id.to_string();
+    ctx.db.run(move |db| db.get_bot(&bot_peer_id)).await.map_err(ServiceError::internal)
+}
+
+/// Create a bot with a new keypair, certified to publish through this node.
+/// Its API token is only returned here (and by [`regenerate_token`]).
+pub async fn create_bot(ctx: &ServiceContext, display_name: &str) -> Result<CreatedBot, ServiceError> {
+    let display_name = validate_name(display_name)?;
+    let keypair = Keypair::generate_ed25519();
+    let certificate = crate::keys::sign_bot_certificate(&keypair, &ctx.peer_id)?;
+    let bot = Bot {
+        peer_id: certificate.bot_peer_id.clone(),
+        display_name: display_name.to_string(),
+        rooms: Vec::new(),
+        created_at: Utc::now().to_rfc3339(),
+    };
+    let stored = bot.clone();
+    ctx.db
+        .run(move |db| {
+            db.insert_bot(&stored, &crate::keys::encode_keypair(&keypair))?;
+            db.store_bot_certificate(&certificate)
+        })
+        .await
+        .map_err(ServiceError::internal)?;
+    let token = issue_bot_token(ctx, &bot).await?;
+    Ok(CreatedBot { bot, token })
+}
+
+pub async fn set_display_name(ctx: &ServiceContext, bot_peer_id: &str, display_name: &str) -> Result<Bot, ServiceError> {
+    let display_name = validate_name(display_name)?;
+    let (id, display_name) = (bot_peer_id.to_string(), display_name.to_string());
+    ctx.db
+        .run(move |db| db.set_bot_display_name(&id, &display_name))
+        .await
+        .map_err(ServiceError::internal)?;
+    let bot = require_bot(ctx, bot_peer_id).await?;
+    for room_id in &bot.rooms {
+        announce(ctx, &bot, room_id).await?;
+    }
+    Ok(bot)
+}
+
+/// Delete the bot and revoke its token. Its messages stay.
+pub async fn delete_bot(ctx: &ServiceContext, bot_peer_id: &str) -> Result<bool, ServiceError> {
+    let bot_peer_id = bot_peer_id.to_string();
+    ctx.db.run(move |db| db.delete_bot(&bot_peer_id)).await.map_err(ServiceError::internal)
+}
+
+pub async fn regenerate_token(ctx: &ServiceContext, bot_peer_id: &str) -> Result<String, ServiceError> {
+    let bot = require_bot(ctx, bot_peer_id).await?;
+    issue_bot_token(ctx, &bot).await
+}
+
+/// Add the bot to one of this node's rooms.
+pub async fn join_room(ctx: &ServiceContext, bot_peer_id: &str, room_id: &str) -> Result<Bot, ServiceError> {
+    let rooms = ctx.db.run(|db| db.list_rooms()).await.map_err(ServiceError::internal)?;
+    if !rooms.iter().any(|r| r.id == room_id) {
+        return Err(ServiceError::not_found("Room not found"));
+    }
+    require_bot(ctx, bot_peer_id).await?;
+    let (id, room) = (bot_peer_id.to_string(), room_id.to_string());
+    ctx.db.run(move |db| db.add_bot_room(&id, &room)).await.map_err(ServiceError::internal)?;
+    let bot = require_bot(ctx, bot_peer_id).await?;
+    announce(ctx, &bot, room_id).await?;
+    Ok(bot)
+}
+
+pub async fn leave_room(ctx: &ServiceContext, bot_peer_id: &str, room_id: &str) -> Result<Bot, ServiceError> {
+    let (id, room) = (bot_peer_id.to_string(), room_id.to_string());
+    ctx.db.run(move |db| db.remove_bot_room(&id, &room)).await.map_err(ServiceError::internal)?;
+    require_bot(ctx, bot_peer_id).await
+}
+
+/// Author for messages a bot token sends.
+pub async fn author(ctx: &ServiceContext, bot_peer_id: &str) -> Result<MessageAuthor, ServiceError> {
+    let bot = require_bot(ctx, bot_peer_id).await?;
+    Ok(MessageAuthor::Bot { peer_id: bot.peer_id, display_name: bot.display_name })
+}
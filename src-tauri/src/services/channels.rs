@@ -1,16 +1,40 @@
 use chrono::Utc;
+use uuid::Uuid;
 
-use crate::models::Channel;
+use crate::events::AppEvent;
+use crate::models::{CategoryLayout, Channel, ChannelCategory, ChannelLayout};
 use crate::network::NetworkCommand;
+use crate::services::ServiceError;
 use crate::services::rooms::deterministic_channel_id;
 use crate::state::ServiceContext;
 
-pub fn create_channel(
+/// Peers only accept categories, layout and permissions from channel managers,
+/// so don't make changes locally that nobody else would apply.
+async fn require_channel_manager(ctx: &ServiceContext, room_id: &str) -> Result<(), ServiceError> {
+    let (room_id, me) = (room_id.to_string(), ctx.peer_id.clone());
+    if !ctx.db.run(move |db| db.can_manage_channels(&room_id, &me)).await.map_err(ServiceError::internal)? {
+        return Err(ServiceError::forbidden("Only room owners and admins can manage channels"));
+    }
+    Ok(())
+}
+
+pub async fn create_channel(
     ctx: &ServiceContext,
     room_id: &str,
     name: &str,
     channel_type: Option<&str>,
-) -> Result<Channel, String> {
+    category_id: Option<&str>,
+) -> Result<Channel, ServiceError> {
+    if let Some(cid) = category_id {
+        require_channel_manager(ctx, room_id).await?;
+        let cid = cid.to_string();
+        let category = ctx.db.run(move |db| db.get_category(&cid)).await.map_err(ServiceError::internal)?
+            .ok_or_else(|| ServiceError::not_found("Category not found"))?;
+        if category.room_id != room_id {
+            return Err(ServiceError::bad_request("Category belongs to a different room"));
+        }
+    }
+
     let channel = Channel {
         id: deterministic_channel_id(room_id, name),
         room_id: room_id.to_string(),
@@ -19,8 +43,13 @@ pub fn create_channel(
         channel_type: channel_type.unwrap_or("text").to_string(),
         topic: None,
         position: 0,
+        category_id: category_id.map(|s| s.to_string()),
+        inherit_permissions: true,
+        permissions: None,
     };
-    ctx.db.create_channel(&channel).map_err(|e| e.to_string())?;
+    let stored = channel.clone();
+    ctx.db.run(move |db| db.create_channel(&stored)).await?;
+    ctx.event_tx.track_channel(&channel.id, &channel.room_id);
 
     // Broadcast channel creation to other peers in this room (try_send for sync context)
     let _ = ctx.network_tx.try_send(NetworkCommand::BroadcastChannelCreated {
@@ -29,24 +58,33 @@ pub fn create_channel(
         name: channel.name.clone(),
         channel_type: channel.channel_type.clone(),
         created_at: channel.created_at.clone(),
+        category_id: channel.category_id.clone(),
     });
 
     Ok(channel)
 }
 
-pub fn update_channel(
+pub async fn update_channel(
     ctx: &ServiceContext,
     channel_id: &str,
     name: Option<&str>,
     topic: Option<&str>,
     position: Option<i32>,
-) -> Result<(), String> {
-    ctx.db.update_channel(channel_id, name, topic, position)
-        .map_err(|e| e.to_string())
+) -> Result<(), ServiceError> {
+    let channel_id = channel_id.to_string();
+    let name = name.map(str::to_string);
+    let topic = topic.map(str::to_string);
+    let updated = ctx.db.run(move |db| db.update_channel(&channel_id, name.as_deref(), topic.as_deref(), position)).await
+        .map_err(ServiceError::internal)?;
+    if !updated {
+        return Err(ServiceError::not_found("Channel not found"));
+    }
+    Ok(())
 }
 
-pub fn delete_channel(ctx: &ServiceContext, channel_id: &str, room_id: Option<&str>) -> Result<(), String> {
-    ctx.db.delete_channel(channel_id).map_err(|e| e.to_string())?;
+pub async fn delete_channel(ctx: &ServiceContext, channel_id: &str, room_id: Option<&str>) -> Result<(), ServiceError> {
+    let id = channel_id.to_string();
+    ctx.db.run(move |db| db.delete_channel(&id)).await.map_err(ServiceError::internal)?;
 
     // Broadcast channel deletion if room_id is known
     if let Some(rid) = room_id {
@@ -59,6 +97,232 @@ pub fn delete_channel(ctx: &ServiceContext, channel_id: &str, room_id: Option<&s
     Ok(())
 }
 
-pub fn get_channels(ctx: &ServiceContext, room_id: &str) -> Result<Vec<Channel>, String> {
-    ctx.db.get_channels(room_id).map_err(|e| e.to_string())
+pub async fn get_channels(ctx: &ServiceContext, room_id: &str) -> Result<Vec<Channel>, ServiceError> {
+    let room_id = room_id.to_string();
+    ctx.db.run(move |db| db.get_channels(&room_id)).await.map_err(ServiceError::internal)
+}
+
+// ============================================================
+// Categories
+// ============================================================
+
+pub async fn create_category(
+    ctx: &ServiceContext,
+    room_id: &str,
+    name: &str,
+    position: Option<i32>,
+) -> Result<ChannelCategory, ServiceError> {
+    require_channel_manager(ctx, room_id).await?;
+    let id = room_id.to_string();
+    let position = match position {
+        Some(p) => p,
+        None => ctx.db.run(move |db| db.get_categories(&id)).await.map_err(ServiceError::internal)?
+            .iter()
+            .map(|c| c.position + 1)
+            .max()
+            .unwrap_or(0),
+    };
+    let category = ChannelCategory {
+        id: Uuid::new_v4().to_string(),
+        room_id: room_id.to_string(),
+        name: name.to_string(),
+        position,
+        created_at: Utc::now().to_rfc3339(),
+        permissions: None,
+        collapsed: false,
+    };
+    let stored = category.clone();
+    ctx.db.run(move |db| db.upsert_category(&stored)).await.map_err(ServiceError::internal)?;
+
+    let _ = ctx.event_tx.send(AppEvent::CategoryUpserted(category.clone()));
+    let _ = ctx.network_tx.try_send(NetworkCommand::BroadcastCategoryUpserted {
+        category: category.clone(),
+    });
+
+    Ok(category)
+}
+
+pub async fn update_category(
+    ctx: &ServiceContext,
+    category_id: &str,
+    name: Option<&str>,
+    position: Option<i32>,
+    permissions: Option<Option<&str>>,
+) -> Result<ChannelCategory, ServiceError> {
+    let id = category_id.to_string();
+    let mut category = ctx.db.run(move |db| db.get_category(&id)).await.map_err(ServiceError::internal)?
+        .ok_or_else(|| ServiceError::not_found("Category not found"))?;
+    require_channel_manager(ctx, &category.room_id).await?;
+    if let Some(name) = name {
+        category.name = name.to_string();
+    }
+    if let Some(position) = position {
+        category.position = position;
+    }
+    if let Some(permissions) = permissions {
+        category.permissions = permissions.map(|s| s.to_string());
+    }
+    let stored = category.clone();
+    ctx.db.run(move |db| db.upsert_category(&stored)).await.map_err(ServiceError::internal)?;
+
+    let _ = ctx.event_tx.send(AppEvent::CategoryUpserted(category.clone()));
+    let _ = ctx.network_tx.try_send(NetworkCommand::BroadcastCategoryUpserted {
+        category: category.clone(),
+    });
+
+    Ok(category)
+}
+
+/// Collapse or expand a category in the local sidebar. Not synced to peers.
+pub async fn set_category_collapsed(ctx: &ServiceContext, category_id: &str, collapsed: bool) -> Result<(), ServiceError> {
+    let category_id = category_id.to_string();
+    ctx.db.run(move |db| db.set_category_collapsed(&category_id, collapsed)).await.map_err(ServiceError::internal)
+}
+
+/// Delete a category. Channels inside it become uncategorized.
+pub async fn delete_category(ctx: &ServiceContext, category_id: &str) -> Result<(), ServiceError> {
+    let id = category_id.to_string();
+    let category = ctx.db.run(move |db| db.get_category(&id)).await.map_err(ServiceError::internal)?
+        .ok_or_else(|| ServiceError::not_found("Category not found"))?;
+    require_channel_manager(ctx, &category.room_id).await?;
+    let id = category_id.to_string();
+    ctx.db.run(move |db| db.delete_category(&id)).await.map_err(ServiceError::internal)?;
+
+    let _ = ctx.event_tx.send(AppEvent::CategoryDeleted {
+        room_id: category.room_id.clone(),
+        category_id: category_id.to_string(),
+    });
+    let _ = ctx.network_tx.try_send(NetworkCommand::BroadcastCategoryDeleted {
+        room_id: category.room_id,
+        category_id: category_id.to_string(),
+    });
+
+    Ok(())
+}
+
+pub async fn get_categories(ctx: &ServiceContext, room_id: &str) -> Result<Vec<ChannelCategory>, ServiceError> {
+    let room_id = room_id.to_string();
+    ctx.db.run(move |db| db.get_categories(&room_id)).await.map_err(ServiceError::internal)
+}
+
+/// Move a channel into a category (or out of one with `None`) and optionally reorder it.
+pub async fn move_channel(
+    ctx: &ServiceContext,
+    channel_id: &str,
+    category_id: Option<&str>,
+    position: Option<i32>,
+) -> Result<Channel, ServiceError> {
+    let id = channel_id.to_string();
+    let mut channel = ctx.db.run(move |db| db.get_channel(&id)).await.map_err(ServiceError::internal)?
+        .ok_or_else(|| ServiceError::not_found("Channel not found"))?;
+    if let Some(cid) = category_id {
+        let cid = cid.to_string();
+        let category = ctx.db.run(move |db| db.get_category(&cid)).await.map_err(ServiceError::internal)?
+            .ok_or_else(|| ServiceError::not_found("Category not found"))?;
+        if category.room_id != channel.room_id {
+            return Err(ServiceError::bad_request("Category belongs to a different room"));
+        }
+    }
+    channel.category_id = category_id.map(|s| s.to_string());
+    if let Some(position) = position {
+        channel.position = position;
+    }
+    apply_channel_layout(ctx, &channel).await?;
+    Ok(channel)
+}
+
+/// Set whether a channel inherits its category's permissions, and/or its own overwrites.
+pub async fn set_channel_permissions(
+    ctx: &ServiceContext,
+    channel_id: &str,
+    inherit_permissions: Option<bool>,
+    permissions: Option<Option<&str>>,
+) -> Result<Channel, ServiceError> {
+    let id = channel_id.to_string();
+    let mut channel = ctx.db.run(move |db| db.get_channel(&id)).await.map_err(ServiceError::internal)?
+        .ok_or_else(|| ServiceError::not_found("Channel not found"))?;
+    if let Some(inherit) = inherit_permissions {
+        channel.inherit_permissions = inherit;
+    }
+    if let Some(permissions) = permissions {
+        channel.permissions = permissions.map(|s| s.to_string());
+    }
+    apply_channel_layout(ctx, &channel).await?;
+    Ok(channel)
+}
+
+/// Resolve the permissions that apply to a channel: the category's when the
+/// channel inherits and sits in a category, otherwise the channel's own.
+pub async fn get_effective_permissions(ctx: &ServiceContext, channel_id: &str) -> Result<Option<String>, ServiceError> {
+    let id = channel_id.to_string();
+    let channel = ctx.db.run(move |db| db.get_channel(&id)).await.map_err(ServiceError::internal)?
+        .ok_or_else(|| ServiceError::not_found("Channel not found"))?;
+    if channel.inherit_permissions {
+        if let Some(cid) = channel.category_id.clone() {
+            if let Some(category) = ctx.db.run(move |db| db.get_category(&cid)).await.map_err(ServiceError::internal)? {
+                return Ok(category.permissions);
+            }
+        }
+    }
+    Ok(channel.permissions)
+}
+
+/// Build the nested sidebar layout of a room.
+pub async fn get_channel_layout(ctx: &ServiceContext, room_id: &str) -> Result<ChannelLayout, ServiceError> {
+    let id = room_id.to_string();
+    let (categories, channels) = ctx
+        .db
+        .run(move |db| Ok::<_, rusqlite::Error>((db.get_categories(&id)?, db.get_channels(&id)?)))
+        .await
+        .map_err(ServiceError::internal)?;
+
+    let mut layout = ChannelLayout {
+        room_id: room_id.to_string(),
+        uncategorized: Vec::new(),
+        categories: categories
+            .into_iter()
+            .map(|category| CategoryLayout { category, channels: Vec::new() })
+            .collect(),
+    };
+    // Channels are already ordered by position; keep that order within each group
+    for channel in channels {
+        let slot = channel.category_id.as_ref().and_then(|cid| {
+            layout.categories.iter_mut().find(|c| &c.category.id == cid)
+        });
+        match slot {
+            Some(group) => group.channels.push(channel),
+            None => layout.uncategorized.push(channel),
+        }
+    }
+    Ok(layout)
+}
+
+async fn apply_channel_layout(ctx: &ServiceContext, channel: &Channel) -> Result<(), ServiceError> {
+    require_channel_manager(ctx, &channel.room_id).await?;
+    let stored = channel.clone();
+    ctx.db
+        .run(move |db| {
+            let channel = &stored;
+            db.set_channel_layout(
+                &channel.id,
+                channel.category_id.as_deref(),
+                channel.position,
+                channel.inherit_permissions,
+                channel.permissions.as_deref(),
+            )
+        })
+        .await
+        .map_err(ServiceError::internal)?;
+
+    let _ = ctx.event_tx.send(AppEvent::ChannelLayoutChanged {
+        room_id: channel.room_id.clone(),
+        channel_id: channel.id.clone(),
+        category_id: channel.category_id.clone(),
+        position: channel.position,
+        inherit_permissions: channel.inherit_permissions,
+    });
+    let _ = ctx.network_tx.try_send(NetworkCommand::BroadcastChannelLayout {
+        channel: channel.clone(),
+    });
+    Ok(())
 }
@@ -0,0 +1,721 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use serde_json::{Map, Value};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::events::AppEvent;
+use crate::models::{
+    CommandInvocation, CommandOption, CommandOptionType, CommandResult, CommandSuggestion, Notification, PeerInfo,
+    Reminder, SlashCommand,
+};
+use crate::network::NetworkCommand;
+use crate::services::ServiceError;
+use crate::state::ServiceContext;
+
+/// How often due reminders are raised.
+pub const REMINDER_INTERVAL: Duration = Duration::from_secs(15);
+
+const MAX_NAME_CHARS: usize = 32;
+const MAX_OPTIONS: usize = 10;
+const MAX_POLL_CHOICES: usize = 10;
+/// Longest `duration` argument: a year.
+const MAX_DURATION_SECS: i64 = 366 * 24 * 60 * 60;
+
+const POLL_EMOJI: [&str; MAX_POLL_CHOICES] = [
+    "1\u{fe0f}\u{20e3}",
+    "2\u{fe0f}\u{20e3}",
+    "3\u{fe0f}\u{20e3}",
+    "4\u{fe0f}\u{20e3}",
+    "5\u{fe0f}\u{20e3}",
+    "6\u{fe0f}\u{20e3}",
+    "7\u{fe0f}\u{20e3}",
+    "8\u{fe0f}\u{20e3}",
+    "9\u{fe0f}\u{20e3}",
+    "\u{1f51f}",
+];
+
+type OptionSpec = (&'static str, CommandOptionType, bool, &'static str);
+
+const MODERATION_OPTIONS: &[OptionSpec] = &[
+    ("user", CommandOptionType::User, true, "Member to act on"),
+    ("reason", CommandOptionType::Text, false, "Recorded in the audit log"),
+];
+
+/// `(name, description, options)` of the commands every channel has.
+const BUILTINS: &[(&str, &str, &[OptionSpec])] = &[
+    ("topic", "Set the channel topic", &[("topic", CommandOptionType::Text, true, "The new topic")]),
+    ("kick", "Remove a member from the room", MODERATION_OPTIONS),
+    ("ban", "Ban a member from the room", MODERATION_OPTIONS),
+    ("warn", "Warn a member", MODERATION_OPTIONS),
+    (
+        "mute",
+        "Mute a member for a while",
+        &[
+            ("user", CommandOptionType::User, true, "Member to mute"),
+            ("duration", CommandOptionType::Duration, true, "How long, e.g. 10m or 1h"),
+            ("reason", CommandOptionType::Text, false, "Recorded in the audit log"),
+        ],
+    ),
+    (
+        "poll",
+        "Post a poll to vote on with reactions",
+        &[
+            ("question", CommandOptionType::String, true, "Quote it if it has spaces"),
+            ("choices", CommandOptionType::Text, true, "Choices separated by |"),
+        ],
+    ),
+    (
+        "remind",
+        "Get a notification later",
+        &[
+            ("when", CommandOptionType::Duration, true, "e.g. 30m, 2h, 1d"),
+            ("what", CommandOptionType::Text, true, "What to remind you of"),
+        ],
+    ),
+];
+
+pub fn builtin_commands() -> Vec<SlashCommand> {
+    BUILTINS
+        .iter()
+        .map(|(name, description, options)| SlashCommand {
+            name: name.to_string(),
+            description: description.to_string(),
+            options: options
+                .iter()
+                .map(|(name, option_type, required, description)| CommandOption {
+                    name: name.to_string(),
+                    description: description.to_string(),
+                    option_type: *option_type,
+                    required: *required,
+                })
+                .collect(),
+            bot_peer_id: None,
+            room_id: None,
+        })
+        .collect()
+}
+
+/// `/name <required> [optional]`.
+pub fn usage(command: &SlashCommand) -> String {
+    let mut usage = format!("/{}", command.name);
+    for option in &command.options {
+        if option.required {
+            usage.push_str(&format!(" <{}>", option.name));
+        } else {
+            usage.push_str(&format!(" [{}]", option.name));
+        }
+    }
+    usage
+}
+
+// ============================================================
+// Parsing
+// ============================================================
+
+/// Split off the next argument, a word or a "quoted phrase", and the rest of the input.
+fn next_word(input: &str) -> Option<(String, &str)> {
+    let input = input.trim_start();
+    if input.is_empty() {
+        return None;
+    }
+    if let Some(quoted) = input.strip_prefix('"') {
+        if let Some(end) = quoted.find('"') {
+            return Some((quoted[..end].to_string(), &quoted[end + 1..]));
+        }
+    }
+    let end = input.find(char::is_whitespace).unwrap_or(input.len());
+    Some((input[..end].to_string(), &input[end..]))
+}
+
+/// Seconds in a duration such as `90s`, `10m`, `1h30m`, `2d` or `1w`.
+pub fn parse_duration(input: &str) -> Result<i64, ServiceError> {
+    let invalid = || ServiceError::bad_request(format!("'{}' is not a duration; try 30s, 10m, 2h, 1d or 1w", input));
+    let mut total: i64 = 0;
+    let mut number = String::new();
+    for c in input.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+        let value: i64 = number.parse().map_err(|_| invalid())?;
+        total = total.saturating_add(value.saturating_mul(unit));
+        number.clear();
+    }
+    if !number.is_empty() || total <= 0 {
+        return Err(invalid());
+    }
+    if total > MAX_DURATION_SECS {
+        return Err(ServiceError::bad_request("Durations are limited to a year"));
+    }
+    Ok(total)
+}
+
+/// `1h 30m` for 5400 seconds.
+fn format_duration(secs: i64) -> String {
+    let parts: Vec<String> = [("d", 24 * 60 * 60), ("h", 60 * 60), ("m", 60), ("s", 1)]
+        .iter()
+        .scan(secs, |left, (unit, size)| {
+            let count = *left / size;
+            *left %= size;
+            Some((count > 0).then(|| format!("{}{}", count, unit)))
+        })
+        .flatten()
+        .collect();
+    parts.join(" ")
+}
+
+/// The peer `input` names: `<@peer_id>`, `@name` or a bare peer ID or name.
+fn resolve_user<'a>(input: &str, peers: &'a [PeerInfo]) -> Result<&'a PeerInfo, ServiceError> {
+    let name = input
+        .strip_prefix("<@")
+        .and_then(|s| s.strip_suffix('>'))
+        .or_else(|| input.strip_prefix('@'))
+        .unwrap_or(input);
+    peers
+        .iter()
+        .find(|p| p.peer_id == name)
+        .or_else(|| peers.iter().find(|p| p.display_name.eq_ignore_ascii_case(name)))
+        .ok_or_else(|| ServiceError::bad_request(format!("No member named '{}' in this room", name)))
+}
+
+fn parse_value(option: &CommandOption, raw: &str, peers: &[PeerInfo]) -> Result<Value, ServiceError> {
+    match option.option_type {
+        CommandOptionType::String | CommandOptionType::Text => Ok(Value::from(raw)),
+        CommandOptionType::Integer => raw
+            .parse::<i64>()
+            .map(Value::from)
+            .map_err(|_| ServiceError::bad_request(format!("<{}> must be a whole number", option.name))),
+        CommandOptionType::User => resolve_user(raw, peers).map(|peer| Value::from(peer.peer_id.as_str())),
+        CommandOptionType::Duration => parse_duration(raw).map(Value::from),
+    }
+}
+
+/// Parse `args` into the command's options, by name.
+pub fn parse_options(command: &SlashCommand, args: &str, peers: &[PeerInfo]) -> Result<Map<String, Value>, ServiceError> {
+    let mut rest = args;
+    let mut values = Map::new();
+    for option in &command.options {
+        let raw = if option.option_type == CommandOptionType::Text {
+            let text = rest.trim();
+            rest = "";
+            (!text.is_empty()).then(|| text.to_string())
+        } else {
+            match next_word(rest) {
+                Some((word, after)) => {
+                    rest = after;
+                    Some(word)
+                }
+                None => None,
+            }
+        };
+        match raw {
+            Some(raw) => {
+                values.insert(option.name.clone(), parse_value(option, &raw, peers)?);
+            }
+            None if option.required => {
+                return Err(ServiceError::bad_request(format!("Missing <{}>. Usage: {}", option.name, usage(command))));
+            }
+            None => {}
+        }
+    }
+    if !rest.trim().is_empty() {
+        return Err(ServiceError::bad_request(format!("Too many arguments. Usage: {}", usage(command))));
+    }
+    Ok(values)
+}
+
+// ============================================================
+// Running commands
+// ============================================================
+
+async fn room_for_channel(ctx: &ServiceContext, channel_id: &str) -> Result<String, ServiceError> {
+    let channel_id = channel_id.to_string();
+    ctx.db
+        .run(move |db| db.get_room_id_for_channel(&channel_id))
+        .await
+        .map_err(ServiceError::internal)?
+        .ok_or_else(|| ServiceError::not_found("Channel not found"))
+}
+
+/// Built-in commands followed by those bots registered in the channel's room.
+pub async fn list_commands(ctx: &ServiceContext, channel_id: &str) -> Result<Vec<SlashCommand>, ServiceError> {
+    let room_id = room_for_channel(ctx, channel_id).await?;
+    let mut commands = builtin_commands();
+    commands.extend(ctx.db.run(move |db| db.list_room_bot_commands(&room_id)).await.map_err(ServiceError::internal)?);
+    Ok(commands)
+}
+
+/// Run `input`, e.g. `/kick @bob spamming`, in `channel_id`.
+pub async fn execute(ctx: &ServiceContext, channel_id: &str, input: &str) -> Result<CommandResult, ServiceError> {
+    let Some(body) = input.trim().strip_prefix('/') else {
+        return Err(ServiceError::bad_request("Commands start with '/'"));
+    };
+    let (name, args) = body.split_once(char::is_whitespace).unwrap_or((body, ""));
+    let room_id = room_for_channel(ctx, channel_id).await?;
+    let command = list_commands(ctx, channel_id)
+        .await?
+        .into_iter()
+        .find(|c| c.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| ServiceError::bad_request(format!("Unknown command /{}", name)))?;
+    let peers = crate::services::peers::get_room_peers(ctx, &room_id).await?;
+    let options = parse_options(&command, args, &peers)?;
+
+    match command.bot_peer_id {
+        Some(bot_peer_id) => invoke_bot(ctx, &command.name, &bot_peer_id, &room_id, channel_id, options).await,
+        None => run_builtin(ctx, &command.name, &room_id, channel_id, &options, &peers).await,
+    }
+}
+
+async fn invoke_bot(
+    ctx: &ServiceContext,
+    command: &str,
+    bot_peer_id: &str,
+    room_id: &str,
+    channel_id: &str,
+    options: Map<String, Value>,
+) -> Result<CommandResult, ServiceError> {
+    let invoker_display_name = ctx.db.run(|db| db.get_display_name()).await.map_err(ServiceError::internal)?;
+    let invocation = CommandInvocation {
+        id: Uuid::new_v4().to_string(),
+        command: command.to_string(),
+        bot_peer_id: bot_peer_id.to_string(),
+        room_id: room_id.to_string(),
+        channel_id: channel_id.to_string(),
+        invoker_peer_id: crate::services::devices::my_identity(ctx).await?,
+        invoker_display_name,
+        options,
+        created_at: Utc::now().to_rfc3339(),
+    };
+    // Bots hosted elsewhere hear of it through their host
+    if crate::services::bots::get_bot(ctx, bot_peer_id).await?.is_some() {
+        let _ = ctx.event_tx.send(AppEvent::CommandInvoked(invocation.clone()));
+    } else {
+        ctx.network_tx
+            .send(NetworkCommand::BroadcastCommandInvocation { invocation: invocation.clone() })
+            .await
+            .map_err(ServiceError::internal)?;
+    }
+    Ok(CommandResult { invocation: Some(invocation), ..Default::default() })
+}
+
+async fn run_builtin(
+    ctx: &ServiceContext,
+    name: &str,
+    room_id: &str,
+    channel_id: &str,
+    options: &Map<String, Value>,
+    peers: &[PeerInfo],
+) -> Result<CommandResult, ServiceError> {
+    let text = |key: &str| options.get(key).and_then(Value::as_str).unwrap_or_default();
+    let response = match name {
+        "topic" => {
+            crate::services::channels::update_channel(ctx, channel_id, None, Some(text("topic")), None).await?;
+            format!("Topic set to \"{}\"", text("topic"))
+        }
+        "kick" | "ban" | "warn" | "mute" => {
+            let target = text("user");
+            let reason = options.get("reason").and_then(Value::as_str);
+            let duration = options.get("duration").and_then(Value::as_i64);
+            let expires_at = duration.map(|secs| (Utc::now() + chrono::Duration::seconds(secs)).to_rfc3339());
+            crate::services::moderation::moderate(ctx, room_id, name, target, reason, expires_at.as_deref()).await?;
+            let target_name = peers.iter().find(|p| p.peer_id == target).map_or(target, |p| p.display_name.as_str());
+            match (name, duration) {
+                ("kick", _) => format!("Kicked {}", target_name),
+                ("ban", _) => format!("Banned {}", target_name),
+                ("mute", Some(secs)) => format!("Muted {} for {}", target_name, format_duration(secs)),
+                _ => format!("Warned {}", target_name),
+            }
+        }
+        "poll" => {
+            let choices: Vec<&str> = text("choices").split('|').map(str::trim).filter(|c| !c.is_empty()).collect();
+            if choices.len() < 2 || choices.len() > MAX_POLL_CHOICES {
+                return Err(ServiceError::bad_request(format!("A poll needs 2 to {} choices separated by |", MAX_POLL_CHOICES)));
+            }
+            let mut content = format!("\u{1f4ca} **{}**", text("question"));
+            for (emoji, choice) in POLL_EMOJI.iter().zip(&choices) {
+                content.push_str(&format!("\n{} {}", emoji, choice));
+            }
+            content.push_str("\nReact to vote.");
+            let message =
+                crate::services::messaging::send_message(ctx, channel_id.to_string(), content, None, None, None).await?;
+            return Ok(CommandResult { message: Some(message), ..Default::default() });
+        }
+        "remind" => {
+            let secs = options.get("when").and_then(Value::as_i64).unwrap_or_default();
+            let now = Utc::now();
+            let reminder = Reminder {
+                id: Uuid::new_v4().to_string(),
+                channel_id: channel_id.to_string(),
+                content: text("what").to_string(),
+                remind_at: (now + chrono::Duration::seconds(secs)).to_rfc3339(),
+                created_at: now.to_rfc3339(),
+            };
+            ctx.db.run(move |db| db.insert_reminder(&reminder)).await.map_err(ServiceError::internal)?;
+            format!("I'll remind you in {}", format_duration(secs))
+        }
+        _ => return Err(ServiceError::bad_request(format!("Unknown command /{}", name))),
+    };
+    Ok(CommandResult { response: Some(response), ..Default::default() })
+}
+
+// ============================================================
+// Autocomplete
+// ============================================================
+
+/// Suggestions for a partly typed command: command names while the name is
+/// being typed, then members for user options, else a hint for the option.
+pub async fn autocomplete(ctx: &ServiceContext, channel_id: &str, input: &str) -> Result<Vec<CommandSuggestion>, ServiceError> {
+    if !input.trim_start().starts_with('/') {
+        return Ok(Vec::new());
+    }
+    let commands = list_commands(ctx, channel_id).await?;
+    match complete(&commands, input) {
+        Completion::Suggestions(suggestions) => Ok(suggestions),
+        Completion::Members(prefix) => {
+            let room_id = room_for_channel(ctx, channel_id).await?;
+            let peers = crate::services::peers::get_room_peers(ctx, &room_id).await?;
+            Ok(member_suggestions(peers, &prefix))
+        }
+    }
+}
+
+enum Completion {
+    Suggestions(Vec<CommandSuggestion>),
+    /// Room members whose name starts with the (lowercased) prefix.
+    Members(String),
+}
+
+fn complete(commands: &[SlashCommand], input: &str) -> Completion {
+    let Some(body) = input.trim_start().strip_prefix('/') else {
+        return Completion::Suggestions(Vec::new());
+    };
+    let Some((name, args)) = body.split_once(char::is_whitespace) else {
+        let prefix = body.to_lowercase();
+        let suggestions = commands
+            .iter()
+            .filter(|c| c.name.starts_with(&prefix))
+            .map(|c| CommandSuggestion {
+                kind: "command".to_string(),
+                insert: format!("/{} ", c.name),
+                label: usage(c),
+                description: Some(c.description.clone()),
+            })
+            .collect();
+        return Completion::Suggestions(suggestions);
+    };
+    let Some(command) = commands.iter().find(|c| c.name.eq_ignore_ascii_case(name)) else {
+        return Completion::Suggestions(Vec::new());
+    };
+
+    // The word being typed, and which option it fills
+    let partial = args.rsplit(char::is_whitespace).next().unwrap_or_default();
+    let mut typed = &args[..args.len() - partial.len()];
+    let mut index = 0;
+    while let Some((_, rest)) = next_word(typed) {
+        typed = rest;
+        index += 1;
+    }
+    let text_at = command.options.iter().position(|o| o.option_type == CommandOptionType::Text);
+    let Some(option) = command.options.get(text_at.map_or(index, |at| index.min(at))) else {
+        return Completion::Suggestions(Vec::new());
+    };
+
+    if option.option_type != CommandOptionType::User {
+        return Completion::Suggestions(vec![CommandSuggestion {
+            kind: "option".to_string(),
+            insert: String::new(),
+            label: format!("<{}>", option.name),
+            description: Some(option.description.clone()).filter(|d| !d.is_empty()),
+        }]);
+    }
+    Completion::Members(partial.trim_start_matches('@').to_lowercase())
+}
+
+fn member_suggestions(peers: Vec<PeerInfo>, prefix: &str) -> Vec<CommandSuggestion> {
+    peers
+        .into_iter()
+        .filter(|p| p.display_name.to_lowercase().starts_with(prefix))
+        .map(|p| CommandSuggestion {
+            kind: "user".to_string(),
+            insert: if p.display_name.contains(char::is_whitespace) {
+                format!("\"@{}\" ", p.display_name)
+            } else {
+                format!("@{} ", p.display_name)
+            },
+            label: p.display_name,
+            description: Some(p.peer_id),
+        })
+        .collect()
+}
+
+// ============================================================
+// Bot commands
+// ============================================================
+
+fn validate_command(name: &str, options: &[CommandOption]) -> Result<(), ServiceError> {
+    let valid_char = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_';
+    if name.is_empty() || name.len() > MAX_NAME_CHARS || !name.chars().all(valid_char) {
+        return Err(ServiceError::bad_request(format!(
+            "Command names are 1 to {} lowercase letters, digits, '-' or '_'",
+            MAX_NAME_CHARS
+        )));
+    }
+    if BUILTINS.iter().any(|(builtin, _, _)| *builtin == name) {
+        return Err(ServiceError::bad_request(format!("/{} is a built-in command", name)));
+    }
+    if options.len() > MAX_OPTIONS {
+        return Err(ServiceError::bad_request(format!("A command can have at most {} options", MAX_OPTIONS)));
+    }
+    for (i, option) in options.iter().enumerate() {
+        if option.name.is_empty() || !option.name.chars().all(valid_char) {
+            return Err(ServiceError::bad_request(format!("Invalid option name '{}'", option.name)));
+        }
+        if options[..i].iter().any(|o| o.name == option.name) {
+            return Err(ServiceError::bad_request(format!("Duplicate option '{}'", option.name)));
+        }
+        if option.option_type == CommandOptionType::Text && i + 1 < options.len() {
+            return Err(ServiceError::bad_request("A text option must be the last one"));
+        }
+        if option.required && i > 0 && !options[i - 1].required {
+            return Err(ServiceError::bad_request("Required options must come before optional ones"));
+        }
+    }
+    Ok(())
+}
+
+pub async fn list_bot_commands(ctx: &ServiceContext, bot_peer_id: &str) -> Result<Vec<SlashCommand>, ServiceError> {
+    let bot_peer_id = bot_peer_id.to_string();
+    ctx.db.run(move |db| db.list_bot_commands(&bot_peer_id)).await.map_err(ServiceError::internal)
+}
+
+/// Register `/name` for a bot in one of its rooms, replacing its earlier
+/// definition. Invocations arrive as `CommandInvoked` events.
+pub async fn register_bot_command(
+    ctx: &ServiceContext,
+    bot_peer_id: &str,
+    room_id: &str,
+    name: &str,
+    description: &str,
+    options: Vec<CommandOption>,
+) -> Result<SlashCommand, ServiceError> {
+    validate_command(name, &options)?;
+    let bot = crate::services::bots::get_bot(ctx, bot_peer_id).await?.ok_or_else(|| ServiceError::not_found("Bot not found"))?;
+    if !bot.rooms.iter().any(|r| r == room_id) {
+        return Err(ServiceError::forbidden("The bot is not in this room"));
+    }
+    let command = SlashCommand {
+        name: name.to_string(),
+        description: description.trim().to_string(),
+        options,
+        bot_peer_id: Some(bot_peer_id.to_string()),
+        room_id: Some(room_id.to_string()),
+    };
+    let now = Utc::now().to_rfc3339();
+    let (registered, room, bot) = (command.clone(), room_id.to_string(), bot_peer_id.to_string());
+    let stored = ctx
+        .db
+        .run(move |db| db.upsert_bot_command(&registered, &room, &bot, &now))
+        .await
+        .map_err(ServiceError::internal)?;
+    if !stored {
+        return Err(ServiceError::conflict(format!("Another bot already registered /{} in this room", name)));
+    }
+    let _ = ctx.network_tx.try_send(NetworkCommand::BroadcastBotCommand {
+        room_id: room_id.to_string(),
+        command: command.clone(),
+    });
+    Ok(command)
+}
+
+pub async fn unregister_bot_command(ctx: &ServiceContext, bot_peer_id: &str, room_id: &str, name: &str) -> Result<bool, ServiceError> {
+    let (room, command, bot) = (room_id.to_string(), name.to_string(), bot_peer_id.to_string());
+    let removed = ctx.db.run(move |db| db.delete_bot_command(&room, &command, &bot)).await.map_err(ServiceError::internal)?;
+    if removed {
+        let _ = ctx.network_tx.try_send(NetworkCommand::BroadcastBotCommandRemoved {
+            room_id: room_id.to_string(),
+            bot_peer_id: bot_peer_id.to_string(),
+            name: name.to_string(),
+        });
+    }
+    Ok(removed)
+}
+
+// ============================================================
+// Reminders
+// ============================================================
+
+/// Raise every due reminder as a notification.
+async fn raise_due_reminders(ctx: &ServiceContext) -> Result<(), ServiceError> {
+    let now = Utc::now().to_rfc3339();
+    let cutoff = now.clone();
+    let due = ctx.db.run(move |db| db.take_due_reminders(&cutoff)).await.map_err(ServiceError::internal)?;
+    if due.is_empty() {
+        return Ok(());
+    }
+    let display_name = ctx.db.run(|db| db.get_display_name()).await.map_err(ServiceError::internal)?;
+    for reminder in due {
+        let channel_id = reminder.channel_id.clone();
+        let room_id = ctx.db.run(move |db| db.get_room_id_for_channel(&channel_id)).await.map_err(ServiceError::internal)?;
+        let _ = ctx.event_tx.send(AppEvent::NotificationRaised(Notification {
+            message_id: reminder.id,
+            room_id,
+            channel_id: Some(reminder.channel_id),
+            conversation_id: None,
+            thread_id: None,
+            sender_peer_id: ctx.peer_id.clone(),
+            sender_display_name: display_name.clone(),
+            content: reminder.content,
+            timestamp: now.clone(),
+            reason: "reminder".to_string(),
+        }));
+    }
+    Ok(())
+}
+
+/// Raise reminders every [`REMINDER_INTERVAL`] for the life of the process.
+pub async fn run_reminders(ctx: ServiceContext) {
+    let mut interval = tokio::time::interval(REMINDER_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = raise_due_reminders(&ctx).await {
+            error!("Raising reminders failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer(peer_id: &str, display_name: &str) -> PeerInfo {
+        PeerInfo {
+            peer_id: peer_id.to_string(),
+            display_name: display_name.to_string(),
+            is_online: true,
+            is_bot: false,
+        }
+    }
+
+    fn builtin(name: &str) -> SlashCommand {
+        builtin_commands().into_iter().find(|c| c.name == name).unwrap()
+    }
+
+    fn suggestions(input: &str) -> Vec<CommandSuggestion> {
+        match complete(&builtin_commands(), input) {
+            Completion::Suggestions(suggestions) => suggestions,
+            Completion::Members(prefix) => panic!("expected suggestions, got members for '{}'", prefix),
+        }
+    }
+
+    fn bad_request<T: std::fmt::Debug>(result: Result<T, ServiceError>) -> bool {
+        matches!(result, Err(ServiceError::BadRequest(_)))
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("90s").unwrap(), 90);
+        assert_eq!(parse_duration("10m").unwrap(), 600);
+        assert_eq!(parse_duration("1h30m").unwrap(), 5400);
+        assert_eq!(parse_duration(" 2D ").unwrap(), 2 * 24 * 60 * 60);
+        assert_eq!(parse_duration("1w").unwrap(), 7 * 24 * 60 * 60);
+    }
+
+    #[test]
+    fn rejects_bad_durations() {
+        for input in ["", "10", "m", "0m", "10x", "1.5h", "-5m", "1h 30m", "99999999999999999999s"] {
+            assert!(bad_request(parse_duration(input)), "accepted '{}'", input);
+        }
+        // Over a year
+        assert!(bad_request(parse_duration("53w")));
+        assert!(parse_duration("52w").is_ok());
+    }
+
+    #[test]
+    fn parses_quoted_and_text_options() {
+        let peers = [peer("12D3KooWAlice", "Alice Smith"), peer("12D3KooWBob", "bob")];
+        let options = parse_options(&builtin("poll"), "\"Lunch where?\" Pizza | Sushi", &peers).unwrap();
+        assert_eq!(options["question"], "Lunch where?");
+        assert_eq!(options["choices"], "Pizza | Sushi");
+
+        let options = parse_options(&builtin("mute"), "\"@Alice Smith\" 10m being loud", &peers).unwrap();
+        assert_eq!(options["user"], "12D3KooWAlice");
+        assert_eq!(options["duration"], 600);
+        assert_eq!(options["reason"], "being loud");
+
+        // Mentions and bare peer ids resolve too; optional options may be left out
+        let options = parse_options(&builtin("kick"), "<@12D3KooWBob>", &peers).unwrap();
+        assert_eq!(options["user"], "12D3KooWBob");
+        assert!(!options.contains_key("reason"));
+    }
+
+    #[test]
+    fn rejects_bad_options() {
+        let peers = [peer("12D3KooWBob", "bob")];
+        assert!(bad_request(parse_options(&builtin("mute"), "@bob", &peers)));
+        assert!(bad_request(parse_options(&builtin("mute"), "@bob soon", &peers)));
+        assert!(bad_request(parse_options(&builtin("kick"), "@carol", &peers)));
+        assert!(bad_request(parse_options(&builtin("remind"), "", &peers)));
+
+        let command = SlashCommand {
+            name: "roll".to_string(),
+            description: String::new(),
+            options: vec![CommandOption {
+                name: "sides".to_string(),
+                description: String::new(),
+                option_type: CommandOptionType::Integer,
+                required: true,
+            }],
+            bot_peer_id: Some("12D3KooWBot".to_string()),
+            room_id: Some("room".to_string()),
+        };
+        assert_eq!(parse_options(&command, "20", &peers).unwrap()["sides"], 20);
+        assert!(bad_request(parse_options(&command, "twenty", &peers)));
+        assert!(bad_request(parse_options(&command, "20 6", &peers)));
+    }
+
+    #[test]
+    fn completes_command_names() {
+        // An empty prefix lists every command
+        assert_eq!(suggestions("/").len(), BUILTINS.len());
+        let names: Vec<String> = suggestions("/M").into_iter().map(|s| s.insert).collect();
+        assert_eq!(names, vec!["/mute "]);
+        assert!(suggestions("/nope").is_empty());
+        assert!(suggestions("hello").is_empty());
+        assert!(suggestions("/nope ").is_empty());
+    }
+
+    #[test]
+    fn completes_options() {
+        let hint = suggestions("/remind ");
+        assert_eq!(hint.len(), 1);
+        assert_eq!(hint[0].label, "<when>");
+        // Everything after the text option's position stays in the text option
+        assert_eq!(suggestions("/remind 10m buy milk and")[0].label, "<what>");
+        assert_eq!(suggestions("/poll \"Lunch where?\" Piz")[0].label, "<choices>");
+        assert_eq!(suggestions("/kick @bob spamming a lot")[0].label, "<reason>");
+    }
+
+    #[test]
+    fn completes_members() {
+        let prefix = match complete(&builtin_commands(), "/kick @Al") {
+            Completion::Members(prefix) => prefix,
+            Completion::Suggestions(_) => panic!("expected members"),
+        };
+        assert_eq!(prefix, "al");
+        let peers = vec![peer("a", "Alice Smith"), peer("b", "bob"), peer("c", "alan")];
+        let inserts: Vec<String> = member_suggestions(peers.clone(), &prefix).into_iter().map(|s| s.insert).collect();
+        assert_eq!(inserts, vec!["\"@Alice Smith\" ", "@alan "]);
+        // An empty prefix suggests everyone
+        assert_eq!(member_suggestions(peers, "").len(), 3);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::events::AppEvent;
+use crate::models::{DeviceCertificate, DeviceLinkRequest, DeviceSyncItem, LinkedDevice};
+use crate::network::NetworkCommand;
+use crate::services::ServiceError;
+use crate::services::identity::load_keypair;
+use crate::state::ServiceContext;
+
+/// The identity this node acts for: itself, unless it is a linked device.
+pub async fn my_identity(ctx: &ServiceContext) -> Result<String, ServiceError> {
+    let peer_id = ctx.peer_id.clone();
+    ctx.db.run(move |db| db.resolve_identity(&peer_id)).await.map_err(ServiceError::internal)
+}
+
+async fn require_primary(ctx: &ServiceContext) -> Result<(), ServiceError> {
+    if my_identity(ctx).await? != ctx.peer_id {
+        return Err(ServiceError::forbidden("Only the primary device can link or unlink devices"));
+    }
+    Ok(())
+}
+
+/// What this device shows to be linked by the primary.
+pub async fn get_link_request(ctx: &ServiceContext, device_name: Option<&str>) -> Result<DeviceLinkRequest, ServiceError> {
+    let keypair = ctx.db.run(load_keypair).await?;
+    Ok(crate::keys::device_link_request(&keypair, device_name.unwrap_or("New device")))
+}
+
+/// Certify another device's key for our identity (primary only). The
+/// certificate takes effect once the device countersigns it in [`accept_link`];
+/// we learn of that when it is broadcast.
+pub async fn link_device(ctx: &ServiceContext, request: &DeviceLinkRequest) -> Result<DeviceCertificate, ServiceError> {
+    require_primary(ctx).await?;
+    let device = request.device_peer_id.clone();
+    if ctx.db.run(move |db| db.resolve_identity(&device)).await.map_err(ServiceError::internal)? != request.device_peer_id {
+        return Err(ServiceError::conflict("Device was already linked"));
+    }
+    let keypair = ctx.db.run(load_keypair).await?;
+    crate::keys::sign_device_certificate(&keypair, request).map_err(ServiceError::bad_request)
+}
+
+/// Countersign and install the certificate the primary issued for this
+/// device. From then on this device acts for the certifying identity.
+pub async fn accept_link(ctx: &ServiceContext, certificate: &DeviceCertificate) -> Result<(), ServiceError> {
+    let keypair = ctx.db.run(load_keypair).await?;
+    let mut certificate = certificate.clone();
+    crate::keys::countersign_device_certificate(&keypair, &mut certificate).map_err(ServiceError::bad_request)?;
+    crate::keys::verify_device_certificate(&certificate).map_err(ServiceError::bad_request)?;
+    let stored = certificate.clone();
+    ctx.db.run(move |db| db.insert_device_certificate(&stored)).await.map_err(ServiceError::internal)?;
+
+    ctx.network_tx
+        .send(NetworkCommand::SubscribeDevices {
+            identity_peer_id: certificate.identity_peer_id.clone(),
+        })
+        .await
+        .map_err(ServiceError::internal)?;
+    ctx.network_tx
+        .send(NetworkCommand::BroadcastDeviceCertificate {
+            certificate: certificate.clone(),
+        })
+        .await
+        .map_err(ServiceError::internal)?;
+    let _ = ctx.event_tx.send(AppEvent::DeviceLinked(certificate.clone()));
+    Ok(())
+}
+
+pub async fn list_devices(ctx: &ServiceContext) -> Result<Vec<LinkedDevice>, ServiceError> {
+    let identity = my_identity(ctx).await?;
+    let mut devices = ctx.db.run(move |db| db.list_devices(&identity)).await.map_err(ServiceError::internal)?;
+    for device in &mut devices {
+        device.is_current = device.device_peer_id == ctx.peer_id;
+    }
+    Ok(devices)
+}
+
+/// Revoke a linked device's certificate (primary only).
+pub async fn unlink_device(ctx: &ServiceContext, device_peer_id: &str) -> Result<bool, ServiceError> {
+    require_primary(ctx).await?;
+    let keypair = ctx.db.run(load_keypair).await?;
+    let revocation = crate::keys::sign_device_revocation(&keypair, device_peer_id)?;
+    let (identity, device, revoked_at) =
+        (revocation.identity_peer_id.clone(), device_peer_id.to_string(), revocation.revoked_at.clone());
+    let revoked = ctx
+        .db
+        .run(move |db| db.revoke_device(&identity, &device, &revoked_at))
+        .await
+        .map_err(ServiceError::internal)?;
+    if revoked {
+        let _ = ctx.event_tx.send(AppEvent::DeviceUnlinked {
+            identity_peer_id: revocation.identity_peer_id.clone(),
+            device_peer_id: device_peer_id.to_string(),
+        });
+        ctx.network_tx
+            .send(NetworkCommand::BroadcastDeviceRevocation { revocation })
+            .await
+            .map_err(ServiceError::internal)?;
+    }
+    Ok(revoked)
+}
+
+/// Mirror a local change to our other devices.
+pub async fn sync_to_devices(ctx: &ServiceContext, item: DeviceSyncItem) -> Result<(), ServiceError> {
+    ctx.network_tx
+        .send(NetworkCommand::SyncToDevices { item })
+        .await
+        .map_err(ServiceError::internal)
+}
@@ -2,14 +2,15 @@ use chrono::Utc;
 use uuid::Uuid;
 
 use crate::events::AppEvent;
-use crate::models::{DmConversation, DmMessage, DmParticipant};
+use crate::models::{DeviceSyncItem, DmConversation, DmMessage, DmMessageNet, DmParticipant};
+use crate::services::ServiceError;
 use crate::state::ServiceContext;
 
-pub fn create_dm(
+pub async fn create_dm(
     ctx: &ServiceContext,
     peer_ids: Vec<String>,
     name: Option<String>,
-) -> Result<DmConversation, String> {
+) -> Result<DmConversation, ServiceError> {
     let is_group = peer_ids.len() > 1;
     let conv = DmConversation {
         id: Uuid::new_v4().to_string(),
@@ -17,7 +18,8 @@ pub fn create_dm(
         name,
         created_at: Utc::now().to_rfc3339(),
     };
-    ctx.db.create_dm_conversation(&conv).map_err(|e| e.to_string())?;
+    let stored = conv.clone();
+    ctx.db.run(move |db| db.create_dm_conversation(&stored)).await.map_err(ServiceError::internal)?;
 
     // Add self as participant
     let now = Utc::now().to_rfc3339();
@@ -26,7 +28,7 @@ pub fn create_dm(
         peer_id: ctx.peer_id.clone(),
         joined_at: now.clone(),
     };
-    ctx.db.add_dm_participant(&self_participant).map_err(|e| e.to_string())?;
+    ctx.db.run(move |db| db.add_dm_participant(&self_participant)).await.map_err(ServiceError::internal)?;
 
     // Add other participants
     for pid in peer_ids {
@@ -35,51 +37,75 @@ pub fn create_dm(
             peer_id: pid,
             joined_at: now.clone(),
         };
-        ctx.db.add_dm_participant(&participant).map_err(|e| e.to_string())?;
+        ctx.db.run(move |db| db.add_dm_participant(&participant)).await.map_err(ServiceError::internal)?;
     }
 
+    let conversation_id = conv.id.clone();
+    let participants = ctx.db.run(move |db| db.get_dm_participants(&conversation_id)).await.map_err(ServiceError::internal)?;
+    crate::services::devices::sync_to_devices(
+        ctx,
+        DeviceSyncItem::Conversation {
+            conversation: conv.clone(),
+            participants,
+        },
+    )
+    .await?;
+
     Ok(conv)
 }
 
-pub fn list_dms(ctx: &ServiceContext) -> Result<Vec<DmConversation>, String> {
-    ctx.db.list_dm_conversations().map_err(|e| e.to_string())
+pub async fn list_dms(ctx: &ServiceContext) -> Result<Vec<DmConversation>, ServiceError> {
+    ctx.db.run(|db| db.list_dm_conversations()).await.map_err(ServiceError::internal)
 }
 
-pub fn get_dm_participants(ctx: &ServiceContext, conversation_id: &str) -> Result<Vec<DmParticipant>, String> {
-    ctx.db.get_dm_participants(conversation_id).map_err(|e| e.to_string())
+pub async fn get_dm_participants(ctx: &ServiceContext, conversation_id: &str) -> Result<Vec<DmParticipant>, ServiceError> {
+    let conversation_id = conversation_id.to_string();
+    ctx.db.run(move |db| db.get_dm_participants(&conversation_id)).await.map_err(ServiceError::internal)
 }
 
-pub fn send_dm_message(
+pub async fn send_dm_message(
     ctx: &ServiceContext,
     conversation_id: &str,
     content: &str,
-) -> Result<DmMessage, String> {
-    let display_name = ctx.db.get_display_name().map_err(|e| e.to_string())?;
-    let now = Utc::now().to_rfc3339();
-    let id = Uuid::new_v4().to_string();
-
-    ctx.db.insert_dm_message(&id, conversation_id, &ctx.peer_id, &display_name, content, &now)
-        .map_err(|e| e.to_string())?;
-
+) -> Result<DmMessage, ServiceError> {
+    let display_name = ctx.db.run(|db| db.get_display_name()).await.map_err(ServiceError::internal)?;
     let msg = DmMessage {
-        id,
+        id: Uuid::new_v4().to_string(),
         conversation_id: conversation_id.to_string(),
         sender_peer_id: ctx.peer_id.clone(),
         sender_display_name: display_name,
         content: content.to_string(),
-        timestamp: now,
+        timestamp: Utc::now().to_rfc3339(),
     };
 
+    let m = msg.clone();
+    ctx.db.run(move |db| db.insert_dm_message(&m.id, &m.conversation_id, &m.sender_peer_id, &m.sender_display_name, &m.content, &m.timestamp)).await
+        .map_err(ServiceError::internal)?;
+
+    crate::services::devices::sync_to_devices(
+        ctx,
+        DeviceSyncItem::DmMessage(DmMessageNet {
+            id: msg.id.clone(),
+            conversation_id: msg.conversation_id.clone(),
+            sender_peer_id: msg.sender_peer_id.clone(),
+            sender_display_name: msg.sender_display_name.clone(),
+            content: msg.content.clone(),
+            timestamp: msg.timestamp.clone(),
+        }),
+    )
+    .await?;
+
     let _ = ctx.event_tx.send(AppEvent::NewDmMessage(msg.clone()));
     Ok(msg)
 }
 
-pub fn get_dm_messages(
+pub async fn get_dm_messages(
     ctx: &ServiceContext,
     conversation_id: &str,
     limit: Option<i64>,
     before: Option<&str>,
-) -> Result<Vec<DmMessage>, String> {
-    ctx.db.get_dm_messages(conversation_id, limit.unwrap_or(50), before)
-        .map_err(|e| e.to_string())
+) -> Result<Vec<DmMessage>, ServiceError> {
+    let (conversation_id, before) = (conversation_id.to_string(), before.map(str::to_string));
+    ctx.db.run(move |db| db.get_dm_messages(&conversation_id, limit.unwrap_or(50), before.as_deref())).await
+        .map_err(ServiceError::internal)
 }
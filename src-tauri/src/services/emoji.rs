@@ -2,14 +2,15 @@ use chrono::Utc;
 use uuid::Uuid;
 
 use crate::models::CustomEmoji;
+use crate::services::ServiceError;
 use crate::state::ServiceContext;
 
-pub fn add_emoji(
+pub async fn add_emoji(
     ctx: &ServiceContext,
     room_id: &str,
     name: &str,
     file_hash: &str,
-) -> Result<CustomEmoji, String> {
+) -> Result<CustomEmoji, ServiceError> {
     let emoji = CustomEmoji {
         id: Uuid::new_v4().to_string(),
         room_id: room_id.to_string(),
@@ -18,14 +19,17 @@ pub fn add_emoji(
         uploaded_by: ctx.peer_id.clone(),
         created_at: Utc::now().to_rfc3339(),
     };
-    ctx.db.add_custom_emoji(&emoji).map_err(|e| e.to_string())?;
+    let stored = emoji.clone();
+    ctx.db.run(move |db| db.add_custom_emoji(&stored)).await?;
     Ok(emoji)
 }
 
-pub fn remove_emoji(ctx: &ServiceContext, emoji_id: &str) -> Result<(), String> {
-    ctx.db.remove_custom_emoji(emoji_id).map_err(|e| e.to_string())
+pub async fn remove_emoji(ctx: &ServiceContext, emoji_id: &str) -> Result<(), ServiceError> {
+    let emoji_id = emoji_id.to_string();
+    ctx.db.run(move |db| db.remove_custom_emoji(&emoji_id)).await.map_err(ServiceError::internal)
 }
 
-pub fn list_emoji(ctx: &ServiceContext, room_id: &str) -> Result<Vec<CustomEmoji>, String> {
-    ctx.db.list_custom_emoji(room_id).map_err(|e| e.to_string())
+pub async fn list_emoji(ctx: &ServiceContext, room_id: &str) -> Result<Vec<CustomEmoji>, ServiceError> {
+    let room_id = room_id.to_string();
+    ctx.db.run(move |db| db.list_custom_emoji(&room_id)).await.map_err(ServiceError::internal)
 }
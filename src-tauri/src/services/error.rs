@@ -0,0 +1,107 @@
+use std::fmt;
+
+/// Why a service call failed. The API answers each kind with its own status
+/// code (see `api::error`); the Tauri commands only pass on the message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServiceError {
+    /// The input is invalid: a bad name, an unknown option, a malformed query.
+    BadRequest(String),
+    /// No valid credentials.
+    Unauthorized(String),
+    /// Valid credentials, but not allowed to do this.
+    Forbidden(String),
+    NotFound(String),
+    /// Clashes with existing state, e.g. a name that is taken.
+    Conflict(String),
+    /// Database, network or other failures that aren't the caller's fault.
+    Internal(String),
+}
+
+impl ServiceError {
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        ServiceError::BadRequest(message.into())
+    }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        ServiceError::Unauthorized(message.into())
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        ServiceError::Forbidden(message.into())
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        ServiceError::NotFound(message.into())
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        ServiceError::Conflict(message.into())
+    }
+
+    /// For `map_err` on database and other lower-level errors.
+    pub fn internal(error: impl fmt::Display) -> Self {
+        ServiceError::Internal(error.to_string())
+    }
+
+    /// Machine-readable kind, e.g. `not_found`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ServiceError::BadRequest(_) => "bad_request",
+            ServiceError::Unauthorized(_) => "unauthorized",
+            ServiceError::Forbidden(_) => "forbidden",
+            ServiceError::NotFound(_) => "not_found",
+            ServiceError::Conflict(_) => "conflict",
+            ServiceError::Internal(_) => "internal",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ServiceError::BadRequest(message)
+            | ServiceError::Unauthorized(message)
+            | ServiceError::Forbidden(message)
+            | ServiceError::NotFound(message)
+            | ServiceError::Conflict(message)
+            | ServiceError::Internal(message) => message,
+        }
+    }
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for ServiceError {}
+
+/// Untyped errors from helpers that still return `String` are internal.
+impl From<String> for ServiceError {
+    fn from(message: String) -> Self {
+        ServiceError::Internal(message)
+    }
+}
+
+/// Database errors are internal, except constraint violations such as a
+/// duplicate name, which clash with existing state.
+impl From<rusqlite::Error> for ServiceError {
+    fn from(error: rusqlite::Error) -> Self {
+        match error.sqlite_error_code() {
+            Some(rusqlite::ErrorCode::ConstraintViolation) => ServiceError::Conflict(error.to_string()),
+            _ => ServiceError::Internal(error.to_string()),
+        }
+    }
+}
+
+impl From<ServiceError> for String {
+    fn from(error: ServiceError) -> Self {
+        match error {
+            ServiceError::BadRequest(message)
+            | ServiceError::Unauthorized(message)
+            | ServiceError::Forbidden(message)
+            | ServiceError::NotFound(message)
+            | ServiceError::Conflict(message)
+            | ServiceError::Internal(message) => message,
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use crate::models::ExportFormat;
+use crate::services::ServiceError;
+use crate::state::ServiceContext;
+
+/// Render a room's history, or one channel of it, in `format`.
+pub async fn export_room(
+    ctx: &ServiceContext,
+    room_id: &str,
+    channel_id: Option<&str>,
+    format: ExportFormat,
+) -> Result<String, ServiceError> {
+    let rooms = ctx.db.run(|db| db.list_rooms()).await.map_err(ServiceError::internal)?;
+    if !rooms.iter().any(|room| room.id == room_id) {
+        return Err(ServiceError::not_found("Room not found"));
+    }
+    let (room_id, channel_id) = (room_id.to_string(), channel_id.map(str::to_string));
+    if let Some(channel_id) = channel_id.clone() {
+        let channel_room = ctx.db.run(move |db| db.get_room_id_for_channel(&channel_id)).await.map_err(ServiceError::internal)?;
+        if channel_room != Some(room_id.clone()) {
+            return Err(ServiceError::not_found("Channel not found in this room"));
+        }
+    }
+    let export = ctx.db.run(move |db| crate::export::collect(db, &room_id, channel_id.as_deref())).await?;
+    Ok(crate::export::render(&export, format)?)
+}
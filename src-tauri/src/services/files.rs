@@ -2,16 +2,17 @@ use chrono::Utc;
 use uuid::Uuid;
 
 use crate::models::{FileMetadata, MessageAttachment};
+use crate::services::ServiceError;
 use crate::state::ServiceContext;
 
-pub fn register_file(
+pub async fn register_file(
     ctx: &ServiceContext,
     filename: &str,
     size: i64,
     mime_type: &str,
     sha256_hash: &str,
     chunk_count: i32,
-) -> Result<FileMetadata, String> {
+) -> Result<FileMetadata, ServiceError> {
     let file = FileMetadata {
         id: Uuid::new_v4().to_string(),
         filename: filename.to_string(),
@@ -22,22 +23,25 @@ pub fn register_file(
         uploader_peer_id: ctx.peer_id.clone(),
         created_at: Utc::now().to_rfc3339(),
     };
-    ctx.db.insert_file(&file).map_err(|e| e.to_string())?;
+    let stored = file.clone();
+    ctx.db.run(move |db| db.insert_file(&stored)).await.map_err(ServiceError::internal)?;
     Ok(file)
 }
 
-pub fn get_file(ctx: &ServiceContext, file_id: &str) -> Result<Option<FileMetadata>, String> {
-    ctx.db.get_file(file_id).map_err(|e| e.to_string())
+pub async fn get_file(ctx: &ServiceContext, file_id: &str) -> Result<Option<FileMetadata>, ServiceError> {
+    let file_id = file_id.to_string();
+    ctx.db.run(move |db| db.get_file(&file_id)).await.map_err(ServiceError::internal)
 }
 
-pub fn attach_file(ctx: &ServiceContext, message_id: &str, file_id: &str) -> Result<(), String> {
+pub async fn attach_file(ctx: &ServiceContext, message_id: &str, file_id: &str) -> Result<(), ServiceError> {
     let attachment = MessageAttachment {
         message_id: message_id.to_string(),
         file_id: file_id.to_string(),
     };
-    ctx.db.insert_message_attachment(&attachment).map_err(|e| e.to_string())
+    ctx.db.run(move |db| db.insert_message_attachment(&attachment)).await.map_err(ServiceError::internal)
 }
 
-pub fn get_attachments(ctx: &ServiceContext, message_id: &str) -> Result<Vec<FileMetadata>, String> {
-    ctx.db.get_message_attachments(message_id).map_err(|e| e.to_string())
+pub async fn get_attachments(ctx: &ServiceContext, message_id: &str) -> Result<Vec<FileMetadata>, ServiceError> {
+    let message_id = message_id.to_string();
+    ctx.db.run(move |db| db.get_message_attachments(&message_id)).await.map_err(ServiceError::internal)
 }
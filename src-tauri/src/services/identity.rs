@@ -1,13 +1,18 @@
-use crate::models::Identity;
+use libp2p::identity::Keypair;
+
+use crate::events::AppEvent;
+use crate::models::{Identity, KeyRotation};
+use crate::network::NetworkCommand;
+use crate::services::ServiceError;
 use crate::state::ServiceContext;
 
-pub fn get_peer_id(ctx: &ServiceContext) -> Result<String, String> {
+pub async fn get_peer_id(ctx: &ServiceContext) -> Result<String, ServiceError> {
     Ok(ctx.peer_id.clone())
 }
 
-pub fn get_identity(ctx: &ServiceContext) -> Result<Identity, String> {
+pub async fn get_identity(ctx: &ServiceContext) -> Result<Identity, ServiceError> {
     let (display_name, avatar_hash, status_message, status_type) =
-        ctx.db.get_identity_profile().map_err(|e| e.to_string())?;
+        ctx.db.run(|db| db.get_identity_profile()).await.map_err(ServiceError::internal)?;
     Ok(Identity {
         peer_id: ctx.peer_id.clone(),
         display_name,
@@ -17,18 +22,122 @@ pub fn get_identity(ctx: &ServiceContext) -> Result<Identity, String> {
     })
 }
 
-pub fn get_display_name(ctx: &ServiceContext) -> Result<String, String> {
-    ctx.db.get_display_name().map_err(|e| e.to_string())
+pub async fn get_display_name(ctx: &ServiceContext) -> Result<String, ServiceError> {
+    ctx.db.run(|db| db.get_display_name()).await.map_err(ServiceError::internal)
+}
+
+pub async fn set_display_name(ctx: &ServiceContext, name: &str) -> Result<(), ServiceError> {
+    let name = name.to_string();
+    ctx.db.run(move |db| db.set_display_name(&name)).await.map_err(ServiceError::internal)
+}
+
+pub async fn set_status(ctx: &ServiceContext, message: Option<&str>, status_type: Option<&str>) -> Result<(), ServiceError> {
+    let (message, status_type) = (message.map(str::to_string), status_type.map(str::to_string));
+    ctx.db.run(move |db| db.set_status(message.as_deref(), status_type.as_deref())).await.map_err(ServiceError::internal)
+}
+
+pub async fn set_avatar_hash(ctx: &ServiceContext, hash: Option<&str>) -> Result<(), ServiceError> {
+    let hash = hash.map(str::to_string);
+    ctx.db.run(move |db| db.set_avatar_hash(hash.as_deref())).await.map_err(ServiceError::internal)
+}
+
+pub async fn is_encrypted(ctx: &ServiceContext) -> Result<bool, ServiceError> {
+    Ok(ctx.db.is_encrypted())
 }
 
-pub fn set_display_name(ctx: &ServiceContext, name: &str) -> Result<(), String> {
-    ctx.db.set_display_name(name).map_err(|e| e.to_string())
+/// Set, change or remove the at-rest encryption passphrase. Blocks other
+/// database work until the file has been re-encrypted.
+pub async fn change_passphrase(
+    ctx: &ServiceContext,
+    current: Option<&str>,
+    new: Option<&str>,
+) -> Result<(), ServiceError> {
+    let (current, new) = (current.map(str::to_string), new.map(str::to_string));
+    ctx.db.run(move |db| db.change_passphrase(current.as_deref(), new.as_deref())).await.map_err(ServiceError::bad_request)
+}
+
+pub fn load_keypair(db: &crate::db::Database) -> Result<libp2p::identity::Keypair, String> {
+    let bytes = db
+        .load_keypair()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "No identity keypair".to_string())?;
+    crate::keys::decode_keypair(&bytes).ok_or_else(|| "Stored identity keypair is corrupt".to_string())
+}
+
+/// The 24-word recovery phrase for the current identity.
+pub async fn get_recovery_phrase(ctx: &ServiceContext) -> Result<String, ServiceError> {
+    let keypair = ctx.db.run(load_keypair).await?;
+    Ok(crate::keys::recovery_phrase(&keypair))
+}
+
+/// Make `new` this node's identity key. The current key, if any, is kept in
+/// `retired_keypairs` and a rotation to `new` signed by both is recorded, so
+/// friendships, roles, room ownership and linked devices carry over. Peers
+/// learn of the rotation from [`rotate_key`] or, after a restart, catch-up.
+pub fn replace_identity(db: &crate::db::Database, new: &Keypair) -> Result<Option<KeyRotation>, String> {
+    let rotation = match db.load_keypair().map_err(|e| e.to_string())? {
+        Some(bytes) => {
+            let old = crate::keys::decode_keypair(&bytes).ok_or_else(|| "Stored identity keypair is corrupt".to_string())?;
+            if old.public() == new.public() {
+                return Err("That is already this node's identity".to_string());
+            }
+            let rotation = crate::keys::sign_rotation(&old, new)?;
+            db.retire_keypair(&rotation.old_peer_id, &bytes, &rotation.rotated_at).map_err(|e| e.to_string())?;
+            db.apply_key_rotation(&rotation).map_err(|e| e.to_string())?;
+            Some(rotation)
+        }
+        None => None,
+    };
+    db.save_keypair(&crate::keys::encode_keypair(new)).map_err(|e| e.to_string())?;
+    Ok(rotation)
+}
+
+/// Switch to `new` and tell peers. The swarm keeps signing with the old key
+/// until restart; peers resolve that key to the new identity meanwhile.
+async fn switch_identity(ctx: &ServiceContext, new: Keypair) -> Result<KeyRotation, ServiceError> {
+    let rotation = ctx
+        .db
+        .run(move |db| replace_identity(db, &new))
+        .await
+        .map_err(ServiceError::bad_request)?
+        .ok_or_else(|| ServiceError::internal("No identity keypair"))?;
+
+    ctx.network_tx
+        .send(NetworkCommand::BroadcastKeyRotation {
+            rotation: rotation.clone(),
+        })
+        .await
+        .map_err(ServiceError::internal)?;
+    // Linked devices now sync under the new identity
+    ctx.network_tx
+        .send(NetworkCommand::SubscribeDevices {
+            identity_peer_id: crate::services::devices::my_identity(ctx).await?,
+        })
+        .await
+        .map_err(ServiceError::internal)?;
+    let _ = ctx.event_tx.send(AppEvent::PeerKeyRotated {
+        old_peer_id: rotation.old_peer_id.clone(),
+        new_peer_id: rotation.new_peer_id.clone(),
+    });
+    Ok(rotation)
 }
 
-pub fn set_status(ctx: &ServiceContext, message: Option<&str>, status_type: Option<&str>) -> Result<(), String> {
-    ctx.db.set_status(message, status_type).map_err(|e| e.to_string())
+/// Replace the identity with one restored from a recovery phrase. Refused
+/// unless `overwrite`, since the current identity is replaced. Returns the
+/// restored peer id; the node must be restarted to use it.
+pub async fn import_identity(ctx: &ServiceContext, phrase: &str, overwrite: bool) -> Result<String, ServiceError> {
+    if !overwrite {
+        return Err(ServiceError::conflict("This node already has an identity; set overwrite to replace it"));
+    }
+    let keypair = crate::keys::keypair_from_phrase(phrase).map_err(ServiceError::bad_request)?;
+    let peer_id = keypair.public().to_peer_id().to_string();
+    switch_identity(ctx, keypair).await?;
+    Ok(peer_id)
 }
 
-pub fn set_avatar_hash(ctx: &ServiceContext, hash: Option<&str>) -> Result<(), String> {
-    ctx.db.set_avatar_hash(hash).map_err(|e| e.to_string())
+/// Generate a new identity key and announce "old peer id is now new peer id",
+/// signed by both keys, so peers carry friendship, roles and room ownership
+/// over. The node must be restarted to use the new key.
+pub async fn rotate_key(ctx: &ServiceContext) -> Result<KeyRotation, ServiceError> {
+    switch_identity(ctx, Keypair::generate_ed25519()).await
 }
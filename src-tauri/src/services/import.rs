@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+use crate::import::{self, DiscordExport};
+use crate::models::{Channel, ImportSummary};
+use crate::services::ServiceError;
+use crate::services::{channels, rooms};
+use crate::state::ServiceContext;
+
+/// Create a room from DiscordChatExporter channel exports. Imported messages
+/// go straight to the database: they are history, not new messages, so
+/// nothing is broadcast for them.
+pub async fn import_discord(
+    ctx: &ServiceContext,
+    room_name: Option<String>,
+    exports: Vec<DiscordExport>,
+) -> Result<ImportSummary, ServiceError> {
+    let first = exports.first().ok_or_else(|| ServiceError::bad_request("No channel exports to import"))?;
+    let name = room_name
+        .filter(|n| !n.trim().is_empty())
+        .unwrap_or_else(|| first.guild.name.clone());
+    let room = rooms::create_room(ctx, name).await?;
+
+    let mut categories: HashMap<String, String> = HashMap::new();
+    let mut channels_by_name: HashMap<String, Channel> = channels::get_channels(ctx, &room.id)
+        .await?
+        .into_iter()
+        .map(|c| (c.name.clone(), c))
+        .collect();
+
+    let ids = import::assign_ids(&exports);
+    let mut imported_channels = Vec::new();
+    let mut messages_imported = 0;
+    let mut messages_skipped = 0;
+    for (position, export) in exports.iter().enumerate() {
+        let category_id = match export.channel.category.as_deref().filter(|c| !c.is_empty()) {
+            Some(category) => match categories.get(category) {
+                Some(id) => Some(id.clone()),
+                None => {
+                    let created = channels::create_category(ctx, &room.id, category, None).await?;
+                    categories.insert(category.to_string(), created.id.clone());
+                    Some(created.id)
+                }
+            },
+            None => None,
+        };
+
+        // Channels sharing a name (including the room's own #general) are merged
+        let channel = match channels_by_name.get(&export.channel.name) {
+            Some(existing) => existing.clone(),
+            None => {
+                let created =
+                    channels::create_channel(ctx, &room.id, &export.channel.name, Some("text"), category_id.as_deref())
+                        .await?;
+                channels::update_channel(
+                    ctx,
+                    &created.id,
+                    None,
+                    export.channel.topic.as_deref().filter(|t| !t.is_empty()),
+                    Some(position as i32),
+                )
+                .await?;
+                channels_by_name.insert(created.name.clone(), created.clone());
+                created
+            }
+        };
+
+        let history = import::convert_channel(export, &channel.id, &ids, &ctx.peer_id);
+        messages_skipped += history.skipped;
+        messages_imported += ctx
+            .db
+            .run(move |db| db.import_messages(&history.messages, &history.reactions, &history.pins))
+            .await
+            .map_err(ServiceError::internal)?;
+        if !imported_channels.iter().any(|c: &Channel| c.id == channel.id) {
+            imported_channels.push(channel);
+        }
+    }
+
+    Ok(ImportSummary {
+        room,
+        channels: imported_channels,
+        messages_imported,
+        messages_skipped,
+    })
+}
@@ -0,0 +1,91 @@
+use chrono::Utc;
+use rand::RngCore;
+use uuid::Uuid;
+
+use crate::api::auth::hash_token;
+use crate::models::{CreatedIncomingWebhook, IncomingWebhook, Message, MessageAuthor, MessageEmbed};
+use crate::services::ServiceError;
+use crate::state::ServiceContext;
+
+const MAX_EMBEDS: usize = 10;
+const MAX_USERNAME_CHARS: usize = 80;
+
+/// Path incoming webhook payloads are POSTed to.
+pub fn execute_path(webhook_id: &str, token: &str) -> String {
+    format!("/api/v1/hooks/{}/{}", webhook_id, token)
+}
+
+pub async fn list_incoming_webhooks(ctx: &ServiceContext, channel_id: Option<&str>) -> Result<Vec<IncomingWebhook>, ServiceError> {
+    let channel_id = channel_id.map(str::to_string);
+    ctx.db.run(move |db| db.list_incoming_webhooks(channel_id.as_deref())).await.map_err(ServiceError::internal)
+}
+
+/// Create a webhook posting into `channel_id` as `name`. The token is only returned here.
+pub async fn create_incoming_webhook(ctx: &ServiceContext, channel_id: &str, name: &str) -> Result<CreatedIncomingWebhook, ServiceError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(ServiceError::bad_request("Webhook name cannot be empty"));
+    }
+    let channel = channel_id.to_string();
+    ctx.db
+        .run(move |db| db.get_room_id_for_channel(&channel))
+        .await
+        .map_err(ServiceError::internal)?
+        .ok_or_else(|| ServiceError::not_found("Channel not found"))?;
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let token = hex::encode(bytes);
+    let webhook = IncomingWebhook {
+        id: Uuid::new_v4().to_string(),
+        channel_id: channel_id.to_string(),
+        name: name.to_string(),
+        created_at: Utc::now().to_rfc3339(),
+    };
+    let (stored, token_hash) = (webhook.clone(), hash_token(&token));
+    ctx.db
+        .run(move |db| db.insert_incoming_webhook(&stored, &token_hash))
+        .await
+        .map_err(ServiceError::internal)?;
+    Ok(CreatedIncomingWebhook { url: execute_path(&webhook.id, &token), webhook, token })
+}
+
+pub async fn revoke_incoming_webhook(ctx: &ServiceContext, webhook_id: &str) -> Result<bool, ServiceError> {
+    let webhook_id = webhook_id.to_string();
+    ctx.db.run(move |db| db.delete_incoming_webhook(&webhook_id)).await.map_err(ServiceError::internal)
+}
+
+/// Post a payload into the webhook's channel. Returns `None` if the id and
+/// token don't match a webhook.
+pub async fn execute_incoming_webhook(
+    ctx: &ServiceContext,
+    webhook_id: &str,
+    token: &str,
+    content: Option<String>,
+    username: Option<String>,
+    embeds: Vec<MessageEmbed>,
+) -> Result<Option<Message>, ServiceError> {
+    let (webhook_id, token_hash) = (webhook_id.to_string(), hash_token(token));
+    let Some(webhook) = ctx
+        .db
+        .run(move |db| db.authenticate_incoming_webhook(&webhook_id, &token_hash))
+        .await
+        .map_err(ServiceError::internal)?
+    else {
+        return Ok(None);
+    };
+    let content = content.unwrap_or_default();
+    if content.trim().is_empty() && embeds.is_empty() {
+        return Err(ServiceError::bad_request("Payload needs content or embeds"));
+    }
+    if embeds.len() > MAX_EMBEDS {
+        return Err(ServiceError::bad_request(format!("At most {} embeds are allowed", MAX_EMBEDS)));
+    }
+    let display_name = username
+        .map(|u| u.trim().chars().take(MAX_USERNAME_CHARS).collect::<String>())
+        .filter(|u| !u.is_empty())
+        .unwrap_or(webhook.name);
+    let author = MessageAuthor::Webhook { webhook_id: webhook.id, display_name, embeds };
+    crate::services::messaging::send_message(ctx, webhook.channel_id, content, None, None, Some(author))
+        .await
+        .map(Some)
+}
@@ -2,8 +2,9 @@ use chrono::Utc;
 use uuid::Uuid;
 
 use crate::events::AppEvent;
-use crate::models::{Message, Reaction, SearchResult};
+use crate::models::{Message, MessageDeleteNet, MessagesPurgeNet, MessageAuthor, Reaction, TypingIndicatorNet};
 use crate::network::NetworkCommand;
+use crate::services::ServiceError;
 use crate::state::ServiceContext;
 
 pub async fn send_message(
@@ -11,28 +12,54 @@ pub async fn send_message(
     channel_id: String,
     content: String,
     reply_to_id: Option<String>,
-) -> Result<Message, String> {
-    let display_name = ctx.db.get_display_name().map_err(|e| e.to_string())?;
+    thread_id: Option<String>,
+    author: Option<MessageAuthor>,
+) -> Result<Message, ServiceError> {
+    let (sender_peer_id, display_name, webhook_id, embeds) = match author {
+        None => {
+            let display_name = ctx.db.run(|db| db.get_display_name()).await.map_err(ServiceError::internal)?;
+            (ctx.peer_id.clone(), display_name, None, Vec::new())
+        }
+        Some(MessageAuthor::Webhook { webhook_id, display_name, embeds }) => {
+            (ctx.peer_id.clone(), display_name, Some(webhook_id), embeds)
+        }
+        Some(MessageAuthor::Bot { peer_id, display_name }) => (peer_id, display_name, None, Vec::new()),
+    };
+
+    if let Some(tid) = &thread_id {
+        crate::services::threads::ensure_thread_for_reply(ctx, tid, &channel_id).await?;
+    }
 
     let msg = Message {
         id: Uuid::new_v4().to_string(),
         channel_id: channel_id.clone(),
-        sender_peer_id: ctx.peer_id.clone(),
+        sender_peer_id,
         sender_display_name: display_name,
         content,
         timestamp: Utc::now().to_rfc3339(),
         edited_at: None,
         deleted_at: None,
         reply_to_id,
+        thread_id,
+        thread: None,
+        imported_from: None,
+        webhook_id,
+        embeds,
     };
 
-    ctx.db.insert_message(&msg).map_err(|e| e.to_string())?;
+    let stored = msg.clone();
+    ctx.db.run(move |db| db.insert_message(&stored)).await.map_err(ServiceError::internal)?;
+    let known_peers: Vec<_> = ctx.peers.lock().await.values().cloned().collect();
+    let (event_tx, peer_id, stored) = (ctx.event_tx.clone(), ctx.peer_id.clone(), msg.clone());
+    ctx.db
+        .run(move |db| {
+            crate::services::notifications::process_channel_message(db, &event_tx, &peer_id, &known_peers, &stored)
+        })
+        .await?;
 
-    let room_id = ctx
-        .db
-        .get_room_id_for_channel(&channel_id)
-        .map_err(|e| e.to_string())?
-        .ok_or_else(|| "Channel not found".to_string())?;
+    let room_id = ctx.db.run(move |db| db.get_room_id_for_channel(&channel_id)).await
+        .map_err(ServiceError::internal)?
+        .ok_or_else(|| ServiceError::not_found("Channel not found"))?;
 
     ctx.network_tx
         .send(NetworkCommand::SendMessage {
@@ -40,31 +67,33 @@ pub async fn send_message(
             message: msg.clone(),
         })
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(ServiceError::internal)?;
 
     Ok(msg)
 }
 
-pub fn get_messages(
+pub async fn get_messages(
     ctx: &ServiceContext,
     channel_id: &str,
     limit: Option<i64>,
     before: Option<&str>,
-) -> Result<Vec<Message>, String> {
+) -> Result<Vec<Message>, ServiceError> {
     let limit = limit.unwrap_or(50);
-    ctx.db
-        .get_messages(channel_id, limit, before)
-        .map_err(|e| e.to_string())
+    let channel_id = channel_id.to_string();
+    let before = before.map(str::to_string);
+    ctx.db.run(move |db| db.get_messages(&channel_id, limit, before.as_deref())).await
+        .map_err(ServiceError::internal)
 }
 
-pub fn edit_message(
+pub async fn edit_message(
     ctx: &ServiceContext,
     message_id: &str,
     new_content: &str,
-) -> Result<bool, String> {
+) -> Result<bool, ServiceError> {
     let edited_at = Utc::now().to_rfc3339();
-    let updated = ctx.db.edit_message(message_id, new_content, &edited_at)
-        .map_err(|e| e.to_string())?;
+    let (id, content, at) = (message_id.to_string(), new_content.to_string(), edited_at.clone());
+    let updated = ctx.db.run(move |db| db.edit_message(&id, &content, &at)).await
+        .map_err(ServiceError::internal)?;
     if updated {
         let _ = ctx.event_tx.send(AppEvent::MessageEdited {
             message_id: message_id.to_string(),
@@ -76,27 +105,96 @@ pub fn edit_message(
     Ok(updated)
 }
 
-pub fn delete_message(
+/// Erase a message to a tombstone. Deleting one of our own messages (or one
+/// of our bots') also deletes it on every peer in the room.
+pub async fn delete_message(
     ctx: &ServiceContext,
     message_id: &str,
-) -> Result<bool, String> {
+) -> Result<bool, ServiceError> {
+    let id = message_id.to_string();
+    let Some(message) = ctx.db.run(move |db| db.get_message(&id)).await.map_err(ServiceError::internal)? else {
+        return Ok(false);
+    };
     let deleted_at = Utc::now().to_rfc3339();
-    let deleted = ctx.db.delete_message(message_id, &deleted_at)
-        .map_err(|e| e.to_string())?;
-    if deleted {
-        let _ = ctx.event_tx.send(AppEvent::MessageDeleted {
-            message_id: message_id.to_string(),
-            channel_id: String::new(),
-        });
+    let (id, at) = (message_id.to_string(), deleted_at.clone());
+    if !ctx.db.run(move |db| db.delete_message(&id, &at)).await.map_err(ServiceError::internal)? {
+        return Ok(false);
+    }
+    let _ = ctx.event_tx.send(AppEvent::MessageDeleted {
+        message_id: message_id.to_string(),
+        channel_id: message.channel_id.clone(),
+    });
+
+    let sender = message.sender_peer_id.clone();
+    let mine = message.sender_peer_id == ctx.peer_id
+        || message.sender_peer_id == crate::services::devices::my_identity(ctx).await?
+        || ctx.db.run(move |db| db.get_bot(&sender)).await.map_err(ServiceError::internal)?.is_some();
+    if mine && message.imported_from.is_none() {
+        let channel_id = message.channel_id.clone();
+        if let Some(room_id) = ctx.db.run(move |db| db.get_room_id_for_channel(&channel_id)).await
+            .map_err(ServiceError::internal)? {
+            let delete = MessageDeleteNet {
+                message_id: message_id.to_string(),
+                channel_id: message.channel_id,
+                sender_peer_id: ctx.peer_id.clone(),
+                deleted_at,
+            };
+            ctx.network_tx
+                .send(NetworkCommand::BroadcastMessageDelete { room_id, delete })
+                .await
+                .map_err(ServiceError::internal)?;
+        }
+    }
+    Ok(true)
+}
+
+/// Delete every message we have posted in a room, here and on every peer.
+/// Returns how many were deleted locally.
+pub async fn delete_my_messages_in_room(ctx: &ServiceContext, room_id: &str) -> Result<usize, ServiceError> {
+    let rooms = ctx.db.run(|db| db.list_rooms()).await.map_err(ServiceError::internal)?;
+    if !rooms.iter().any(|r| r.id == room_id) {
+        return Err(ServiceError::not_found("Room not found"));
     }
-    Ok(deleted)
+    let deleted_at = Utc::now().to_rfc3339();
+    let identity = crate::services::devices::my_identity(ctx).await?;
+    let mut senders = vec![identity];
+    if !senders.contains(&ctx.peer_id) {
+        senders.push(ctx.peer_id.clone());
+    }
+
+    let mut message_ids = Vec::new();
+    for sender in &senders {
+        let (room, sender, at) = (room_id.to_string(), sender.clone(), deleted_at.clone());
+        let deleted = ctx.db
+            .run(move |db| db.delete_sender_messages_in_room(&room, &sender, &at))
+            .await
+            .map_err(ServiceError::internal)?;
+        message_ids.extend(deleted.into_iter().map(|(_, id)| id));
+    }
+    let count = message_ids.len();
+    let _ = ctx.event_tx.send(AppEvent::MessagesPurged {
+        room_id: room_id.to_string(),
+        sender_peer_id: senders[0].clone(),
+        message_ids,
+    });
+
+    let purge = MessagesPurgeNet {
+        room_id: room_id.to_string(),
+        sender_peer_id: ctx.peer_id.clone(),
+        deleted_at,
+    };
+    ctx.network_tx
+        .send(NetworkCommand::BroadcastMessagesPurged { purge })
+        .await
+        .map_err(ServiceError::internal)?;
+    Ok(count)
 }
 
-pub fn add_reaction(
+pub async fn add_reaction(
     ctx: &ServiceContext,
     message_id: &str,
     emoji: &str,
-) -> Result<Reaction, String> {
+) -> Result<Reaction, ServiceError> {
     let reaction = Reaction {
         id: Uuid::new_v4().to_string(),
         message_id: message_id.to_string(),
@@ -104,7 +202,8 @@ pub fn add_reaction(
         emoji: emoji.to_string(),
         created_at: Utc::now().to_rfc3339(),
     };
-    ctx.db.add_reaction(&reaction).map_err(|e| e.to_string())?;
+    let stored = reaction.clone();
+    ctx.db.run(move |db| db.add_reaction(&stored)).await.map_err(ServiceError::internal)?;
     let _ = ctx.event_tx.send(AppEvent::ReactionAdded {
         message_id: message_id.to_string(),
         channel_id: String::new(),
@@ -114,13 +213,14 @@ pub fn add_reaction(
     Ok(reaction)
 }
 
-pub fn remove_reaction(
+pub async fn remove_reaction(
     ctx: &ServiceContext,
     message_id: &str,
     emoji: &str,
-) -> Result<bool, String> {
-    let removed = ctx.db.remove_reaction(message_id, &ctx.peer_id, emoji)
-        .map_err(|e| e.to_string())?;
+) -> Result<bool, ServiceError> {
+    let (id, peer_id, stored_emoji) = (message_id.to_string(), ctx.peer_id.clone(), emoji.to_string());
+    let removed = ctx.db.run(move |db| db.remove_reaction(&id, &peer_id, &stored_emoji)).await
+        .map_err(ServiceError::internal)?;
     if removed {
         let _ = ctx.event_tx.send(AppEvent::ReactionRemoved {
             message_id: message_id.to_string(),
@@ -132,47 +232,59 @@ pub fn remove_reaction(
     Ok(removed)
 }
 
-pub fn get_reactions(
+pub async fn get_reactions(
     ctx: &ServiceContext,
     message_id: &str,
-) -> Result<Vec<Reaction>, String> {
-    ctx.db.get_reactions(message_id).map_err(|e| e.to_string())
+) -> Result<Vec<Reaction>, ServiceError> {
+    let message_id = message_id.to_string();
+    ctx.db.run(move |db| db.get_reactions(&message_id)).await.map_err(ServiceError::internal)
 }
 
-pub fn mark_read(
+pub async fn mark_read(
     ctx: &ServiceContext,
     channel_id: &str,
     last_read_message_id: &str,
-) -> Result<(), String> {
+) -> Result<(), ServiceError> {
     let updated_at = Utc::now().to_rfc3339();
-    ctx.db.set_read_receipt(channel_id, &ctx.peer_id, last_read_message_id, &updated_at)
-        .map_err(|e| e.to_string())?;
+    let (channel, peer_id, last_read) = (channel_id.to_string(), ctx.peer_id.clone(), last_read_message_id.to_string());
+    ctx.db.run(move |db| db.set_read_receipt(&channel, &peer_id, &last_read, &updated_at)).await
+        .map_err(ServiceError::internal)?;
     let _ = ctx.event_tx.send(AppEvent::ReadReceiptUpdated {
         channel_id: channel_id.to_string(),
         peer_id: ctx.peer_id.clone(),
         last_read_message_id: last_read_message_id.to_string(),
     });
-    Ok(())
+    crate::services::devices::sync_to_devices(
+        ctx,
+        crate::models::DeviceSyncItem::Read {
+            target_id: channel_id.to_string(),
+            last_read_message_id: last_read_message_id.to_string(),
+        },
+    )
+    .await
 }
 
-pub fn get_read_receipts(
+pub async fn get_read_receipts(
     ctx: &ServiceContext,
     channel_id: &str,
-) -> Result<Vec<crate::models::ReadReceipt>, String> {
-    ctx.db.get_read_receipts(channel_id).map_err(|e| e.to_string())
+) -> Result<Vec<crate::models::ReadReceipt>, ServiceError> {
+    let channel_id = channel_id.to_string();
+    ctx.db.run(move |db| db.get_read_receipts(&channel_id)).await.map_err(ServiceError::internal)
 }
 
-pub fn typing_indicator(
+pub async fn typing_indicator(
     ctx: &ServiceContext,
     channel_id: &str,
     typing: bool,
-) -> Result<(), String> {
-    let display_name = ctx.db.get_display_name().map_err(|e| e.to_string())?;
+) -> Result<(), ServiceError> {
+    let display_name = ctx.db.run(|db| db.get_display_name()).await.map_err(ServiceError::internal)?;
+    let channel = channel_id.to_string();
+    let room_id = ctx.db.run(move |db| db.get_room_id_for_channel(&channel)).await.map_err(ServiceError::internal)?;
     if typing {
         let _ = ctx.event_tx.send(AppEvent::TypingStarted {
             channel_id: channel_id.to_string(),
             peer_id: ctx.peer_id.clone(),
-            display_name,
+            display_name: display_name.clone(),
         });
     } else {
         let _ = ctx.event_tx.send(AppEvent::TypingStopped {
@@ -180,25 +292,26 @@ pub fn typing_indicator(
             peer_id: ctx.peer_id.clone(),
         });
     }
+    // Typing is best effort: dropped rather than waiting on a busy network loop
+    if let Some(room_id) = room_id {
+        let _ = ctx.network_tx.try_send(NetworkCommand::BroadcastTyping {
+            room_id,
+            indicator: TypingIndicatorNet {
+                channel_id: channel_id.to_string(),
+                peer_id: ctx.peer_id.clone(),
+                display_name,
+                typing,
+            },
+        });
+    }
     Ok(())
 }
 
-pub fn search_messages(
-    ctx: &ServiceContext,
-    query: &str,
-    channel_id: Option<&str>,
-    limit: Option<i64>,
-    offset: Option<i64>,
-) -> Result<SearchResult, String> {
-    ctx.db.search_messages(channel_id, query, limit.unwrap_or(20), offset.unwrap_or(0))
-        .map_err(|e| e.to_string())
-}
-
-pub fn pin_message(
+pub async fn pin_message(
     ctx: &ServiceContext,
     channel_id: &str,
     message_id: &str,
-) -> Result<crate::models::PinnedMessage, String> {
+) -> Result<crate::models::PinnedMessage, ServiceError> {
     let pin = crate::models::PinnedMessage {
         id: Uuid::new_v4().to_string(),
         channel_id: channel_id.to_string(),
@@ -206,17 +319,19 @@ pub fn pin_message(
         pinned_by: ctx.peer_id.clone(),
         pinned_at: Utc::now().to_rfc3339(),
     };
-    ctx.db.pin_message(&pin).map_err(|e| e.to_string())?;
+    let stored = pin.clone();
+    ctx.db.run(move |db| db.pin_message(&stored)).await.map_err(ServiceError::internal)?;
     let _ = ctx.event_tx.send(AppEvent::MessagePinned(pin.clone()));
     Ok(pin)
 }
 
-pub fn unpin_message(
+pub async fn unpin_message(
     ctx: &ServiceContext,
     channel_id: &str,
     message_id: &str,
-) -> Result<bool, String> {
-    let removed = ctx.db.unpin_message(message_id).map_err(|e| e.to_string())?;
+) -> Result<bool, ServiceError> {
+    let id = message_id.to_string();
+    let removed = ctx.db.run(move |db| db.unpin_message(&id)).await.map_err(ServiceError::internal)?;
     if removed {
         let _ = ctx.event_tx.send(AppEvent::MessageUnpinned {
             channel_id: channel_id.to_string(),
@@ -226,9 +341,10 @@ pub fn unpin_message(
     Ok(removed)
 }
 
-pub fn get_pinned_messages(
+pub async fn get_pinned_messages(
     ctx: &ServiceContext,
     channel_id: &str,
-) -> Result<Vec<crate::models::PinnedMessage>, String> {
-    ctx.db.get_pinned_messages(channel_id).map_err(|e| e.to_string())
+) -> Result<Vec<crate::models::PinnedMessage>, ServiceError> {
+    let channel_id = channel_id.to_string();
+    ctx.db.run(move |db| db.get_pinned_messages(&channel_id)).await.map_err(ServiceError::internal)
 }
@@ -1,5 +1,8 @@
+pub mod error;
+
 pub mod identity;
 pub mod messaging;
+pub mod threads;
 pub mod peers;
 pub mod rooms;
 pub mod channels;
@@ -12,3 +15,15 @@ pub mod friends;
 pub mod settings;
 pub mod notifications;
 pub mod emoji;
+pub mod devices;
+pub mod backup;
+pub mod export;
+pub mod import;
+pub mod retention;
+pub mod api_tokens;
+pub mod webhooks;
+pub mod incoming_webhooks;
+pub mod bots;
+pub mod commands;
+
+pub use error::ServiceError;
@@ -2,16 +2,17 @@ use chrono::Utc;
 use uuid::Uuid;
 
 use crate::models::{BlockedPeer, ModerationAction};
+use crate::services::ServiceError;
 use crate::state::ServiceContext;
 
-pub fn moderate(
+pub async fn moderate(
     ctx: &ServiceContext,
     room_id: &str,
     action_type: &str,
     target_peer_id: &str,
     reason: Option<&str>,
     expires_at: Option<&str>,
-) -> Result<ModerationAction, String> {
+) -> Result<ModerationAction, ServiceError> {
     let action = ModerationAction {
         id: Uuid::new_v4().to_string(),
         room_id: room_id.to_string(),
@@ -22,22 +23,26 @@ pub fn moderate(
         created_at: Utc::now().to_rfc3339(),
         expires_at: expires_at.map(|s| s.to_string()),
     };
-    ctx.db.add_moderation_action(&action).map_err(|e| e.to_string())?;
+    let stored = action.clone();
+    ctx.db.run(move |db| db.add_moderation_action(&stored)).await.map_err(ServiceError::internal)?;
     Ok(action)
 }
 
-pub fn get_audit_log(ctx: &ServiceContext, room_id: &str) -> Result<Vec<ModerationAction>, String> {
-    ctx.db.get_moderation_actions(room_id).map_err(|e| e.to_string())
+pub async fn get_audit_log(ctx: &ServiceContext, room_id: &str) -> Result<Vec<ModerationAction>, ServiceError> {
+    let room_id = room_id.to_string();
+    ctx.db.run(move |db| db.get_moderation_actions(&room_id)).await.map_err(ServiceError::internal)
 }
 
-pub fn block_peer(ctx: &ServiceContext, peer_id: &str) -> Result<(), String> {
-    ctx.db.block_peer(peer_id, &Utc::now().to_rfc3339()).map_err(|e| e.to_string())
+pub async fn block_peer(ctx: &ServiceContext, peer_id: &str) -> Result<(), ServiceError> {
+    let peer_id = peer_id.to_string();
+    ctx.db.run(move |db| db.block_peer(&peer_id, &Utc::now().to_rfc3339())).await.map_err(ServiceError::internal)
 }
 
-pub fn unblock_peer(ctx: &ServiceContext, peer_id: &str) -> Result<(), String> {
-    ctx.db.unblock_peer(peer_id).map_err(|e| e.to_string())
+pub async fn unblock_peer(ctx: &ServiceContext, peer_id: &str) -> Result<(), ServiceError> {
+    let peer_id = peer_id.to_string();
+    ctx.db.run(move |db| db.unblock_peer(&peer_id)).await.map_err(ServiceError::internal)
 }
 
-pub fn get_blocked_peers(ctx: &ServiceContext) -> Result<Vec<BlockedPeer>, String> {
-    ctx.db.get_blocked_peers().map_err(|e| e.to_string())
+pub async fn get_blocked_peers(ctx: &ServiceContext) -> Result<Vec<BlockedPeer>, ServiceError> {
+    ctx.db.run(|db| db.get_blocked_peers()).await.map_err(ServiceError::internal)
 }
@@ -1,14 +1,323 @@
-use crate::models::NotificationSetting;
+use crate::db::Database;
+use crate::events::{AppEvent, EventSender};
+use crate::models::{
+    DmMessage, Message, MessageMention, Notification, NotificationSetting, PeerInfo, RoomUnread,
+    UnreadSummary,
+};
+use crate::services::ServiceError;
 use crate::state::ServiceContext;
 
-pub fn get_notification_setting(ctx: &ServiceContext, target_id: &str, target_type: &str) -> Result<Option<String>, String> {
-    ctx.db.get_notification_setting(target_id, target_type).map_err(|e| e.to_string())
+/// Role names that can be mentioned with `@role` (see `RoomRole::role`).
+const MENTIONABLE_ROLES: &[&str] = &["owner", "admin", "moderator", "member"];
+
+pub async fn get_notification_setting(ctx: &ServiceContext, target_id: &str, target_type: &str) -> Result<Option<String>, ServiceError> {
+    let (target_id, target_type) = (target_id.to_string(), target_type.to_string());
+    ctx.db.run(move |db| db.get_notification_setting(&target_id, &target_type)).await.map_err(ServiceError::internal)
+}
+
+pub async fn set_notification_setting(ctx: &ServiceContext, target_id: &str, target_type: &str, level: &str) -> Result<(), ServiceError> {
+    if !matches!(level, "all" | "mentions" | "none") {
+        return Err(ServiceError::bad_request(format!("Invalid notification level: {}", level)));
+    }
+    let (target_id, target_type, level) = (target_id.to_string(), target_type.to_string(), level.to_string());
+    ctx.db.run(move |db| db.set_notification_setting(&target_id, &target_type, &level)).await.map_err(ServiceError::internal)
+}
+
+pub async fn get_all_notification_settings(ctx: &ServiceContext) -> Result<Vec<NotificationSetting>, ServiceError> {
+    ctx.db.run(|db| db.get_all_notification_settings()).await.map_err(ServiceError::internal)
+}
+
+// ============================================================
+// Mentions
+// ============================================================
+
+/// Extract mentions from message content.
+///
+/// Recognised forms: `@everyone` / `@here`, `<@peer_id>` for users,
+/// `<@&role>` for roles, and the shorthand `@word`, which is a role when it
+/// names one and otherwise a user resolved by peer ID or display name.
+pub fn parse_mentions(content: &str, known_peers: &[PeerInfo]) -> Vec<MessageMention> {
+    let mut mentions: Vec<MessageMention> = Vec::new();
+    let mut push = |kind: &str, target: &str| {
+        let mention = MessageMention { kind: kind.to_string(), target: target.to_string() };
+        if !mentions.contains(&mention) {
+            mentions.push(mention);
+        }
+    };
+
+    let chars: Vec<char> = content.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        // Explicit form: <@peer_id> or <@&role>
+        if chars[i] == '<' && chars.get(i + 1) == Some(&'@') {
+            if let Some(len) = chars[i + 2..].iter().position(|c| *c == '>') {
+                let inner: String = chars[i + 2..i + 2 + len].iter().collect();
+                if !inner.is_empty() && !inner.contains(char::is_whitespace) {
+                    match inner.strip_prefix('&') {
+                        Some(role) => push("role", &role.to_lowercase()),
+                        None => push("user", &inner),
+                    }
+                    i += len + 3;
+                    continue;
+                }
+            }
+        }
+
+        // Shorthand: @word, not preceded by a word character (skips e-mail addresses)
+        let at_boundary = i == 0 || !(chars[i - 1].is_alphanumeric() || chars[i - 1] == '_');
+        if chars[i] == '@' && at_boundary {
+            let len = chars[i + 1..]
+                .iter()
+                .position(|c| !(c.is_alphanumeric() || matches!(c, '_' | '-' | '.')))
+                .unwrap_or(chars.len() - i - 1);
+            let word: String = chars[i + 1..i + 1 + len].iter().collect();
+            let word = word.trim_end_matches('.');
+            let lower = word.to_lowercase();
+            if lower == "everyone" || lower == "here" {
+                push("everyone", "");
+            } else if MENTIONABLE_ROLES.contains(&lower.as_str()) {
+                push("role", &lower);
+            } else if !word.is_empty() {
+                let peer = known_peers
+                    .iter()
+                    .find(|p| p.peer_id == word)
+                    .or_else(|| known_peers.iter().find(|p| p.display_name.eq_ignore_ascii_case(word)));
+                if let Some(peer) = peer {
+                    push("user", &peer.peer_id);
+                }
+            }
+            i += len + 1;
+            continue;
+        }
+        i += 1;
+    }
+    mentions
+}
+
+// ============================================================
+// Notification Engine
+// ============================================================
+
+/// Notification level for a channel: the channel's own setting, else its
+/// room's, else "all".
+pub fn effective_level(db: &Database, channel_id: &str, room_id: Option<&str>) -> Result<String, String> {
+    if let Some(level) = db.get_notification_setting(channel_id, "channel").map_err(|e| e.to_string())? {
+        return Ok(level);
+    }
+    if let Some(rid) = room_id {
+        if let Some(level) = db.get_notification_setting(rid, "room").map_err(|e| e.to_string())? {
+            return Ok(level);
+        }
+    }
+    Ok("all".to_string())
+}
+
+/// Record the mentions of a channel message (local or incoming) and raise a
+/// notification when the configured level allows it. Our own messages never notify.
+///
+/// Takes the database and event sender directly so the network event loop can call it.
+pub fn process_channel_message(
+    db: &Database,
+    event_tx: &EventSender,
+    my_peer_id: &str,
+    known_peers: &[PeerInfo],
+    msg: &Message,
+) -> Result<(), String> {
+    // We are not in the peers map ourselves, but can still be mentioned by name
+    let mut peers = known_peers.to_vec();
+    if !peers.iter().any(|p| p.peer_id == my_peer_id) {
+        peers.push(PeerInfo {
+            peer_id: my_peer_id.to_string(),
+            display_name: db.get_display_name().map_err(|e| e.to_string())?,
+            is_online: true,
+            is_bot: false,
+        });
+    }
+    let mentions = parse_mentions(&msg.content, &peers);
+    if !mentions.is_empty() {
+        db.insert_message_mentions(&msg.id, &mentions).map_err(|e| e.to_string())?;
+    }
+    if msg.sender_peer_id == my_peer_id {
+        return Ok(());
+    }
+
+    let room_id = db.get_room_id_for_channel(&msg.channel_id).map_err(|e| e.to_string())?;
+    let my_role = match &room_id {
+        Some(rid) => db.get_role(rid, my_peer_id).map_err(|e| e.to_string())?.map(|r| r.role),
+        None => None,
+    };
+    let mentioned = mentions.iter().any(|m| match m.kind.as_str() {
+        "everyone" => true,
+        "user" => m.target == my_peer_id,
+        "role" => my_role.as_deref() == Some(m.target.as_str()),
+        _ => false,
+    });
+
+    let allowed = match effective_level(db, &msg.channel_id, room_id.as_deref())?.as_str() {
+        "none" => false,
+        "mentions" => mentioned,
+        _ => true,
+    };
+    if !allowed {
+        return Ok(());
+    }
+    // Thread replies only notify followers of the thread, unless they mention us
+    if let Some(thread_id) = &msg.thread_id {
+        if !mentioned && !db.is_thread_subscribed(thread_id).map_err(|e| e.to_string())? {
+            return Ok(());
+        }
+    }
+
+    let _ = event_tx.send(AppEvent::NotificationRaised(Notification {
+        message_id: msg.id.clone(),
+        room_id,
+        channel_id: Some(msg.channel_id.clone()),
+        conversation_id: None,
+        thread_id: msg.thread_id.clone(),
+        sender_peer_id: msg.sender_peer_id.clone(),
+        sender_display_name: msg.sender_display_name.clone(),
+        content: msg.content.clone(),
+        timestamp: msg.timestamp.clone(),
+        reason: if mentioned { "mention" } else { "message" }.to_string(),
+    }));
+    Ok(())
 }
 
-pub fn set_notification_setting(ctx: &ServiceContext, target_id: &str, target_type: &str, level: &str) -> Result<(), String> {
-    ctx.db.set_notification_setting(target_id, target_type, level).map_err(|e| e.to_string())
+/// Raise a notification for an incoming DM. DMs use the "dm" target type and
+/// are treated as mentions, so only "none" silences them.
+pub fn process_dm_message(
+    db: &Database,
+    event_tx: &EventSender,
+    my_peer_id: &str,
+    msg: &DmMessage,
+) -> Result<(), String> {
+    if msg.sender_peer_id == my_peer_id {
+        return Ok(());
+    }
+    let level = db
+        .get_notification_setting(&msg.conversation_id, "dm")
+        .map_err(|e| e.to_string())?
+        .unwrap_or_else(|| "all".to_string());
+    if level == "none" {
+        return Ok(());
+    }
+
+    let _ = event_tx.send(AppEvent::NotificationRaised(Notification {
+        message_id: msg.id.clone(),
+        room_id: None,
+        channel_id: None,
+        conversation_id: Some(msg.conversation_id.clone()),
+        thread_id: None,
+        sender_peer_id: msg.sender_peer_id.clone(),
+        sender_display_name: msg.sender_display_name.clone(),
+        content: msg.content.clone(),
+        timestamp: msg.timestamp.clone(),
+        reason: "dm".to_string(),
+    }));
+    Ok(())
+}
+
+// ============================================================
+// Unread Counters
+// ============================================================
+
+pub async fn get_message_mentions(ctx: &ServiceContext, message_id: &str) -> Result<Vec<MessageMention>, ServiceError> {
+    let message_id = message_id.to_string();
+    ctx.db.run(move |db| db.get_message_mentions(&message_id)).await.map_err(ServiceError::internal)
+}
+
+/// Unread and mention counts across all rooms (grouped per channel) and DMs.
+pub async fn get_unread_summary(ctx: &ServiceContext) -> Result<UnreadSummary, ServiceError> {
+    let mut summary = UnreadSummary::default();
+
+    let peer_id = ctx.peer_id.clone();
+    let channels = ctx.db.run(move |db| db.get_channel_unreads(&peer_id, None)).await.map_err(ServiceError::internal)?;
+    for (room_id, channel) in channels {
+        summary.total_unread += channel.unread;
+        summary.total_mentions += channel.mentions;
+        // Rows come ordered by room, so a new room starts whenever the ID changes
+        match summary.rooms.last_mut() {
+            Some(room) if room.room_id == room_id => {
+                room.unread += channel.unread;
+                room.mentions += channel.mentions;
+                room.channels.push(channel);
+            }
+            _ => summary.rooms.push(RoomUnread {
+                room_id,
+                unread: channel.unread,
+                mentions: channel.mentions,
+                channels: vec![channel],
+            }),
+        }
+    }
+
+    let peer_id = ctx.peer_id.clone();
+    summary.dms = ctx.db.run(move |db| db.get_dm_unreads(&peer_id)).await.map_err(ServiceError::internal)?;
+    for dm in &summary.dms {
+        summary.total_unread += dm.unread;
+        // Every unread DM is addressed to us
+        summary.total_mentions += dm.unread;
+    }
+    Ok(summary)
 }
 
-pub fn get_all_notification_settings(ctx: &ServiceContext) -> Result<Vec<NotificationSetting>, String> {
-    ctx.db.get_all_notification_settings().map_err(|e| e.to_string())
+pub async fn get_room_unread(ctx: &ServiceContext, room_id: &str) -> Result<RoomUnread, ServiceError> {
+    let (peer_id, room) = (ctx.peer_id.clone(), room_id.to_string());
+    let channels: Vec<_> = ctx.db.run(move |db| db.get_channel_unreads(&peer_id, Some(&room))).await
+        .map_err(ServiceError::internal)?
+        .into_iter()
+        .map(|(_, channel)| channel)
+        .collect();
+    Ok(RoomUnread {
+        room_id: room_id.to_string(),
+        unread: channels.iter().map(|c| c.unread).sum(),
+        mentions: channels.iter().map(|c| c.mentions).sum(),
+        channels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer(peer_id: &str, display_name: &str) -> PeerInfo {
+        PeerInfo { peer_id: peer_id.into(), display_name: display_name.into(), is_online: true, is_bot: false }
+    }
+
+    fn mentions(content: &str) -> Vec<(String, String)> {
+        let peers = [peer("12D3KooAlice", "Alice"), peer("12D3KooBob", "bob.smith")];
+        parse_mentions(content, &peers).into_iter().map(|m| (m.kind, m.target)).collect()
+    }
+
+    fn mention(kind: &str, target: &str) -> (String, String) {
+        (kind.to_string(), target.to_string())
+    }
+
+    #[test]
+    fn explicit_forms() {
+        assert_eq!(mentions("hi <@12D3KooXyz>"), vec![mention("user", "12D3KooXyz")]);
+        assert_eq!(mentions("<@&Moderator> please"), vec![mention("role", "moderator")]);
+        // Whitespace or an empty target is not a mention
+        assert!(mentions("<@ 12D3KooXyz> <@>").is_empty());
+    }
+
+    #[test]
+    fn shorthand_resolves_roles_then_peers() {
+        assert_eq!(mentions("@everyone and @HERE"), vec![mention("everyone", "")]);
+        assert_eq!(mentions("ping @admin"), vec![mention("role", "admin")]);
+        assert_eq!(mentions("thanks @alice!"), vec![mention("user", "12D3KooAlice")]);
+        assert_eq!(mentions("cc @12D3KooBob"), vec![mention("user", "12D3KooBob")]);
+        // Dots are allowed inside names but a trailing one ends the sentence
+        assert_eq!(mentions("ask @bob.smith."), vec![mention("user", "12D3KooBob")]);
+        assert!(mentions("@nobody").is_empty());
+    }
+
+    #[test]
+    fn skips_email_addresses_and_duplicates() {
+        assert!(mentions("mail alice@example.com").is_empty());
+        assert_eq!(
+            mentions("@Alice <@12D3KooAlice> @alice"),
+            vec![mention("user", "12D3KooAlice")]
+        );
+    }
 }
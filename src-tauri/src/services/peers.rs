@@ -1,10 +1,11 @@
 use crate::models::PeerInfo;
+use crate::services::ServiceError;
 use crate::state::ServiceContext;
 
 pub async fn get_room_peers(
     ctx: &ServiceContext,
     room_id: &str,
-) -> Result<Vec<PeerInfo>, String> {
+) -> Result<Vec<PeerInfo>, ServiceError> {
     let peers = ctx.peers.lock().await;
     let room_peers = ctx.room_peers.lock().await;
 
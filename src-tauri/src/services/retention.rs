@@ -0,0 +1,136 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use tracing::{error, info};
+
+use crate::events::AppEvent;
+use crate::models::{DeviceSyncItem, RetentionPolicy, RetentionScope};
+use crate::network::NetworkCommand;
+use crate::services::ServiceError;
+use crate::state::ServiceContext;
+
+/// How often expired messages are pruned. Also the resolution of DM timers.
+pub const PRUNE_INTERVAL: Duration = Duration::from_secs(30);
+
+fn validate(max_age_secs: Option<i64>, max_messages: Option<i64>) -> Result<(), ServiceError> {
+    if max_age_secs.is_some_and(|s| s <= 0) {
+        return Err(ServiceError::bad_request("Retention age must be positive"));
+    }
+    if max_messages.is_some_and(|n| n <= 0) {
+        return Err(ServiceError::bad_request("Retention message count must be positive"));
+    }
+    Ok(())
+}
+
+fn new_policy(
+    ctx: &ServiceContext,
+    scope: RetentionScope,
+    target_id: &str,
+    max_age_secs: Option<i64>,
+    max_messages: Option<i64>,
+) -> RetentionPolicy {
+    RetentionPolicy {
+        scope,
+        target_id: target_id.to_string(),
+        max_age_secs,
+        max_messages,
+        updated_by: ctx.peer_id.clone(),
+        updated_at: Utc::now().to_rfc3339(),
+    }
+}
+
+pub async fn get_policy(
+    ctx: &ServiceContext,
+    scope: RetentionScope,
+    target_id: &str,
+) -> Result<Option<RetentionPolicy>, ServiceError> {
+    let target_id = target_id.to_string();
+    ctx.db.run(move |db| db.get_retention_policy(scope, &target_id)).await.map_err(ServiceError::internal)
+}
+
+async fn apply_room_policy(ctx: &ServiceContext, room_id: String, policy: RetentionPolicy) -> Result<RetentionPolicy, ServiceError> {
+    let (room, me) = (room_id.clone(), ctx.peer_id.clone());
+    if !ctx.db.run(move |db| db.can_manage_channels(&room, &me)).await.map_err(ServiceError::internal)? {
+        return Err(ServiceError::forbidden("Only room owners and admins can change retention"));
+    }
+    let stored = policy.clone();
+    ctx.db.run(move |db| db.upsert_retention_policy(&stored)).await.map_err(ServiceError::internal)?;
+    let _ = ctx.event_tx.send(AppEvent::RetentionPolicyUpdated(policy.clone()));
+    ctx.network_tx
+        .send(NetworkCommand::BroadcastRetentionPolicy { room_id, policy: policy.clone() })
+        .await
+        .map_err(ServiceError::internal)?;
+    Ok(policy)
+}
+
+/// Set how long a room keeps history. No limits means keep forever.
+pub async fn set_room_policy(
+    ctx: &ServiceContext,
+    room_id: &str,
+    max_age_secs: Option<i64>,
+    max_messages: Option<i64>,
+) -> Result<RetentionPolicy, ServiceError> {
+    validate(max_age_secs, max_messages)?;
+    let policy = new_policy(ctx, RetentionScope::Room, room_id, max_age_secs, max_messages);
+    apply_room_policy(ctx, room_id.to_string(), policy).await
+}
+
+/// Set a channel's retention, overriding its room's.
+pub async fn set_channel_policy(
+    ctx: &ServiceContext,
+    channel_id: &str,
+    max_age_secs: Option<i64>,
+    max_messages: Option<i64>,
+) -> Result<RetentionPolicy, ServiceError> {
+    validate(max_age_secs, max_messages)?;
+    let channel = channel_id.to_string();
+    let room_id = ctx
+        .db
+        .run(move |db| db.get_room_id_for_channel(&channel))
+        .await
+        .map_err(ServiceError::internal)?
+        .ok_or_else(|| ServiceError::not_found("Channel not found"))?;
+    let policy = new_policy(ctx, RetentionScope::Channel, channel_id, max_age_secs, max_messages);
+    apply_room_policy(ctx, room_id, policy).await
+}
+
+/// Set (or with `None`, turn off) a DM's disappearing-message timer.
+pub async fn set_dm_timer(
+    ctx: &ServiceContext,
+    conversation_id: &str,
+    timer_secs: Option<i64>,
+) -> Result<RetentionPolicy, ServiceError> {
+    validate(timer_secs, None)?;
+    let policy = new_policy(ctx, RetentionScope::Dm, conversation_id, timer_secs, None);
+    let stored = policy.clone();
+    ctx.db.run(move |db| db.upsert_retention_policy(&stored)).await.map_err(ServiceError::internal)?;
+    let _ = ctx.event_tx.send(AppEvent::RetentionPolicyUpdated(policy.clone()));
+    crate::services::devices::sync_to_devices(ctx, DeviceSyncItem::Retention(policy.clone())).await?;
+    Ok(policy)
+}
+
+/// Delete everything past its policy.
+pub async fn prune_expired(ctx: &ServiceContext) -> Result<(), ServiceError> {
+    let report = ctx.db.run(|db| db.prune_expired(Utc::now())).await.map_err(ServiceError::internal)?;
+
+    for (channel_id, message_ids) in report.channels {
+        info!("Expired {} messages in channel {}", message_ids.len(), channel_id);
+        let _ = ctx.event_tx.send(AppEvent::MessagesExpired { channel_id, message_ids });
+    }
+    for (conversation_id, message_ids) in report.conversations {
+        info!("Expired {} messages in DM {}", message_ids.len(), conversation_id);
+        let _ = ctx.event_tx.send(AppEvent::DmMessagesExpired { conversation_id, message_ids });
+    }
+    Ok(())
+}
+
+/// Prune every [`PRUNE_INTERVAL`] for the life of the process.
+pub async fn run_pruner(ctx: ServiceContext) {
+    let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = prune_expired(&ctx).await {
+            error!("Retention pruning failed: {}", e);
+        }
+    }
+}
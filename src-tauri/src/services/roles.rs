@@ -2,14 +2,15 @@ use chrono::Utc;
 use uuid::Uuid;
 
 use crate::models::RoomRole;
+use crate::services::ServiceError;
 use crate::state::ServiceContext;
 
-pub fn set_role(
+pub async fn set_role(
     ctx: &ServiceContext,
     room_id: &str,
     peer_id: &str,
     role: &str,
-) -> Result<RoomRole, String> {
+) -> Result<RoomRole, ServiceError> {
     let r = RoomRole {
         id: Uuid::new_v4().to_string(),
         room_id: room_id.to_string(),
@@ -18,18 +19,22 @@ pub fn set_role(
         assigned_by: ctx.peer_id.clone(),
         assigned_at: Utc::now().to_rfc3339(),
     };
-    ctx.db.set_role(&r).map_err(|e| e.to_string())?;
+    let stored = r.clone();
+    ctx.db.run(move |db| db.set_role(&stored)).await.map_err(ServiceError::internal)?;
     Ok(r)
 }
 
-pub fn get_role(ctx: &ServiceContext, room_id: &str, peer_id: &str) -> Result<Option<RoomRole>, String> {
-    ctx.db.get_role(room_id, peer_id).map_err(|e| e.to_string())
+pub async fn get_role(ctx: &ServiceContext, room_id: &str, peer_id: &str) -> Result<Option<RoomRole>, ServiceError> {
+    let (room_id, peer_id) = (room_id.to_string(), peer_id.to_string());
+    ctx.db.run(move |db| db.get_role(&room_id, &peer_id)).await.map_err(ServiceError::internal)
 }
 
-pub fn get_room_roles(ctx: &ServiceContext, room_id: &str) -> Result<Vec<RoomRole>, String> {
-    ctx.db.get_room_roles(room_id).map_err(|e| e.to_string())
+pub async fn get_room_roles(ctx: &ServiceContext, room_id: &str) -> Result<Vec<RoomRole>, ServiceError> {
+    let room_id = room_id.to_string();
+    ctx.db.run(move |db| db.get_room_roles(&room_id)).await.map_err(ServiceError::internal)
 }
 
-pub fn remove_role(ctx: &ServiceContext, room_id: &str, peer_id: &str) -> Result<(), String> {
-    ctx.db.remove_role(room_id, peer_id).map_err(|e| e.to_string())
+pub async fn remove_role(ctx: &ServiceContext, room_id: &str, peer_id: &str) -> Result<(), ServiceError> {
+    let (room_id, peer_id) = (room_id.to_string(), peer_id.to_string());
+    ctx.db.run(move |db| db.remove_role(&room_id, &peer_id)).await.map_err(ServiceError::internal)
 }
@@ -3,6 +3,7 @@ use uuid::Uuid;
 
 use crate::models::{Channel, Room};
 use crate::network::NetworkCommand;
+use crate::services::ServiceError;
 use crate::state::ServiceContext;
 
 fn generate_invite_code() -> String {
@@ -33,7 +34,7 @@ pub fn deterministic_channel_id(room_id: &str, channel_name: &str) -> String {
     )
 }
 
-pub async fn create_room(ctx: &ServiceContext, name: String) -> Result<Room, String> {
+pub async fn create_room(ctx: &ServiceContext, name: String) -> Result<Room, ServiceError> {
     let room_id = Uuid::new_v4().to_string();
     let invite_code = generate_invite_code();
     let now = Utc::now().to_rfc3339();
@@ -46,7 +47,8 @@ pub async fn create_room(ctx: &ServiceContext, name: String) -> Result<Room, Str
         owner_peer_id: Some(ctx.peer_id.clone()),
     };
 
-    ctx.db.create_room(&room).map_err(|e| e.to_string())?;
+    let stored = room.clone();
+    ctx.db.run(move |db| db.create_room(&stored)).await.map_err(ServiceError::internal)?;
 
     // Auto-create #general channel with deterministic ID
     let channel = Channel {
@@ -57,10 +59,13 @@ pub async fn create_room(ctx: &ServiceContext, name: String) -> Result<Room, Str
         channel_type: "text".to_string(),
         topic: None,
         position: 0,
+        category_id: None,
+        inherit_permissions: true,
+        permissions: None,
     };
-    ctx.db
-        .create_channel(&channel)
-        .map_err(|e| e.to_string())?;
+    ctx.event_tx.track_channel(&channel.id, &channel.room_id);
+    ctx.db.run(move |db| db.create_channel(&channel)).await
+        .map_err(ServiceError::internal)?;
 
     // Subscribe to the room's topics in the network
     ctx.network_tx
@@ -68,7 +73,7 @@ pub async fn create_room(ctx: &ServiceContext, name: String) -> Result<Room, Str
             room_id: room_id.clone(),
         })
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(ServiceError::internal)?;
 
     // Publish room info to DHT for discovery
     ctx.network_tx
@@ -78,17 +83,16 @@ pub async fn create_room(ctx: &ServiceContext, name: String) -> Result<Room, Str
             room_name: name,
         })
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(ServiceError::internal)?;
 
     Ok(room)
 }
 
-pub async fn join_room(ctx: &ServiceContext, invite_code: String) -> Result<Room, String> {
+pub async fn join_room(ctx: &ServiceContext, invite_code: String) -> Result<Room, ServiceError> {
     // Check if we already have this room locally
-    if let Some(room) = ctx
-        .db
-        .get_room_by_invite(&invite_code)
-        .map_err(|e| e.to_string())?
+    let code = invite_code.clone();
+    if let Some(room) = ctx.db.run(move |db| db.get_room_by_invite(&code)).await
+        .map_err(ServiceError::internal)?
     {
         return Ok(room);
     }
@@ -101,7 +105,7 @@ pub async fn join_room(ctx: &ServiceContext, invite_code: String) -> Result<Room
             reply: tx,
         })
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(ServiceError::internal)?;
 
     // Wait up to 3 seconds for a GossipSub response
     let room_info =
@@ -116,7 +120,7 @@ pub async fn join_room(ctx: &ServiceContext, invite_code: String) -> Result<Room
                         reply: tx2,
                     })
                     .await
-                    .map_err(|e| e.to_string())?;
+                    .map_err(ServiceError::internal)?;
 
                 match tokio::time::timeout(std::time::Duration::from_secs(5), rx2).await {
                     Ok(Ok(info)) => info,
@@ -135,7 +139,8 @@ pub async fn join_room(ctx: &ServiceContext, invite_code: String) -> Result<Room
                 created_at: now.clone(),
                 owner_peer_id: None,
             };
-            ctx.db.create_room(&room).map_err(|e| e.to_string())?;
+            let stored = room.clone();
+            ctx.db.run(move |db| db.create_room(&stored)).await.map_err(ServiceError::internal)?;
 
             // Create #general channel with deterministic ID (matches room creator)
             let channel = Channel {
@@ -146,27 +151,31 @@ pub async fn join_room(ctx: &ServiceContext, invite_code: String) -> Result<Room
                 channel_type: "text".to_string(),
                 topic: None,
                 position: 0,
+                category_id: None,
+                inherit_permissions: true,
+                permissions: None,
             };
-            ctx.db
-                .create_channel(&channel)
-                .map_err(|e| e.to_string())?;
+            ctx.event_tx.track_channel(&channel.id, &channel.room_id);
+            ctx.db.run(move |db| db.create_channel(&channel)).await
+                .map_err(ServiceError::internal)?;
 
             // Subscribe to room topics
             ctx.network_tx
                 .send(NetworkCommand::SubscribeRoom { room_id })
                 .await
-                .map_err(|e| e.to_string())?;
+                .map_err(ServiceError::internal)?;
 
             Ok(room)
         }
-        None => Err("Room not found. Make sure someone with the room is online.".to_string()),
+        None => Err(ServiceError::not_found("Room not found. Make sure someone with the room is online.")),
     }
 }
 
-pub fn list_rooms(ctx: &ServiceContext) -> Result<Vec<Room>, String> {
-    ctx.db.list_rooms().map_err(|e| e.to_string())
+pub async fn list_rooms(ctx: &ServiceContext) -> Result<Vec<Room>, ServiceError> {
+    ctx.db.run(|db| db.list_rooms()).await.map_err(ServiceError::internal)
 }
 
-pub fn get_channels(ctx: &ServiceContext, room_id: &str) -> Result<Vec<Channel>, String> {
-    ctx.db.get_channels(room_id).map_err(|e| e.to_string())
+pub async fn get_channels(ctx: &ServiceContext, room_id: &str) -> Result<Vec<Channel>, ServiceError> {
+    let room_id = room_id.to_string();
+    ctx.db.run(move |db| db.get_channels(&room_id)).await.map_err(ServiceError::internal)
 }
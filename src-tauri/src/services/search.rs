@@ -1,14 +1,360 @@
-use crate::models::SearchResult;
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::models::{MessageSearch, PeerInfo, SearchHit, SearchResult, UnifiedSearchResult};
+use crate::services::ServiceError;
 use crate::state::ServiceContext;
 
-pub fn search(
+/// Search channel messages.
+///
+/// Supported syntax, combinable in any order:
+/// - free words (`deploy`, prefix `depl*`) and `"quoted phrases"`
+/// - `from:<peer id or display name>`
+/// - `in:<channel id or #name>`
+/// - `has:attachment` (alias `has:file`)
+/// - `before:<date>` / `after:<date>`, as `YYYY-MM-DD` or RFC 3339;
+///   a bare date excludes that day itself, like `before:` does
+/// - `mentions:<peer id, display name or "me">`
+///
+/// Values containing spaces can be quoted: `from:"Jane Doe"`.
+pub async fn search_messages(
     ctx: &ServiceContext,
     query: &str,
     channel_id: Option<&str>,
+    room_id: Option<&str>,
     limit: Option<i64>,
     offset: Option<i64>,
-) -> Result<SearchResult, String> {
-    ctx.db
-        .search_messages(channel_id, query, limit.unwrap_or(20), offset.unwrap_or(0))
-        .map_err(|e| e.to_string())
+) -> Result<SearchResult, ServiceError> {
+    let mut search = prepare_search(ctx, query).await?;
+    if let Some(cid) = channel_id {
+        search.channels.push(cid.to_string());
+    }
+    search.room_id = room_id.map(|s| s.to_string());
+
+    ctx.db.run(move |db| db.search_messages(&search, limit.unwrap_or(20), offset.unwrap_or(0))).await
+        .map_err(ServiceError::internal)
+}
+
+pub const SEARCH_SOURCES: &[&str] = &["channel", "dm", "file"];
+
+/// Search channel messages, DMs and file names at once, newest first.
+///
+/// `sources` restricts the search to a subset of [`SEARCH_SOURCES`]. Filters
+/// that only make sense for channels (`in:`, `has:`, `mentions:`, room scope)
+/// exclude DMs and files; `from:` matches the uploader for files.
+pub async fn search_all(
+    ctx: &ServiceContext,
+    query: &str,
+    sources: Option<&[String]>,
+    room_id: Option<&str>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<UnifiedSearchResult, ServiceError> {
+    if let Some(unknown) = sources.and_then(|s| s.iter().find(|s| !SEARCH_SOURCES.contains(&s.as_str()))) {
+        return Err(ServiceError::bad_request(format!("Unknown search source: {}", unknown)));
+    }
+    let wants = |source: &str| sources.map_or(true, |s| s.iter().any(|x| x == source));
+
+    let mut search = prepare_search(ctx, query).await?;
+    search.room_id = room_id.map(|s| s.to_string());
+    let channel_only = !search.channels.is_empty()
+        || !search.mentions.is_empty()
+        || search.has_attachment
+        || search.room_id.is_some();
+
+    // Each source is paged independently, so fetch enough of each to fill
+    // this page after interleaving
+    let limit = limit.unwrap_or(20);
+    let offset = offset.unwrap_or(0);
+    let window = limit + offset;
+    let mut results = Vec::new();
+    let mut total = 0;
+
+    if wants("channel") {
+        let channel_search = search.clone();
+        let found = ctx.db.run(move |db| db.search_messages(&channel_search, window, 0)).await.map_err(ServiceError::internal)?;
+        total += found.total;
+        let mut snippets = found.snippets;
+        results.extend(found.messages.into_iter().map(|message| SearchHit::Channel {
+            snippet: snippets.remove(&message.id),
+            message,
+        }));
+    }
+    if wants("dm") && !channel_only {
+        let dm_search = search.clone();
+        let (hits, count) = ctx.db.run(move |db| db.search_dm_messages(&dm_search, window, 0)).await.map_err(ServiceError::internal)?;
+        total += count;
+        results.extend(hits.into_iter().map(|(message, snippet)| SearchHit::Dm { message, snippet }));
+    }
+    if wants("file") && !channel_only {
+        let known_peers: Vec<PeerInfo> = ctx.peers.lock().await.values().cloned().collect();
+        let my_display_name = ctx.db.run(|db| db.get_display_name()).await.map_err(ServiceError::internal)?;
+        let mut file_search = search.clone();
+        file_search.from = search
+            .from
+            .iter()
+            .map(|f| resolve_peer(ctx, &known_peers, &my_display_name, f))
+            .collect();
+        let (hits, count) = ctx.db.run(move |db| db.search_files(&file_search, window, 0)).await.map_err(ServiceError::internal)?;
+        total += count;
+        results.extend(hits.into_iter().map(|(file, snippet)| SearchHit::File { file, snippet }));
+    }
+
+    results.sort_by(|a, b| b.timestamp().cmp(a.timestamp()));
+    let results = results.into_iter().skip(offset as usize).take(limit as usize).collect();
+    Ok(UnifiedSearchResult { results, total })
+}
+
+/// Parse a query and resolve `mentions:` targets to peer IDs, which is what
+/// `message_mentions` stores.
+async fn prepare_search(ctx: &ServiceContext, query: &str) -> Result<MessageSearch, ServiceError> {
+    let known_peers: Vec<PeerInfo> = ctx.peers.lock().await.values().cloned().collect();
+    let my_display_name = ctx.db.run(|db| db.get_display_name()).await.map_err(ServiceError::internal)?;
+    let mut search = parse_query(query)?;
+    search.mentions = search
+        .mentions
+        .iter()
+        .map(|m| resolve_peer(ctx, &known_peers, &my_display_name, m))
+        .collect();
+    Ok(search)
+}
+
+/// Map "me", a display name or a peer ID to a peer ID. Unknown names are kept as-is.
+fn resolve_peer(ctx: &ServiceContext, known_peers: &[PeerInfo], my_display_name: &str, name: &str) -> String {
+    if name.eq_ignore_ascii_case("me") || name.eq_ignore_ascii_case(my_display_name) {
+        return ctx.peer_id.clone();
+    }
+    known_peers
+        .iter()
+        .find(|p| p.peer_id == name || p.display_name.eq_ignore_ascii_case(name))
+        .map(|p| p.peer_id.clone())
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// Parse user search syntax into an FTS5 expression and SQL filters. Every
+/// free-text term is quoted, so user input can never produce invalid FTS5.
+pub fn parse_query(input: &str) -> Result<MessageSearch, ServiceError> {
+    let mut search = MessageSearch::default();
+    let mut fts_terms: Vec<String> = Vec::new();
+
+    for token in tokenize(input) {
+        match token {
+            Token::Phrase(phrase) => {
+                if has_searchable_text(&phrase) {
+                    fts_terms.push(quote_fts(&phrase));
+                }
+            }
+            Token::Filter(key, value) => match key.as_str() {
+                "from" => search.from.push(value.trim_start_matches('@').to_string()),
+                "in" => search.channels.push(value.trim_start_matches('#').to_string()),
+                "mentions" => search.mentions.push(value.trim_start_matches('@').to_string()),
+                "has" => match value.to_lowercase().as_str() {
+                    "attachment" | "file" => search.has_attachment = true,
+                    other => return Err(ServiceError::bad_request(format!("Unsupported filter has:{}", other))),
+                },
+                "before" => search.before = Some(parse_bound(&value, false)?),
+                "after" => search.after = Some(parse_bound(&value, true)?),
+                _ => unreachable!("tokenize only yields known filter keys"),
+            },
+            Token::Word(word) => {
+                let (stem, prefix) = match word.strip_suffix('*') {
+                    Some(stem) => (stem, true),
+                    None => (word.as_str(), false),
+                };
+                if has_searchable_text(stem) {
+                    let quoted = quote_fts(stem);
+                    fts_terms.push(if prefix { format!("{}*", quoted) } else { quoted });
+                }
+            }
+        }
+    }
+
+    if !fts_terms.is_empty() {
+        search.fts = Some(fts_terms.join(" "));
+    }
+    Ok(search)
+}
+
+const FILTER_KEYS: &[&str] = &["from", "in", "has", "before", "after", "mentions"];
+
+enum Token {
+    Word(String),
+    Phrase(String),
+    Filter(String, String),
+}
+
+/// Split on whitespace, keeping `"quoted phrases"` together. An unterminated
+/// quote runs to the end of the input. Unknown `key:value` tokens stay plain words.
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        if c == '"' {
+            chars.next();
+            tokens.push(Token::Phrase(read_quoted(&mut chars)));
+            continue;
+        }
+
+        let mut word = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                break;
+            }
+            word.push(c);
+            chars.next();
+            // key:"quoted value"
+            if c == ':' && chars.peek() == Some(&'"') {
+                let key = word.trim_end_matches(':').to_lowercase();
+                if FILTER_KEYS.contains(&key.as_str()) {
+                    chars.next();
+                    word.push_str(&read_quoted(&mut chars));
+                    break;
+                }
+            }
+        }
+
+        match word.split_once(':') {
+            Some((key, value)) if !value.is_empty() && FILTER_KEYS.contains(&key.to_lowercase().as_str()) => {
+                tokens.push(Token::Filter(key.to_lowercase(), value.to_string()));
+            }
+            _ => tokens.push(Token::Word(word)),
+        }
+    }
+    tokens
+}
+
+fn read_quoted(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+    let mut text = String::new();
+    for c in chars.by_ref() {
+        if c == '"' {
+            break;
+        }
+        text.push(c);
+    }
+    text
+}
+
+/// FTS5 string literal: wrap in double quotes, doubling any embedded quote.
+/// NULs are dropped, as SQLite would end the expression there.
+fn quote_fts(text: &str) -> String {
+    format!("\"{}\"", text.replace('\0', "").replace('"', "\"\""))
+}
+
+/// Terms made only of punctuation produce no tokens and would match nothing.
+fn has_searchable_text(text: &str) -> bool {
+    text.chars().any(|c| c.is_alphanumeric())
+}
+
+/// Turn a `before:`/`after:` value into a timestamp bound. The queries compare
+/// bounds with `julianday()`, so stored offsets don't matter. Bare dates are
+/// whole UTC days: `after:` starts the next day.
+fn parse_bound(value: &str, after: bool) -> Result<String, ServiceError> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let day = if after {
+            date.succ_opt().ok_or_else(|| ServiceError::bad_request(format!("Invalid date: {}", value)))?
+        } else {
+            date
+        };
+        return Ok(day.format("%Y-%m-%d").to_string());
+    }
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc).to_rfc3339())
+        .map_err(|_| ServiceError::bad_request(format!("Invalid date '{}', expected YYYY-MM-DD or RFC 3339", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bad_request(query: &str) -> bool {
+        matches!(parse_query(query), Err(ServiceError::BadRequest(_)))
+    }
+
+    /// Run the parsed FTS expression against a real FTS5 table.
+    fn matches(query: &str, rows: &[&str]) -> Vec<String> {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE VIRTUAL TABLE t USING fts5(content)").unwrap();
+        for row in rows {
+            conn.execute("INSERT INTO t (content) VALUES (?1)", [row]).unwrap();
+        }
+        let Some(fts) = parse_query(query).unwrap().fts else {
+            return Vec::new();
+        };
+        let mut stmt = conn.prepare("SELECT content FROM t WHERE t MATCH ?1 ORDER BY rowid").unwrap();
+        stmt.query_map([fts], |row| row.get(0)).unwrap().collect::<rusqlite::Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn parses_filters() {
+        let search = parse_query("deploy from:@alice in:#general has:file mentions:me").unwrap();
+        assert_eq!(search.fts.as_deref(), Some("\"deploy\""));
+        assert_eq!(search.from, vec!["alice"]);
+        assert_eq!(search.channels, vec!["general"]);
+        assert_eq!(search.mentions, vec!["me"]);
+        assert!(search.has_attachment);
+    }
+
+    #[test]
+    fn filter_keys_are_case_insensitive_and_values_can_be_quoted() {
+        let search = parse_query("FROM:\"Jane Doe\" In:random").unwrap();
+        assert_eq!(search.from, vec!["Jane Doe"]);
+        assert_eq!(search.channels, vec!["random"]);
+        assert_eq!(search.fts, None);
+    }
+
+    #[test]
+    fn date_bounds() {
+        let search = parse_query("before:2024-03-01 after:2024-02-01").unwrap();
+        assert_eq!(search.before.as_deref(), Some("2024-03-01"));
+        // A bare after: date excludes that day itself
+        assert_eq!(search.after.as_deref(), Some("2024-02-02"));
+
+        let search = parse_query("after:2024-02-01T10:00:00+02:00").unwrap();
+        assert_eq!(search.after.as_deref(), Some("2024-02-01T08:00:00+00:00"));
+    }
+
+    #[test]
+    fn quoted_phrases_and_prefixes() {
+        let search = parse_query("\"release notes\" depl*").unwrap();
+        assert_eq!(search.fts.as_deref(), Some("\"release notes\" \"depl\"*"));
+        // An unterminated quote runs to the end
+        let search = parse_query("\"half open").unwrap();
+        assert_eq!(search.fts.as_deref(), Some("\"half open\""));
+    }
+
+    #[test]
+    fn malformed_filters() {
+        assert!(bad_request("has:video"));
+        assert!(bad_request("before:yesterday"));
+        assert!(bad_request("after:2024-13-01"));
+        // Unknown keys and empty values are plain words
+        let search = parse_query("foo:bar from:").unwrap();
+        assert!(search.from.is_empty());
+        assert_eq!(search.fts.as_deref(), Some("\"foo:bar\" \"from:\""));
+    }
+
+    #[test]
+    fn punctuation_only_terms_are_dropped() {
+        let search = parse_query("*** ... \"--\"").unwrap();
+        assert_eq!(search.fts, None);
+    }
+
+    #[test]
+    fn fts_syntax_is_not_injectable() {
+        let rows = ["alpha beta", "gamma", "alpha OR gamma", "near content"];
+        // Operators are searched as words, not interpreted
+        assert_eq!(matches("alpha OR gamma", &rows), vec!["alpha OR gamma"]);
+        assert!(matches("NOT alpha", &rows).is_empty());
+        assert!(matches("content:gamma", &rows).is_empty());
+        // None of these may make MATCH fail
+        for query in ["NEAR(alpha beta)", "alpha\" OR \"gamma", "(alpha", "^alpha", "-alpha", "alpha AND", "a\"b\"\"c", "{content}: gamma", "\u{0}alpha"] {
+            matches(query, &rows);
+        }
+        // An embedded quote cannot close the phrase early and expose OR
+        assert_eq!(matches("alpha\" OR \"gamma", &rows), vec!["alpha OR gamma"]);
+    }
 }
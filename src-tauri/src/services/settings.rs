@@ -1,19 +1,23 @@
 use crate::models::Setting;
+use crate::services::ServiceError;
 use crate::state::ServiceContext;
 
-pub fn get_setting(ctx: &ServiceContext, key: &str) -> Result<Option<String>, String> {
-    ctx.db.get_setting(key).map_err(|e| e.to_string())
+pub async fn get_setting(ctx: &ServiceContext, key: &str) -> Result<Option<String>, ServiceError> {
+    let key = key.to_string();
+    ctx.db.run(move |db| db.get_setting(&key)).await.map_err(ServiceError::internal)
 }
 
-pub fn set_setting(ctx: &ServiceContext, key: &str, value: &str) -> Result<(), String> {
-    ctx.db.set_setting(key, value).map_err(|e| e.to_string())
+pub async fn set_setting(ctx: &ServiceContext, key: &str, value: &str) -> Result<(), ServiceError> {
+    let (key, value) = (key.to_string(), value.to_string());
+    ctx.db.run(move |db| db.set_setting(&key, &value)).await.map_err(ServiceError::internal)
 }
 
-pub fn get_all_settings(ctx: &ServiceContext) -> Result<Vec<Setting>, String> {
-    ctx.db.get_all_settings().map_err(|e| e.to_string())
+pub async fn get_all_settings(ctx: &ServiceContext) -> Result<Vec<Setting>, ServiceError> {
+    ctx.db.run(|db| db.get_all_settings()).await.map_err(ServiceError::internal)
         .map(|pairs| pairs.into_iter().map(|(k, v)| Setting { key: k, value: v }).collect())
 }
 
-pub fn delete_setting(ctx: &ServiceContext, key: &str) -> Result<(), String> {
-    ctx.db.delete_setting(key).map_err(|e| e.to_string())
+pub async fn delete_setting(ctx: &ServiceContext, key: &str) -> Result<(), ServiceError> {
+    let key = key.to_string();
+    ctx.db.run(move |db| db.delete_setting(&key)).await.map_err(ServiceError::internal)
 }
@@ -0,0 +1,144 @@
+use chrono::Utc;
+
+use crate::db::Database;
+use crate::events::AppEvent;
+use crate::models::{Message, Thread, ThreadInfo};
+use crate::network::NetworkCommand;
+use crate::services::ServiceError;
+use crate::state::ServiceContext;
+
+/// Start a thread from an existing channel message. Idempotent: starting a
+/// thread that already exists just returns it.
+pub async fn create_thread(
+    ctx: &ServiceContext,
+    message_id: &str,
+    name: Option<String>,
+) -> Result<ThreadInfo, ServiceError> {
+    let id = message_id.to_string();
+    let root = ctx.db.run(move |db| db.get_message(&id)).await.map_err(ServiceError::internal)?
+        .ok_or_else(|| ServiceError::not_found("Message not found"))?;
+    if root.thread_id.is_some() {
+        return Err(ServiceError::bad_request("Cannot start a thread from a message inside a thread"));
+    }
+
+    let thread = Thread {
+        id: root.id.clone(),
+        channel_id: root.channel_id.clone(),
+        name,
+        created_by: ctx.peer_id.clone(),
+        created_at: Utc::now().to_rfc3339(),
+    };
+    record_new_thread(ctx, &thread).await?;
+    ctx.db.run(move |db| db.set_thread_subscription(&thread.id, true, &thread.created_at)).await
+        .map_err(ServiceError::internal)?;
+
+    get_thread(ctx, message_id).await
+}
+
+/// Called before posting into a thread: validates the root and creates the
+/// thread implicitly if nobody started it explicitly. Repliers are auto-subscribed.
+pub async fn ensure_thread_for_reply(ctx: &ServiceContext, thread_id: &str, channel_id: &str) -> Result<(), ServiceError> {
+    let thread_id = thread_id.to_string();
+    let id = thread_id.clone();
+    let root = ctx.db.run(move |db| db.get_message(&id)).await.map_err(ServiceError::internal)?
+        .ok_or_else(|| ServiceError::not_found("Thread root message not found"))?;
+    if root.channel_id != channel_id {
+        return Err(ServiceError::bad_request("Thread belongs to a different channel"));
+    }
+    if root.thread_id.is_some() {
+        return Err(ServiceError::bad_request("Cannot reply to a thread inside a thread"));
+    }
+
+    let now = Utc::now().to_rfc3339();
+    let id = thread_id.clone();
+    if ctx.db.run(move |db| db.get_thread(&id)).await.map_err(ServiceError::internal)?.is_none() {
+        let thread = Thread {
+            id: root.id,
+            channel_id: root.channel_id,
+            name: None,
+            created_by: ctx.peer_id.clone(),
+            created_at: now.clone(),
+        };
+        record_new_thread(ctx, &thread).await?;
+    }
+    ctx.db.run(move |db| db.auto_subscribe_thread(&thread_id, &now)).await.map_err(ServiceError::internal)
+}
+
+pub async fn get_thread(ctx: &ServiceContext, thread_id: &str) -> Result<ThreadInfo, ServiceError> {
+    let id = thread_id.to_string();
+    let thread = ctx.db.run(move |db| db.get_thread(&id)).await.map_err(ServiceError::internal)?
+        .ok_or_else(|| ServiceError::not_found("Thread not found"))?;
+    ctx.db
+        .run(move |db| {
+            let root = db.get_message(&thread.id)?;
+            thread_info(db, thread, root)
+        })
+        .await
+        .map_err(ServiceError::internal)
+}
+
+pub async fn get_thread_messages(
+    ctx: &ServiceContext,
+    thread_id: &str,
+    limit: Option<i64>,
+    before: Option<&str>,
+) -> Result<Vec<Message>, ServiceError> {
+    let (thread_id, before) = (thread_id.to_string(), before.map(str::to_string));
+    ctx.db.run(move |db| db.get_thread_messages(&thread_id, limit.unwrap_or(50), before.as_deref())).await
+        .map_err(ServiceError::internal)
+}
+
+pub async fn list_channel_threads(ctx: &ServiceContext, channel_id: &str) -> Result<Vec<ThreadInfo>, ServiceError> {
+    let channel_id = channel_id.to_string();
+    ctx.db
+        .run(move |db| {
+            db.get_channel_threads(&channel_id)?
+                .into_iter()
+                .map(|thread| thread_info(db, thread, None))
+                .collect::<rusqlite::Result<Vec<_>>>()
+        })
+        .await
+        .map_err(ServiceError::internal)
+}
+
+pub async fn set_subscription(ctx: &ServiceContext, thread_id: &str, subscribed: bool) -> Result<(), ServiceError> {
+    let thread_id = thread_id.to_string();
+    ctx.db.run(move |db| db.set_thread_subscription(&thread_id, subscribed, &Utc::now().to_rfc3339())).await
+        .map_err(ServiceError::internal)
+}
+
+pub async fn list_subscribed_threads(ctx: &ServiceContext) -> Result<Vec<ThreadInfo>, ServiceError> {
+    ctx.db
+        .run(|db| {
+            db.get_subscribed_threads()?
+                .into_iter()
+                .map(|thread| thread_info(db, thread, None))
+                .collect::<rusqlite::Result<Vec<_>>>()
+        })
+        .await
+        .map_err(ServiceError::internal)
+}
+
+fn thread_info(db: &Database, thread: Thread, root: Option<Message>) -> rusqlite::Result<ThreadInfo> {
+    let summary = db.get_thread_summary(&thread.id)?;
+    let subscribed = db.is_thread_subscribed(&thread.id)?;
+    Ok(ThreadInfo { thread, summary, subscribed, root })
+}
+
+async fn record_new_thread(ctx: &ServiceContext, thread: &Thread) -> Result<(), ServiceError> {
+    let stored = thread.clone();
+    let created = ctx.db.run(move |db| db.create_thread(&stored)).await.map_err(ServiceError::internal)?;
+    if !created {
+        return Ok(());
+    }
+
+    let _ = ctx.event_tx.send(AppEvent::ThreadCreated(thread.clone()));
+    let channel_id = thread.channel_id.clone();
+    if let Some(room_id) = ctx.db.run(move |db| db.get_room_id_for_channel(&channel_id)).await.map_err(ServiceError::internal)? {
+        let _ = ctx.network_tx.try_send(NetworkCommand::BroadcastThreadCreated {
+            room_id,
+            thread: thread.clone(),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,288 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::events::{EventFilter, JournaledEvent};
+use crate::models::{CreatedWebhook, Webhook, WebhookDelivery};
+use crate::services::ServiceError;
+use crate::state::ServiceContext;
+
+/// `sha256=<hex HMAC-SHA256 of "{timestamp}.{body}">`, keyed with the webhook secret.
+pub const SIGNATURE_HEADER: &str = "X-Chatr-Signature";
+/// Unix seconds the request was signed at.
+pub const TIMESTAMP_HEADER: &str = "X-Chatr-Timestamp";
+pub const EVENT_HEADER: &str = "X-Chatr-Event";
+/// Same for every attempt of one delivery, so receivers can deduplicate.
+pub const DELIVERY_HEADER: &str = "X-Chatr-Delivery";
+
+/// Wait before each retry; a delivery is given up after the last.
+const RETRY_DELAYS: [Duration; 4] = [
+    Duration::from_secs(1),
+    Duration::from_secs(10),
+    Duration::from_secs(60),
+    Duration::from_secs(300),
+];
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Journal entries read per query when the dispatcher falls behind.
+const CATCH_UP_BATCH: i64 = 500;
+const DEFAULT_DELIVERY_LIMIT: i64 = 50;
+
+fn validate_url(url: &str) -> Result<(), ServiceError> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| ServiceError::bad_request(format!("Invalid webhook URL: {}", e)))?;
+    match parsed.scheme() {
+        "http" | "https" => Ok(()),
+        other => Err(ServiceError::bad_request(format!("Unsupported webhook URL scheme '{}'", other))),
+    }
+}
+
+/// Signature of `body` as sent in [`SIGNATURE_HEADER`].
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build().map_err(|e| e.to_string())
+}
+
+pub async fn list_webhooks(ctx: &ServiceContext) -> Result<Vec<Webhook>, ServiceError> {
+    ctx.db.run(|db| db.list_webhooks()).await.map_err(ServiceError::internal)
+}
+
+pub async fn get_webhook(ctx: &ServiceContext, webhook_id: &str) -> Result<Option<Webhook>, ServiceError> {
+    let webhook_id = webhook_id.to_string();
+    ctx.db.run(move |db| db.get_webhook(&webhook_id)).await.map_err(ServiceError::internal)
+}
+
+/// Register a webhook for events passing `filter`. The secret is only returned here.
+pub async fn create_webhook(ctx: &ServiceContext, url: &str, filter: EventFilter) -> Result<CreatedWebhook, ServiceError> {
+    validate_url(url)?;
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let secret = format!("whsec_{}", hex::encode(bytes));
+    let webhook = Webhook {
+        id: Uuid::new_v4().to_string(),
+        url: url.to_string(),
+        filter,
+        enabled: true,
+        created_at: Utc::now().to_rfc3339(),
+    };
+    let (stored, stored_secret) = (webhook.clone(), secret.clone());
+    ctx.db.run(move |db| db.insert_webhook(&stored, &stored_secret)).await.map_err(ServiceError::internal)?;
+    Ok(CreatedWebhook { webhook, secret })
+}
+
+/// Change a webhook's URL, filter or enabled state. Unset fields stay as they are.
+pub async fn update_webhook(
+    ctx: &ServiceContext,
+    webhook_id: &str,
+    url: Option<String>,
+    filter: Option<EventFilter>,
+    enabled: Option<bool>,
+) -> Result<Webhook, ServiceError> {
+    let mut webhook = get_webhook(ctx, webhook_id).await?.ok_or_else(|| ServiceError::not_found("Webhook not found"))?;
+    if let Some(url) = url {
+        validate_url(&url)?;
+        webhook.url = url;
+    }
+    if let Some(filter) = filter {
+        webhook.filter = filter;
+    }
+    if let Some(enabled) = enabled {
+        webhook.enabled = enabled;
+    }
+    let stored = webhook.clone();
+    ctx.db.run(move |db| db.update_webhook(&stored)).await.map_err(ServiceError::internal)?;
+    Ok(webhook)
+}
+
+pub async fn delete_webhook(ctx: &ServiceContext, webhook_id: &str) -> Result<bool, ServiceError> {
+    let webhook_id = webhook_id.to_string();
+    ctx.db.run(move |db| db.delete_webhook(&webhook_id)).await.map_err(ServiceError::internal)
+}
+
+/// Logged delivery attempts, newest first.
+pub async fn get_deliveries(ctx: &ServiceContext, webhook_id: &str, limit: Option<i64>) -> Result<Vec<WebhookDelivery>, ServiceError> {
+    let limit = limit.unwrap_or(DEFAULT_DELIVERY_LIMIT);
+    let webhook_id = webhook_id.to_string();
+    ctx.db.run(move |db| db.get_webhook_deliveries(&webhook_id, limit)).await.map_err(ServiceError::internal)
+}
+
+/// POST once; returns the status code, or the error if there was no response.
+async fn post(
+    client: &reqwest::Client,
+    url: &str,
+    secret: &str,
+    event_type: &str,
+    delivery_id: &str,
+    body: &str,
+) -> (Option<u16>, Option<String>) {
+    let timestamp = Utc::now().timestamp();
+    let result = client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(EVENT_HEADER, event_type)
+        .header(DELIVERY_HEADER, delivery_id)
+        .header(TIMESTAMP_HEADER, timestamp.to_string())
+        .header(SIGNATURE_HEADER, sign(secret, timestamp, body))
+        .body(body.to_string())
+        .send()
+        .await;
+    match result {
+        Ok(response) if response.status().is_success() => (Some(response.status().as_u16()), None),
+        Ok(response) => (Some(response.status().as_u16()), Some(format!("HTTP {}", response.status()))),
+        Err(e) => (None, Some(e.to_string())),
+    }
+}
+
+async fn log_attempt(
+    ctx: &ServiceContext,
+    webhook_id: &str,
+    event_seq: Option<i64>,
+    event_type: &str,
+    attempt: i64,
+    (status_code, error): (Option<u16>, Option<String>),
+) -> WebhookDelivery {
+    let delivery = WebhookDelivery {
+        id: Uuid::new_v4().to_string(),
+        webhook_id: webhook_id.to_string(),
+        event_seq,
+        event_type: event_type.to_string(),
+        attempt,
+        status_code: status_code.map(i64::from),
+        success: error.is_none(),
+        error,
+        attempted_at: Utc::now().to_rfc3339(),
+    };
+    let stored = delivery.clone();
+    if let Err(e) = ctx.db.run(move |db| db.insert_webhook_delivery(&stored)).await {
+        warn!("Failed to log webhook delivery: {}", e);
+    }
+    delivery
+}
+
+/// Deliver one event, retrying with backoff. Every attempt is logged.
+async fn deliver(ctx: ServiceContext, client: reqwest::Client, webhook: Webhook, secret: String, event: JournaledEvent) {
+    let body = event.to_json();
+    let delivery_id = Uuid::new_v4().to_string();
+    for attempt in 0..=RETRY_DELAYS.len() {
+        if attempt > 0 {
+            tokio::time::sleep(RETRY_DELAYS[attempt - 1]).await;
+            // Stop retrying if the webhook was disabled or deleted meanwhile
+            match get_webhook(&ctx, &webhook.id).await {
+                Ok(Some(current)) if current.enabled => {}
+                _ => return,
+            }
+        }
+        let outcome = post(&client, &webhook.url, &secret, &event.event_type, &delivery_id, &body).await;
+        let delivery = log_attempt(&ctx, &webhook.id, event.seq, &event.event_type, attempt as i64 + 1, outcome).await;
+        if delivery.success {
+            return;
+        }
+        debug!("Webhook {} delivery attempt {} failed: {:?}", webhook.id, attempt + 1, delivery.error);
+    }
+    warn!("Giving up on webhook {} for event {:?}", webhook.id, event.seq);
+}
+
+/// Send a `Ping` event once, without retries, and return the logged attempt.
+pub async fn test_webhook(ctx: &ServiceContext, webhook_id: &str) -> Result<WebhookDelivery, ServiceError> {
+    let webhook = get_webhook(ctx, webhook_id).await?.ok_or_else(|| ServiceError::not_found("Webhook not found"))?;
+    let id = webhook.id.clone();
+    let secret = ctx
+        .db
+        .run(move |db| db.get_webhook_secret(&id))
+        .await
+        .map_err(ServiceError::internal)?
+        .ok_or_else(|| ServiceError::not_found("Webhook not found"))?;
+    let body = serde_json::json!({ "seq": null, "type": "Ping", "data": { "webhook_id": webhook.id } }).to_string();
+    let outcome = post(&http_client()?, &webhook.url, &secret, "Ping", &Uuid::new_v4().to_string(), &body).await;
+    Ok(log_attempt(ctx, &webhook.id, None, "Ping", 1, outcome).await)
+}
+
+/// Hand `event` to every enabled webhook whose filter it passes.
+async fn dispatch(ctx: &ServiceContext, client: &reqwest::Client, event: &JournaledEvent) {
+    let webhooks = match ctx.db.run(|db| db.get_active_webhooks()).await {
+        Ok(webhooks) => webhooks,
+        Err(e) => {
+            warn!("Failed to load webhooks: {}", e);
+            return;
+        }
+    };
+    for (webhook, secret) in webhooks.into_iter().filter(|(w, _)| w.filter.matches(event)) {
+        tokio::spawn(deliver(ctx.clone(), client.clone(), webhook, secret, event.clone()));
+    }
+}
+
+/// POST journaled events to matching webhooks for the life of the process.
+/// Transient events (typing, speaking) have no sequence number and are not sent.
+pub async fn run_dispatcher(ctx: ServiceContext) {
+    let client = match http_client() {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Webhooks disabled, could not create HTTP client: {}", e);
+            return;
+        }
+    };
+    let mut event_rx = ctx.event_tx.subscribe_journaled();
+    let mut last_seq: Option<i64> = None;
+    loop {
+        match event_rx.recv().await {
+            Ok(event) => {
+                let Some(seq) = event.seq else { continue };
+                if last_seq.is_some_and(|last| seq <= last) {
+                    continue;
+                }
+                last_seq = Some(seq);
+                dispatch(&ctx, &client, &event).await;
+            }
+            Err(broadcast::error::RecvError::Lagged(n)) => {
+                let Some(mut after) = last_seq else {
+                    warn!("Webhook dispatcher lagged, skipped {} events", n);
+                    continue;
+                };
+                // Catch up from the journal once the skipped events are in it
+                ctx.event_tx.flushed().await;
+                loop {
+                    let events = ctx.db.run(move |db| db.get_events_since(after, CATCH_UP_BATCH)).await.unwrap_or_default();
+                    let Some(last) = events.last().and_then(|e| e.seq) else { break };
+                    for event in &events {
+                        dispatch(&ctx, &client, event).await;
+                    }
+                    after = last;
+                }
+                last_seq = Some(after);
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_known_answers() {
+        assert_eq!(
+            sign("whsec_test", 1_700_000_000, r#"{"event":"message.created"}"#),
+            "sha256=9884eb2fcc09ffc10f00127fff0a0c5686da2fef61d0363442271c6dfa1917eb"
+        );
+        assert_eq!(
+            sign("Jefe", 0, "what do ya want for nothing?"),
+            "sha256=37f471929915ccd2cbbe79feb84ffcff4f2bb25e15fc41c2506687331ae179cc"
+        );
+        assert_eq!(sign("", 1, ""), "sha256=c026d3e9b78f258f71e236d9191954480d6b609fd93524964f0643741eaf81b3");
+    }
+
+    #[test]
+    fn signature_covers_the_timestamp() {
+        assert_ne!(sign("whsec_test", 1, "{}"), sign("whsec_test", 2, "{}"));
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::{mpsc, watch, Mutex as TokioMutex};
 
@@ -12,6 +13,8 @@ use crate::network::NetworkCommand;
 #[derive(Clone)]
 pub struct ServiceContext {
     pub db: Arc<Database>,
+    /// Directory holding `chatr.db`.
+    pub data_dir: PathBuf,
     pub peer_id: String,
     pub network_tx: mpsc::Sender<NetworkCommand>,
     pub peers: Arc<TokioMutex<HashMap<String, PeerInfo>>>,
@@ -26,4 +29,15 @@ pub struct ServiceContext {
 pub struct AppState {
     pub ctx: ServiceContext,
     pub api_port: u16,
+    /// Bearer token the GUI uses for the local API.
+    pub api_token: String,
+}
+
+/// Tauri-managed state available before the database is unlocked.
+/// `AppState` is only managed once it has been.
+pub struct LockState {
+    pub data_dir: PathBuf,
+    pub api_port: u16,
+    /// Held while an unlock attempt opens the database.
+    pub unlocking: TokioMutex<()>,
 }
@@ -0,0 +1,577 @@
+//! State of the terminal client, and how keys and node events change it.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use chatr_client::{Channel, Client, DmConversation, DmMessage, Error, Event, Message, Room};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Messages fetched per page of scrollback.
+const PAGE: i64 = 50;
+/// Resend "typing" this often while the user keeps typing.
+const TYPING_RESEND: Duration = Duration::from_secs(3);
+/// Forget a typist who went quiet without a `TypingStopped`.
+const TYPING_EXPIRY: Duration = Duration::from_secs(8);
+
+/// What the message pane shows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    Channel { room_id: String, channel_id: String },
+    Dm { conversation_id: String },
+}
+
+impl Target {
+    /// The ID unread counts, read markers and typing are kept under.
+    pub fn key(&self) -> &str {
+        match self {
+            Target::Channel { channel_id, .. } => channel_id,
+            Target::Dm { conversation_id } => conversation_id,
+        }
+    }
+}
+
+/// A channel message or DM, as shown in the scrollback.
+#[derive(Debug, Clone)]
+pub struct Line {
+    pub id: String,
+    pub sender_peer_id: String,
+    pub sender: String,
+    pub content: String,
+    pub timestamp: String,
+    pub edited: bool,
+}
+
+impl From<Message> for Line {
+    fn from(m: Message) -> Self {
+        Self {
+            id: m.id,
+            sender_peer_id: m.sender_peer_id,
+            sender: m.sender_display_name,
+            content: m.content,
+            timestamp: m.timestamp,
+            edited: m.edited_at.is_some(),
+        }
+    }
+}
+
+impl From<DmMessage> for Line {
+    fn from(m: DmMessage) -> Self {
+        Self {
+            id: m.id,
+            sender_peer_id: m.sender_peer_id,
+            sender: m.sender_display_name,
+            content: m.content,
+            timestamp: m.timestamp,
+            edited: false,
+        }
+    }
+}
+
+pub struct RoomEntry {
+    pub room: Room,
+    /// Text channels; voice needs the GUI.
+    pub channels: Vec<Channel>,
+}
+
+pub struct DmEntry {
+    pub conversation: DmConversation,
+    pub label: String,
+}
+
+/// One row of the sidebar; headers have no target.
+pub struct SidebarRow {
+    pub label: String,
+    pub target: Option<Target>,
+    pub unread: i64,
+}
+
+/// The open channel or conversation.
+pub struct View {
+    pub target: Target,
+    pub title: String,
+    pub lines: Vec<Line>,
+    /// Rows scrolled up from the bottom.
+    pub scroll: usize,
+    /// Largest useful `scroll` and the pane's height in rows, as of the last draw.
+    pub max_scroll: usize,
+    pub height: usize,
+    /// Every older message is loaded.
+    pub complete: bool,
+    /// Index of the first message that was unread when the view opened.
+    pub first_unread: Option<usize>,
+}
+
+struct Typist {
+    peer_id: String,
+    name: String,
+    since: Instant,
+}
+
+pub struct App {
+    client: Client,
+    pub peer_id: String,
+    pub rooms: Vec<RoomEntry>,
+    pub dms: Vec<DmEntry>,
+    unread: HashMap<String, i64>,
+    typing: HashMap<String, Vec<Typist>>,
+    /// Display names of friends, for naming DMs.
+    names: HashMap<String, String>,
+    pub view: Option<View>,
+    pub input: String,
+    /// Cursor position in `input`, in chars.
+    pub cursor: usize,
+    pub status: String,
+    pub quit: bool,
+    last_typing_sent: Option<Instant>,
+}
+
+impl App {
+    pub async fn load(client: Client) -> Result<Self, Error> {
+        let identity = client.identity().await?;
+        let names = client
+            .friends()
+            .await?
+            .into_iter()
+            .map(|f| (f.peer_id, f.display_name))
+            .collect();
+        let mut app = Self {
+            client,
+            peer_id: identity.peer_id,
+            rooms: Vec::new(),
+            dms: Vec::new(),
+            unread: HashMap::new(),
+            typing: HashMap::new(),
+            names,
+            view: None,
+            input: String::new(),
+            cursor: 0,
+            status: "Tab: next channel · PgUp: older · Enter: send · Ctrl-R: reload · Esc: quit".to_string(),
+            quit: false,
+            last_typing_sent: None,
+        };
+        app.reload().await?;
+        if let Some(target) = app.targets().into_iter().next() {
+            app.open(target).await;
+        }
+        Ok(app)
+    }
+
+    /// Refetch rooms, channels, DMs and unread counts.
+    async fn reload(&mut self) -> Result<(), Error> {
+        let mut rooms = Vec::new();
+        for room in self.client.rooms().await? {
+            let mut channels: Vec<Channel> = self
+                .client
+                .channels(&room.id)
+                .await?
+                .into_iter()
+                .filter(|c| c.channel_type == "text")
+                .collect();
+            channels.sort_by_key(|c| c.position);
+            rooms.push(RoomEntry { room, channels });
+        }
+        self.rooms = rooms;
+
+        let mut dms = Vec::new();
+        for conversation in self.client.dms().await? {
+            let label = match &conversation.name {
+                Some(name) => name.clone(),
+                None => {
+                    let participants = self.client.dm_participants(&conversation.id).await?;
+                    let others: Vec<String> = participants
+                        .iter()
+                        .filter(|p| p.peer_id != self.peer_id)
+                        .map(|p| self.name_of(&p.peer_id))
+                        .collect();
+                    if others.is_empty() { "(just you)".to_string() } else { others.join(", ") }
+                }
+            };
+            dms.push(DmEntry { conversation, label });
+        }
+        self.dms = dms;
+
+        let summary = self.client.unread().await?;
+        self.unread = summary
+            .rooms
+            .into_iter()
+            .flat_map(|room| room.channels)
+            .map(|c| (c.channel_id, c.unread))
+            .chain(summary.dms.into_iter().map(|d| (d.conversation_id, d.unread)))
+            .collect();
+        if let Some(view) = &self.view {
+            self.unread.remove(view.target.key());
+        }
+        Ok(())
+    }
+
+    fn name_of(&self, peer_id: &str) -> String {
+        self.names.get(peer_id).cloned().unwrap_or_else(|| {
+            let short: String = peer_id.chars().rev().take(8).collect::<Vec<_>>().into_iter().rev().collect();
+            format!("…{}", short)
+        })
+    }
+
+    /// Channels and conversations in sidebar order.
+    fn targets(&self) -> Vec<Target> {
+        self.sidebar().into_iter().filter_map(|row| row.target).collect()
+    }
+
+    pub fn sidebar(&self) -> Vec<SidebarRow> {
+        let unread = |key: &str| self.unread.get(key).copied().unwrap_or(0);
+        let mut rows = Vec::new();
+        for entry in &self.rooms {
+            rows.push(SidebarRow {
+                label: entry.room.name.clone(),
+                target: None,
+                unread: entry.channels.iter().map(|c| unread(&c.id)).sum(),
+            });
+            for channel in &entry.channels {
+                rows.push(SidebarRow {
+                    label: format!("  #{}", channel.name),
+                    target: Some(Target::Channel {
+                        room_id: entry.room.id.clone(),
+                        channel_id: channel.id.clone(),
+                    }),
+                    unread: unread(&channel.id),
+                });
+            }
+        }
+        if !self.dms.is_empty() {
+            rows.push(SidebarRow {
+                label: "Direct messages".to_string(),
+                target: None,
+                unread: self.dms.iter().map(|d| unread(&d.conversation.id)).sum(),
+            });
+            for dm in &self.dms {
+                rows.push(SidebarRow {
+                    label: format!("  @{}", dm.label),
+                    target: Some(Target::Dm {
+                        conversation_id: dm.conversation.id.clone(),
+                    }),
+                    unread: unread(&dm.conversation.id),
+                });
+            }
+        }
+        rows
+    }
+
+    fn title_of(&self, target: &Target) -> String {
+        match target {
+            Target::Channel { room_id, channel_id } => self
+                .rooms
+                .iter()
+                .find(|r| &r.room.id == room_id)
+                .and_then(|r| {
+                    let channel = r.channels.iter().find(|c| &c.id == channel_id)?;
+                    Some(match &channel.topic {
+                        Some(topic) if !topic.is_empty() => format!("#{} · {} — {}", channel.name, r.room.name, topic),
+                        _ => format!("#{} · {}", channel.name, r.room.name),
+                    })
+                })
+                .unwrap_or_else(|| channel_id.clone()),
+            Target::Dm { conversation_id } => self
+                .dms
+                .iter()
+                .find(|d| &d.conversation.id == conversation_id)
+                .map(|d| format!("@{}", d.label))
+                .unwrap_or_else(|| conversation_id.clone()),
+        }
+    }
+
+    async fn fetch(&self, target: &Target, before: Option<&str>) -> Result<Vec<Line>, Error> {
+        Ok(match target {
+            Target::Channel { channel_id, .. } => self
+                .client
+                .messages(channel_id, Some(PAGE), before)
+                .await?
+                .into_iter()
+                .map(Line::from)
+                .collect(),
+            Target::Dm { conversation_id } => self
+                .client
+                .dm_messages(conversation_id, Some(PAGE), before)
+                .await?
+                .into_iter()
+                .map(Line::from)
+                .collect(),
+        })
+    }
+
+    /// Show the latest page of `target` and mark it read.
+    async fn open(&mut self, target: Target) {
+        let lines = match self.fetch(&target, None).await {
+            Ok(lines) => lines,
+            Err(e) => {
+                self.status = format!("Failed to load messages: {}", e);
+                return;
+            }
+        };
+        let unread = self.unread.remove(target.key()).unwrap_or(0) as usize;
+        let first_unread = (unread > 0).then(|| lines.len().saturating_sub(unread));
+        self.view = Some(View {
+            title: self.title_of(&target),
+            complete: (lines.len() as i64) < PAGE,
+            target,
+            lines,
+            scroll: 0,
+            max_scroll: 0,
+            height: 0,
+            first_unread,
+        });
+        self.mark_read();
+        self.last_typing_sent = None;
+    }
+
+    /// Prepend the page before the oldest loaded message.
+    async fn load_older(&mut self) {
+        let Some(view) = &self.view else { return };
+        if view.complete {
+            return;
+        }
+        let Some(oldest) = view.lines.first().map(|l| l.timestamp.clone()) else { return };
+        let target = view.target.clone();
+        match self.fetch(&target, Some(&oldest)).await {
+            Ok(older) => {
+                let Some(view) = self.view.as_mut().filter(|v| v.target == target) else { return };
+                view.complete = (older.len() as i64) < PAGE;
+                view.first_unread = view.first_unread.map(|i| i + older.len());
+                view.lines.splice(0..0, older);
+            }
+            Err(e) => self.status = format!("Failed to load older messages: {}", e),
+        }
+    }
+
+    /// Move the read marker of the open view to its newest message.
+    fn mark_read(&self) {
+        let Some(view) = &self.view else { return };
+        let Some(last) = view.lines.last() else { return };
+        let client = self.client.clone();
+        let (key, id) = (view.target.key().to_string(), last.id.clone());
+        tokio::spawn(async move { client.mark_read(&key, &id).await });
+    }
+
+    /// Peers typing in the open channel.
+    pub fn typists(&self) -> Vec<&str> {
+        self.view
+            .as_ref()
+            .and_then(|v| self.typing.get(v.target.key()))
+            .map(|t| t.iter().map(|t| t.name.as_str()).collect())
+            .unwrap_or_default()
+    }
+
+    fn send_typing(&mut self, typing: bool) {
+        let Some(Target::Channel { channel_id, .. }) = self.view.as_ref().map(|v| &v.target) else { return };
+        if typing && self.last_typing_sent.is_some_and(|t| t.elapsed() < TYPING_RESEND) {
+            return;
+        }
+        if !typing && self.last_typing_sent.is_none() {
+            return;
+        }
+        self.last_typing_sent = typing.then(Instant::now);
+        let client = self.client.clone();
+        let channel_id = channel_id.clone();
+        tokio::spawn(async move { client.set_typing(&channel_id, typing).await });
+    }
+
+    async fn select(&mut self, step: isize) {
+        let targets = self.targets();
+        if targets.is_empty() {
+            return;
+        }
+        let current = self
+            .view
+            .as_ref()
+            .and_then(|v| targets.iter().position(|t| *t == v.target))
+            .unwrap_or(0) as isize;
+        let next = (current + step).rem_euclid(targets.len() as isize) as usize;
+        self.send_typing(false);
+        self.open(targets[next].clone()).await;
+    }
+
+    /// Send the composer's text, or run it as a slash command in a channel.
+    async fn submit(&mut self) {
+        let text = self.input.trim().to_string();
+        let Some(target) = self.view.as_ref().map(|v| v.target.clone()) else { return };
+        if text.is_empty() {
+            return;
+        }
+        self.send_typing(false);
+        let result = match &target {
+            Target::Channel { channel_id, .. } if text.starts_with('/') => {
+                self.client.run_command(channel_id, &text).await.map(|result| {
+                    if let Some(response) = result.response {
+                        self.status = response;
+                    }
+                    result.message.map(Line::from)
+                })
+            }
+            Target::Channel { channel_id, .. } => self.client.send_message(channel_id, &text).await.map(|m| Some(m.into())),
+            Target::Dm { conversation_id } => self.client.send_dm(conversation_id, &text).await.map(|m| Some(m.into())),
+        };
+        match result {
+            Ok(line) => {
+                self.input.clear();
+                self.cursor = 0;
+                if let Some(line) = line {
+                    self.push(target.key(), line);
+                }
+            }
+            Err(e) => self.status = format!("Not sent: {}", e),
+        }
+    }
+
+    /// Add a message that arrived for `key`: to the open view, or to the unread count.
+    fn push(&mut self, key: &str, line: Line) {
+        if let Some(typists) = self.typing.get_mut(key) {
+            typists.retain(|t| t.peer_id != line.sender_peer_id);
+        }
+        match self.view.as_mut().filter(|v| v.target.key() == key) {
+            Some(view) => {
+                if view.lines.iter().any(|l| l.id == line.id) {
+                    return;
+                }
+                view.lines.push(line);
+                self.mark_read();
+            }
+            None if line.sender_peer_id != self.peer_id => *self.unread.entry(key.to_string()).or_default() += 1,
+            None => {}
+        }
+    }
+
+    pub async fn handle_key(&mut self, key: KeyEvent) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => self.quit = true,
+            KeyCode::Char('c') | KeyCode::Char('d') if ctrl => self.quit = true,
+            KeyCode::Char('r') if ctrl => match self.reload().await {
+                Ok(()) => self.status = "Reloaded".to_string(),
+                Err(e) => self.status = format!("Failed to reload: {}", e),
+            },
+            KeyCode::Tab => self.select(1).await,
+            KeyCode::BackTab => self.select(-1).await,
+            KeyCode::Down if ctrl => self.select(1).await,
+            KeyCode::Up if ctrl => self.select(-1).await,
+            KeyCode::PageUp => self.scroll_up(self.page()).await,
+            KeyCode::Up => self.scroll_up(1).await,
+            KeyCode::PageDown => self.scroll_down(self.page()),
+            KeyCode::Down => self.scroll_down(1),
+            KeyCode::Enter => self.submit().await,
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.input.chars().count()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.input.chars().count(),
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.input.remove(self.byte_index());
+            }
+            KeyCode::Delete if self.cursor < self.input.chars().count() => {
+                self.input.remove(self.byte_index());
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.input.insert(self.byte_index(), c);
+                self.cursor += 1;
+                self.send_typing(true);
+            }
+            _ => {}
+        }
+    }
+
+    fn page(&self) -> usize {
+        self.view.as_ref().map_or(1, |v| v.height.saturating_sub(1).max(1))
+    }
+
+    /// Scroll towards older messages, paging them in at the top.
+    async fn scroll_up(&mut self, rows: usize) {
+        if self.view.as_ref().is_some_and(|v| v.scroll + rows > v.max_scroll) {
+            self.load_older().await;
+        }
+        if let Some(view) = self.view.as_mut() {
+            view.scroll += rows;
+        }
+    }
+
+    fn scroll_down(&mut self, rows: usize) {
+        if let Some(view) = self.view.as_mut() {
+            view.scroll = view.scroll.saturating_sub(rows);
+        }
+    }
+
+    fn byte_index(&self) -> usize {
+        self.input.char_indices().nth(self.cursor).map_or(self.input.len(), |(i, _)| i)
+    }
+
+    pub async fn handle_event(&mut self, event: Event) {
+        let data = &event.data;
+        let str_of = |field: &str| data[field].as_str().unwrap_or_default().to_string();
+        match event.event_type.as_str() {
+            "NewMessage" => {
+                if let Some(message) = event.new_message() {
+                    let key = message.channel_id.clone();
+                    self.push(&key, message.into());
+                }
+            }
+            "NewDmMessage" => {
+                if let Some(message) = event.data_as::<DmMessage>() {
+                    if !self.dms.iter().any(|d| d.conversation.id == message.conversation_id) {
+                        // A new conversation; list it before counting its message
+                        if let Err(e) = self.reload().await {
+                            self.status = format!("Failed to reload: {}", e);
+                        }
+                    }
+                    let key = message.conversation_id.clone();
+                    self.push(&key, message.into());
+                }
+            }
+            "MessageEdited" => {
+                let id = str_of("message_id");
+                if let Some(line) = self.view_line_mut(&str_of("channel_id"), &id) {
+                    line.content = str_of("new_content");
+                    line.edited = true;
+                }
+            }
+            "MessageDeleted" => {
+                let (channel_id, id) = (str_of("channel_id"), str_of("message_id"));
+                if let Some(view) = self.view.as_mut().filter(|v| v.target.key() == channel_id) {
+                    view.lines.retain(|l| l.id != id);
+                }
+            }
+            "TypingStarted" => {
+                let peer_id = str_of("peer_id");
+                if peer_id == self.peer_id {
+                    return;
+                }
+                let typists = self.typing.entry(str_of("channel_id")).or_default();
+                typists.retain(|t| t.peer_id != peer_id);
+                typists.push(Typist {
+                    peer_id,
+                    name: str_of("display_name"),
+                    since: Instant::now(),
+                });
+            }
+            "TypingStopped" => {
+                let peer_id = str_of("peer_id");
+                if let Some(typists) = self.typing.get_mut(&str_of("channel_id")) {
+                    typists.retain(|t| t.peer_id != peer_id);
+                }
+            }
+            "ChannelCreated" | "ChannelDeleted" => {
+                if let Err(e) = self.reload().await {
+                    self.status = format!("Failed to reload: {}", e);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn view_line_mut(&mut self, key: &str, id: &str) -> Option<&mut Line> {
+        let view = self.view.as_mut().filter(|v| v.target.key() == key)?;
+        view.lines.iter_mut().find(|l| l.id == id)
+    }
+
+    /// Once a second: forget typists who went quiet.
+    pub fn tick(&mut self) {
+        for typists in self.typing.values_mut() {
+            typists.retain(|t| t.since.elapsed() < TYPING_EXPIRY);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! `chatr tui`: a full-screen terminal client for a local or remote node.
+//!
+//! Like the other API clients it sees the node's services through the REST
+//! API and its `AppEvent` bus through the `/ws` socket, so the same binary
+//! works over SSH against a headless node elsewhere.
+
+mod app;
+mod ui;
+
+use std::time::Duration;
+
+use chatr_client::{Client, Event, EventFilter};
+use ratatui::crossterm::event::{self as term, Event as TermEvent, KeyEventKind};
+use tokio::sync::mpsc;
+
+use app::App;
+
+/// Event types the client follows; everything else is dropped by the node.
+const EVENT_TYPES: &[&str] = &[
+    "NewMessage",
+    "MessageEdited",
+    "MessageDeleted",
+    "TypingStarted",
+    "TypingStopped",
+    "NewDmMessage",
+    "ChannelCreated",
+    "ChannelDeleted",
+];
+
+/// Load the node's rooms and DMs, then run until the user quits.
+pub async fn run(client: Client) -> Result<(), chatr_client::Error> {
+    let mut events = client.events().await?;
+    events
+        .subscribe(
+            &EventFilter {
+                types: EVENT_TYPES.iter().map(|t| t.to_string()).collect(),
+                ..Default::default()
+            },
+            None,
+        )
+        .await?;
+    let mut app = App::load(client).await?;
+
+    // `None` tells the loop the node closed the socket
+    let (event_tx, mut event_rx) = mpsc::channel::<Option<Event>>(256);
+    tokio::spawn(async move {
+        while let Ok(Some(event)) = events.next_event().await {
+            if event_tx.send(Some(event)).await.is_err() {
+                return;
+            }
+        }
+        let _ = event_tx.send(None).await;
+    });
+
+    // crossterm's reader blocks, so it gets a thread of its own
+    let (key_tx, mut key_rx) = mpsc::channel::<TermEvent>(64);
+    std::thread::spawn(move || {
+        while let Ok(event) = term::read() {
+            if key_tx.blocking_send(event).is_err() {
+                return;
+            }
+        }
+    });
+
+    let mut terminal = ratatui::init();
+    let mut tick = tokio::time::interval(Duration::from_secs(1));
+    while !app.quit {
+        if let Err(e) = terminal.draw(|frame| ui::draw(frame, &mut app)) {
+            app.status = format!("Failed to draw: {}", e);
+        }
+        tokio::select! {
+            Some(input) = key_rx.recv() => {
+                if let TermEvent::Key(key) = input {
+                    if key.kind == KeyEventKind::Press {
+                        app.handle_key(key).await;
+                    }
+                }
+            }
+            Some(event) = event_rx.recv() => match event {
+                Some(event) => app.handle_event(event).await,
+                None => app.status = "Disconnected from the node; restart to reconnect".to_string(),
+            },
+            _ = tick.tick() => app.tick(),
+        }
+    }
+    ratatui::restore();
+    Ok(())
+}
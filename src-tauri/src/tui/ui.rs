@@ -0,0 +1,185 @@
+//! Drawing: sidebar on the left; scrollback, typing line, composer and
+//! status line on the right.
+
+use chrono::{DateTime, Local};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line as TextLine, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::Frame;
+
+use super::app::{App, Line};
+
+const SIDEBAR_WIDTH: u16 = 28;
+
+pub fn draw(frame: &mut Frame, app: &mut App) {
+    let [sidebar, main] = Layout::horizontal([Constraint::Length(SIDEBAR_WIDTH), Constraint::Min(20)]).areas(frame.area());
+    let [messages, typing, composer, status] = Layout::vertical([
+        Constraint::Min(3),
+        Constraint::Length(1),
+        Constraint::Length(3),
+        Constraint::Length(1),
+    ])
+    .areas(main);
+
+    draw_sidebar(frame, app, sidebar);
+    draw_messages(frame, app, messages);
+    frame.render_widget(Paragraph::new(typing_text(&app.typists())).dim().italic(), typing);
+    draw_composer(frame, app, composer);
+    frame.render_widget(Paragraph::new(app.status.as_str()).dim(), status);
+}
+
+fn draw_sidebar(frame: &mut Frame, app: &App, area: Rect) {
+    let open = app.view.as_ref().map(|v| &v.target);
+    let rows = app.sidebar();
+    let selected = rows.iter().position(|row| row.target.is_some() && row.target.as_ref() == open);
+    let items: Vec<ListItem> = rows
+        .into_iter()
+        .map(|row| {
+            let mut style = Style::default();
+            if row.target.is_none() {
+                style = style.add_modifier(Modifier::BOLD);
+            } else if row.unread > 0 {
+                style = style.fg(Color::White).add_modifier(Modifier::BOLD);
+            } else {
+                style = style.fg(Color::Gray);
+            }
+            let mut spans = vec![Span::styled(row.label, style)];
+            if row.unread > 0 {
+                spans.push(Span::styled(format!(" {}", row.unread), Style::default().fg(Color::Red).bold()));
+            }
+            ListItem::new(TextLine::from(spans))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::RIGHT))
+        .highlight_style(Style::default().bg(Color::DarkGray));
+    let mut state = ListState::default().with_selected(selected);
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_messages(frame: &mut Frame, app: &mut App, area: Rect) {
+    let Some(view) = app.view.as_mut() else {
+        let hint = "No rooms or conversations yet. Join one with `chatr join <INVITE_CODE>`, then press Ctrl-R.";
+        frame.render_widget(Paragraph::new(hint).dim(), area);
+        return;
+    };
+    let block = Block::default().borders(Borders::BOTTOM).title(view.title.as_str().bold());
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let width = inner.width.max(1) as usize;
+    let height = inner.height as usize;
+    let mut rows: Vec<TextLine<'static>> = Vec::new();
+    if view.complete {
+        rows.push(TextLine::from("Beginning of the conversation").dim().italic());
+    }
+    let mut last_day = None;
+    for (i, line) in view.lines.iter().enumerate() {
+        let time = DateTime::parse_from_rfc3339(&line.timestamp).ok().map(|t| t.with_timezone(&Local));
+        let day = time.map(|t| t.format("%a %-d %b %Y").to_string());
+        if day.is_some() && day != last_day {
+            rows.push(TextLine::from(format!("── {} ──", day.clone().unwrap_or_default())).dim());
+            last_day = day;
+        }
+        if view.first_unread == Some(i) {
+            rows.push(TextLine::from("── new ──").fg(Color::Red));
+        }
+        let clock = time.map(|t| t.format("%H:%M").to_string()).unwrap_or_default();
+        rows.extend(message_rows(line, &clock, width));
+    }
+
+    view.height = height;
+    view.max_scroll = rows.len().saturating_sub(height);
+    view.scroll = view.scroll.min(view.max_scroll);
+    let end = rows.len() - view.scroll;
+    let start = end.saturating_sub(height);
+    let visible: Vec<TextLine> = rows.drain(start..end).collect();
+    frame.render_widget(Paragraph::new(visible), inner);
+}
+
+/// `HH:MM name: text`, wrapped to `width` with continuation rows indented.
+fn message_rows(line: &Line, clock: &str, width: usize) -> Vec<TextLine<'static>> {
+    let prefix_len = clock.chars().count() + line.sender.chars().count() + 3;
+    let mut text = line.content.clone();
+    if line.edited {
+        text.push_str(" (edited)");
+    }
+    let first_width = width.saturating_sub(prefix_len).max(8);
+    let chunks = wrap(&text, first_width, width.saturating_sub(2).max(8));
+
+    let mut rows = Vec::with_capacity(chunks.len());
+    for (i, chunk) in chunks.into_iter().enumerate() {
+        if i == 0 {
+            rows.push(TextLine::from(vec![
+                Span::raw(format!("{} ", clock)).dim(),
+                Span::styled(line.sender.clone(), Style::default().fg(name_color(&line.sender_peer_id)).bold()),
+                Span::raw(": "),
+                Span::raw(chunk),
+            ]));
+        } else {
+            rows.push(TextLine::from(format!("  {}", chunk)));
+        }
+    }
+    rows
+}
+
+/// Greedy word wrap: the first row holds `first` chars, the others `rest`.
+/// Words longer than a row are split.
+fn wrap(text: &str, first: usize, rest: usize) -> Vec<String> {
+    let mut rows = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut row = String::new();
+        let mut row_len = 0;
+        for word in paragraph.split(' ') {
+            let limit = if rows.is_empty() { first } else { rest };
+            let word_len = word.chars().count();
+            if row_len > 0 && row_len + 1 + word_len > limit {
+                rows.push(std::mem::take(&mut row));
+                row_len = 0;
+            }
+            if row_len > 0 {
+                row.push(' ');
+                row_len += 1;
+            }
+            for c in word.chars() {
+                let limit = if rows.is_empty() { first } else { rest };
+                if row_len == limit {
+                    rows.push(std::mem::take(&mut row));
+                    row_len = 0;
+                }
+                row.push(c);
+                row_len += 1;
+            }
+        }
+        rows.push(row);
+    }
+    rows
+}
+
+/// A stable color per sender.
+fn name_color(peer_id: &str) -> Color {
+    const COLORS: [Color; 6] = [Color::Cyan, Color::Green, Color::Yellow, Color::Magenta, Color::Blue, Color::LightRed];
+    let hash = peer_id.bytes().fold(0usize, |h, b| h.wrapping_mul(31).wrapping_add(b as usize));
+    COLORS[hash % COLORS.len()]
+}
+
+fn typing_text(names: &[&str]) -> String {
+    match names {
+        [] => String::new(),
+        [one] => format!("{} is typing…", one),
+        [a, b] => format!("{} and {} are typing…", a, b),
+        _ => "Several people are typing…".to_string(),
+    }
+}
+
+fn draw_composer(frame: &mut Frame, app: &App, area: Rect) {
+    let block = Block::default().borders(Borders::ALL);
+    let inner = block.inner(area);
+    let width = inner.width.max(1) as usize;
+    // Scroll the line so the cursor stays visible
+    let offset = (app.cursor + 1).saturating_sub(width);
+    let shown: String = app.input.chars().skip(offset).take(width).collect();
+    frame.render_widget(Paragraph::new(shown).block(block), area);
+    frame.set_cursor_position((inner.x + (app.cursor - offset) as u16, inner.y));
+}
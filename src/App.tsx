@@ -1,10 +1,11 @@
 import { useEffect } from "react";
 import { useIdentityStore } from "./stores/identityStore";
 import SetupScreen from "./components/identity/SetupScreen";
+import UnlockScreen from "./components/identity/UnlockScreen";
 import AppLayout from "./components/layout/AppLayout";
 
 function App() {
-  const { isLoading, isSetup, loadIdentity } = useIdentityStore();
+  const { isLoading, isLocked, isSetup, loadIdentity } = useIdentityStore();
 
   useEffect(() => {
     loadIdentity();
@@ -21,6 +22,10 @@ function App() {
     );
   }
 
+  if (isLocked) {
+    return <UnlockScreen />;
+  }
+
   if (!isSetup) {
     return <SetupScreen />;
   }
@@ -1,14 +1,17 @@
 import { useState, useRef, useEffect, useCallback } from "react";
 import { useMessageStore } from "../../stores/messageStore";
 import { useChatStore } from "../../stores/chatStore";
-import { messages } from "../../lib/api";
+import { messages, commands } from "../../lib/api";
+import type { CommandSuggestion } from "../../lib/types";
 
 export default function MessageInput() {
   const [content, setContent] = useState("");
   const inputRef = useRef<HTMLTextAreaElement>(null);
   const typingTimeoutRef = useRef<ReturnType<typeof setTimeout> | null>(null);
   const isTypingRef = useRef(false);
-  const { sendMessage, replyingTo, setReplyingTo } = useMessageStore();
+  const [suggestions, setSuggestions] = useState<CommandSuggestion[]>([]);
+  const [commandResponse, setCommandResponse] = useState<string | null>(null);
+  const { sendMessage, addMessage, replyingTo, setReplyingTo } = useMessageStore();
   const { selectedChannelId, channels } = useChatStore();
 
   const channelName =
@@ -27,8 +30,36 @@ export default function MessageInput() {
     [selectedChannelId]
   );
 
+  // Slash command suggestions while the input is a command
+  useEffect(() => {
+    if (!selectedChannelId || !content.startsWith("/")) {
+      setSuggestions([]);
+      return;
+    }
+    let cancelled = false;
+    commands
+      .autocomplete(selectedChannelId, content)
+      .then((result) => {
+        if (!cancelled) setSuggestions(result);
+      })
+      .catch(() => {
+        if (!cancelled) setSuggestions([]);
+      });
+    return () => {
+      cancelled = true;
+    };
+  }, [content, selectedChannelId]);
+
+  const applySuggestion = (suggestion: CommandSuggestion) => {
+    if (!suggestion.insert) return;
+    const partial = content.split(/\s/).pop() ?? "";
+    setContent(content.slice(0, content.length - partial.length) + suggestion.insert);
+    inputRef.current?.focus();
+  };
+
   const handleContentChange = (newContent: string) => {
     setContent(newContent);
+    setCommandResponse(null);
 
     if (!newContent.trim()) {
       // Clear typing if input is empty
@@ -84,6 +115,18 @@ export default function MessageInput() {
       sendTypingIndicator(false);
     }
 
+    if (msg.startsWith("/")) {
+      try {
+        const result = await commands.run(selectedChannelId, msg);
+        if (result.message) addMessage(result.message);
+        setCommandResponse(result.response ?? null);
+      } catch (err) {
+        setCommandResponse(String(err));
+        setContent(msg);
+      }
+      return;
+    }
+
     try {
       await sendMessage(selectedChannelId, msg);
     } catch (err) {
@@ -93,6 +136,11 @@ export default function MessageInput() {
   };
 
   const handleKeyDown = (e: React.KeyboardEvent) => {
+    if (e.key === "Tab" && suggestions.length > 0) {
+      e.preventDefault();
+      applySuggestion(suggestions[0]);
+      return;
+    }
     if (e.key === "Enter" && !e.shiftKey) {
       e.preventDefault();
       handleSubmit();
@@ -106,6 +154,25 @@ export default function MessageInput() {
 
   return (
     <div className="px-4 pb-4 pt-1 shrink-0">
+      {/* Slash command suggestions */}
+      {suggestions.length > 0 && (
+        <div className="mb-1 bg-gray-800 rounded-lg py-1 max-h-60 overflow-y-auto">
+          {suggestions.map((s) => (
+            <button
+              key={`${s.kind}:${s.label}:${s.insert}`}
+              onClick={() => applySuggestion(s)}
+              disabled={!s.insert}
+              className="w-full text-left px-3 py-1.5 hover:bg-gray-700 disabled:hover:bg-transparent flex items-baseline gap-2"
+            >
+              <span className="text-sm text-white font-mono">{s.label}</span>
+              {s.description && <span className="text-xs text-gray-400 truncate">{s.description}</span>}
+            </button>
+          ))}
+        </div>
+      )}
+      {commandResponse && (
+        <div className="mb-1 px-3 py-1.5 text-xs text-gray-300 bg-gray-700/60 rounded">{commandResponse}</div>
+      )}
       {/* Reply preview bar */}
       {replyingTo && (
         <div className="flex items-center gap-2 px-4 py-2 bg-gray-600/50 rounded-t-lg border-b border-gray-500/50">
@@ -288,6 +288,52 @@ export default function MessageItem({ message, showHeader, replyMessage, reactio
           <span className="text-xs text-gray-500 ml-1">(edited)</span>
         )}
       </div>
+      {message.embeds?.map((embed, i) => (
+        <div
+          key={i}
+          className="mt-1 max-w-lg rounded bg-gray-800 border-l-4 px-3 py-2 text-sm"
+          style={{
+            borderLeftColor:
+              embed.color !== undefined
+                ? `#${embed.color.toString(16).padStart(6, "0")}`
+                : "#4b5563",
+          }}
+        >
+          {embed.title &&
+            (embed.url ? (
+              <a
+                href={embed.url}
+                target="_blank"
+                rel="noreferrer"
+                className="font-semibold text-indigo-400 hover:underline"
+              >
+                {embed.title}
+              </a>
+            ) : (
+              <div className="font-semibold text-white">{embed.title}</div>
+            ))}
+          {embed.description && (
+            <div className="text-gray-300 whitespace-pre-wrap">
+              {embed.description}
+            </div>
+          )}
+          {embed.fields && embed.fields.length > 0 && (
+            <div className="mt-1 grid grid-cols-3 gap-2">
+              {embed.fields.map((field, j) => (
+                <div key={j} className={field.inline ? "" : "col-span-3"}>
+                  <div className="text-xs font-semibold text-gray-400">
+                    {field.name}
+                  </div>
+                  <div className="text-gray-300">{field.value}</div>
+                </div>
+              ))}
+            </div>
+          )}
+          {embed.footer && (
+            <div className="mt-1 text-xs text-gray-500">{embed.footer}</div>
+          )}
+        </div>
+      ))}
       {reactionBar}
     </>
   );
@@ -357,6 +403,19 @@ export default function MessageItem({ message, showHeader, replyMessage, reactio
           {message.edited_at && (
             <span className="text-xs text-gray-500">(edited)</span>
           )}
+          {message.webhook_id && (
+            <span className="text-[10px] uppercase tracking-wide bg-indigo-600 text-white rounded px-1">
+              webhook
+            </span>
+          )}
+          {message.imported_from && (
+            <span
+              className="text-[10px] uppercase tracking-wide bg-gray-700 text-gray-300 rounded px-1"
+              title={`Imported from ${message.imported_from}`}
+            >
+              imported
+            </span>
+          )}
         </div>
         {contentElement}
       </div>
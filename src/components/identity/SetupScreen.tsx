@@ -1,17 +1,43 @@
-import { useState } from "react";
+import { useEffect, useState } from "react";
 import { useIdentityStore } from "../../stores/identityStore";
+import { identity as identityApi } from "../../lib/api";
 
 export default function SetupScreen() {
   const [name, setName] = useState("");
+  const [phrase, setPhrase] = useState<string | null>(null);
+  const [backedUp, setBackedUp] = useState(false);
+  const [importing, setImporting] = useState(false);
+  const [importPhrase, setImportPhrase] = useState("");
+  const [importResult, setImportResult] = useState<string | null>(null);
+  const [error, setError] = useState<string | null>(null);
   const { setDisplayName, identity } = useIdentityStore();
 
+  useEffect(() => {
+    identityApi
+      .getRecoveryPhrase()
+      .then((res) => setPhrase(res.phrase))
+      .catch(console.error);
+  }, []);
+
   const handleSubmit = async (e: React.FormEvent) => {
     e.preventDefault();
-    if (name.trim()) {
+    if (name.trim() && (backedUp || !phrase)) {
       await setDisplayName(name.trim());
     }
   };
 
+  const handleImport = async (e: React.FormEvent) => {
+    e.preventDefault();
+    setError(null);
+    try {
+      // The identity generated on first start has no history yet
+      const res = await identityApi.importIdentity(importPhrase.trim(), true);
+      setImportResult(res.peer_id);
+    } catch (err) {
+      setError(String(err));
+    }
+  };
+
   return (
     <div className="h-full bg-gray-900 flex items-center justify-center">
       <div className="bg-gray-800 rounded-xl p-8 max-w-md w-full mx-4 shadow-2xl">
@@ -24,34 +50,113 @@ export default function SetupScreen() {
           </p>
         </div>
 
-        <form onSubmit={handleSubmit} className="space-y-4">
-          <div>
-            <label
-              htmlFor="displayName"
-              className="block text-sm font-medium text-gray-300 mb-1"
-            >
-              Choose a display name
-            </label>
-            <input
-              id="displayName"
-              type="text"
-              value={name}
-              onChange={(e) => setName(e.target.value)}
-              placeholder="Enter your name..."
-              className="w-full px-4 py-3 bg-gray-700 text-white rounded-lg border border-gray-600 focus:border-indigo-500 focus:ring-1 focus:ring-indigo-500 outline-none placeholder-gray-500"
-              maxLength={32}
-              autoFocus
-            />
-          </div>
+        {importing ? (
+          importResult ? (
+            <p className="text-gray-300 text-sm">
+              Identity restored as{" "}
+              <span className="font-mono break-all">{importResult}</span>.
+              Restart Chatr to use it.
+            </p>
+          ) : (
+            <form onSubmit={handleImport} className="space-y-4">
+              <div>
+                <label
+                  htmlFor="recoveryPhrase"
+                  className="block text-sm font-medium text-gray-300 mb-1"
+                >
+                  Recovery phrase
+                </label>
+                <textarea
+                  id="recoveryPhrase"
+                  value={importPhrase}
+                  onChange={(e) => setImportPhrase(e.target.value)}
+                  placeholder="24 words separated by spaces"
+                  rows={4}
+                  className="w-full px-4 py-3 bg-gray-700 text-white rounded-lg border border-gray-600 focus:border-indigo-500 focus:ring-1 focus:ring-indigo-500 outline-none placeholder-gray-500 font-mono text-sm"
+                  autoFocus
+                />
+              </div>
 
-          <button
-            type="submit"
-            disabled={!name.trim()}
-            className="w-full py-3 bg-indigo-600 hover:bg-indigo-500 disabled:bg-gray-600 disabled:cursor-not-allowed text-white font-medium rounded-lg transition-colors"
-          >
-            Get Started
-          </button>
-        </form>
+              {error && <p className="text-sm text-red-400">{error}</p>}
+
+              <button
+                type="submit"
+                disabled={!importPhrase.trim()}
+                className="w-full py-3 bg-indigo-600 hover:bg-indigo-500 disabled:bg-gray-600 disabled:cursor-not-allowed text-white font-medium rounded-lg transition-colors"
+              >
+                Restore Identity
+              </button>
+              <button
+                type="button"
+                onClick={() => setImporting(false)}
+                className="w-full text-sm text-gray-400 hover:text-gray-200"
+              >
+                Back
+              </button>
+            </form>
+          )
+        ) : (
+          <form onSubmit={handleSubmit} className="space-y-4">
+            <div>
+              <label
+                htmlFor="displayName"
+                className="block text-sm font-medium text-gray-300 mb-1"
+              >
+                Choose a display name
+              </label>
+              <input
+                id="displayName"
+                type="text"
+                value={name}
+                onChange={(e) => setName(e.target.value)}
+                placeholder="Enter your name..."
+                className="w-full px-4 py-3 bg-gray-700 text-white rounded-lg border border-gray-600 focus:border-indigo-500 focus:ring-1 focus:ring-indigo-500 outline-none placeholder-gray-500"
+                maxLength={32}
+                autoFocus
+              />
+            </div>
+
+            {phrase && (
+              <div>
+                <p className="block text-sm font-medium text-gray-300 mb-1">
+                  Your recovery phrase
+                </p>
+                <p className="text-xs text-gray-400 mb-2">
+                  Write these words down. They are the only way to restore
+                  your identity on another device.
+                </p>
+                <ol className="grid grid-cols-3 gap-1 bg-gray-900 rounded-lg p-3 text-sm font-mono text-gray-200 list-decimal list-inside">
+                  {phrase.split(" ").map((word, i) => (
+                    <li key={i}>{word}</li>
+                  ))}
+                </ol>
+                <label className="flex items-center gap-2 mt-2 text-sm text-gray-300">
+                  <input
+                    type="checkbox"
+                    checked={backedUp}
+                    onChange={(e) => setBackedUp(e.target.checked)}
+                  />
+                  I have written down my recovery phrase
+                </label>
+              </div>
+            )}
+
+            <button
+              type="submit"
+              disabled={!name.trim() || (!!phrase && !backedUp)}
+              className="w-full py-3 bg-indigo-600 hover:bg-indigo-500 disabled:bg-gray-600 disabled:cursor-not-allowed text-white font-medium rounded-lg transition-colors"
+            >
+              Get Started
+            </button>
+            <button
+              type="button"
+              onClick={() => setImporting(true)}
+              className="w-full text-sm text-gray-400 hover:text-gray-200"
+            >
+              Restore an existing identity
+            </button>
+          </form>
+        )}
 
         {identity && (
           <p className="mt-4 text-xs text-gray-500 text-center font-mono truncate">
@@ -0,0 +1,66 @@
+import { useState } from "react";
+import { useIdentityStore } from "../../stores/identityStore";
+
+export default function UnlockScreen() {
+  const [passphrase, setPassphrase] = useState("");
+  const [error, setError] = useState<string | null>(null);
+  const [unlocking, setUnlocking] = useState(false);
+  const { unlock } = useIdentityStore();
+
+  const handleSubmit = async (e: React.FormEvent) => {
+    e.preventDefault();
+    if (!passphrase) return;
+    setUnlocking(true);
+    setError(null);
+    try {
+      await unlock(passphrase);
+    } catch (err) {
+      setError(String(err));
+      setPassphrase("");
+    } finally {
+      setUnlocking(false);
+    }
+  };
+
+  return (
+    <div className="h-full bg-gray-900 flex items-center justify-center">
+      <div className="bg-gray-800 rounded-xl p-8 max-w-md w-full mx-4 shadow-2xl">
+        <div className="text-center mb-6">
+          <h1 className="text-3xl font-bold text-white mb-2">Unlock Chatr</h1>
+          <p className="text-gray-400">
+            Your data is encrypted. Enter your passphrase to continue.
+          </p>
+        </div>
+
+        <form onSubmit={handleSubmit} className="space-y-4">
+          <div>
+            <label
+              htmlFor="passphrase"
+              className="block text-sm font-medium text-gray-300 mb-1"
+            >
+              Passphrase
+            </label>
+            <input
+              id="passphrase"
+              type="password"
+              value={passphrase}
+              onChange={(e) => setPassphrase(e.target.value)}
+              className="w-full px-4 py-3 bg-gray-700 text-white rounded-lg border border-gray-600 focus:border-indigo-500 focus:ring-1 focus:ring-indigo-500 outline-none placeholder-gray-500"
+              autoFocus
+            />
+          </div>
+
+          {error && <p className="text-sm text-red-400">{error}</p>}
+
+          <button
+            type="submit"
+            disabled={!passphrase || unlocking}
+            className="w-full py-3 bg-indigo-600 hover:bg-indigo-500 disabled:bg-gray-600 disabled:cursor-not-allowed text-white font-medium rounded-lg transition-colors"
+          >
+            {unlocking ? "Unlocking..." : "Unlock"}
+          </button>
+        </form>
+      </div>
+    </div>
+  );
+}
@@ -17,7 +17,7 @@ import type { Message, PeerInfo } from "../../lib/types";
 
 export default function AppLayout() {
   const { loadRooms, rooms } = useChatStore();
-  const { addMessage, editMessage, deleteMessage, addReaction, removeReaction, setTyping } =
+  const { addMessage, editMessage, deleteMessage, removeMessages, addReaction, removeReaction, setTyping } =
     useMessageStore();
   const { addPeer, removePeer } = usePeerStore();
   const { mode, setSearchOpen } = useViewStore();
@@ -85,6 +85,14 @@ export default function AppLayout() {
       deleteMessage(event.payload.message_id);
     }).then((u) => unlisteners.push(u));
 
+    listen<{ channel_id: string; message_ids: string[] }>("messages-expired", (event) => {
+      removeMessages(event.payload.message_ids);
+    }).then((u) => unlisteners.push(u));
+
+    listen<{ room_id: string; sender_peer_id: string; message_ids: string[] }>("messages-purged", (event) => {
+      removeMessages(event.payload.message_ids);
+    }).then((u) => unlisteners.push(u));
+
     listen<{ message_id: string; channel_id: string; peer_id: string; emoji: string }>(
       "reaction-added",
       (event) => {
@@ -172,7 +180,7 @@ export default function AppLayout() {
     return () => {
       unlisteners.forEach((u) => u());
     };
-  }, [addMessage, addPeer, removePeer, editMessage, deleteMessage, addReaction, removeReaction, setTyping, handleVoiceStateChanged, handleVoiceConnected, handleVoiceDisconnected, handleSpeakingChanged]);
+  }, [addMessage, addPeer, removePeer, editMessage, deleteMessage, removeMessages, addReaction, removeReaction, setTyping, handleVoiceStateChanged, handleVoiceConnected, handleVoiceDisconnected, handleSpeakingChanged]);
 
   // No rooms state (only for rooms mode)
   if (mode === "rooms" && rooms.length === 0) {
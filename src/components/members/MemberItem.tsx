@@ -47,6 +47,11 @@ export default function MemberItem({ peer }: MemberItemProps) {
       >
         {peer.display_name}
       </span>
+      {peer.is_bot && (
+        <span className="text-[10px] uppercase tracking-wide bg-indigo-600 text-white rounded px-1">
+          bot
+        </span>
+      )}
     </div>
   );
 }
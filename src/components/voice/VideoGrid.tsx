@@ -1,6 +1,7 @@
 import { invoke } from "@tauri-apps/api/core";
 import { useVoiceStore } from "../../stores/voiceStore";
 import { useEffect, useRef, useState, useCallback } from "react";
+import { getApiToken } from "../../lib/api";
 
 /** Get the API port from Tauri and construct the base URL for frame endpoints. */
 function useFrameBaseUrl() {
@@ -105,7 +106,8 @@ function FrameTile({
     const endpoint = type === "video" ? "media/video" : "media/screen";
     const url = `${baseUrl}/${endpoint}/${effectivePeerId}/frame`;
     try {
-      const resp = await fetch(url);
+      const token = await getApiToken();
+      const resp = await fetch(url, { headers: { Authorization: `Bearer ${token}` } });
       if (!resp.ok) return;
       const blob = await resp.blob();
       const newUrl = URL.createObjectURL(blob);
@@ -2,9 +2,26 @@ import { invoke } from "@tauri-apps/api/core";
 import type {
   Message,
   Room,
+  ExportFormat,
+  ApiScope,
+  ApiToken,
+  IssuedApiToken,
+  Bot,
+  CreatedBot,
+  SlashCommand,
+  CommandResult,
+  CommandSuggestion,
+  IncomingWebhook,
+  CreatedIncomingWebhook,
+  ImportSummary,
+  RetentionPolicy,
   Channel,
   PeerInfo,
   Identity,
+  KeyRotation,
+  DeviceLinkRequest,
+  DeviceCertificate,
+  LinkedDevice,
   Reaction,
   DmConversation,
   DmMessage,
@@ -12,6 +29,7 @@ import type {
   RoomRole,
   Friend,
   SearchResult,
+  UnifiedSearchResult,
 } from "./types";
 
 let _apiPort: number | null = null;
@@ -22,19 +40,40 @@ async function getApiPort(): Promise<number> {
   return _apiPort;
 }
 
-async function api<T>(path: string, options?: RequestInit): Promise<T> {
-  const port = await getApiPort();
+let _apiToken: string | null = null;
+
+/** Bearer token for the local API, issued to the GUI by the backend. */
+export async function getApiToken(): Promise<string> {
+  if (_apiToken) return _apiToken;
+  _apiToken = await invoke<string>("get_api_token");
+  return _apiToken;
+}
+
+async function apiRaw(path: string, options?: RequestInit): Promise<Response> {
+  const [port, token] = await Promise.all([getApiPort(), getApiToken()]);
   const res = await fetch(`http://127.0.0.1:${port}${path}`, {
     ...options,
     headers: {
       "Content-Type": "application/json",
+      Authorization: `Bearer ${token}`,
       ...options?.headers,
     },
   });
   if (!res.ok) {
     const text = await res.text();
-    throw new Error(text || `API error ${res.status}`);
+    let message = text;
+    try {
+      message = (JSON.parse(text) as { message?: string }).message ?? text;
+    } catch {
+      // Not a JSON error body; use the raw text.
+    }
+    throw new Error(message || `API error ${res.status}`);
   }
+  return res;
+}
+
+async function api<T>(path: string, options?: RequestInit): Promise<T> {
+  const res = await apiRaw(path, options);
   const text = await res.text();
   return text ? JSON.parse(text) : ({} as T);
 }
@@ -54,6 +93,127 @@ export const identity = {
       method: "PUT",
       body: JSON.stringify({ status_message, status_type }),
     }),
+  getEncryption: () => api<{ encrypted: boolean }>("/api/v1/identity/encryption"),
+  changePassphrase: (current_passphrase: string | null, new_passphrase: string | null) =>
+    api<void>("/api/v1/identity/passphrase", {
+      method: "PUT",
+      body: JSON.stringify({ current_passphrase, new_passphrase }),
+    }),
+  getRecoveryPhrase: () => api<{ phrase: string }>("/api/v1/identity/recovery-phrase"),
+  importIdentity: (phrase: string, overwrite = false) =>
+    api<{ peer_id: string; restart_required: boolean }>("/api/v1/identity/import", {
+      method: "POST",
+      body: JSON.stringify({ phrase, overwrite }),
+    }),
+  rotateKey: () => api<KeyRotation>("/api/v1/identity/rotate", { method: "POST" }),
+};
+
+// ============================================================
+// Devices
+// ============================================================
+export const devices = {
+  list: () => api<LinkedDevice[]>("/api/v1/devices"),
+  getLinkRequest: (name?: string) =>
+    api<DeviceLinkRequest>(
+      `/api/v1/devices/link-request${name ? `?name=${encodeURIComponent(name)}` : ""}`
+    ),
+  link: (request: DeviceLinkRequest) =>
+    api<DeviceCertificate>("/api/v1/devices", {
+      method: "POST",
+      body: JSON.stringify(request),
+    }),
+  accept: (certificate: DeviceCertificate) =>
+    api<void>("/api/v1/devices/accept", {
+      method: "POST",
+      body: JSON.stringify(certificate),
+    }),
+  unlink: (devicePeerId: string) =>
+    api<{ revoked: boolean }>(`/api/v1/devices/${devicePeerId}`, { method: "DELETE" }),
+};
+
+// ============================================================
+// Import
+// ============================================================
+export const importHistory = {
+  /** `exports` are parsed DiscordChatExporter JSON files, one per channel. */
+  discord: (exports: unknown[], room_name?: string) =>
+    api<ImportSummary>("/api/v1/import/discord", {
+      method: "POST",
+      body: JSON.stringify({ room_name, exports }),
+    }),
+};
+
+// ============================================================
+// API tokens
+// ============================================================
+export const apiTokens = {
+  list: () => api<ApiToken[]>("/api/v1/tokens"),
+  issue: (name: string, scopes: ApiScope[]) =>
+    api<IssuedApiToken>("/api/v1/tokens", {
+      method: "POST",
+      body: JSON.stringify({ name, scopes }),
+    }),
+  revoke: (tokenId: string) =>
+    api<void>(`/api/v1/tokens/${tokenId}`, { method: "DELETE" }),
+};
+
+// ============================================================
+// Bots
+// ============================================================
+export const bots = {
+  list: () => api<Bot[]>("/api/v1/bots"),
+  create: (displayName: string) =>
+    api<CreatedBot>("/api/v1/bots", {
+      method: "POST",
+      body: JSON.stringify({ display_name: displayName }),
+    }),
+  rename: (botPeerId: string, displayName: string) =>
+    api<Bot>(`/api/v1/bots/${botPeerId}`, {
+      method: "PUT",
+      body: JSON.stringify({ display_name: displayName }),
+    }),
+  remove: (botPeerId: string) =>
+    api<void>(`/api/v1/bots/${botPeerId}`, { method: "DELETE" }),
+  regenerateToken: (botPeerId: string) =>
+    api<{ token: string }>(`/api/v1/bots/${botPeerId}/token`, {
+      method: "POST",
+    }),
+  joinRoom: (botPeerId: string, roomId: string) =>
+    api<Bot>(`/api/v1/bots/${botPeerId}/rooms/${roomId}`, { method: "PUT" }),
+  leaveRoom: (botPeerId: string, roomId: string) =>
+    api<Bot>(`/api/v1/bots/${botPeerId}/rooms/${roomId}`, {
+      method: "DELETE",
+    }),
+};
+
+// ============================================================
+// Incoming webhooks
+// ============================================================
+export const incomingWebhooks = {
+  list: (channelId?: string) =>
+    api<IncomingWebhook[]>(
+      `/api/v1/incoming-webhooks${channelId ? `?channel_id=${encodeURIComponent(channelId)}` : ""}`,
+    ),
+  create: (channelId: string, name: string) =>
+    api<CreatedIncomingWebhook>("/api/v1/incoming-webhooks", {
+      method: "POST",
+      body: JSON.stringify({ channel_id: channelId, name }),
+    }),
+  revoke: (webhookId: string) =>
+    api<void>(`/api/v1/incoming-webhooks/${webhookId}`, { method: "DELETE" }),
+};
+
+// ============================================================
+// Backup
+// ============================================================
+export const backup = {
+  create: async (passphrase: string): Promise<Blob> => {
+    const res = await apiRaw("/api/v1/backup", {
+      method: "POST",
+      body: JSON.stringify({ passphrase }),
+    });
+    return res.blob();
+  },
 };
 
 // ============================================================
@@ -81,6 +241,21 @@ export const rooms = {
       body: JSON.stringify({ action_type, target_peer_id, reason }),
     }),
   getAuditLog: (roomId: string) => api<any[]>(`/api/v1/rooms/${roomId}/audit-log`),
+  getRetention: (roomId: string) =>
+    api<RetentionPolicy | null>(`/api/v1/rooms/${roomId}/retention`),
+  setRetention: (roomId: string, max_age_days?: number, max_messages?: number) =>
+    api<RetentionPolicy>(`/api/v1/rooms/${roomId}/retention`, {
+      method: "PUT",
+      body: JSON.stringify({ max_age_days, max_messages }),
+    }),
+  deleteMyMessages: (roomId: string) =>
+    api<{ deleted: number }>(`/api/v1/rooms/${roomId}/messages/mine`, { method: "DELETE" }),
+  exportHistory: async (roomId: string, format: ExportFormat, channelId?: string) => {
+    const params = new URLSearchParams({ format });
+    if (channelId) params.set("channel_id", channelId);
+    const res = await apiRaw(`/api/v1/rooms/${roomId}/export?${params}`);
+    return res.blob();
+  },
 };
 
 // ============================================================
@@ -99,6 +274,29 @@ export const channels = {
     }),
   delete: (channelId: string) =>
     api<void>(`/api/v1/channels/${channelId}`, { method: "DELETE" }),
+  getRetention: (channelId: string) =>
+    api<RetentionPolicy | null>(`/api/v1/channels/${channelId}/retention`),
+  setRetention: (channelId: string, max_age_days?: number, max_messages?: number) =>
+    api<RetentionPolicy>(`/api/v1/channels/${channelId}/retention`, {
+      method: "PUT",
+      body: JSON.stringify({ max_age_days, max_messages }),
+    }),
+};
+
+// ============================================================
+// Slash commands
+// ============================================================
+export const commands = {
+  list: (channelId: string) => api<SlashCommand[]>(`/api/v1/channels/${channelId}/commands`),
+  run: (channelId: string, input: string) =>
+    api<CommandResult>(`/api/v1/channels/${channelId}/commands`, {
+      method: "POST",
+      body: JSON.stringify({ input }),
+    }),
+  autocomplete: (channelId: string, input: string) =>
+    api<CommandSuggestion[]>(
+      `/api/v1/channels/${channelId}/commands/autocomplete?input=${encodeURIComponent(input)}`,
+    ),
 };
 
 // ============================================================
@@ -157,12 +355,19 @@ export const messages = {
   unpin: (channelId: string, messageId: string) =>
     api<void>(`/api/v1/channels/${channelId}/pins/${messageId}`, { method: "DELETE" }),
   // Search
-  search: (query: string, channel_id?: string, limit?: number) => {
+  search: (query: string, channel_id?: string, limit?: number, room_id?: string) => {
     const params = new URLSearchParams({ q: query });
     if (channel_id) params.set("channel_id", channel_id);
+    if (room_id) params.set("room_id", room_id);
     if (limit) params.set("limit", String(limit));
     return api<SearchResult>(`/api/v1/search/messages?${params}`);
   },
+  searchAll: (query: string, sources?: ("channel" | "dm" | "file")[], limit?: number) => {
+    const params = new URLSearchParams({ q: query });
+    if (sources?.length) params.set("sources", sources.join(","));
+    if (limit) params.set("limit", String(limit));
+    return api<UnifiedSearchResult>(`/api/v1/search?${params}`);
+  },
 };
 
 // ============================================================
@@ -184,6 +389,14 @@ export const dms = {
       method: "POST",
       body: JSON.stringify({ content }),
     }),
+  getTimer: (conversationId: string) =>
+    api<RetentionPolicy | null>(`/api/v1/dms/${conversationId}/timer`),
+  /** Pass `null` to turn disappearing messages off. */
+  setTimer: (conversationId: string, timer_secs: number | null) =>
+    api<RetentionPolicy>(`/api/v1/dms/${conversationId}/timer`, {
+      method: "PUT",
+      body: JSON.stringify({ timer_secs }),
+    }),
 };
 
 // ============================================================
@@ -7,6 +7,15 @@ import type {
   Identity,
 } from "./types";
 
+// Unlock
+export async function isLocked(): Promise<boolean> {
+  return invoke("is_locked");
+}
+
+export async function unlock(passphrase: string): Promise<void> {
+  return invoke("unlock", { passphrase });
+}
+
 // Identity
 export async function getMyPeerId(): Promise<string> {
   return invoke("get_my_peer_id");
@@ -8,6 +8,30 @@ export interface Message {
   edited_at?: string | null;
   deleted_at?: string | null;
   reply_to_id?: string | null;
+  imported_from?: string | null;
+  webhook_id?: string | null;
+  embeds?: MessageEmbed[];
+}
+
+export interface MessageEmbed {
+  title?: string;
+  description?: string;
+  url?: string;
+  color?: number;
+  fields?: { name: string; value: string; inline?: boolean }[];
+  footer?: string;
+}
+
+export interface IncomingWebhook {
+  id: string;
+  channel_id: string;
+  name: string;
+  created_at: string;
+}
+
+export interface CreatedIncomingWebhook extends IncomingWebhook {
+  token: string;
+  url: string;
 }
 
 export interface Room {
@@ -18,6 +42,41 @@ export interface Room {
   owner_peer_id?: string | null;
 }
 
+export interface ImportSummary {
+  room: Room;
+  channels: Channel[];
+  messages_imported: number;
+  messages_skipped: number;
+}
+
+export type RetentionScope = "room" | "channel" | "dm";
+
+/** No limits set means history is kept forever. */
+export interface RetentionPolicy {
+  scope: RetentionScope;
+  target_id: string;
+  max_age_secs?: number;
+  max_messages?: number;
+  updated_by: string;
+  updated_at: string;
+}
+
+export type ExportFormat = "json" | "html" | "markdown";
+
+export type ApiScope = "read" | "write" | "admin";
+
+export interface ApiToken {
+  id: string;
+  name: string;
+  scopes: ApiScope[];
+  created_at: string;
+  last_used_at?: string;
+}
+
+export interface IssuedApiToken extends ApiToken {
+  secret: string;
+}
+
 export interface Channel {
   id: string;
   room_id: string;
@@ -32,6 +91,60 @@ export interface PeerInfo {
   peer_id: string;
   display_name: string;
   is_online: boolean;
+  is_bot?: boolean;
+}
+
+export interface Bot {
+  peer_id: string;
+  display_name: string;
+  rooms: string[];
+  created_at: string;
+}
+
+export interface CreatedBot extends Bot {
+  token: string;
+}
+
+export type CommandOptionType = "string" | "text" | "integer" | "user" | "duration";
+
+export interface CommandOption {
+  name: string;
+  description: string;
+  type: CommandOptionType;
+  required: boolean;
+}
+
+export interface SlashCommand {
+  name: string;
+  description: string;
+  options: CommandOption[];
+  bot_peer_id?: string;
+  room_id?: string;
+}
+
+export interface CommandInvocation {
+  id: string;
+  command: string;
+  bot_peer_id: string;
+  room_id: string;
+  channel_id: string;
+  invoker_peer_id: string;
+  invoker_display_name: string;
+  options: Record<string, string | number>;
+  created_at: string;
+}
+
+export interface CommandResult {
+  message?: Message;
+  response?: string;
+  invocation?: CommandInvocation;
+}
+
+export interface CommandSuggestion {
+  kind: "command" | "user" | "option";
+  insert: string;
+  label: string;
+  description?: string;
 }
 
 export interface Identity {
@@ -42,6 +155,41 @@ export interface Identity {
   status_type?: string | null;
 }
 
+export interface KeyRotation {
+  old_peer_id: string;
+  new_peer_id: string;
+  old_public_key: number[];
+  new_public_key: number[];
+  rotated_at: string;
+  old_signature: number[];
+  new_signature: number[];
+}
+
+export interface DeviceLinkRequest {
+  device_peer_id: string;
+  device_public_key: number[];
+  device_name: string;
+}
+
+export interface DeviceCertificate {
+  identity_peer_id: string;
+  device_peer_id: string;
+  identity_public_key: number[];
+  device_public_key: number[];
+  device_name: string;
+  issued_at: string;
+  signature: number[];
+  device_signature: number[];
+}
+
+export interface LinkedDevice {
+  device_peer_id: string;
+  device_name: string;
+  issued_at: string;
+  revoked_at?: string | null;
+  is_current: boolean;
+}
+
 export interface Reaction {
   id: string;
   message_id: string;
@@ -93,6 +241,29 @@ export interface Friend {
 export interface SearchResult {
   messages: Message[];
   total: number;
+  /** Highlighted excerpt per message id, HTML-escaped, matches wrapped in <mark>. */
+  snippets: Record<string, string>;
+}
+
+export interface FileMetadata {
+  id: string;
+  filename: string;
+  size: number;
+  mime_type: string;
+  sha256_hash: string;
+  chunk_count: number;
+  uploader_peer_id: string;
+  created_at: string;
+}
+
+export type SearchHit =
+  | { source: "channel"; message: Message; snippet?: string }
+  | { source: "dm"; message: DmMessage; snippet?: string }
+  | { source: "file"; file: FileMetadata; snippet?: string };
+
+export interface UnifiedSearchResult {
+  results: SearchHit[];
+  total: number;
 }
 
 export interface VoiceParticipant {
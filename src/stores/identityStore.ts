@@ -1,12 +1,20 @@
 import { create } from "zustand";
-import { getIdentity, setDisplayName as setName, setStatus as setStatusApi } from "../lib/tauri";
+import {
+  getIdentity,
+  isLocked as isLockedApi,
+  unlock as unlockApi,
+  setDisplayName as setName,
+  setStatus as setStatusApi,
+} from "../lib/tauri";
 import type { Identity } from "../lib/types";
 
 interface IdentityState {
   identity: Identity | null;
   isLoading: boolean;
   isSetup: boolean;
+  isLocked: boolean;
   loadIdentity: () => Promise<void>;
+  unlock: (passphrase: string) => Promise<void>;
   setDisplayName: (name: string) => Promise<void>;
   setStatus: (statusMessage?: string, statusType?: string) => Promise<void>;
 }
@@ -15,9 +23,14 @@ export const useIdentityStore = create<IdentityState>((set, get) => ({
   identity: null,
   isLoading: true,
   isSetup: false,
+  isLocked: false,
 
   loadIdentity: async () => {
     try {
+      if (await isLockedApi()) {
+        set({ isLocked: true, isLoading: false });
+        return;
+      }
       const identity = await getIdentity();
       const isSetup = identity.display_name !== "Anonymous";
       set({ identity, isLoading: false, isSetup });
@@ -27,6 +40,12 @@ export const useIdentityStore = create<IdentityState>((set, get) => ({
     }
   },
 
+  unlock: async (passphrase: string) => {
+    await unlockApi(passphrase);
+    set({ isLocked: false, isLoading: true });
+    await get().loadIdentity();
+  },
+
   setDisplayName: async (name: string) => {
     await setName(name);
     const identity = get().identity;
@@ -19,6 +19,7 @@ interface MessageState {
   loadMoreMessages: () => Promise<boolean>;
   editMessage: (messageId: string, newContent: string, editedAt: string) => void;
   deleteMessage: (messageId: string) => void;
+  removeMessages: (messageIds: string[]) => void;
   addReaction: (messageId: string, reaction: Reaction) => void;
   removeReaction: (messageId: string, peerId: string, emoji: string) => void;
   setTyping: (channelId: string, peerId: string, displayName: string, isTyping: boolean) => void;
@@ -84,6 +85,13 @@ export const useMessageStore = create<MessageState>((set, get) => ({
     }));
   },
 
+  removeMessages: (messageIds: string[]) => {
+    const ids = new Set(messageIds);
+    set((state) => ({
+      messages: state.messages.filter((m) => !ids.has(m.id)),
+    }));
+  },
+
   addReaction: (messageId: string, reaction: Reaction) => {
     set((state) => {
       const existing = state.reactions[messageId] ?? [];